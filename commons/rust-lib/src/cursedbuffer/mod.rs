@@ -1,10 +1,14 @@
-#![allow(dead_code)]
-#![allow(unused_imports)]
-use std::{clone, fs::read, future, ops::{Deref, DerefMut}, rc::Rc, sync::{Arc, Mutex, Weak}};
-use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
+use std::{ops::Deref, sync::{atomic::{AtomicBool, AtomicUsize, Ordering}, Arc, Mutex, OnceLock}};
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::path::PathBuf;
 use derive_more::{Display, Error};
 use tokio::sync::Notify;
 
+mod spill;
+
+pub use spill::{SpillConfig, Spillable};
+use spill::{SpillWriter, SpillableChunk};
+
 
 //internal state structs
 
@@ -21,31 +25,135 @@ impl Debug for Callback {
         }
     }
 }
+
+/// Holds the callback that is fired whenever data is finally removed from the buffer.
+/// Every node references it, because removal happens when the last node referencing some data is dropped.
 #[derive(Debug)]
-struct CursedBufferInternalState<T> {
-    first_read_position: usize, //the first element's of the first slice of buffers position
-    buffers: Vec<Arc<Vec<T>>>,
-    is_closed: bool,
-    all_readers: Vec<Option<CursedBufferReaderInternalState>>,
-    callback_function: Callback,
+struct PruneCallback {
+    is_set: AtomicBool,
+    callback: Mutex<Callback>,
+}
+
+impl PruneCallback {
+    fn call(&self) {
+        //only lock if somebody is listening, so that pruning stays lock free otherwise
+        if self.is_set.load(Ordering::Acquire)
+            && let Callback::Function(callback) = &*self.callback.lock().unwrap() {
+            callback();
+        }
+    }
+}
+
+/// The buffer content is a singly linked list of nodes. A node marks a position in the stream and
+/// links to the chunk that was written after that position together with the next node.
+/// Readers walk this list without taking any buffer wide lock. Data is freed as soon as no reader
+/// (or the buffer itself while no reader is registered) holds a node in front of it.
+struct CursedBufferNode<T> {
+    position: usize, //position of the first element of the chunk in next
+    next: OnceLock<CursedBufferLink<T>>,
+    pruned: Arc<PruneCallback>,
+}
+
+struct CursedBufferLink<T> {
+    data: CursedBufferData<T>,
+    node: Arc<CursedBufferNode<T>>,
+}
+
+/// Chunks of a buffer in spill mode may be moved to disk while they are linked
+enum CursedBufferData<T> {
+    Memory(Arc<Vec<T>>),
+    Spillable(Arc<SpillableChunk<T>>),
+}
+
+impl<T> CursedBufferData<T> {
+    fn load(&self) -> Result<Arc<Vec<T>>, CursedBufferError> {
+        match self {
+            CursedBufferData::Memory(data) => Ok(data.clone()),
+            CursedBufferData::Spillable(chunk) => chunk.load(),
+        }
+    }
+}
+
+impl<T> CursedBufferNode<T> {
+    fn new(position: usize, pruned: Arc<PruneCallback>) -> CursedBufferNode<T> {
+        CursedBufferNode {
+            position,
+            next: OnceLock::new(),
+            pruned
+        }
+    }
+
+    /// Walks the list from `node` as long as the next chunk ends before or at `position`
+    fn advance(mut node: Arc<CursedBufferNode<T>>, position: usize) -> Arc<CursedBufferNode<T>> {
+        while let Some(link) = node.next.get() {
+            if link.node.position > position {
+                break;
+            }
+            node = link.node.clone();
+        }
+        node
+    }
+}
+
+impl<T> Drop for CursedBufferNode<T> {
+    fn drop(&mut self) {
+        //unlink iteratively, a long chain would otherwise overflow the stack with recursive drops
+        let mut removed = false;
+        let mut next = self.next.take();
+        while let Some(link) = next {
+            removed = true;
+            drop(link.data);
+            next = match Arc::into_inner(link.node) {
+                Some(mut node) => node.next.take(),
+                None => None
+            };
+        }
+        if removed {
+            self.pruned.call();
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CursedBufferWriterState<T> {
+    tail: Arc<CursedBufferNode<T>>,
+}
+
+#[derive(Debug)]
+struct CursedBufferReaderSlots<T> {
+    slots: Vec<Option<Arc<CursedBufferReaderInternalState<T>>>>,
+    free_slots: Vec<usize>,
+    active: usize,
+    head: Option<Arc<CursedBufferNode<T>>>, //keeps the data alive while no reader is registered
+}
+
+#[derive(Debug)]
+struct CursedBufferShared<T> {
+    writer: Mutex<CursedBufferWriterState<T>>,
+    spill: Option<Mutex<SpillWriter<T>>>, //locked before the writer, file I/O never blocks readers on the writer lock
+    readers: Mutex<CursedBufferReaderSlots<T>>,
+    is_closed: AtomicBool,
+    pruned: Arc<PruneCallback>,
     notify_written_async: Arc<Notify>
 }
 
 #[derive(Debug)]
-struct CursedBufferReaderInternalState {
-    position: usize,
+struct CursedBufferReaderInternalState<T> {
+    position: AtomicUsize,
+    cursor: Mutex<Arc<CursedBufferNode<T>>>, //only ever contended while a new reader is registered
 }
 
 // public structs
 
 #[derive(Debug)]
 pub struct CursedBuffer<T> {
-    bufferstate: Arc<Mutex<CursedBufferInternalState<T>>>,
+    bufferstate: Arc<CursedBufferShared<T>>,
 }
 
 #[derive(Debug)]
 pub struct CursedBufferReader<T> {
-    bufferstate: Arc<Mutex<CursedBufferInternalState<T>>>,
+    bufferstate: Arc<CursedBufferShared<T>>,
+    readerstate: Arc<CursedBufferReaderInternalState<T>>,
     reader_in_buffer: usize,
 }
 
@@ -67,6 +175,16 @@ pub enum CursedBufferError {
 
 // implementations
 
+impl<T> Debug for CursedBufferNode<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        //don't follow the links, the list can be very long
+        f.debug_struct("CursedBufferNode")
+            .field("position", &self.position)
+            .field("has_next", &self.next.get().is_some())
+            .finish()
+    }
+}
+
 impl<T> Clone for CursedBuffer<T> {
     fn clone(&self) -> Self {
         CursedBuffer {
@@ -75,147 +193,244 @@ impl<T> Clone for CursedBuffer<T> {
     }
 }
 
+impl<T> Default for CursedBuffer<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T> CursedBuffer<T> {
     pub fn new() -> CursedBuffer<T> {
-        let state = CursedBufferInternalState::<T> {
-            first_read_position: 0,
-            buffers: Vec::new(),
-            all_readers: Vec::new(),
-            is_closed: false,
-            callback_function: Callback::None,
+        Self::with_spill_writer(None)
+    }
+
+    /// Creates a buffer that keeps at most `config.memory_budget` bytes of chunks in memory.
+    /// Older chunks are written to a private spill directory and read back transparently by readers that fall behind.
+    /// Spilled data is deleted once all readers have passed it.
+    pub fn with_spill(config: SpillConfig) -> Result<CursedBuffer<T>, CursedBufferError> where T: Spillable {
+        Ok(Self::with_spill_writer(Some(SpillWriter::new(config)?)))
+    }
+
+    fn with_spill_writer(spill: Option<SpillWriter<T>>) -> CursedBuffer<T> {
+        let pruned = Arc::new(PruneCallback {
+            is_set: AtomicBool::new(false),
+            callback: Mutex::new(Callback::None),
+        });
+        let head = Arc::new(CursedBufferNode::new(0, pruned.clone()));
+        let state = CursedBufferShared::<T> {
+            writer: Mutex::new(CursedBufferWriterState { tail: head.clone() }),
+            spill: spill.map(Mutex::new),
+            readers: Mutex::new(CursedBufferReaderSlots {
+                slots: Vec::new(),
+                free_slots: Vec::new(),
+                active: 0,
+                head: Some(head),
+            }),
+            is_closed: AtomicBool::new(false),
+            pruned,
             notify_written_async: Arc::new(Notify::new())
         };
-        let bufferstate = Arc::new(Mutex::new(state));
-        
+
         CursedBuffer {
-            bufferstate
+            bufferstate: Arc::new(state)
         }
     }
 
+    /// Returns the spill directory if the buffer was created with [`CursedBuffer::with_spill`]
+    pub fn spill_directory(&self) -> Option<PathBuf> {
+        self.bufferstate.spill.as_ref().map(|s| s.lock().unwrap().directory().clone())
+    }
+
+    /// Returns the number of bytes of chunks kept in memory, only tracked in spill mode
+    pub fn memory_used(&self) -> Option<usize> {
+        self.bufferstate.spill.as_ref().map(|s| s.lock().unwrap().memory_used())
+    }
+
     /// Sets a callback function that will be called whenever some data is finally removed from the buffer
     pub fn set_callback(&mut self, callback: Box<dyn Fn() + Send>) {
-        let mut bufferstate = self.bufferstate.lock().unwrap();
-        bufferstate.callback_function = Callback::Function(callback);
+        *self.bufferstate.pruned.callback.lock().unwrap() = Callback::Function(callback);
+        self.bufferstate.pruned.is_set.store(true, Ordering::Release);
     }
 
     pub fn close(&self) {
-        let mut bufferstate = self.bufferstate.lock().unwrap();
-        bufferstate.is_closed = true;
+        //take the writer lock so that no write can slip in after closing
+        let _writer = self.bufferstate.writer.lock().unwrap();
+        self.bufferstate.is_closed.store(true, Ordering::Release);
+        self.bufferstate.notify_written_async.notify_waiters();
     }
 
+    /// Creates a new reader starting at `position`.
+    /// If the data at `position` was already removed, the reader starts at the oldest data still available.
+    /// Slots of dropped readers are reused, so creating short lived readers does not grow the buffer.
     pub fn reader(&self, position: usize) -> CursedBufferReader<T> {
-        let mut bufferstate = self.bufferstate.lock().unwrap();
-
-        let readerstate = CursedBufferReaderInternalState {
-            position: position,
+        let mut readers = self.bufferstate.readers.lock().unwrap();
+
+        let start = match readers.head.take() {
+            Some(head) => head,
+            None => {
+                //the oldest data still available is at the cursor of the slowest reader
+                readers.slots.iter()
+                    .flatten()
+                    .map(|r| r.cursor.lock().unwrap().clone())
+                    .min_by_key(|node| node.position)
+                    .unwrap_or_else(|| self.bufferstate.writer.lock().unwrap().tail.clone())
+            }
         };
+        let position = position.max(start.position);
+
+        let readerstate = Arc::new(CursedBufferReaderInternalState {
+            position: AtomicUsize::new(position),
+            cursor: Mutex::new(CursedBufferNode::advance(start, position)),
+        });
 
-        bufferstate.all_readers.push(Some(readerstate));
+        let reader_in_buffer = match readers.free_slots.pop() {
+            Some(slot) => {
+                readers.slots[slot] = Some(readerstate.clone());
+                slot
+            }
+            None => {
+                readers.slots.push(Some(readerstate.clone()));
+                readers.slots.len() - 1
+            }
+        };
+        readers.active += 1;
 
         CursedBufferReader {
             bufferstate: self.bufferstate.clone(),
-            reader_in_buffer: bufferstate.all_readers.len()-1
+            readerstate,
+            reader_in_buffer
         }
     }
 
+    /// Returns the position of the slowest reader or None if no reader is registered.
+    /// Scans all reader slots under the reader lock, so it costs O(readers) and is meant for monitoring, not for hot paths
+    pub fn min_position(&self) -> Option<usize> {
+        let readers = self.bufferstate.readers.lock().unwrap();
+        readers.slots.iter()
+            .flatten()
+            .map(|r| r.position.load(Ordering::Acquire))
+            .min()
+    }
+
+    /// Appends `data` and wakes up waiting readers. An error means nothing was written, so the write can be retried.
+    /// In spill mode older chunks are written to disk first to make room, readers are not blocked meanwhile
     pub fn write(&self, data: Vec<T>) -> Result<(), CursedBufferError> {
-        let mut s = self.bufferstate.lock().unwrap();
-        if s.is_closed {
+        if self.bufferstate.is_closed.load(Ordering::Acquire) {
             return Err(CursedBufferError::BufferClosed);
         }
-        s.buffers.push(Arc::new(data));
-        s.notify_written_async.notify_waiters();
+        let len = data.len();
+        let mut spill = self.bufferstate.spill.as_ref().map(|s| s.lock().unwrap());
+        let data = match &mut spill {
+            Some(spill) => {
+                let chunk = spill.track(data);
+                //a failed spill drops the chunk before it is linked
+                spill.enforce_budget()?;
+                CursedBufferData::Spillable(chunk)
+            }
+            None => CursedBufferData::Memory(Arc::new(data)),
+        };
+        let mut writer = self.bufferstate.writer.lock().unwrap();
+        if self.bufferstate.is_closed.load(Ordering::Acquire) {
+            return Err(CursedBufferError::BufferClosed);
+        }
+        let node = Arc::new(CursedBufferNode::new(writer.tail.position + len, self.bufferstate.pruned.clone()));
+        let link = CursedBufferLink {
+            data,
+            node: node.clone(),
+        };
+        if writer.tail.next.set(link).is_err() {
+            //only the writer sets links and it holds the writer lock
+            unreachable!("tail of CursedBuffer was linked twice");
+        }
+        writer.tail = node;
+        drop(writer);
+        drop(spill);
+        self.bufferstate.notify_written_async.notify_waiters();
         Ok(())
     }
 
-    pub async fn awrite(&self, data: Vec<T>) -> Result<(), CursedBufferError> {
-        self.write(data)
+    /// Like [`CursedBuffer::write`], in spill mode the file I/O runs on a blocking thread and not on the executor
+    pub async fn awrite(&self, data: Vec<T>) -> Result<(), CursedBufferError> where T: Send + Sync + 'static {
+        if self.bufferstate.spill.is_none() {
+            return self.write(data);
+        }
+        let buffer = self.clone();
+        tokio::task::spawn_blocking(move || buffer.write(data)).await.map_err(|_| CursedBufferError::IoError)?
     }
 
 }
 
-impl<T> CursedBufferInternalState<T> {
-    fn sync_reader_states(&mut self) {
-        //we basically look for the lowest position of all readers and whether we can therefor remove some buffers
-        let mut min_pos = usize::MAX;
-        for r in self.all_readers.iter() {
-            if let Some(r) = r {
-                if r.position < min_pos {
-                    min_pos = r.position;
-                }
-            }
-        }
-        let mut can_delete = min_pos - self.first_read_position;
-        let mut call = false;
-        while !self.buffers.is_empty() && self.buffers[0].len() <= can_delete {
-            can_delete -= self.buffers[0].len();
-            self.first_read_position += self.buffers[0].len();
-            self.buffers.remove(0);
-            call = true;
-        }
-        if let Callback::Function(callback) = &self.callback_function {
-            if call {
-                callback();
-            }
-        }
-}
-}
-
 impl<T> Drop for CursedBufferReader<T> {
     fn drop(&mut self) {
-        let mut bufferstate = self.bufferstate.lock().unwrap();
-        bufferstate.all_readers[self.reader_in_buffer] = None;
-        bufferstate.sync_reader_states();
+        let mut readers = self.bufferstate.readers.lock().unwrap();
+        let slot = readers.slots[self.reader_in_buffer].take();
+        readers.free_slots.push(self.reader_in_buffer);
+        readers.active -= 1;
+        if readers.active == 0 {
+            //nobody is interested in the old data anymore, but data written from now on must be kept for future readers
+            readers.head = Some(self.bufferstate.writer.lock().unwrap().tail.clone());
+        }
+        //the slot is dropped after the lock is released, so the callback never runs while holding it
+        drop(readers);
+        drop(slot);
     }
 }
 
 
 impl<T> CursedBufferReader<T> {
     pub fn pos(&self) -> usize {
-        let bufferstate = self.bufferstate.lock().unwrap();
-        bufferstate.all_readers[self.reader_in_buffer].as_ref().unwrap().position
+        self.readerstate.position.load(Ordering::Acquire)
     }
 
     pub fn skip(&self, len:usize) {
-        let mut bufferstate = self.bufferstate.lock().unwrap();
-        bufferstate.all_readers[self.reader_in_buffer].as_mut().unwrap().position += len;
-        bufferstate.sync_reader_states();
+        let mut cursor = self.readerstate.cursor.lock().unwrap();
+        let position = self.readerstate.position.load(Ordering::Acquire) + len;
+        self.readerstate.position.store(position, Ordering::Release);
+        let next = CursedBufferNode::advance(cursor.clone(), position);
+        let old = std::mem::replace(&mut *cursor, next);
+        //moving the cursor may drop the last reference to old data, which calls the callback
+        drop(cursor);
+        drop(old);
     }
 
+    /// Returns the data after the reader position up to the end of the next chunk.
+    /// Fails with [`CursedBufferError::NotEnoughData`] while the writer has not written more and with
+    /// [`CursedBufferError::BufferClosed`] once the buffer is closed and everything was read
     pub fn next_chunk(&self) -> Result<CursedChunk<T>, CursedBufferError> {
-        let mut bufferstate = self.bufferstate.lock().unwrap();
-
-        //our own position is in internalstate.pos
-        //now we need to find the next buffer which holds that data
-        let internalstate = bufferstate.all_readers[self.reader_in_buffer].as_ref().unwrap();
-        let mut still_to_go = internalstate.position - bufferstate.first_read_position;
+        let mut cursor = self.readerstate.cursor.lock().unwrap();
+        let position = self.readerstate.position.load(Ordering::Acquire);
 
-        if bufferstate.buffers.len() == 0 {
-            return Err(CursedBufferError::NotEnoughData);
-        }
-
-        let mut i = 0;
-        while still_to_go >= bufferstate.buffers[i].len() {
-            still_to_go -= bufferstate.buffers[i].len();
-            i += 1;
-            if i >= bufferstate.buffers.len() {
-                return Err(CursedBufferError::NotEnoughData);
+        loop {
+            //load closed before looking at the next link, a write always happens before closing
+            let is_closed = self.bufferstate.is_closed.load(Ordering::Acquire);
+            let (r, next) = match cursor.next.get() {
+                None if is_closed => return Err(CursedBufferError::BufferClosed),
+                None => return Err(CursedBufferError::NotEnoughData),
+                Some(link) if link.node.position <= position => (None, link.node.clone()),
+                Some(link) => {
+                    let slice = link.data.load()?;
+                    self.readerstate.position.store(link.node.position, Ordering::Release);
+                    let r = CursedChunk {
+                        len: slice.len(),
+                        slice,
+                        pos: position - cursor.position,
+                    };
+                    (Some(r), link.node.clone())
+                }
+            };
+            let old = std::mem::replace(&mut *cursor, next);
+            if let Some(r) = r {
+                //moving the cursor may drop the last reference to old data, which calls the callback
+                drop(cursor);
+                drop(old);
+                return Ok(r);
             }
         }
-
-        bufferstate.all_readers[self.reader_in_buffer].as_mut().unwrap().position += bufferstate.buffers[i].len();
-
-        let r = CursedChunk{
-            slice: bufferstate.buffers[i].clone(),
-            pos: still_to_go,
-            len: bufferstate.buffers[i].len()
-        };
-        bufferstate.sync_reader_states();
-        Ok(r)
     }
 
+    /// Like [`CursedBufferReader::next_chunk`] but waits for data, it fails with [`CursedBufferError::BufferClosed`] at the end
     pub async fn anext_chunk(&self) -> Result<CursedChunk<T>, CursedBufferError> {
-        let n = self.bufferstate.lock().unwrap().notify_written_async.clone();
+        let n = self.bufferstate.notify_written_async.clone();
         loop {
             let n1 = n.notified();
             let chunk = self.next_chunk();
@@ -240,7 +455,7 @@ impl<T> Deref for CursedChunk<T> {
     }
 }
 
-impl<'a,T> CursedChunk<T> {
+impl<T> CursedChunk<T> {
     pub fn as_slice(&self) -> &[T] {
         &self.slice.as_ref()[self.pos..]
     }
@@ -248,7 +463,7 @@ impl<'a,T> CursedChunk<T> {
 
 #[cfg(test)]
 mod tests {
-    use std::{cell::{OnceCell, RefCell}, sync::RwLock};
+    use std::{ops::DerefMut, sync::RwLock, thread};
 
     use super::*;
 
@@ -281,6 +496,17 @@ mod tests {
         b.write(vec![1, 2, 3, 4, 5]).expect_err("");
     }
 
+    #[test]
+    fn test_read_after_close() {
+        let b = CursedBuffer::<u8>::new();
+        let r = b.reader(0);
+        assert_eq!(r.next_chunk().unwrap_err(), CursedBufferError::NotEnoughData);
+        b.write(vec![1, 2, 3]).unwrap();
+        b.close();
+        assert_eq!(r.next_chunk().unwrap().as_slice(), &[1, 2, 3]);
+        assert_eq!(r.next_chunk().unwrap_err(), CursedBufferError::BufferClosed);
+    }
+
     #[test]
     fn test_callback() {
         let mut b = CursedBuffer::<u8>::new();
@@ -296,4 +522,222 @@ mod tests {
         println!("{:?}",x);
         assert_eq!(*c.read().unwrap(), 1);
     }
+
+    #[test]
+    fn test_reader_slots_are_reused() {
+        let b = CursedBuffer::<u8>::new();
+        let long_lived = b.reader(0);
+        for _ in 0..1000 {
+            let r = b.reader(0);
+            assert_eq!(r.reader_in_buffer, 1);
+        }
+        assert_eq!(b.bufferstate.readers.lock().unwrap().slots.len(), 2);
+        drop(long_lived);
+        assert_eq!(b.bufferstate.readers.lock().unwrap().active, 0);
+    }
+
+    #[test]
+    fn test_slowest_reader_keeps_data() {
+        let b = CursedBuffer::<u8>::new();
+        b.write(vec![1, 2]).unwrap();
+        b.write(vec![3, 4]).unwrap();
+        let slow = b.reader(0);
+        let fast = b.reader(0);
+        fast.skip(4);
+        assert_eq!(b.min_position(), Some(0));
+
+        //a new reader can still start at the position of the slowest one
+        let late = b.reader(1);
+        assert_eq!(late.next_chunk().unwrap().as_slice(), &[2]);
+        assert_eq!(slow.next_chunk().unwrap().as_slice(), &[1, 2]);
+        drop(late);
+        assert_eq!(b.min_position(), Some(2));
+
+        //data in front of all readers is gone, so new readers are moved forward
+        let late = b.reader(0);
+        assert_eq!(late.pos(), 2);
+        assert_eq!(late.next_chunk().unwrap().as_slice(), &[3, 4]);
+    }
+
+    #[test]
+    fn test_data_is_kept_without_readers() {
+        let b = CursedBuffer::<u8>::new();
+        let r = b.reader(0);
+        b.write(vec![1, 2]).unwrap();
+        drop(r);
+        //everything written so far was only interesting for the dropped reader
+        b.write(vec![3, 4]).unwrap();
+        let r = b.reader(0);
+        assert_eq!(r.pos(), 2);
+        assert_eq!(r.next_chunk().unwrap().as_slice(), &[3, 4]);
+    }
+
+    #[test]
+    fn test_long_chain_drop() {
+        let b = CursedBuffer::<u8>::new();
+        for _ in 0..1_000_000 {
+            b.write(vec![1]).unwrap();
+        }
+        let r = b.reader(0);
+        r.skip(1_000_000);
+        assert_eq!(r.next_chunk().unwrap_err(), CursedBufferError::NotEnoughData);
+    }
+
+    /// Stress harness: one writer and several readers, some of them coming and going all the time.
+    /// Every reader must see every element exactly once and in order.
+    #[test]
+    fn test_stress_concurrent_readers() {
+        const CHUNKS: u32 = 20_000;
+        let mut b = CursedBuffer::<u32>::new();
+        let pruned = Arc::new(AtomicUsize::new(0));
+        let pruned2 = pruned.clone();
+        b.set_callback(Box::new(move || {
+            pruned2.fetch_add(1, Ordering::Relaxed);
+        }));
+
+        let readers: Vec<_> = (0..4).map(|_| b.reader(0)).collect();
+        let writer = {
+            let b = b.clone();
+            thread::spawn(move || {
+                let mut next = 0u32;
+                for i in 0..CHUNKS {
+                    let len = i % 7;
+                    b.write((next..next + len).collect()).unwrap();
+                    next += len;
+                }
+                b.close();
+                next
+            })
+        };
+        let churn = {
+            let b = b.clone();
+            thread::spawn(move || {
+                let mut created = 0;
+                while !b.bufferstate.is_closed.load(Ordering::Acquire) {
+                    let r = b.reader(0);
+                    let start = r.pos() as u32;
+                    if let Ok(chunk) = r.next_chunk()
+                        && let Some(first) = chunk.as_slice().first() {
+                        assert_eq!(*first, start);
+                    }
+                    created += 1;
+                }
+                created
+            })
+        };
+        let handles: Vec<_> = readers.into_iter().map(|r| {
+            thread::spawn(move || {
+                let mut expected = 0u32;
+                loop {
+                    match r.next_chunk() {
+                        Ok(chunk) => {
+                            for v in chunk.as_slice() {
+                                assert_eq!(*v, expected);
+                                expected += 1;
+                            }
+                        }
+                        Err(CursedBufferError::NotEnoughData) => thread::yield_now(),
+                        Err(CursedBufferError::BufferClosed) => return expected,
+                        Err(e) => panic!("unexpected error {e}"),
+                    }
+                }
+            })
+        }).collect();
+
+        let total = writer.join().unwrap();
+        for h in handles {
+            assert_eq!(h.join().unwrap(), total);
+        }
+        churn.join().unwrap();
+
+        //all readers are gone, so there is nothing to keep and all slots are free
+        let readers = b.bufferstate.readers.lock().unwrap();
+        assert_eq!(readers.active, 0);
+        assert!(readers.slots.len() <= 5);
+        assert!(pruned.load(Ordering::Relaxed) > 0);
+    }
+
+    #[tokio::test]
+    async fn test_async_reader_wakes_up() {
+        let b = CursedBuffer::<u8>::new();
+        let r = b.reader(0);
+        let b2 = b.clone();
+        let h = tokio::spawn(async move {
+            tokio::task::yield_now().await;
+            b2.write(vec![7]).unwrap();
+            b2.close();
+        });
+        assert_eq!(r.anext_chunk().await.unwrap().as_slice(), &[7]);
+        assert_eq!(r.anext_chunk().await.unwrap_err(), CursedBufferError::BufferClosed);
+        h.await.unwrap();
+    }
+
+    fn spill_files(dir: &PathBuf) -> usize {
+        std::fs::read_dir(dir).map(|d| d.count()).unwrap_or(0)
+    }
+
+    #[test]
+    fn test_spill_to_disk() {
+        let mut config = SpillConfig::new(16);
+        config.segment_size = 32;
+        let mut b = CursedBuffer::<u8>::with_spill(config).unwrap();
+        let pruned = Arc::new(AtomicUsize::new(0));
+        let p2 = pruned.clone();
+        b.set_callback(Box::new(move || {
+            p2.fetch_add(1, Ordering::Relaxed);
+        }));
+        let dir = b.spill_directory().unwrap();
+
+        let slow = b.reader(0);
+        let fast = b.reader(0);
+        for i in 0..10u8 {
+            b.write(vec![i; 8]).unwrap();
+            assert_eq!(fast.next_chunk().unwrap().as_slice(), &[i; 8]);
+            assert!(b.memory_used().unwrap() <= 16);
+        }
+        //the two newest chunks fit the budget, the other 64 bytes went to two segments of 32 bytes
+        assert_eq!(spill_files(&dir), 2);
+        assert_eq!(pruned.load(Ordering::Relaxed), 0);
+
+        slow.skip(4);
+        assert_eq!(slow.next_chunk().unwrap().as_slice(), &[0; 4]);
+        for i in 1..10u8 {
+            assert_eq!(slow.next_chunk().unwrap().as_slice(), &[i; 8]);
+        }
+        //all readers passed the spilled data
+        assert_eq!(spill_files(&dir), 0);
+        assert!(pruned.load(Ordering::Relaxed) > 0);
+
+        drop(slow);
+        drop(fast);
+        drop(b);
+        assert!(!dir.exists());
+    }
+
+    #[tokio::test]
+    async fn test_failed_spill_writes_nothing() {
+        let b = CursedBuffer::<u8>::with_spill(SpillConfig::new(8)).unwrap();
+        let dir = b.spill_directory().unwrap();
+        let r = b.reader(0);
+        b.write(vec![1; 8]).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(b.awrite(vec![2; 8]).await.unwrap_err(), CursedBufferError::IoError);
+        assert_eq!(b.memory_used(), Some(8));
+        std::fs::create_dir(&dir).unwrap();
+        //the retry is the only copy of the data
+        b.awrite(vec![2; 8]).await.unwrap();
+        assert_eq!(r.next_chunk().unwrap().as_slice(), &[1; 8]);
+        assert_eq!(r.next_chunk().unwrap().as_slice(), &[2; 8]);
+        assert_eq!(r.next_chunk().unwrap_err(), CursedBufferError::NotEnoughData);
+    }
+
+    #[test]
+    fn test_spill_chars() {
+        let b = CursedBuffer::<char>::with_spill(SpillConfig::new(0)).unwrap();
+        b.write("grüße".chars().collect()).unwrap();
+        assert_eq!(b.memory_used(), Some(0));
+        let r = b.reader(0);
+        assert_eq!(r.next_chunk().unwrap().iter().collect::<String>(), "grüße");
+        assert_eq!(CursedBuffer::<char>::new().spill_directory(), None);
+    }
 }
@@ -0,0 +1,242 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::collections::VecDeque;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+
+use super::CursedBufferError;
+
+static SPILL_DIR_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Element types that can be written to disk when a [`super::CursedBuffer`] runs out of its memory budget
+pub trait Spillable: Sized {
+    fn spill(data: &[Self], out: &mut Vec<u8>);
+    fn restore(bytes: &[u8]) -> Vec<Self>;
+}
+
+impl Spillable for u8 {
+    fn spill(data: &[Self], out: &mut Vec<u8>) {
+        out.extend_from_slice(data);
+    }
+
+    fn restore(bytes: &[u8]) -> Vec<Self> {
+        bytes.to_vec()
+    }
+}
+
+impl Spillable for char {
+    fn spill(data: &[Self], out: &mut Vec<u8>) {
+        for c in data {
+            out.extend_from_slice(&(*c as u32).to_le_bytes());
+        }
+    }
+
+    fn restore(bytes: &[u8]) -> Vec<Self> {
+        bytes.chunks_exact(4)
+            .map(|b| char::from_u32(u32::from_le_bytes([b[0], b[1], b[2], b[3]])).unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect()
+    }
+}
+
+/// Configuration of the spill-to-disk mode of a [`super::CursedBuffer`]
+#[derive(Debug, Clone)]
+pub struct SpillConfig {
+    /// number of bytes the buffer may keep in memory before old chunks are written to disk
+    pub memory_budget: usize,
+    /// directory in which a private spill directory is created, defaults to the system temp directory
+    pub directory: Option<PathBuf>,
+    /// spilled chunks are appended to segment files of roughly this size. A segment file is deleted once all readers passed all of its chunks
+    pub segment_size: usize,
+}
+
+impl SpillConfig {
+    pub fn new(memory_budget: usize) -> SpillConfig {
+        SpillConfig {
+            memory_budget,
+            directory: None,
+            segment_size: 64 * 1024 * 1024,
+        }
+    }
+}
+
+/// State shared by the buffer and all of its spillable chunks
+pub(super) struct SpillShared<T> {
+    directory: PathBuf,
+    memory_used: AtomicUsize,
+    restore: fn(&[u8]) -> Vec<T>,
+}
+
+impl<T> Drop for SpillShared<T> {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.directory);
+    }
+}
+
+struct SpillSegment {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl Drop for SpillSegment {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+enum ChunkStorage<T> {
+    Memory(Arc<Vec<T>>),
+    Disk { segment: Arc<SpillSegment>, offset: u64, bytes: usize },
+}
+
+/// A chunk that may move from memory to disk while readers still need it
+pub(super) struct SpillableChunk<T> {
+    storage: Mutex<ChunkStorage<T>>,
+    shared: Arc<SpillShared<T>>,
+}
+
+impl<T> SpillableChunk<T> {
+    pub(super) fn load(&self) -> Result<Arc<Vec<T>>, CursedBufferError> {
+        let storage = self.storage.lock().unwrap();
+        match &*storage {
+            ChunkStorage::Memory(data) => Ok(data.clone()),
+            ChunkStorage::Disk { segment, offset, bytes } => {
+                let mut raw = vec![0; *bytes];
+                let mut file = segment.file.lock().unwrap();
+                file.seek(SeekFrom::Start(*offset)).map_err(|_| CursedBufferError::IoError)?;
+                file.read_exact(&mut raw).map_err(|_| CursedBufferError::IoError)?;
+                //read back data is handed out but not cached, so slow readers don't blow the memory budget
+                Ok(Arc::new((self.shared.restore)(&raw)))
+            }
+        }
+    }
+}
+
+impl<T> Drop for SpillableChunk<T> {
+    fn drop(&mut self) {
+        if let ChunkStorage::Memory(data) = &*self.storage.lock().unwrap() {
+            self.shared.memory_used.fetch_sub(std::mem::size_of_val(data.as_slice()), Ordering::AcqRel);
+        }
+        //a spilled chunk releases its segment, the last chunk of a segment deletes the file
+    }
+}
+
+/// The writer side of the spill mode, it has a lock of its own that writers take before the writer lock
+pub(super) struct SpillWriter<T> {
+    config: SpillConfig,
+    shared: Arc<SpillShared<T>>,
+    spill: fn(&[T], &mut Vec<u8>),
+    in_memory: VecDeque<Weak<SpillableChunk<T>>>,
+    segment: Option<(Weak<SpillSegment>, u64)>, //weak, so the segment is deleted as soon as its chunks are gone
+    segment_counter: usize,
+}
+
+impl<T> Debug for SpillWriter<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("SpillWriter")
+            .field("config", &self.config)
+            .field("directory", &self.shared.directory)
+            .field("memory_used", &self.shared.memory_used.load(Ordering::Acquire))
+            .finish()
+    }
+}
+
+impl<T> SpillWriter<T> {
+    pub(super) fn new(config: SpillConfig) -> Result<SpillWriter<T>, CursedBufferError> where T: Spillable {
+        let parent = config.directory.clone().unwrap_or_else(std::env::temp_dir);
+        let directory = parent.join(format!("cursedbuffer-{}-{}", std::process::id(), SPILL_DIR_COUNTER.fetch_add(1, Ordering::Relaxed)));
+        std::fs::create_dir_all(&directory).map_err(|_| CursedBufferError::IoError)?;
+        Ok(SpillWriter {
+            config,
+            shared: Arc::new(SpillShared {
+                directory,
+                memory_used: AtomicUsize::new(0),
+                restore: T::restore,
+            }),
+            spill: T::spill,
+            in_memory: VecDeque::new(),
+            segment: None,
+            segment_counter: 0,
+        })
+    }
+
+    pub(super) fn directory(&self) -> &PathBuf {
+        &self.shared.directory
+    }
+
+    pub(super) fn memory_used(&self) -> usize {
+        self.shared.memory_used.load(Ordering::Acquire)
+    }
+
+    /// Wraps newly written data, the caller must call [`SpillWriter::enforce_budget`] before linking it into the buffer
+    pub(super) fn track(&mut self, data: Vec<T>) -> Arc<SpillableChunk<T>> {
+        self.shared.memory_used.fetch_add(std::mem::size_of_val(data.as_slice()), Ordering::AcqRel);
+        let chunk = Arc::new(SpillableChunk {
+            storage: Mutex::new(ChunkStorage::Memory(Arc::new(data))),
+            shared: self.shared.clone(),
+        });
+        //chunks are pruned in order, drop entries of chunks all readers have passed so the queue doesn't grow forever
+        while self.in_memory.front().is_some_and(|c| c.strong_count() == 0) {
+            self.in_memory.pop_front();
+        }
+        self.in_memory.push_back(Arc::downgrade(&chunk));
+        chunk
+    }
+
+    /// Spills the oldest chunks still in memory until the memory budget is met
+    pub(super) fn enforce_budget(&mut self) -> Result<(), CursedBufferError> {
+        while self.memory_used() > self.config.memory_budget {
+            let Some(chunk) = self.in_memory.pop_front() else {
+                break;
+            };
+            //chunks all readers have passed are already gone
+            if let Some(chunk) = chunk.upgrade()
+                && let Err(e) = self.spill_chunk(&chunk) {
+                //the chunk stays in memory and is spilled first next time
+                self.in_memory.push_front(Arc::downgrade(&chunk));
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    fn spill_chunk(&mut self, chunk: &SpillableChunk<T>) -> Result<(), CursedBufferError> {
+        let mut storage = chunk.storage.lock().unwrap();
+        let ChunkStorage::Memory(data) = &*storage else {
+            return Ok(());
+        };
+        let mut raw = Vec::new();
+        (self.spill)(data, &mut raw);
+        let memory = std::mem::size_of_val(data.as_slice());
+
+        let (segment, offset) = self.segment_for(raw.len())?;
+        {
+            let mut file = segment.file.lock().unwrap();
+            file.seek(SeekFrom::Start(offset)).map_err(|_| CursedBufferError::IoError)?;
+            file.write_all(&raw).map_err(|_| CursedBufferError::IoError)?;
+        }
+        *storage = ChunkStorage::Disk { segment, offset, bytes: raw.len() };
+        self.shared.memory_used.fetch_sub(memory, Ordering::AcqRel);
+        Ok(())
+    }
+
+    fn segment_for(&mut self, bytes: usize) -> Result<(Arc<SpillSegment>, u64), CursedBufferError> {
+        if let Some((segment, offset)) = &mut self.segment
+            && *offset < self.config.segment_size as u64
+            && let Some(segment) = segment.upgrade() {
+            let r = (segment, *offset);
+            *offset += bytes as u64;
+            return Ok(r);
+        }
+        let path = self.shared.directory.join(format!("{}.spill", self.segment_counter));
+        self.segment_counter += 1;
+        let file = OpenOptions::new().read(true).write(true).create_new(true).open(&path)
+            .map_err(|_| CursedBufferError::IoError)?;
+        let segment = Arc::new(SpillSegment { path, file: Mutex::new(file) });
+        self.segment = Some((Arc::downgrade(&segment), bytes as u64));
+        Ok((segment, 0))
+    }
+}
@@ -1,6 +1,7 @@
 #[derive(Debug)]
 pub enum TextDecoderError {
     BytesLeftAfterFinish,
+    InvalidSequence, //the bytes are not valid in this encoding
 }
 
 pub struct TextDecoderResult {
@@ -19,44 +20,129 @@ pub trait TextDecoder {
     }
 }
 
+/// Decodes `bytes` completely, an incomplete sequence at the end is an error
+pub fn decode_to_string(decoder: &dyn TextDecoder, bytes: &[u8]) -> Result<String, TextDecoderError> {
+    //no encoding produces more chars than bytes
+    let mut chars = vec!['\0'; bytes.len()];
+    let result = decoder.decode(bytes, &mut chars)?;
+    if result.consumed_bytes < bytes.len() {
+        //decoding the rest reports invalid bytes, otherwise the input ends in an incomplete sequence
+        decoder.decode(&bytes[result.consumed_bytes..], &mut chars)?;
+        return Err(TextDecoderError::BytesLeftAfterFinish);
+    }
+    Ok(chars[..result.generated_chars].iter().collect())
+}
+
 // Implementations
 
+/// Decodes complete sequences only, an incomplete sequence at the end is left for the next call
 pub struct UTF8Decoder {
 }
 
+impl UTF8Decoder {
+    pub fn new() -> UTF8Decoder {
+        UTF8Decoder {}
+    }
+}
+
+impl Default for UTF8Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TextDecoder for UTF8Decoder {
     fn decode(&self, bytes: &[u8], chars: &mut [char]) -> Result<TextDecoderResult, TextDecoderError> {
+        let valid = match std::str::from_utf8(bytes) {
+            Ok(s) => s,
+            //invalid bytes are reported once everything before them was decoded
+            Err(e) if e.valid_up_to() == 0 && e.error_len().is_some() => return Err(TextDecoderError::InvalidSequence),
+            Err(e) => std::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap(), //checked above
+        };
+        let mut consumed_bytes = 0;
+        let mut generated_chars = 0;
+        for (c, target) in valid.chars().zip(chars.iter_mut()) {
+            *target = c;
+            consumed_bytes += c.len_utf8();
+            generated_chars += 1;
+        }
         Ok(
             TextDecoderResult {
-                consumed_bytes: 0,
-                generated_chars: 0,
+                consumed_bytes,
+                generated_chars,
             })
     }
 }
 
+fn decode_utf16(bytes: &[u8], chars: &mut [char], unit: fn([u8; 2]) -> u16) -> Result<TextDecoderResult, TextDecoderError> {
+    let mut consumed_bytes = 0;
+    let mut generated_chars = 0;
+    while generated_chars < chars.len() {
+        let Some(first) = bytes[consumed_bytes..].first_chunk::<2>() else { break };
+        let first = unit(*first);
+        let (c, used) = match first {
+            0xD800..=0xDBFF => {
+                let Some(second) = bytes[consumed_bytes + 2..].first_chunk::<2>() else { break };
+                let second = unit(*second);
+                if !(0xDC00..=0xDFFF).contains(&second) {
+                    return Err(TextDecoderError::InvalidSequence);
+                }
+                let c = 0x10000 + ((first as u32 - 0xD800) << 10) + (second as u32 - 0xDC00);
+                (char::from_u32(c).ok_or(TextDecoderError::InvalidSequence)?, 4)
+            }
+            0xDC00..=0xDFFF => return Err(TextDecoderError::InvalidSequence),
+            _ => (char::from_u32(first as u32).ok_or(TextDecoderError::InvalidSequence)?, 2),
+        };
+        chars[generated_chars] = c;
+        generated_chars += 1;
+        consumed_bytes += used;
+    }
+    Ok(
+        TextDecoderResult {
+            consumed_bytes,
+            generated_chars,
+        })
+}
+
 pub struct UTF16LEDecoder {
 }
 
+impl UTF16LEDecoder {
+    pub fn new() -> UTF16LEDecoder {
+        UTF16LEDecoder {}
+    }
+}
+
+impl Default for UTF16LEDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TextDecoder for UTF16LEDecoder {
     fn decode(&self, bytes: &[u8], chars: &mut [char]) -> Result<TextDecoderResult, TextDecoderError> {
-        Ok(
-            TextDecoderResult {
-                consumed_bytes: 0,
-                generated_chars: 0,
-            })
+        decode_utf16(bytes, chars, u16::from_le_bytes)
     }
 }
 
 pub struct UTF16BEDecoder {
 }
 
+impl UTF16BEDecoder {
+    pub fn new() -> UTF16BEDecoder {
+        UTF16BEDecoder {}
+    }
+}
+
+impl Default for UTF16BEDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TextDecoder for UTF16BEDecoder {
     fn decode(&self, bytes: &[u8], chars: &mut [char]) -> Result<TextDecoderResult, TextDecoderError> {
-        Ok(
-            TextDecoderResult {
-                consumed_bytes: 0,
-                generated_chars: 0,
-            })
+        decode_utf16(bytes, chars, u16::from_be_bytes)
     }
 }
 
@@ -69,6 +155,12 @@ impl ASCIIDecoder {
     }
 }
 
+impl Default for ASCIIDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TextDecoder for ASCIIDecoder {
     
     fn decode(&self, bytes: &[u8], chars: &mut [char]) -> Result<TextDecoderResult, TextDecoderError> {
@@ -107,4 +199,37 @@ mod tests {
         assert_eq!(result.consumed_bytes, 0);
         assert_eq!(result.generated_chars, 0);
     }
+
+    #[test]
+    fn utf8_multibyte_and_incomplete() {
+        let decoder = UTF8Decoder::new();
+        let bytes = "grüße €".as_bytes();
+        let mut chars = ['\0'; 16];
+        //the euro sign is cut in the middle
+        let result = decoder.decode(&bytes[..bytes.len() - 1], &mut chars).unwrap();
+        assert_eq!(result.consumed_bytes, bytes.len() - 3);
+        assert_eq!(result.generated_chars, 6);
+        let result = decoder.decode(&bytes[result.consumed_bytes..], &mut chars).unwrap();
+        assert_eq!((result.consumed_bytes, result.generated_chars), (3, 1));
+        assert_eq!(chars[0], '€');
+
+        assert_eq!(decode_to_string(&decoder, bytes).unwrap(), "grüße €");
+        assert!(matches!(decode_to_string(&decoder, &bytes[..bytes.len() - 1]), Err(TextDecoderError::BytesLeftAfterFinish)));
+        assert!(matches!(decode_to_string(&decoder, &[b'a', 0xff]), Err(TextDecoderError::InvalidSequence)));
+    }
+
+    #[test]
+    fn utf16_surrogates() {
+        let text = "a€😀";
+        let le: Vec<u8> = text.encode_utf16().flat_map(|u| u.to_le_bytes()).collect();
+        let be: Vec<u8> = text.encode_utf16().flat_map(|u| u.to_be_bytes()).collect();
+        assert_eq!(decode_to_string(&UTF16LEDecoder::new(), &le).unwrap(), text);
+        assert_eq!(decode_to_string(&UTF16BEDecoder::new(), &be).unwrap(), text);
+
+        //a surrogate pair is only decoded once complete
+        let mut chars = ['\0'; 4];
+        let result = UTF16LEDecoder::new().decode(&le[..6], &mut chars).unwrap();
+        assert_eq!((result.consumed_bytes, result.generated_chars), (4, 2));
+        assert!(matches!(decode_to_string(&UTF16LEDecoder::new(), &[0x00, 0xdc]), Err(TextDecoderError::InvalidSequence)));
+    }
 }
@@ -1,4 +1,5 @@
 #![allow(dead_code)]
 #![allow(unused_imports)]
 
+#[allow(clippy::module_inception)]
 pub mod decoders;
\ No newline at end of file
@@ -28,6 +28,12 @@ impl<'a,K,V> Iterator for OrderedBagIterator<'a, K,V> {
     }
 }
 
+impl<K,V> Default for OrderedBag<K,V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<K,V> OrderedBag<K,V> {
     pub fn new() -> OrderedBag<K,V> {
         OrderedBag {
@@ -66,12 +72,9 @@ impl<K,V> OrderedBag<K,V> {
 
     pub fn remove(&mut self, key: K) where K: PartialEq {
         let index = self.keys.iter().position(|x| *x == key);
-        match index {
-            Some(i) => {
-                self.keys.remove(i);
-                self.entries.remove(i);
-            },
-            None => ()
+        if let Some(i) = index {
+            self.keys.remove(i);
+            self.entries.remove(i);
         }
     }
 
@@ -79,8 +82,8 @@ impl<K,V> OrderedBag<K,V> {
         self.entries.len()
     }
 
-    pub fn iter(&self) -> OrderedBagIterator<K,V> {
-        OrderedBagIterator { dict: &self, current: 0 }
+    pub fn iter(&self) -> OrderedBagIterator<'_,K,V> {
+        OrderedBagIterator { dict: self, current: 0 }
     }
 
 }
@@ -0,0 +1,384 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+use std::fs::File;
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Arc;
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::cursedbuffer::CursedBuffer;
+use crate::decoders::decoders::{TextDecoder, TextDecoderError, UTF8Decoder};
+use super::reader::{ChunkData, Readable, ReadableChunk, ReaderError};
+
+const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Adapter from any `std::io::Read`. Data is read in chunks of `chunk_size` bytes, skipping reads and discards.
+pub struct ReadReadable<R> {
+    reader: R,
+    chunk_size: usize,
+    current_chunk: Option<Arc<Vec<u8>>>,
+    current_chunk_pos: usize,
+    pos: usize,
+}
+
+impl<R: Read> ReadReadable<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_chunk_size(reader, DEFAULT_CHUNK_SIZE)
+    }
+
+    pub fn with_chunk_size(reader: R, chunk_size: usize) -> Self {
+        ReadReadable {
+            reader,
+            chunk_size: chunk_size.max(1),
+            current_chunk: None,
+            current_chunk_pos: 0,
+            pos: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Makes sure there is unread data in current_chunk, returns false at the end of the stream
+    fn fill(&mut self) -> Result<bool, ReaderError> {
+        if let Some(chunk) = &self.current_chunk
+            && self.current_chunk_pos < chunk.len() {
+            return Ok(true);
+        }
+        let mut chunk = vec![0; self.chunk_size];
+        let read = loop {
+            match self.reader.read(&mut chunk) {
+                Ok(read) => break read,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(ReaderError::IO(e)),
+            }
+        };
+        if read == 0 {
+            self.current_chunk = None;
+            return Ok(false);
+        }
+        chunk.truncate(read);
+        self.current_chunk = Some(Arc::new(chunk));
+        self.current_chunk_pos = 0;
+        Ok(true)
+    }
+
+    /// Number of bytes already read from the underlying reader but not handed out
+    fn buffered(&self) -> usize {
+        self.current_chunk.as_ref().map_or(0, |c| c.len() - self.current_chunk_pos)
+    }
+}
+
+impl<R: Read> Readable<u8> for ReadReadable<R> {
+    fn read_next(&mut self) -> Result<u8, ReaderError> {
+        if !self.fill()? {
+            return Err(ReaderError::EOF);
+        }
+        let r = self.current_chunk.as_ref().unwrap()[self.current_chunk_pos]; //fill guarantees a chunk
+        self.current_chunk_pos += 1;
+        self.pos += 1;
+        Ok(r)
+    }
+
+    fn skip(&mut self, skipped: usize) -> Result<usize, ReaderError> {
+        let mut remaining = skipped;
+        while remaining > 0 && self.fill()? {
+            let n = remaining.min(self.buffered());
+            self.current_chunk_pos += n;
+            self.pos += n;
+            remaining -= n;
+        }
+        Ok(skipped - remaining)
+    }
+
+    fn read_chunk(&mut self) -> Result<ReadableChunk<u8>, ReaderError> {
+        if !self.fill()? {
+            return Err(ReaderError::EOF);
+        }
+        let chunk = self.current_chunk.take().unwrap(); //fill guarantees a chunk
+        self.pos += chunk.len() - self.current_chunk_pos;
+        Ok(ReadableChunk {
+            pos: self.current_chunk_pos,
+            len: chunk.len(),
+            chunk: ChunkData::Vec(chunk),
+        })
+    }
+
+    fn pos(&self) -> Option<usize> {
+        Some(self.pos)
+    }
+
+    fn len(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Text from any `std::io::Read` as characters, decoded chunk by chunk with a [`TextDecoder`]. A character split
+/// between two chunks is completed by the next one, bytes the decoder rejects fail with an `InvalidData` error.
+pub struct TextReadable<R> {
+    reader: R,
+    decoder: Box<dyn TextDecoder>,
+    chunk_size: usize,
+    pending: Vec<u8>, //bytes of a character split between chunks
+    chars: Vec<char>,
+    chars_pos: usize,
+    pos: usize,
+}
+
+impl<R: Read> TextReadable<R> {
+    pub fn new(reader: R, decoder: Box<dyn TextDecoder>) -> Self {
+        Self::with_chunk_size(reader, decoder, DEFAULT_CHUNK_SIZE)
+    }
+
+    pub fn utf8(reader: R) -> Self {
+        Self::new(reader, Box::new(UTF8Decoder::new()))
+    }
+
+    pub fn with_chunk_size(reader: R, decoder: Box<dyn TextDecoder>, chunk_size: usize) -> Self {
+        TextReadable {
+            reader,
+            decoder,
+            chunk_size: chunk_size.max(1),
+            pending: Vec::new(),
+            chars: Vec::new(),
+            chars_pos: 0,
+            pos: 0,
+        }
+    }
+
+    /// Makes sure there are unread characters, returns false at the end of the stream
+    fn fill(&mut self) -> Result<bool, ReaderError> {
+        if self.chars_pos < self.chars.len() {
+            return Ok(true);
+        }
+        let mut chunk = vec![0; self.chunk_size];
+        while self.chars_pos == self.chars.len() {
+            let read = match self.reader.read(&mut chunk) {
+                Ok(read) => read,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(ReaderError::IO(e)),
+            };
+            if read == 0 {
+                if !self.pending.is_empty() {
+                    return Err(invalid_text(TextDecoderError::BytesLeftAfterFinish));
+                }
+                return Ok(false);
+            }
+            self.pending.extend_from_slice(&chunk[..read]);
+            //no encoding produces more chars than bytes
+            self.chars = vec!['\0'; self.pending.len()];
+            let result = self.decoder.decode(&self.pending, &mut self.chars).map_err(invalid_text)?;
+            self.chars.truncate(result.generated_chars);
+            self.chars_pos = 0;
+            self.pending.drain(..result.consumed_bytes);
+        }
+        Ok(true)
+    }
+}
+
+fn invalid_text(e: TextDecoderError) -> ReaderError {
+    ReaderError::IO(std::io::Error::new(ErrorKind::InvalidData, format!("invalid text: {e:?}")))
+}
+
+impl<R: Read> Readable<char> for TextReadable<R> {
+    fn read_next(&mut self) -> Result<char, ReaderError> {
+        if !self.fill()? {
+            return Err(ReaderError::EOF);
+        }
+        let r = self.chars[self.chars_pos];
+        self.chars_pos += 1;
+        self.pos += 1;
+        Ok(r)
+    }
+
+    fn skip(&mut self, skipped: usize) -> Result<usize, ReaderError> {
+        let mut remaining = skipped;
+        while remaining > 0 && self.fill()? {
+            let n = remaining.min(self.chars.len() - self.chars_pos);
+            self.chars_pos += n;
+            self.pos += n;
+            remaining -= n;
+        }
+        Ok(skipped - remaining)
+    }
+
+    fn read_chunk(&mut self) -> Result<ReadableChunk<char>, ReaderError> {
+        if !self.fill()? {
+            return Err(ReaderError::EOF);
+        }
+        let chunk = std::mem::take(&mut self.chars);
+        self.pos += chunk.len() - self.chars_pos;
+        let pos = std::mem::replace(&mut self.chars_pos, 0);
+        Ok(ReadableChunk {
+            pos,
+            len: chunk.len(),
+            chunk: ChunkData::Vec(Arc::new(chunk)),
+        })
+    }
+
+    fn pos(&self) -> Option<usize> {
+        Some(self.pos)
+    }
+
+    fn len(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Reads a local file. Skipping seeks instead of reading and the length is known upfront.
+pub struct FileReadable {
+    inner: ReadReadable<File>,
+    len: usize,
+}
+
+impl FileReadable {
+    pub fn open<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        Self::new(File::open(path)?)
+    }
+
+    /// Reading starts at the current position of `file`
+    pub fn new(file: File) -> std::io::Result<Self> {
+        Self::with_chunk_size(file, DEFAULT_CHUNK_SIZE)
+    }
+
+    pub fn with_chunk_size(mut file: File, chunk_size: usize) -> std::io::Result<Self> {
+        let len = file.metadata()?.len() as usize;
+        let pos = file.stream_position()? as usize;
+        let mut inner = ReadReadable::with_chunk_size(file, chunk_size);
+        inner.pos = pos;
+        Ok(FileReadable { inner, len })
+    }
+}
+
+impl Readable<u8> for FileReadable {
+    fn read_next(&mut self) -> Result<u8, ReaderError> {
+        self.inner.read_next()
+    }
+
+    fn skip(&mut self, skipped: usize) -> Result<usize, ReaderError> {
+        let buffered = self.inner.buffered();
+        if skipped <= buffered {
+            return self.inner.skip(skipped);
+        }
+        let target = (self.inner.pos + skipped).min(self.len.max(self.inner.pos));
+        self.inner.reader.seek(SeekFrom::Start(target as u64)).map_err(ReaderError::IO)?;
+        self.inner.current_chunk = None;
+        let skipped = target - self.inner.pos;
+        self.inner.pos = target;
+        Ok(skipped)
+    }
+
+    fn read_chunk(&mut self) -> Result<ReadableChunk<u8>, ReaderError> {
+        self.inner.read_chunk()
+    }
+
+    fn pos(&self) -> Option<usize> {
+        self.inner.pos()
+    }
+
+    fn len(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+/// Adapter from a `tokio::io::AsyncRead`: copies everything into `buffer` in chunks of up to `chunk_size` bytes
+/// and closes the buffer at the end of the stream. Readers of the buffer, e.g. a
+/// [`super::reader::CursedBufferReadable`], see the data as soon as it arrives.
+/// Returns the number of bytes copied.
+pub async fn pump_async_read<R: AsyncRead + Unpin>(mut reader: R, buffer: &CursedBuffer<u8>, chunk_size: usize) -> std::io::Result<usize> {
+    let mut total = 0;
+    loop {
+        let mut chunk = vec![0; chunk_size.max(1)];
+        let read = reader.read(&mut chunk).await?;
+        if read == 0 {
+            buffer.close();
+            return Ok(total);
+        }
+        chunk.truncate(read);
+        total += read;
+        buffer.awrite(chunk).await.map_err(std::io::Error::other)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::readers::reader::CursedBufferReadable;
+
+    fn file_with(data: &[u8]) -> tempfile::NamedTempFile {
+        let mut f = tempfile::NamedTempFile::new().unwrap();
+        f.write_all(data).unwrap();
+        f.flush().unwrap();
+        f
+    }
+
+    #[test]
+    fn test_read_readable() {
+        let data: Vec<u8> = (0..100).collect();
+        let mut r = ReadReadable::with_chunk_size(&data[..], 16);
+        assert_eq!(r.read_next().unwrap(), 0);
+        assert_eq!(r.skip(20).unwrap(), 20);
+        assert_eq!(r.read_next().unwrap(), 21);
+        let c = r.read_chunk().unwrap();
+        assert_eq!(c.as_slice(), &[22, 23, 24, 25, 26, 27, 28, 29, 30, 31]);
+        assert_eq!(r.pos(), Some(32));
+        assert_eq!(r.skip(1000).unwrap(), 68);
+        assert!(matches!(r.read_next(), Err(ReaderError::EOF)));
+    }
+
+    #[test]
+    fn test_text_readable() {
+        //chunks of 3 bytes split the two byte characters
+        let text = "grüße, ünd";
+        let mut r = TextReadable::with_chunk_size(text.as_bytes(), Box::new(UTF8Decoder::new()), 3);
+        assert_eq!(r.read_next().unwrap(), 'g');
+        assert_eq!(r.skip(3).unwrap(), 3);
+        let mut rest = String::new();
+        while let Ok(c) = r.read_chunk() {
+            rest.extend(c.as_slice());
+        }
+        assert_eq!(rest, "e, ünd");
+        assert_eq!(r.pos(), Some(10));
+
+        let mut r = TextReadable::utf8(&[b'a', 0xc3][..]);
+        assert_eq!(r.read_next().unwrap(), 'a');
+        assert!(matches!(r.read_next(), Err(ReaderError::IO(e)) if e.kind() == ErrorKind::InvalidData));
+        let mut r = TextReadable::utf8(&[b'a', 0xff, b'b'][..]);
+        assert_eq!(r.read_next().unwrap(), 'a');
+        assert!(matches!(r.read_next(), Err(ReaderError::IO(e)) if e.kind() == ErrorKind::InvalidData));
+    }
+
+    #[test]
+    fn test_file_readable_seeks() {
+        let data: Vec<u8> = (0..=255).collect();
+        let f = file_with(&data);
+        let mut r = FileReadable::with_chunk_size(File::open(f.path()).unwrap(), 8).unwrap();
+        assert_eq!(r.len(), Some(256));
+        assert_eq!(r.read_next().unwrap(), 0);
+        assert_eq!(r.skip(3).unwrap(), 3);
+        assert_eq!(r.read_next().unwrap(), 4);
+        //leaves the buffered chunk, so this is a seek
+        assert_eq!(r.skip(195).unwrap(), 195);
+        assert_eq!(r.pos(), Some(200));
+        assert_eq!(r.read_next().unwrap(), 200);
+        let c = r.read_chunk().unwrap();
+        assert_eq!(c.as_slice(), &[201, 202, 203, 204, 205, 206, 207]);
+        assert_eq!(r.skip(100).unwrap(), 48);
+        assert!(matches!(r.read_chunk(), Err(ReaderError::EOF)));
+    }
+
+    #[tokio::test]
+    async fn test_pump_async_read() {
+        let data: Vec<u8> = (0..100).collect();
+        let buffer = CursedBuffer::<u8>::new();
+        let mut readable = CursedBufferReadable::new(buffer.reader(0));
+        let copied = pump_async_read(&data[..], &buffer, 30).await.unwrap();
+        assert_eq!(copied, 100);
+        assert_eq!(readable.skip(99).unwrap(), 99);
+        assert_eq!(readable.read_next().unwrap(), 99);
+        assert!(buffer.write(vec![1]).is_err());
+    }
+}
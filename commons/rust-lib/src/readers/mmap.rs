@@ -0,0 +1,106 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use memmap2::Mmap;
+
+use super::reader::{ChunkData, Readable, ReadableChunk, ReaderError};
+
+/// Reads a memory mapped file. Chunks point directly into the mapping, nothing is copied.
+/// The file must not be modified while it is mapped.
+pub struct MmapReadable {
+    map: Arc<Mmap>,
+    pos: usize,
+    chunk_size: usize,
+}
+
+impl MmapReadable {
+    pub fn open<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        Self::new(&File::open(path)?)
+    }
+
+    pub fn new(file: &File) -> std::io::Result<Self> {
+        //SAFETY: the mapping is read only, modifying the file while it is mapped is documented as not allowed
+        let map = unsafe { Mmap::map(file)? };
+        Ok(MmapReadable {
+            map: Arc::new(map),
+            pos: 0,
+            chunk_size: usize::MAX,
+        })
+    }
+
+    /// By default `read_chunk` returns everything up to the end of the file, this limits the chunk length
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.map[self.pos..]
+    }
+}
+
+impl Readable<u8> for MmapReadable {
+    fn read_next(&mut self) -> Result<u8, ReaderError> {
+        match self.map.get(self.pos) {
+            Some(b) => {
+                self.pos += 1;
+                Ok(*b)
+            }
+            None => Err(ReaderError::EOF)
+        }
+    }
+
+    fn skip(&mut self, skipped: usize) -> Result<usize, ReaderError> {
+        let skipped = skipped.min(self.map.len() - self.pos);
+        self.pos += skipped;
+        Ok(skipped)
+    }
+
+    fn read_chunk(&mut self) -> Result<ReadableChunk<u8>, ReaderError> {
+        if self.pos >= self.map.len() {
+            return Err(ReaderError::EOF);
+        }
+        let pos = self.pos;
+        self.pos = pos.saturating_add(self.chunk_size).min(self.map.len());
+        Ok(ReadableChunk {
+            chunk: ChunkData::Shared(self.map.clone()),
+            pos,
+            len: self.pos,
+        })
+    }
+
+    fn pos(&self) -> Option<usize> {
+        Some(self.pos)
+    }
+
+    fn len(&self) -> Option<usize> {
+        Some(self.map.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn test_mmap_readable() {
+        let mut f = tempfile::NamedTempFile::new().unwrap();
+        f.write_all(&(0..100).collect::<Vec<u8>>()).unwrap();
+        f.flush().unwrap();
+
+        let mut r = MmapReadable::open(f.path()).unwrap().with_chunk_size(40);
+        assert_eq!(r.len(), Some(100));
+        assert_eq!(r.read_next().unwrap(), 0);
+        assert_eq!(r.skip(9).unwrap(), 9);
+        let c = r.read_chunk().unwrap();
+        assert_eq!((c.pos, c.len), (10, 50));
+        assert_eq!(c.chunk[c.pos], 10);
+        assert_eq!(r.as_slice()[0], 50);
+        assert_eq!(r.skip(100).unwrap(), 50);
+        assert!(matches!(r.read_chunk(), Err(ReaderError::EOF)));
+    }
+}
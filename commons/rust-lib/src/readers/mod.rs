@@ -1 +1,3 @@
-pub mod reader;
\ No newline at end of file
+pub mod reader;
+pub mod io;
+pub mod mmap;
@@ -1,15 +1,29 @@
 /* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+use std::ops::Deref;
 use std::sync::Arc;
 
-use crate::cursedbuffer::CursedBufferReader;
+use crate::cursedbuffer::{CursedBufferError, CursedBufferReader};
 
 
 #[derive(Debug)]
 pub enum ReaderError {
-    EOF,
+    EOF, //the stream ended, no more data will arrive
+    NeedMoreData, //no data available right now, try again once more data was written
     IO(std::io::Error),
 }
 
+impl From<CursedBufferError> for ReaderError {
+    fn from(e: CursedBufferError) -> Self {
+        match e {
+            CursedBufferError::NotEnoughData => ReaderError::NeedMoreData,
+            CursedBufferError::BufferClosed => ReaderError::EOF,
+            CursedBufferError::InvalidData => ReaderError::IO(std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+            CursedBufferError::IoError => ReaderError::IO(std::io::Error::other(e)),
+        }
+    }
+}
+
+#[allow(clippy::len_without_is_empty)]
 pub trait Readable<T> {
     fn read_next(&mut self) -> Result<T, ReaderError> where T: Copy;
     fn skip(&mut self, skipped: usize) -> Result<usize, ReaderError>;
@@ -37,21 +51,75 @@ impl<T> CursedBufferReadable<T> {
 }
 
 pub struct ReadableChunk<T> {
-    chunk: Arc<Vec<T>>,
-    pos: usize,
-    len: usize
+    pub(crate) chunk: ChunkData<T>,
+    pub(crate) pos: usize,
+    pub(crate) len: usize
+}
+
+impl<T> ReadableChunk<T> {
+    /// The unread part of the chunk
+    pub fn as_slice(&self) -> &[T] {
+        &self.chunk[self.pos..self.len]
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.as_slice().iter()
+    }
+
+    /// Number of unread elements in the chunk
+    pub fn len(&self) -> usize {
+        self.len - self.pos
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == self.pos
+    }
+}
+
+impl<T> Deref for ReadableChunk<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a ReadableChunk<T> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Storage behind a [`ReadableChunk`], either an owned vector or some shared zero-copy memory like a mmap
+pub(crate) enum ChunkData<T> {
+    Vec(Arc<Vec<T>>),
+    Shared(Arc<dyn AsRef<[T]> + Send + Sync>),
+}
+
+impl<T> Deref for ChunkData<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        match self {
+            ChunkData::Vec(v) => v.as_slice(),
+            ChunkData::Shared(s) => (**s).as_ref(),
+        }
+    }
 }
 
 impl<T> Readable<T> for CursedBufferReadable<T> {
     fn read_next(&mut self) -> Result<T, ReaderError> where T: Copy {
         if self.current_chunk.is_none() {
-            match self.read_chunk() {
+            match self.reader.next_chunk() {
                 Ok(chunk) => {
-                    self.current_chunk = Some(chunk.chunk);
+                    self.current_chunk = Some(chunk.slice);
                     self.current_chunk_pos = chunk.pos;
                     self.current_chunk_len = chunk.len;
                 }
-                Err(e) => return Err(e)
+                Err(e) => return Err(e.into())
             }
         }
 
@@ -74,16 +142,14 @@ impl<T> Readable<T> for CursedBufferReadable<T> {
             let remaining = self.current_chunk_len - self.current_chunk_pos;
             if skipped <= remaining {
                 self.current_chunk_pos += skipped;
-                return Ok(skipped);
             } else {
                 self.current_chunk = None;
                 self.reader.skip(skipped);
-                return Ok(skipped);
             }
         } else {
             self.reader.skip(skipped);
-            return Ok(skipped);
         }
+        Ok(skipped)
     }
 
     fn read_chunk(&mut self) -> Result<ReadableChunk<T>, ReaderError> {
@@ -95,11 +161,11 @@ impl<T> Readable<T> for CursedBufferReadable<T> {
                     self.current_chunk_pos = chunk.pos;
                     self.current_chunk_len = chunk.len;
                 }
-                Err(_) => return Err(ReaderError::EOF),
+                Err(e) => return Err(e.into()),
             }
         }
         let r = ReadableChunk {
-            chunk: std::mem::replace(&mut self.current_chunk, None).unwrap(), //we know this is save because of the previous check
+            chunk: ChunkData::Vec(self.current_chunk.take().unwrap()), //we know this is save because of the previous check
             pos: self.current_chunk_pos,
             len: self.current_chunk_len
         };
@@ -118,13 +184,20 @@ impl<T> Readable<T> for CursedBufferReadable<T> {
 pub struct IteratorReadable<T> {
     iter: Box<dyn Iterator<Item=T>>,
     pos: usize,
+    chunk_size: usize,
 }
 
 impl<T> IteratorReadable<T> {
     pub fn new(iter: Box<dyn Iterator<Item=T>>)  -> Self {
+        Self::with_chunk_size(iter, 4096)
+    }
+
+    /// `read_chunk` collects up to `chunk_size` items into one chunk
+    pub fn with_chunk_size(iter: Box<dyn Iterator<Item=T>>, chunk_size: usize) -> Self {
         IteratorReadable {
             iter,
             pos: 0,
+            chunk_size: chunk_size.max(1),
         }
     }
 }
@@ -141,27 +214,26 @@ impl<T> Readable<T> for IteratorReadable<T> where T: Copy {
     }
 
     fn skip(&mut self, skipped: usize) -> Result<usize, ReaderError> {
-        for _ in 0..skipped {
+        for i in 0..skipped {
             match self.iter.next() {
                 Some(_) => self.pos += 1,
-                None => return Ok(self.pos)
+                None => return Ok(i)
             }
         }
-        Ok(self.pos)
+        Ok(skipped)
     }
 
     fn read_chunk(&mut self) -> Result<ReadableChunk<T>, ReaderError> {
-        let n = self.iter.next();
-        match n {
-            None => return Err(ReaderError::EOF),
-            Some(v) => {
-                Ok(ReadableChunk {
-                    chunk: Arc::new(vec![v]),
-                    pos: 0,
-                    len: 1
-                })
-            }
+        let chunk: Vec<T> = self.iter.by_ref().take(self.chunk_size).collect();
+        if chunk.is_empty() {
+            return Err(ReaderError::EOF);
         }
+        self.pos += chunk.len();
+        Ok(ReadableChunk {
+            len: chunk.len(),
+            chunk: ChunkData::Vec(Arc::new(chunk)),
+            pos: 0,
+        })
     }
 
     fn pos(&self) -> Option<usize> {
@@ -169,7 +241,11 @@ impl<T> Readable<T> for IteratorReadable<T> where T: Copy {
     }
 
     fn len(&self) -> Option<usize> {
-        None
+        //only known if the iterator knows its exact length
+        match self.iter.size_hint() {
+            (lower, Some(upper)) if lower == upper => Some(self.pos + lower),
+            _ => None
+        }
     }
 }
 
@@ -229,4 +305,30 @@ mod tests {
         assert_eq!(b.chunk[b.pos], 4);
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_chunk_api() {
+        let buffer = CursedBuffer::<u8>::new();
+        buffer.write(vec![1, 2, 3, 4, 5]).unwrap();
+        let mut readable = CursedBufferReadable::new(buffer.reader(0));
+        readable.skip(2).unwrap();
+        let b = readable.read_chunk().unwrap();
+        assert_eq!(b.as_slice(), &[3, 4, 5]);
+        assert_eq!(b.len(), 3);
+        assert_eq!(b.iter().sum::<u8>(), 12);
+        assert_eq!((&b).into_iter().count(), 3);
+        assert_eq!(b[0], 3);
+    }
+
+    #[test]
+    fn test_need_more_data_and_eof() {
+        let buffer = CursedBuffer::<u8>::new();
+        let mut readable = CursedBufferReadable::new(buffer.reader(0));
+        assert!(matches!(readable.read_next(), Err(ReaderError::NeedMoreData)));
+        assert!(matches!(readable.read_chunk(), Err(ReaderError::NeedMoreData)));
+        buffer.write(vec![1]).unwrap();
+        assert_eq!(readable.read_next().unwrap(), 1);
+        buffer.close();
+        assert!(matches!(readable.read_next(), Err(ReaderError::EOF)));
+        assert!(matches!(readable.read_chunk(), Err(ReaderError::EOF)));
+    }
+}
@@ -1,12 +1,42 @@
 /* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
 
 use std::ops::Deref;
+use std::sync::{Arc, Mutex, Weak};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+
+/// Called with the value once the last [`Reusable`] handle referencing it is dropped
+pub enum Callback<T> {
+    None,
+    Function(String, Box<dyn Fn(T) + Send + Sync>)
+}
 
-enum Callback<T> {
+/// A [`Callback`] whose function needs neither `Send` nor `Sync`, handles using it stay on their thread
+pub enum LocalCallback<T> {
     None,
     Function(String, Box<dyn Fn(T)>)
 }
-use std::fmt::{Debug, Formatter, Result as FmtResult};
+
+/// What a [`Reusable`] does with its value once the last handle is gone
+pub trait ReusableCallback<T> {
+    fn call(&self, value: T);
+}
+
+impl<T> ReusableCallback<T> for Callback<T> {
+    fn call(&self, value: T) {
+        if let Callback::Function(_, callback) = self {
+            callback(value);
+        }
+    }
+}
+
+impl<T> ReusableCallback<T> for LocalCallback<T> {
+    fn call(&self, value: T) {
+        if let LocalCallback::Function(_, callback) = self {
+            callback(value);
+        }
+    }
+}
 
 impl<T> Debug for Callback<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
@@ -19,69 +49,186 @@ impl<T> Debug for Callback<T> {
     }
 }
 
+impl<T> Debug for LocalCallback<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            LocalCallback::None => write!(f, "None"),
+            LocalCallback::Function(name, _) => {
+                write!(f, "Function {name}({name})")
+            }
+        }
+    }
+}
+
+/// A shared handle to a value. Cloning the handle does not clone the value, dropping the last handle
+/// hands the value to the callback instead of freeing it, e.g. to return it to a [`Pool`].
+/// Handles are `Send` and `Sync` whenever `T` is, unless they use a [`LocalCallback`].
 #[derive(Debug)]
-pub struct Reusable<T> {
-    reusable_inner: *mut ReusableInner<T>
+pub struct Reusable<T, C: ReusableCallback<T> = Callback<T>> {
+    reusable_inner: Arc<ReusableInner<T, C>>
 }
 
-pub struct ReusableInner<T> {
-    inner: T,
-    strong_count: usize,
-    weak_count: usize,
-    callback: Callback<T>,
+/// Handles of a value with a [`LocalCallback`]
+pub type LocalReusable<T> = Reusable<T, LocalCallback<T>>;
+
+/// The value shared by all handles of a [`Reusable`]
+pub struct ReusableInner<T, C: ReusableCallback<T> = Callback<T>> {
+    inner: Option<T>, //only None while dropping
+    callback: C,
 }
 
-impl<T> Reusable<T> {
-    pub fn new(inner: T, callback: Callback<T>) -> Self {
-        let inner = Box::new(ReusableInner {
-            inner,
-            strong_count: 1,
-            weak_count: 0,
-            callback,
-        });
+impl<T: Debug, C: ReusableCallback<T> + Debug> Debug for ReusableInner<T, C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("ReusableInner")
+            .field("inner", &self.inner)
+            .field("callback", &self.callback)
+            .finish()
+    }
+}
 
+impl<T, C: ReusableCallback<T>> Reusable<T, C> {
+    pub fn new(inner: T, callback: C) -> Self {
         Reusable {
-            reusable_inner: Box::into_raw(inner)
+            reusable_inner: Arc::new(ReusableInner {
+                inner: Some(inner),
+                callback,
+            })
         }
     }
+
+    /// Returns a mutable reference if this is the only handle to the value
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        Arc::get_mut(&mut self.reusable_inner).and_then(|r| r.inner.as_mut())
+    }
+
+    pub fn strong_count(&self) -> usize {
+        Arc::strong_count(&self.reusable_inner)
+    }
 }
 
-impl<T> Clone for Reusable<T> {
+impl<T, C: ReusableCallback<T>> Clone for Reusable<T, C> {
     fn clone(&self) -> Self {
-        unsafe {self.reusable_inner.as_mut()}.unwrap().strong_count += 1;
         Reusable {
-            reusable_inner: self.reusable_inner
+            reusable_inner: self.reusable_inner.clone()
         }
     }
 }
 
-impl<T> Deref for Reusable<T> {
+impl<T, C: ReusableCallback<T>> Deref for Reusable<T, C> {
     type Target = T;
 
     fn deref(&self) -> &Self::Target {
-        unsafe { &(*self.reusable_inner).inner }
+        self.reusable_inner.inner.as_ref().expect("value of Reusable is only taken on drop")
     }
 }
 
-impl<T> Drop for Reusable<T> {
+impl<T, C: ReusableCallback<T>> Drop for ReusableInner<T, C> {
     fn drop(&mut self) {
-        let mut inner = unsafe {Box::from_raw(self.reusable_inner)};
-        inner.strong_count -= 1;
-        if inner.strong_count == 0 {
-            match inner.callback {
-                Callback::None => {}
-                Callback::Function(_, ref callback) => (callback)(inner.inner)
-            }
-        } else {
-            self.reusable_inner = Box::into_raw(inner);
+        //runs exactly once, when the last handle is gone
+        if let Some(inner) = self.inner.take() {
+            self.callback.call(inner);
         }
     }
 }
 
+type CreateFn<T> = Box<dyn Fn() -> T + Send + Sync>;
+type ResetFn<T> = Box<dyn Fn(&mut T) + Send + Sync>;
+
+struct PoolInner<T> {
+    idle: Mutex<Vec<T>>,
+    create: CreateFn<T>,
+    reset: Option<ResetFn<T>>,
+    max_idle: AtomicUsize,
+}
+
+impl<T> PoolInner<T> {
+    fn put(&self, mut value: T) {
+        if let Some(reset) = &self.reset {
+            reset(&mut value);
+        }
+        let mut idle = self.idle.lock().unwrap();
+        if idle.len() < self.max_idle.load(Ordering::Relaxed) {
+            idle.push(value);
+        }
+    }
+}
+
+/// A thread safe object pool. [`Pool::get`] hands out [`Reusable`] handles, the value goes back to the pool
+/// (after the optional reset function ran) when the last handle is dropped.
+/// Values returned after the pool itself was dropped are simply freed.
+pub struct Pool<T> {
+    pool_inner: Arc<PoolInner<T>>
+}
+
+impl<T> Debug for Pool<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("Pool")
+            .field("idle", &self.idle())
+            .field("max_idle", &self.pool_inner.max_idle.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+impl<T> Clone for Pool<T> {
+    fn clone(&self) -> Self {
+        Pool {
+            pool_inner: self.pool_inner.clone()
+        }
+    }
+}
+
+impl<T: Send + 'static> Pool<T> {
+    /// Creates a pool that calls `create` whenever no idle value is available
+    pub fn new(create: impl Fn() -> T + Send + Sync + 'static) -> Self {
+        Self::build(Box::new(create), None)
+    }
+
+    /// Creates a pool that runs `reset` on every value before it is put back, e.g. `Vec::clear`
+    pub fn with_reset(create: impl Fn() -> T + Send + Sync + 'static, reset: impl Fn(&mut T) + Send + Sync + 'static) -> Self {
+        Self::build(Box::new(create), Some(Box::new(reset)))
+    }
+
+    fn build(create: CreateFn<T>, reset: Option<ResetFn<T>>) -> Self {
+        Pool {
+            pool_inner: Arc::new(PoolInner {
+                idle: Mutex::new(Vec::new()),
+                create,
+                reset,
+                max_idle: AtomicUsize::new(usize::MAX),
+            })
+        }
+    }
+
+    /// Takes an idle value or creates a new one
+    pub fn get(&self) -> Reusable<T> {
+        let value = self.pool_inner.idle.lock().unwrap().pop();
+        let value = value.unwrap_or_else(|| (self.pool_inner.create)());
+        let pool: Weak<PoolInner<T>> = Arc::downgrade(&self.pool_inner);
+        Reusable::new(value, Callback::Function(String::from("pool"), Box::new(move |value| {
+            if let Some(pool) = pool.upgrade() {
+                pool.put(value);
+            }
+        })))
+    }
+}
+
+impl<T> Pool<T> {
+    /// Limits the number of idle values kept, surplus values are freed
+    pub fn set_max_idle(&self, max_idle: usize) {
+        self.pool_inner.max_idle.store(max_idle, Ordering::Relaxed);
+        self.pool_inner.idle.lock().unwrap().truncate(max_idle);
+    }
+
+    /// Number of values waiting in the pool
+    pub fn idle(&self) -> usize {
+        self.pool_inner.idle.lock().unwrap().len()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::{cell::RefCell, rc::Rc};
+    use std::{cell::RefCell, rc::Rc, thread};
 
     #[test]
     fn test_reusable() {
@@ -104,4 +251,73 @@ mod tests {
         assert_eq!(*inner.borrow(), 1);
         assert_eq!(Rc::strong_count(&inner), 1);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_local_callback() {
+        //the callback holds an Rc, so it is neither Send nor Sync
+        let returned = Rc::new(RefCell::new(Vec::new()));
+        let r2 = returned.clone();
+        let reusable = LocalReusable::new(7, LocalCallback::Function(String::from("local"), Box::new(move |x| r2.borrow_mut().push(x))));
+        let cloned = reusable.clone();
+        drop(reusable);
+        assert!(returned.borrow().is_empty());
+        drop(cloned);
+        assert_eq!(*returned.borrow(), [7]);
+    }
+
+    #[test]
+    fn test_pool_recycles() {
+        let pool = Pool::with_reset(|| Vec::<u8>::with_capacity(1024), |v| v.clear());
+        let mut a = pool.get();
+        a.get_mut().unwrap().extend_from_slice(b"hello");
+        let ptr = a.as_ptr();
+        let b = a.clone();
+        assert!(a.get_mut().is_none());
+        drop(a);
+        assert_eq!(pool.idle(), 0);
+        drop(b);
+        assert_eq!(pool.idle(), 1);
+
+        let c = pool.get();
+        assert_eq!(pool.idle(), 0);
+        assert!(c.is_empty());
+        assert_eq!(c.as_ptr(), ptr);
+        assert!(c.capacity() >= 1024);
+    }
+
+    #[test]
+    fn test_pool_max_idle_and_drop() {
+        let pool = Pool::new(|| 0u32);
+        pool.set_max_idle(1);
+        let a = pool.get();
+        let b = pool.get();
+        drop(a);
+        drop(b);
+        assert_eq!(pool.idle(), 1);
+        let c = pool.get();
+        drop(pool);
+        //returning a value to a dropped pool just frees it
+        drop(c);
+    }
+
+    #[test]
+    fn test_pool_threads() {
+        let pool = Pool::with_reset(Vec::<usize>::new, |v| v.clear());
+        let handles: Vec<_> = (0..4).map(|t| {
+            let pool = pool.clone();
+            thread::spawn(move || {
+                for i in 0..1000 {
+                    let mut v = pool.get();
+                    assert!(v.is_empty());
+                    v.get_mut().unwrap().push(t * i);
+                    let shared = v.clone();
+                    thread::spawn(move || assert_eq!(shared.len(), 1)).join().unwrap();
+                }
+            })
+        }).collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+        assert!(pool.idle() <= 4);
+    }
+}
@@ -0,0 +1,227 @@
+use std::fmt;
+use std::str::FromStr;
+
+use derive_more::{Display, Error};
+
+#[derive(Debug, Display, Error, PartialEq)]
+pub enum DateTimeError {
+    #[display("precision must be 0 to 9 fractional digits")]
+    InvalidPrecision,
+    #[display("utc offset must be less than 24 hours")]
+    InvalidOffset,
+    #[display("not an RFC 3339 date time")]
+    InvalidFormat,
+    #[display("date time out of range")]
+    OutOfRange,
+}
+
+const MAX_PRECISION: u8 = 9;
+const MAX_OFFSET_MINUTES: i16 = 24 * 60 - 1;
+
+/// A point in time: `value` units of 10^-`precision` seconds since 1970-01-01T00:00:00Z.
+/// The utc offset only records the local time zone the value was written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DateTime {
+    value: i64,
+    precision: u8,
+    offset_minutes: i16,
+}
+
+impl DateTime {
+    pub fn new(value: i64, precision: u8, offset_minutes: i16) -> Result<DateTime, DateTimeError> {
+        if precision > MAX_PRECISION {
+            return Err(DateTimeError::InvalidPrecision);
+        }
+        if offset_minutes.unsigned_abs() > MAX_OFFSET_MINUTES as u16 {
+            return Err(DateTimeError::InvalidOffset);
+        }
+        Ok(DateTime { value, precision, offset_minutes })
+    }
+
+    /// Seconds since the epoch in utc
+    pub fn from_unix_seconds(seconds: i64) -> DateTime {
+        DateTime { value: seconds, precision: 0, offset_minutes: 0 }
+    }
+
+    /// Milliseconds since the epoch in utc
+    pub fn from_unix_millis(millis: i64) -> DateTime {
+        DateTime { value: millis, precision: 3, offset_minutes: 0 }
+    }
+
+    pub fn value(&self) -> i64 {
+        self.value
+    }
+
+    /// Number of fractional second digits of the value
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    pub fn offset_minutes(&self) -> i16 {
+        self.offset_minutes
+    }
+
+    /// Nanoseconds since the epoch, comparable between precisions
+    pub fn unix_nanos(&self) -> i128 {
+        self.value as i128 * 10i128.pow((MAX_PRECISION - self.precision) as u32)
+    }
+
+    /// Whole seconds since the epoch and the fractional part in units of the precision
+    fn split(&self) -> (i64, i64) {
+        let unit = 10i64.pow(self.precision as u32);
+        (self.value.div_euclid(unit), self.value.rem_euclid(unit))
+    }
+}
+
+/// Formats as RFC 3339 in the recorded offset, with as many fractional digits as the precision
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (seconds, fraction) = self.split();
+        let local = seconds as i128 + self.offset_minutes as i128 * 60;
+        let (year, month, day) = civil_from_days(local.div_euclid(86400) as i64);
+        let second_of_day = local.rem_euclid(86400);
+        write!(f, "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}", second_of_day / 3600, second_of_day / 60 % 60, second_of_day % 60)?;
+        if self.precision > 0 {
+            write!(f, ".{fraction:0width$}", width = self.precision as usize)?;
+        }
+        match self.offset_minutes {
+            0 => write!(f, "Z"),
+            o => write!(f, "{}{:02}:{:02}", if o < 0 { '-' } else { '+' }, o.unsigned_abs() / 60, o.unsigned_abs() % 60),
+        }
+    }
+}
+
+/// Parses RFC 3339 like `2025-03-01T12:30:00.250+01:00`. The precision is the number of fractional digits,
+/// a missing offset means utc
+impl FromStr for DateTime {
+    type Err = DateTimeError;
+
+    fn from_str(s: &str) -> Result<DateTime, DateTimeError> {
+        let invalid = DateTimeError::InvalidFormat;
+        let b = s.as_bytes();
+        //the byte positions below are only char boundaries in ascii
+        if b.len() < 19 || !b[..19].is_ascii() || b[4] != b'-' || b[7] != b'-' || !matches!(b[10], b'T' | b't' | b' ') || b[13] != b':' || b[16] != b':' {
+            return Err(invalid);
+        }
+        let number = |range: std::ops::Range<usize>| -> Result<i64, DateTimeError> {
+            let digits = &s[range];
+            match digits.bytes().all(|c| c.is_ascii_digit()) {
+                true => digits.parse().map_err(|_| DateTimeError::InvalidFormat),
+                false => Err(DateTimeError::InvalidFormat),
+            }
+        };
+        let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+        let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+        if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) || hour > 23 || minute > 59 || second > 60 {
+            return Err(invalid);
+        }
+
+        let mut rest = &s[19..];
+        let mut precision = 0;
+        let mut fraction = 0;
+        if let Some(digits) = rest.strip_prefix('.') {
+            let len = digits.bytes().take_while(u8::is_ascii_digit).count();
+            if len == 0 {
+                return Err(invalid);
+            }
+            if len > MAX_PRECISION as usize {
+                return Err(DateTimeError::InvalidPrecision);
+            }
+            precision = len as u8;
+            fraction = digits[..len].parse::<i64>().map_err(|_| DateTimeError::InvalidFormat)?;
+            rest = &digits[len..];
+        }
+        let offset_minutes = match rest {
+            "" | "Z" | "z" => 0,
+            _ if rest.len() == 6 && rest.as_bytes()[3] == b':' => {
+                let sign = match rest.as_bytes()[0] {
+                    b'+' => 1,
+                    b'-' => -1,
+                    _ => return Err(invalid),
+                };
+                let parse = |digits: &str| match digits.bytes().all(|c| c.is_ascii_digit()) {
+                    true => digits.parse::<i16>().map_err(|_| DateTimeError::InvalidFormat),
+                    false => Err(DateTimeError::InvalidFormat),
+                };
+                let (hours, minutes) = (parse(&rest[1..3])?, parse(&rest[4..6])?);
+                if minutes > 59 {
+                    return Err(invalid);
+                }
+                sign * (hours * 60 + minutes)
+            }
+            _ => return Err(invalid),
+        };
+
+        let local = days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second;
+        let seconds = local - offset_minutes as i64 * 60;
+        let value = seconds.checked_mul(10i64.pow(precision as u32))
+            .and_then(|v| v.checked_add(fraction))
+            .ok_or(DateTimeError::OutOfRange)?;
+        DateTime::new(value, precision, offset_minutes)
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+//days since 1970-01-01 of a proleptic gregorian date, see http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (year_of_era + era * 400 + (month <= 2) as i64, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_and_parse() {
+        assert_eq!(DateTime::from_unix_seconds(0).to_string(), "1970-01-01T00:00:00Z");
+        assert_eq!(DateTime::from_unix_millis(-1).to_string(), "1969-12-31T23:59:59.999Z");
+        let dt = DateTime::new(1_740_832_200_250, 3, 60).unwrap();
+        assert_eq!(dt.to_string(), "2025-03-01T13:30:00.250+01:00");
+        for s in ["2025-03-01T13:30:00.250+01:00", "2000-02-29T23:59:59Z", "1900-01-01T00:00:00.000000001-09:30", "0001-01-01T00:00:00Z"] {
+            assert_eq!(s.parse::<DateTime>().unwrap().to_string(), s);
+        }
+        assert_eq!("2025-03-01T12:30:00.25Z".parse::<DateTime>().unwrap().unix_nanos(), dt.unix_nanos());
+        assert_eq!("2025-03-01 12:30:00".parse::<DateTime>().unwrap(), DateTime::from_unix_seconds(1_740_832_200));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(DateTime::new(0, 10, 0), Err(DateTimeError::InvalidPrecision));
+        assert_eq!(DateTime::new(0, 0, 24 * 60), Err(DateTimeError::InvalidOffset));
+        for s in ["2025-02-29T00:00:00Z", "2025-13-01T00:00:00Z", "2025-01-01", "2025-01-01T00:00:00.Z", "2025-01-01T00:00:00+1:00", "2025-01-01T24:00:00Z",
+            "2025-01-01T00:00:0é", "2é25-01-01T00:00:00Z", "2025-01-01T00:00:00+é:00", "2025-01-01T00:00:00.5é"] {
+            assert_eq!(s.parse::<DateTime>(), Err(DateTimeError::InvalidFormat), "{s}");
+        }
+        assert_eq!("2025-01-01T00:00:00.0000000001Z".parse::<DateTime>(), Err(DateTimeError::InvalidPrecision));
+        assert_eq!("9999-01-01T00:00:00.000000001Z".parse::<DateTime>(), Err(DateTimeError::OutOfRange));
+    }
+}
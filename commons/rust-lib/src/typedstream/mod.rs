@@ -2,6 +2,9 @@
 
 use crate::orderedbag::OrderedBag;
 
+mod datetime;
+pub use datetime::{DateTime, DateTimeError};
+
 #[derive(Debug)]
 pub enum TypedStreamEvent {
     INIT,
@@ -13,6 +16,8 @@ pub enum TypedStreamEvent {
     ENDTYPE,
     STRING(String),
     DECIMAL(usize),
+    FLOAT(f64),
+    DATETIME(DateTime),
     NULL,
     TRUE,
     FALSE,
@@ -50,6 +55,8 @@ pub enum TypedStreamElement {
     Type(String, Vec<TypedStreamElement>),
     String(String),
     Decimal,
+    Float(f64),
+    DateTime(DateTime),
     Null,
     Boolean(bool),
     ByteArray(Vec<u8>),
@@ -64,6 +71,8 @@ impl PartialEq<TypedStreamElement> for TypedStreamElement {
             (TypedStreamElement::Boolean(b1), TypedStreamElement::Boolean(b2)) => b1 == b2,
             (TypedStreamElement::Null, TypedStreamElement::Null) => true,
             (TypedStreamElement::ByteArray(b1), TypedStreamElement::ByteArray(b2)) => b1 == b2,
+            (TypedStreamElement::Float(f1), TypedStreamElement::Float(f2)) => f1 == f2,
+            (TypedStreamElement::DateTime(d1), TypedStreamElement::DateTime(d2)) => d1 == d2,
             (TypedStreamElement::Object(o1), TypedStreamElement::Object(o2)) => o1 == o2,
             (TypedStreamElement::Array(a1), TypedStreamElement::Array(a2)) => a1 == a2,
             (TypedStreamElement::Type(t1, v1), TypedStreamElement::Type(t2, v2)) => t1 == t2 && v1 == v2,
@@ -79,6 +88,12 @@ pub struct TypeStream2OrderedMultiDictProcessor {
     results: Vec<TypedStreamElement>,
 }
 
+impl Default for TypeStream2OrderedMultiDictProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TypeStream2OrderedMultiDictProcessor {
     pub fn new() -> Self {
         Self {
@@ -141,6 +156,8 @@ impl TypeStream2OrderedMultiDictProcessor {
             }
             TypedStreamEvent::STRING(s) => self.insert_into_last_stack_element(TypedStreamElement::String(s)),
             TypedStreamEvent::DECIMAL(_) => Ok(TypedStreamEventReturn::CONTINUE),
+            TypedStreamEvent::FLOAT(f) => self.insert_into_last_stack_element(TypedStreamElement::Float(f)),
+            TypedStreamEvent::DATETIME(d) => self.insert_into_last_stack_element(TypedStreamElement::DateTime(d)),
             TypedStreamEvent::NULL => self.insert_into_last_stack_element(TypedStreamElement::Null),
             TypedStreamEvent::TRUE => self.insert_into_last_stack_element(TypedStreamElement::Boolean(true)),
             TypedStreamEvent::FALSE => self.insert_into_last_stack_element(TypedStreamElement::Boolean(false)),
@@ -0,0 +1,142 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+//! `datagate` transforms records from stdin (or a file) to stdout (or a file) while they stream.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use clap::Parser;
+use datagate::{DossWriter, Format, GateError, TextWriter, Transform};
+use doss::{DossDictionaryRegistry, DossSerializer, DossSerializerOptions};
+
+#[derive(Debug, Parser)]
+#[command(name = "datagate", version, about = "Transforms JSON, streamablejson and DOSS records while they stream")]
+struct Cli {
+    /// Transform definition in streamablejson
+    #[arg(short, long, value_name = "FILE")]
+    transform: PathBuf,
+    /// Input file, stdin if missing or `-`
+    input: Option<PathBuf>,
+    /// Output file, stdout if missing or `-`
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Input format: json, sjson or doss. Guessed from the file name, sjson otherwise
+    #[arg(long, value_name = "FORMAT", value_parser = format)]
+    from: Option<Format>,
+    /// Output format: json, sjson or doss. Guessed from the file name, sjson otherwise
+    #[arg(long, value_name = "FORMAT", value_parser = format)]
+    to: Option<Format>,
+    /// Predefined dictionary file for DOSS imports. Can be repeated
+    #[arg(long = "dictionary", value_name = "FILE")]
+    dictionaries: Vec<PathBuf>,
+}
+
+fn format(name: &str) -> Result<Format, String> {
+    Format::from_name(name).ok_or_else(|| format!("unknown format {name}"))
+}
+
+//None for stdin and stdout
+fn file_arg(path: &Option<PathBuf>) -> Option<&Path> {
+    path.as_deref().filter(|p| *p != Path::new("-"))
+}
+
+fn registry(files: &[PathBuf]) -> Result<Option<Arc<DossDictionaryRegistry>>, GateError> {
+    if files.is_empty() {
+        return Ok(None);
+    }
+    let mut registry = DossDictionaryRegistry::new();
+    for file in files {
+        registry.load_file(file)?;
+    }
+    Ok(Some(Arc::new(registry)))
+}
+
+/// Output held back until the run succeeded, so invalid input does not leave a truncated value behind.
+/// Only output beyond `HOLD_LIMIT` bytes streams before the end of the input
+struct HeldOutput {
+    out: Box<dyn Write>,
+    //a file output is written next to its path and only replaces it once complete
+    file: Option<(tempfile::NamedTempFile, PathBuf)>,
+    held: Vec<u8>,
+}
+
+const HOLD_LIMIT: usize = 1 << 20;
+
+impl HeldOutput {
+    fn new(path: Option<&Path>) -> Result<HeldOutput, GateError> {
+        let Some(path) = path else {
+            return Ok(HeldOutput { out: Box::new(io::stdout().lock()), file: None, held: Vec::new() });
+        };
+        let dir = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let file = tempfile::NamedTempFile::new_in(dir)?;
+        let out = Box::new(BufWriter::new(file.reopen()?));
+        Ok(HeldOutput { out, file: Some((file, path.to_path_buf())), held: Vec::new() })
+    }
+
+    fn commit(mut self) -> Result<(), GateError> {
+        self.out.write_all(&self.held)?;
+        self.out.flush()?;
+        if let Some((file, path)) = self.file {
+            file.persist(path).map_err(|e| e.error)?;
+        }
+        Ok(())
+    }
+}
+
+impl Write for HeldOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.held.extend_from_slice(buf);
+        if self.file.is_some() || self.held.len() >= HOLD_LIMIT {
+            self.out.write_all(&self.held)?;
+            self.held.clear();
+        }
+        Ok(buf.len())
+    }
+
+    //held output is only written by commit
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn run(cli: Cli) -> Result<(), GateError> {
+    let mut transform = Transform::parse(&fs::read_to_string(&cli.transform)?)?;
+    let input_file = file_arg(&cli.input);
+    let output_file = file_arg(&cli.output);
+    let from = cli.from.or_else(|| input_file.and_then(Format::from_path)).unwrap_or(Format::Sjson);
+    let to = cli.to.or_else(|| output_file.and_then(Format::from_path)).unwrap_or(Format::Sjson);
+    let input: Box<dyn Read> = match input_file {
+        Some(path) => Box::new(File::open(path)?),
+        None => Box::new(io::stdin().lock()),
+    };
+    let output = HeldOutput::new(output_file)?;
+    let registry = registry(&cli.dictionaries)?;
+    let output = match to {
+        Format::Doss => {
+            let mut writer = DossWriter::new(DossSerializer::with_options(output, DossSerializerOptions::default()));
+            datagate::run(input, from, registry, &mut transform, &mut writer)?;
+            writer.finish()?
+        }
+        Format::Json | Format::Sjson => {
+            let mut writer = if to == Format::Json { TextWriter::json(output) } else { TextWriter::sjson(output) };
+            datagate::run(input, from, registry, &mut transform, &mut writer)?;
+            writer.finish()?
+        }
+    };
+    output.commit()
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        //a closed pipe like `datagate -t t.sjson big.doss | head` is no error
+        Err(GateError::Io(e)) if e.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("datagate: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,408 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use dataflowgrid_commons::typedstream::{DateTime, TypedStreamEvent};
+
+use crate::error::GateError;
+use crate::input::read_text;
+use crate::path::Path;
+use crate::value::{Scalar, parse_hex};
+
+/// One step of a transform, applied to the records the step before passes on
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    /// gives fields a new key, they stay in their object
+    Rename(Vec<(Path, String)>),
+    /// removes fields or array elements
+    Drop(Vec<Path>),
+    /// removes everything except the paths, their parents and their content
+    Keep(Vec<Path>),
+    /// replaces scalars
+    Map(Vec<(Path, Function)>),
+    /// passes on records that match the predicate
+    Filter(Predicate),
+    /// moves the fields of objects nested in the object at `path` into it, their keys joined by `separator`
+    Flatten { path: Path, separator: String },
+    /// moves fields (key, new key) of the object holding `path` into a new object at `path`
+    Nest { path: Path, keys: Vec<(String, String)> },
+    /// replaces types with a single value by that value converted, like `ObjectId("5f1")`
+    Convert(Vec<(String, Conversion)>),
+}
+
+/// How a value is converted, values that can't be converted stay as they are
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conversion {
+    /// the text of the value
+    String,
+    /// numbers from their text
+    Number,
+    /// date times from RFC 3339 text or milliseconds since the epoch
+    DateTime,
+    /// binaries from hex text
+    Bytes,
+    /// the content of a type as it is
+    Unwrap,
+}
+
+impl Conversion {
+    pub fn from_name(name: &str) -> Option<Conversion> {
+        Some(match name {
+            "string" => Conversion::String,
+            "number" => Conversion::Number,
+            "datetime" => Conversion::DateTime,
+            "bytes" => Conversion::Bytes,
+            "unwrap" => Conversion::Unwrap,
+            _ => return None,
+        })
+    }
+
+    /// None if the value can't be converted
+    pub fn apply(&self, value: &Scalar) -> Option<Scalar> {
+        match (self, value) {
+            (Conversion::Unwrap, value) | (_, value @ Scalar::Null) => Some(value.clone()),
+            (Conversion::String, value) => Some(Scalar::String(value.to_string())),
+            (Conversion::Number, Scalar::String(s)) => Scalar::parse_number(s.trim()),
+            (Conversion::Number, value @ (Scalar::UInt(_) | Scalar::Int(_) | Scalar::Float(_) | Scalar::Decimal(_))) => Some(value.clone()),
+            (Conversion::DateTime, Scalar::String(s)) => s.trim().parse::<DateTime>().ok().map(Scalar::DateTime),
+            (Conversion::DateTime, Scalar::UInt(v)) => i64::try_from(*v).ok().map(|v| Scalar::DateTime(DateTime::from_unix_millis(v))),
+            (Conversion::DateTime, Scalar::Int(v)) => Some(Scalar::DateTime(DateTime::from_unix_millis(*v))),
+            (Conversion::DateTime, value @ Scalar::DateTime(_)) => Some(value.clone()),
+            (Conversion::Bytes, Scalar::String(s)) => parse_hex(s).map(Scalar::Bytes),
+            (Conversion::Bytes, value @ Scalar::Bytes(_)) => Some(value.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// What [`Step::Map`] does with a scalar
+#[derive(Debug, Clone, PartialEq)]
+pub enum Function {
+    Upper,
+    Lower,
+    Trim,
+    Convert(Conversion),
+    /// replaces the value, objects and arrays as well
+    Set(Scalar),
+    /// replaces null
+    Default(Scalar),
+}
+
+impl Function {
+    pub fn apply(&self, value: Scalar) -> Scalar {
+        match (self, value) {
+            (Function::Upper, Scalar::String(s)) => Scalar::String(s.to_uppercase()),
+            (Function::Lower, Scalar::String(s)) => Scalar::String(s.to_lowercase()),
+            (Function::Trim, Scalar::String(s)) => Scalar::String(s.trim().to_string()),
+            (Function::Convert(conversion), value) => conversion.apply(&value).unwrap_or(value),
+            (Function::Set(v), _) => v.clone(),
+            (Function::Default(v), Scalar::Null) => v.clone(),
+            (_, value) => value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+/// A condition on the values of a record. Paths with `[]` match if any element matches
+#[derive(Debug, Clone, PartialEq)]
+pub enum Predicate {
+    Compare(Path, Comparison, Scalar),
+    In(Path, Vec<Scalar>),
+    /// a string value contains the text
+    Contains(Path, String),
+    /// there is a value at the path, null included
+    Exists(Path),
+    All(Vec<Predicate>),
+    Any(Vec<Predicate>),
+    Not(Box<Predicate>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Scalar(Scalar),
+    Array(Vec<Node>),
+    Object(Vec<(Node, Node)>),
+    Type(String, Vec<Node>),
+}
+
+#[derive(Debug)]
+enum Open {
+    Array(Vec<Node>),
+    //keys and values one after the other
+    Object(Vec<Node>),
+    Type(String, Vec<Node>),
+}
+
+#[derive(Debug, Default)]
+struct NodeBuilder {
+    stack: Vec<Open>,
+    nodes: Vec<Node>,
+}
+
+impl NodeBuilder {
+    fn push(&mut self, event: TypedStreamEvent) -> Result<(), GateError> {
+        let node = match event {
+            event @ (TypedStreamEvent::STARTOBJECT | TypedStreamEvent::STARTARRAY | TypedStreamEvent::STARTTYPE(_)) => {
+                self.stack.push(match event {
+                    TypedStreamEvent::STARTOBJECT => Open::Object(Vec::new()),
+                    TypedStreamEvent::STARTTYPE(name) => Open::Type(name, Vec::new()),
+                    _ => Open::Array(Vec::new()),
+                });
+                return Ok(());
+            }
+            TypedStreamEvent::ENDOBJECT | TypedStreamEvent::ENDARRAY | TypedStreamEvent::ENDTYPE => match self.stack.pop() {
+                Some(Open::Array(items)) => Node::Array(items),
+                Some(Open::Type(name, items)) => Node::Type(name, items),
+                Some(Open::Object(items)) if items.len() % 2 == 0 => {
+                    let mut items = items.into_iter();
+                    Node::Object(std::iter::from_fn(|| Some((items.next()?, items.next()?))).collect())
+                }
+                _ => return Err(GateError::UnbalancedStructure),
+            },
+            event => match Scalar::from_event(&event) {
+                Some(scalar) => Node::Scalar(scalar),
+                None => return Ok(()),
+            },
+        };
+        match self.stack.last_mut() {
+            None => self.nodes.push(node),
+            Some(Open::Array(items) | Open::Object(items) | Open::Type(_, items)) => items.push(node),
+        }
+        Ok(())
+    }
+}
+
+/// Reads the steps of a transform definition: step types like `drop(["internal"])`, either as top level
+/// values or in a top level array
+pub fn parse_steps(definition: &str) -> Result<Vec<Step>, GateError> {
+    let mut builder = NodeBuilder::default();
+    read_text(definition.as_bytes(), &mut |event| builder.push(event))?;
+    let mut steps = Vec::new();
+    for node in builder.nodes {
+        match node {
+            Node::Array(nodes) => {
+                for node in nodes {
+                    parse_step(node, &mut steps)?;
+                }
+            }
+            node => parse_step(node, &mut steps)?,
+        }
+    }
+    Ok(steps)
+}
+
+fn invalid(what: &str, node: &Node) -> GateError {
+    GateError::Definition(format!("{what} expected, found {node:?}"))
+}
+
+fn text(node: &Node) -> Result<&str, GateError> {
+    match node {
+        Node::Scalar(Scalar::String(s)) => Ok(s),
+        node => Err(invalid("a string", node)),
+    }
+}
+
+fn path(node: &Node) -> Result<Path, GateError> {
+    text(node)?.parse()
+}
+
+//a field path, neither the record nor array elements
+fn field_path(node: &Node) -> Result<Path, GateError> {
+    let path = path(node)?;
+    match path.key() {
+        Some(_) => Ok(path),
+        None => Err(GateError::Definition(format!("{path} is no field"))),
+    }
+}
+
+fn paths(node: &Node) -> Result<Vec<Path>, GateError> {
+    match node {
+        Node::Array(items) => items.iter().map(path).collect(),
+        node => Ok(vec![path(node)?]),
+    }
+}
+
+fn scalar(node: &Node) -> Result<Scalar, GateError> {
+    match node {
+        Node::Scalar(scalar) => Ok(scalar.clone()),
+        node => Err(invalid("a scalar", node)),
+    }
+}
+
+fn fields(node: &Node) -> Result<&[(Node, Node)], GateError> {
+    match node {
+        Node::Object(fields) => Ok(fields),
+        node => Err(invalid("an object", node)),
+    }
+}
+
+//functions are types like `upper()` or just their name
+fn function(node: &Node) -> Result<Function, GateError> {
+    let (name, args) = match node {
+        Node::Type(name, args) => (name.as_str(), args.as_slice()),
+        node => (text(node)?, &[][..]),
+    };
+    Ok(match (name, args) {
+        ("upper", []) => Function::Upper,
+        ("lower", []) => Function::Lower,
+        ("trim", []) => Function::Trim,
+        ("set", [value]) => Function::Set(scalar(value)?),
+        ("default", [value]) => Function::Default(scalar(value)?),
+        (name, []) => match Conversion::from_name(name) {
+            Some(Conversion::Unwrap) | None => return Err(GateError::Definition(format!("unknown function {name}"))),
+            Some(conversion) => Function::Convert(conversion),
+        },
+        (name, _) => return Err(GateError::Definition(format!("wrong arguments of function {name}"))),
+    })
+}
+
+fn predicate(node: &Node) -> Result<Predicate, GateError> {
+    let Node::Type(name, args) = node else {
+        return Err(invalid("a predicate", node));
+    };
+    let [arg] = args.as_slice() else {
+        return Err(GateError::Definition(format!("{name} takes one argument")));
+    };
+    //conditions on several paths must all hold
+    let each = |f: &dyn Fn(Path, &Node) -> Result<Predicate, GateError>| -> Result<Predicate, GateError> {
+        let mut all = fields(arg)?.iter().map(|(k, v)| f(path(k)?, v)).collect::<Result<Vec<_>, _>>()?;
+        Ok(match all.len() {
+            1 => all.remove(0),
+            _ => Predicate::All(all),
+        })
+    };
+    let compare = |comparison| each(&|path, value| Ok(Predicate::Compare(path, comparison, scalar(value)?)));
+    Ok(match name.as_str() {
+        "eq" => compare(Comparison::Eq)?,
+        "ne" => Predicate::Not(Box::new(compare(Comparison::Eq)?)),
+        "gt" => compare(Comparison::Gt)?,
+        "gte" => compare(Comparison::Gte)?,
+        "lt" => compare(Comparison::Lt)?,
+        "lte" => compare(Comparison::Lte)?,
+        "in" => each(&|path, values| match values {
+            Node::Array(values) => Ok(Predicate::In(path, values.iter().map(scalar).collect::<Result<_, _>>()?)),
+            node => Err(invalid("an array", node)),
+        })?,
+        "contains" => each(&|path, value| Ok(Predicate::Contains(path, text(value)?.to_string())))?,
+        "exists" => {
+            let mut all: Vec<_> = paths(arg)?.into_iter().map(Predicate::Exists).collect();
+            match all.len() {
+                1 => all.remove(0),
+                _ => Predicate::All(all),
+            }
+        }
+        "all" | "any" => {
+            let Node::Array(items) = arg else {
+                return Err(invalid("an array", arg));
+            };
+            let items = items.iter().map(predicate).collect::<Result<Vec<_>, _>>()?;
+            if name == "all" { Predicate::All(items) } else { Predicate::Any(items) }
+        }
+        "not" => Predicate::Not(Box::new(predicate(arg)?)),
+        name => return Err(GateError::Definition(format!("unknown predicate {name}"))),
+    })
+}
+
+fn parse_step(node: Node, steps: &mut Vec<Step>) -> Result<(), GateError> {
+    let Node::Type(name, args) = &node else {
+        return Err(invalid("a step", &node));
+    };
+    let [arg] = args.as_slice() else {
+        return Err(GateError::Definition(format!("{name} takes one argument")));
+    };
+    match name.as_str() {
+        "rename" => steps.push(Step::Rename(fields(arg)?.iter().map(|(k, v)| Ok((field_path(k)?, text(v)?.to_string()))).collect::<Result<_, GateError>>()?)),
+        "drop" => {
+            let paths = paths(arg)?;
+            if paths.iter().any(Path::is_root) {
+                return Err(GateError::Definition(String::from("records are removed with filter")));
+            }
+            steps.push(Step::Drop(paths));
+        }
+        "keep" => steps.push(Step::Keep(paths(arg)?)),
+        "map" => steps.push(Step::Map(fields(arg)?.iter().map(|(k, v)| Ok((path(k)?, function(v)?))).collect::<Result<_, GateError>>()?)),
+        "filter" => steps.push(Step::Filter(predicate(arg)?)),
+        "flatten" => steps.push(match arg {
+            Node::Object(options) => {
+                let option = |key: &str| options.iter().find(|(k, _)| matches!(k, Node::Scalar(Scalar::String(s)) if s == key)).map(|(_, v)| v);
+                Step::Flatten {
+                    path: option("path").map(path).transpose()?.unwrap_or_default(),
+                    separator: option("separator").map(text).transpose()?.unwrap_or("_").to_string(),
+                }
+            }
+            arg => Step::Flatten { path: path(arg)?, separator: String::from("_") },
+        }),
+        "nest" => {
+            for (path, keys) in fields(arg)? {
+                let keys = match keys {
+                    Node::Array(keys) => keys.iter().map(|k| Ok((text(k)?.to_string(), text(k)?.to_string()))).collect::<Result<_, GateError>>()?,
+                    keys => fields(keys)?.iter().map(|(k, v)| Ok((text(k)?.to_string(), text(v)?.to_string()))).collect::<Result<_, GateError>>()?,
+                };
+                steps.push(Step::Nest { path: field_path(path)?, keys });
+            }
+        }
+        "convert" => steps.push(Step::Convert(
+            fields(arg)?
+                .iter()
+                .map(|(k, v)| {
+                    let conversion = Conversion::from_name(text(v)?).ok_or_else(|| invalid("string, number, datetime, bytes or unwrap", v))?;
+                    Ok((text(k)?.to_string(), conversion))
+                })
+                .collect::<Result<_, GateError>>()?,
+        )),
+        name => return Err(GateError::Definition(format!("unknown step {name}"))),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_steps() {
+        let definition = r#"
+            [
+                rename({"customer.name": "customer_name"}),
+                drop(["internal", "items[].debug"]),
+                filter(all([eq({"status": "paid"}), gt({"total": 100}), not(exists("deleted"))])),
+                map({"customer_name": upper(), "note": default("none"), "created": datetime()}),
+                flatten({"path": "address", "separator": "."}),
+                nest({"customer": ["customer_name", "email"]}),
+                convert({"ObjectId": "string", "Date": "datetime"})
+            ]
+            keep(["customer", "items"])
+        "#;
+        let steps = parse_steps(definition).unwrap();
+        let path = |p: &str| p.parse::<Path>().unwrap();
+        assert_eq!(steps.len(), 8);
+        assert_eq!(steps[1], Step::Drop(vec![path("internal"), path("items[].debug")]));
+        assert_eq!(
+            steps[2],
+            Step::Filter(Predicate::All(vec![
+                Predicate::Compare(path("status"), Comparison::Eq, Scalar::String(String::from("paid"))),
+                Predicate::Compare(path("total"), Comparison::Gt, Scalar::UInt(100)),
+                Predicate::Not(Box::new(Predicate::Exists(path("deleted")))),
+            ]))
+        );
+        assert_eq!(steps[4], Step::Flatten { path: path("address"), separator: String::from(".") });
+        assert_eq!(steps[7], Step::Keep(vec![path("customer"), path("items")]));
+
+        for (definition, error) in [
+            (r#"explode("a")"#, "unknown step explode"),
+            (r#"drop("")"#, "records are removed with filter"),
+            (r#"rename({"items[]": "x"})"#, "items[] is no field"),
+            (r#"map({"a": shout()})"#, "unknown function shout"),
+            (r#"filter(eq("a"))"#, "an object expected"),
+        ] {
+            let e = parse_steps(definition).unwrap_err().to_string();
+            assert!(e.contains(error), "{e}");
+        }
+    }
+}
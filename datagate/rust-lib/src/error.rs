@@ -0,0 +1,43 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::io::ErrorKind;
+
+use dataflowgrid_commons::readers::reader::ReaderError;
+use derive_more::{Display, Error, From};
+use doss::DossError;
+use streamablejson::parser::StreamableJSONReaderError;
+
+#[derive(Debug, Display, Error, From)]
+pub enum GateError {
+    #[display("{_0}")]
+    Io(std::io::Error),
+    #[display("{_0}")]
+    Doss(DossError),
+    #[display("invalid input: {_0}")]
+    #[from(ignore)]
+    Input(#[error(not(source))] String),
+    #[display("invalid transform definition: {_0}")]
+    #[from(ignore)]
+    Definition(#[error(not(source))] String),
+    #[display("end of object, array or type does not match its start")]
+    UnbalancedStructure,
+    #[display("{_0} can not be written as {_1}")]
+    #[from(ignore)]
+    Unsupported(#[error(not(source))] String, &'static str),
+}
+
+impl From<StreamableJSONReaderError> for GateError {
+    fn from(e: StreamableJSONReaderError) -> Self {
+        match e {
+            //callbacks stop with the error of the transform they feed
+            StreamableJSONReaderError::CallbackError(e) => match e.downcast::<GateError>() {
+                Ok(e) => *e,
+                Err(e) => GateError::Input(e.to_string()),
+            },
+            //text that is not utf8 is invalid input, other read errors are I/O errors
+            StreamableJSONReaderError::ReaderError(ReaderError::IO(e)) if e.kind() == ErrorKind::InvalidData => GateError::Input(e.to_string()),
+            StreamableJSONReaderError::ReaderError(ReaderError::IO(e)) => GateError::Io(e),
+            e => GateError::Input(format!("{e:?}")),
+        }
+    }
+}
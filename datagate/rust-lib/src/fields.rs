@@ -0,0 +1,89 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::collections::HashMap;
+
+use dataflowgrid_commons::typedstream::TypedStreamEvent;
+
+use crate::error::GateError;
+use crate::path::{Cursor, Path, Position};
+use crate::transform::{Skip, Stage};
+
+/// [`Step::Rename`](crate::definition::Step::Rename)
+#[derive(Debug)]
+pub(crate) struct Rename {
+    cursor: Cursor,
+    names: HashMap<Path, String>,
+}
+
+impl Rename {
+    pub fn new(names: Vec<(Path, String)>) -> Self {
+        Rename { cursor: Cursor::new(), names: names.into_iter().collect() }
+    }
+}
+
+impl Stage for Rename {
+    fn push(&mut self, event: TypedStreamEvent, out: &mut Vec<TypedStreamEvent>) -> Result<(), GateError> {
+        match self.cursor.push(&event)? {
+            Position::Key(Some(path)) if let Some(name) = self.names.get(&path) => out.push(TypedStreamEvent::STRING(name.clone())),
+            _ => out.push(event),
+        }
+        Ok(())
+    }
+}
+
+/// [`Step::Drop`](crate::definition::Step::Drop) and [`Step::Keep`](crate::definition::Step::Keep)
+#[derive(Debug)]
+pub(crate) struct Select {
+    cursor: Cursor,
+    paths: Vec<Path>,
+    keep: bool,
+    skip: Skip,
+}
+
+impl Select {
+    pub fn drop(paths: Vec<Path>) -> Self {
+        Select { cursor: Cursor::new(), paths, keep: false, skip: Skip::default() }
+    }
+
+    pub fn keep(paths: Vec<Path>) -> Self {
+        Select { cursor: Cursor::new(), paths, keep: true, skip: Skip::default() }
+    }
+
+    fn selected(&self, path: &Path) -> bool {
+        match self.keep {
+            //parents of kept paths stay for the way to them, their other content goes
+            true => path.is_root() || self.paths.iter().any(|p| p.starts_with(path) || path.starts_with(p)),
+            false => !self.paths.iter().any(|p| path.starts_with(p)),
+        }
+    }
+}
+
+impl Stage for Select {
+    fn push(&mut self, event: TypedStreamEvent, out: &mut Vec<TypedStreamEvent>) -> Result<(), GateError> {
+        if self.skip.skipping(&mut self.cursor, &event)? {
+            return Ok(());
+        }
+        let depth = self.cursor.depth();
+        match self.cursor.push(&event)? {
+            //a key has the path of its value, both go
+            Position::Key(Some(path)) if !self.selected(&path) => {}
+            Position::Value(Some(path)) if !self.selected(&path) => self.skip.start(&self.cursor, depth),
+            _ => out.push(event),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transform::tests::apply;
+
+    #[test]
+    fn test_select() {
+        let input = r#"{"a": {"b": 1, "c": [2, {"d": 3}]}, "items": [{"x": 1, "debug": {"y": 2}}, {"x": 2}], "e": ObjectId("5f")}"#;
+        assert_eq!(apply(r#"drop(["a.c", "items[].debug", "e"])"#, input), "{\"a\":{\"b\":1},\"items\":[{\"x\":1},{\"x\":2}]}\n");
+        assert_eq!(apply(r#"keep(["a.c", "items[].x"])"#, input), "{\"a\":{\"c\":[2,{\"d\":3}]},\"items\":[{\"x\":1},{\"x\":2}]}\n");
+        assert_eq!(apply(r#"drop("items[]")"#, input), "{\"a\":{\"b\":1,\"c\":[2,{\"d\":3}]},\"items\":[],\"e\":ObjectId(\"5f\")}\n");
+        assert_eq!(apply(r#"rename({"a.b": "B", "items[].x": "X"})"#, input), apply("[]", &input.replace("\"b\"", "\"B\"").replace("\"x\"", "\"X\"")));
+    }
+}
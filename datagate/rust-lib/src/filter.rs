@@ -0,0 +1,167 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+use dataflowgrid_commons::typedstream::TypedStreamEvent;
+
+use crate::definition::{Comparison, Predicate};
+use crate::error::GateError;
+use crate::path::{Cursor, Path, Position};
+use crate::transform::Stage;
+use crate::value::Scalar;
+
+#[derive(Debug)]
+enum State {
+    Outside,
+    //the record so far, until the predicate is decided
+    Deciding(Vec<TypedStreamEvent>),
+    Passing,
+    Dropping,
+}
+
+/// [`Step::Filter`](crate::definition::Step::Filter). A record is held back only until its values decide
+/// the predicate, the rest of it streams through or is dropped
+#[derive(Debug)]
+pub(crate) struct Filter {
+    cursor: Cursor,
+    predicate: Predicate,
+    paths: HashSet<Path>,
+    //values found at the paths of the predicate, None for objects, arrays and types
+    values: HashMap<Path, Vec<Option<Scalar>>>,
+    state: State,
+}
+
+impl Filter {
+    pub fn new(predicate: Predicate) -> Self {
+        let mut paths = HashSet::new();
+        collect_paths(&predicate, &mut paths);
+        Filter { cursor: Cursor::new(), predicate, paths, values: HashMap::new(), state: State::Outside }
+    }
+}
+
+fn collect_paths(predicate: &Predicate, paths: &mut HashSet<Path>) {
+    match predicate {
+        Predicate::Compare(path, ..) | Predicate::In(path, _) | Predicate::Contains(path, _) | Predicate::Exists(path) => {
+            paths.insert(path.clone());
+        }
+        Predicate::All(predicates) | Predicate::Any(predicates) => predicates.iter().for_each(|p| collect_paths(p, paths)),
+        Predicate::Not(predicate) => collect_paths(predicate, paths),
+    }
+}
+
+/// None while the values seen so far don't decide the predicate. Once the record is `complete` it is decided
+fn evaluate(predicate: &Predicate, values: &HashMap<Path, Vec<Option<Scalar>>>, complete: bool) -> Option<bool> {
+    let test = |path: &Path, matches: &dyn Fn(&Option<Scalar>) -> bool| {
+        let found = values.get(path).map(Vec::as_slice).unwrap_or_default();
+        if found.iter().any(matches) {
+            Some(true)
+        } else if complete || (!path.has_elements() && !found.is_empty()) {
+            Some(false)
+        } else {
+            None
+        }
+    };
+    match predicate {
+        Predicate::Compare(path, comparison, expected) => test(path, &|value| {
+            let ordering = value.as_ref().and_then(|v| v.compare(expected));
+            match comparison {
+                Comparison::Eq => ordering == Some(Ordering::Equal),
+                Comparison::Gt => ordering == Some(Ordering::Greater),
+                Comparison::Gte => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+                Comparison::Lt => ordering == Some(Ordering::Less),
+                Comparison::Lte => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            }
+        }),
+        Predicate::In(path, expected) => test(path, &|value| value.as_ref().is_some_and(|v| expected.iter().any(|e| v.compare(e) == Some(Ordering::Equal)))),
+        Predicate::Contains(path, text) => test(path, &|value| matches!(value, Some(Scalar::String(s)) if s.contains(text.as_str()))),
+        Predicate::Exists(path) => test(path, &|_| true),
+        Predicate::All(predicates) => {
+            let mut result = Some(true);
+            for predicate in predicates {
+                match evaluate(predicate, values, complete) {
+                    Some(false) => return Some(false),
+                    None => result = None,
+                    Some(true) => {}
+                }
+            }
+            result
+        }
+        Predicate::Any(predicates) => {
+            let mut result = Some(false);
+            for predicate in predicates {
+                match evaluate(predicate, values, complete) {
+                    Some(true) => return Some(true),
+                    None => result = None,
+                    Some(false) => {}
+                }
+            }
+            result
+        }
+        Predicate::Not(predicate) => evaluate(predicate, values, complete).map(|matched| !matched),
+    }
+}
+
+impl Stage for Filter {
+    fn push(&mut self, event: TypedStreamEvent, out: &mut Vec<TypedStreamEvent>) -> Result<(), GateError> {
+        let position = self.cursor.push(&event)?;
+        let ended = self.cursor.record_ended();
+        let mut changed = false;
+        if matches!(self.state, State::Outside) && position != Position::Outside {
+            self.state = State::Deciding(Vec::new());
+            self.values.clear();
+            changed = true;
+        }
+        match &mut self.state {
+            State::Outside | State::Passing => out.push(event),
+            State::Dropping => {}
+            State::Deciding(record) => {
+                if let Position::Value(Some(path)) = &position
+                    && self.paths.contains(path)
+                {
+                    self.values.entry(path.clone()).or_default().push(Scalar::from_event(&event));
+                    changed = true;
+                }
+                record.push(event);
+                if changed || ended {
+                    match evaluate(&self.predicate, &self.values, ended) {
+                        Some(true) => {
+                            out.append(record);
+                            self.state = State::Passing;
+                        }
+                        Some(false) => self.state = State::Dropping,
+                        None => {}
+                    }
+                }
+            }
+        }
+        if ended {
+            self.state = State::Outside;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transform::tests::apply;
+
+    #[test]
+    fn test_filter() {
+        let input = r#"[
+            {"status": "paid", "total": 150, "items": [{"sku": "a"}, {"sku": "b"}]},
+            {"status": "open", "total": 300, "items": []},
+            {"total": 120, "status": "paid", "deleted": true, "items": [{"sku": "b"}]},
+            {"status": "paid", "total": 90, "note": "rush order"}
+        ]"#;
+        let totals = |definition: &str| {
+            let output = apply(definition, input);
+            output.match_indices("\"total\":").map(|(i, _)| output[i + 8..].split([',', '}']).next().unwrap().to_string()).collect::<Vec<_>>().join(" ")
+        };
+        assert_eq!(totals(r#"filter(all([eq({"status": "paid"}), gt({"total": 100}), not(exists("deleted"))]))"#), "150");
+        assert_eq!(totals(r#"filter(eq({"items[].sku": "b"}))"#), "150 120");
+        assert_eq!(totals(r#"filter(any([in({"status": ["open"]}), contains({"note": "rush"})]))"#), "300 90");
+        assert_eq!(totals(r#"filter(ne({"status": "paid"}))"#), "300");
+        assert_eq!(apply(r#"filter(lte({"": 2}))"#, "1 5 2"), "1\n2\n");
+    }
+}
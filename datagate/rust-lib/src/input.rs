@@ -0,0 +1,90 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::io::Read;
+use std::sync::Arc;
+
+use dataflowgrid_commons::readers::io::TextReadable;
+use dataflowgrid_commons::typedstream::TypedStreamEvent;
+use doss::{DossDictionaryRegistry, DossEvent, DossPullParser, DossTextEvents};
+use streamablejson::parser::{StreamableJSONReader, StreamableJSONReaderCallback, StreamableJSONReaderCallbackReturn, StreamableJSONReaderEvent};
+
+use crate::error::GateError;
+
+/// Receives the events of an input one by one
+pub type EventHandler<'a> = dyn FnMut(TypedStreamEvent) -> Result<(), GateError> + 'a;
+
+struct TextEvents<'a, 'h> {
+    handler: &'a mut EventHandler<'h>,
+    text: DossTextEvents,
+    events: Vec<DossEvent>,
+}
+
+impl TextEvents<'_, '_> {
+    fn push(&mut self, event: StreamableJSONReaderEvent) -> Result<(), GateError> {
+        self.text.push(event, &mut self.events)?;
+        for event in self.events.drain(..) {
+            if let Some(event) = event.to_typed_stream_event() {
+                (self.handler)(event)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl StreamableJSONReaderCallback for TextEvents<'_, '_> {
+    fn on_streamablejson_event(&mut self, event: StreamableJSONReaderEvent) -> StreamableJSONReaderCallbackReturn {
+        match self.push(event) {
+            Ok(()) => StreamableJSONReaderCallbackReturn::Continue,
+            Err(e) => StreamableJSONReaderCallbackReturn::StopErr(Box::new(e)),
+        }
+    }
+}
+
+/// Parses streamablejson or JSON text from `input` chunk by chunk. Constants become numbers, typed values
+/// written by the DOSS tools become date times, binaries and decimals
+pub fn read_text<R: Read>(input: R, handler: &mut EventHandler) -> Result<(), GateError> {
+    let mut events = TextEvents { handler, text: DossTextEvents::new(), events: Vec::new() };
+    let mut reader = StreamableJSONReader::new(&mut events);
+    reader.pushdata(&mut TextReadable::utf8(input))?;
+    reader.finish()?;
+    Ok(())
+}
+
+/// Decodes a DOSS stream from `input`, imports need their predefined dictionary in `registry`
+pub fn read_doss<R: Read>(input: R, registry: Option<Arc<DossDictionaryRegistry>>, handler: &mut EventHandler) -> Result<(), GateError> {
+    let mut parser = DossPullParser::new(input);
+    if let Some(registry) = registry {
+        parser.set_registry(registry);
+    }
+    for event in parser {
+        handler(event?)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::output::EventSink;
+
+    use super::*;
+
+    #[test]
+    fn test_read_text() {
+        let mut events: Vec<TypedStreamEvent> = Vec::new();
+        let text = r#"{"n": 7, "when": datetime("2025-01-01T00:00:00Z"), "raw": bytes("00ff"), "id": ObjectId("5f"), "x": datetime(1)} 12"#;
+        read_text(text.as_bytes(), &mut |e| events.write(e)).unwrap();
+        assert_eq!(
+            events.iter().map(|e| format!("{e:?}")).collect::<Vec<_>>().join(" "),
+            concat!(
+                r#"STARTOBJECT STRING("n") DECIMAL(7) STRING("when") DATETIME(DateTime { value: 1735689600, precision: 0, offset_minutes: 0 }) "#,
+                r#"STRING("raw") BYTEARRAY([0, 255]) STRING("id") STARTTYPE("ObjectId") STRING("5f") ENDTYPE "#,
+                r#"STRING("x") STARTTYPE("datetime") DECIMAL(1) ENDTYPE ENDOBJECT DECIMAL(12)"#
+            )
+        );
+        assert!(read_text(&b"[1,"[..], &mut |_| Ok(())).is_err());
+
+        let mut events: Vec<TypedStreamEvent> = Vec::new();
+        read_text(&b"[-3, 9.5]"[..], &mut |e| events.write(e)).unwrap();
+        assert_eq!(events.iter().map(|e| format!("{e:?}")).collect::<Vec<_>>().join(" "), "STARTARRAY ANY(Any { .. }) FLOAT(9.5) ENDARRAY");
+    }
+}
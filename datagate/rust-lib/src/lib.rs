@@ -0,0 +1,105 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+//! DataGate transforms records while they stream. Inputs and outputs are JSON, streamablejson or DOSS,
+//! all of them pass as [`TypedStreamEvent`]s through the steps of a [`Transform`] written in streamablejson.
+
+mod definition;
+mod error;
+mod fields;
+mod filter;
+mod input;
+mod map;
+mod output;
+mod path;
+mod shape;
+mod transform;
+mod value;
+
+use std::io::Read;
+use std::sync::Arc;
+
+use dataflowgrid_commons::typedstream::TypedStreamEvent;
+use doss::DossDictionaryRegistry;
+
+pub use definition::{Comparison, Conversion, Function, Predicate, Step, parse_steps};
+pub use error::GateError;
+pub use input::{EventHandler, read_doss, read_text};
+pub use output::{ARGS_KEY, DossWriter, EventSink, TYPE_KEY, TextWriter};
+pub use path::{Cursor, Path, Position, Segment};
+pub use transform::Transform;
+pub use value::Scalar;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Sjson,
+    Doss,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        Some(match name {
+            "json" => Format::Json,
+            "sjson" => Format::Sjson,
+            "doss" => Format::Doss,
+            _ => return None,
+        })
+    }
+
+    /// The format a file name ends with
+    pub fn from_path(path: &std::path::Path) -> Option<Format> {
+        path.extension().and_then(|e| e.to_str()).and_then(Self::from_name)
+    }
+}
+
+/// Reads records from `input`, transforms them and passes them to `sink`. DOSS imports need their
+/// predefined dictionary in `registry`
+pub fn run<R: Read>(input: R, format: Format, registry: Option<Arc<DossDictionaryRegistry>>, transform: &mut Transform, sink: &mut dyn EventSink) -> Result<(), GateError> {
+    let handler = &mut |event: TypedStreamEvent| transform.push(event, sink);
+    match format {
+        Format::Json | Format::Sjson => read_text(input, handler),
+        Format::Doss => read_doss(input, registry, handler),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use doss::{DossSerializer, DossSerializerOptions};
+
+    use super::*;
+
+    #[test]
+    fn test_formats() {
+        let definition = r#"[
+            convert({"ObjectId": "string", "Date": "datetime"}),
+            filter(gte({"total": 100})),
+            rename({"_id": "id"}),
+            map({"customer.name": upper()}),
+            flatten("customer"),
+            drop("internal")
+        ]"#;
+        let records = r#"[
+            {"_id": ObjectId("5f1"), "at": Date("2025-01-01T00:00:00Z"), "total": 150, "customer": {"name": "ada", "geo": {"city": "Graz"}}, "internal": [1, 2]},
+            {"_id": ObjectId("5f2"), "total": 20, "customer": {"name": "bob"}}
+        ]"#;
+        let expected = "[{\"id\":\"5f1\",\"at\":datetime(\"2025-01-01T00:00:00Z\"),\"total\":150,\"customer\":{\"name\":\"ADA\",\"geo_city\":\"Graz\"}}]\n";
+
+        let transformed = |input: &[u8], format: Format| {
+            let mut writer = TextWriter::sjson(Vec::new());
+            run(input, format, None, &mut Transform::parse(definition).unwrap(), &mut writer).unwrap();
+            String::from_utf8(writer.finish().unwrap()).unwrap()
+        };
+        let encode = |text: &str| {
+            let mut writer = DossWriter::new(DossSerializer::with_options(Vec::new(), DossSerializerOptions::default()));
+            read_text(text.as_bytes(), &mut |event| writer.write(event)).unwrap();
+            writer.finish().unwrap()
+        };
+        //plain JSON has strings where the export had types
+        let json = records.replace("ObjectId(", "").replace("Date(", "").replace("\"),", "\",");
+
+        assert_eq!(transformed(records.as_bytes(), Format::Sjson), expected);
+        assert_eq!(transformed(&encode(records), Format::Doss), expected);
+        assert_eq!(transformed(json.as_bytes(), Format::Json), expected.replace("datetime(\"2025-01-01T00:00:00Z\")", "\"2025-01-01T00:00:00Z\""));
+        assert_eq!(Format::from_path(std::path::Path::new("orders.doss")), Some(Format::Doss));
+    }
+}
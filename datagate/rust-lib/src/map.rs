@@ -0,0 +1,168 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::collections::HashMap;
+
+use dataflowgrid_commons::typedstream::TypedStreamEvent;
+
+use crate::definition::{Conversion, Function};
+use crate::error::GateError;
+use crate::path::{Cursor, Path, Position};
+use crate::transform::{Skip, Stage};
+use crate::value::Scalar;
+
+/// [`Step::Map`](crate::definition::Step::Map)
+#[derive(Debug)]
+pub(crate) struct Map {
+    cursor: Cursor,
+    functions: HashMap<Path, Function>,
+    skip: Skip,
+}
+
+impl Map {
+    pub fn new(functions: Vec<(Path, Function)>) -> Self {
+        Map { cursor: Cursor::new(), functions: functions.into_iter().collect(), skip: Skip::default() }
+    }
+}
+
+impl Stage for Map {
+    fn push(&mut self, event: TypedStreamEvent, out: &mut Vec<TypedStreamEvent>) -> Result<(), GateError> {
+        if self.skip.skipping(&mut self.cursor, &event)? {
+            return Ok(());
+        }
+        let depth = self.cursor.depth();
+        if let Position::Value(Some(path)) = self.cursor.push(&event)?
+            && let Some(function) = self.functions.get(&path)
+        {
+            match Scalar::from_event(&event) {
+                Some(value) => {
+                    out.push(function.apply(value).to_event());
+                    return Ok(());
+                }
+                None if let Function::Set(value) = function
+                    && self.cursor.depth() > depth =>
+                {
+                    out.push(value.to_event());
+                    self.skip.start(&self.cursor, depth);
+                    return Ok(());
+                }
+                None => {}
+            }
+        }
+        out.push(event);
+        Ok(())
+    }
+}
+
+//a type to convert, held back until it ends
+#[derive(Debug)]
+struct Held {
+    name: String,
+    conversion: Conversion,
+    depth: usize,
+    events: Vec<TypedStreamEvent>,
+}
+
+impl Held {
+    fn convert(self) -> Vec<TypedStreamEvent> {
+        if let [event] = self.events.as_slice()
+            && let Some(value) = Scalar::from_event(event)
+            && let Some(converted) = self.conversion.apply(&value)
+        {
+            return vec![converted.to_event()];
+        }
+        if self.conversion == Conversion::Unwrap && single_value(&self.events) {
+            return self.events;
+        }
+        let mut events = Vec::with_capacity(self.events.len() + 2);
+        events.push(TypedStreamEvent::STARTTYPE(self.name));
+        events.extend(self.events);
+        events.push(TypedStreamEvent::ENDTYPE);
+        events
+    }
+}
+
+//the events are exactly one value
+fn single_value(events: &[TypedStreamEvent]) -> bool {
+    let mut depth = 0usize;
+    let mut values = 0;
+    for event in events {
+        if depth == 0 {
+            values += 1;
+        }
+        match event {
+            TypedStreamEvent::STARTOBJECT | TypedStreamEvent::STARTARRAY | TypedStreamEvent::STARTTYPE(_) => depth += 1,
+            TypedStreamEvent::ENDOBJECT | TypedStreamEvent::ENDARRAY | TypedStreamEvent::ENDTYPE => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    values == 1
+}
+
+/// [`Step::Convert`](crate::definition::Step::Convert), types are converted wherever they are
+#[derive(Debug)]
+pub(crate) struct Convert {
+    conversions: HashMap<String, Conversion>,
+    depth: usize,
+    held: Vec<Held>,
+}
+
+impl Convert {
+    pub fn new(conversions: Vec<(String, Conversion)>) -> Self {
+        Convert { conversions: conversions.into_iter().collect(), depth: 0, held: Vec::new() }
+    }
+}
+
+impl Stage for Convert {
+    fn push(&mut self, event: TypedStreamEvent, out: &mut Vec<TypedStreamEvent>) -> Result<(), GateError> {
+        match &event {
+            TypedStreamEvent::STARTTYPE(name) if let Some(conversion) = self.conversions.get(name) => {
+                self.depth += 1;
+                self.held.push(Held { name: name.clone(), conversion: *conversion, depth: self.depth, events: Vec::new() });
+                return Ok(());
+            }
+            TypedStreamEvent::ENDTYPE if self.held.last().is_some_and(|held| held.depth == self.depth) => {
+                self.depth -= 1;
+                let events = self.held.pop().map(Held::convert).unwrap_or_default();
+                match self.held.last_mut() {
+                    Some(held) => held.events.extend(events),
+                    None => out.extend(events),
+                }
+                return Ok(());
+            }
+            TypedStreamEvent::STARTOBJECT | TypedStreamEvent::STARTARRAY | TypedStreamEvent::STARTTYPE(_) => self.depth += 1,
+            TypedStreamEvent::ENDOBJECT | TypedStreamEvent::ENDARRAY | TypedStreamEvent::ENDTYPE => {
+                self.depth = self.depth.checked_sub(1).ok_or(GateError::UnbalancedStructure)?;
+            }
+            _ => {}
+        }
+        match self.held.last_mut() {
+            Some(held) => held.events.push(event),
+            None => out.push(event),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transform::tests::apply;
+
+    #[test]
+    fn test_map() {
+        let input = r#"{"name": " Ada ", "note": null, "meta": {"a": 1}, "tags": ["x", "y"], "n": "12"}"#;
+        assert_eq!(
+            apply(r#"map({"name": trim(), "note": default("none"), "meta": set(0), "tags[]": upper(), "n": number()})"#, input),
+            "{\"name\":\"Ada\",\"note\":\"none\",\"meta\":0,\"tags\":[\"X\",\"Y\"],\"n\":12}\n"
+        );
+        assert_eq!(apply(r#"map({"meta": upper()})"#, input), apply("[]", input));
+    }
+
+    #[test]
+    fn test_convert() {
+        let input = r#"{"_id": ObjectId("5f1"), "at": Date(1735689600000), "w": Wrap({"a": Wrap(1)}), "bad": Date("x")}"#;
+        assert_eq!(
+            apply(r#"convert({"ObjectId": "string", "Date": "datetime", "Wrap": "unwrap"})"#, input),
+            "{\"_id\":\"5f1\",\"at\":datetime(\"2025-01-01T00:00:00.000Z\"),\"w\":{\"a\":1},\"bad\":Date(\"x\")}\n"
+        );
+    }
+}
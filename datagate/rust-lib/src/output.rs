@@ -0,0 +1,244 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::io::Write;
+
+use dataflowgrid_commons::typedstream::TypedStreamEvent;
+use doss::{BYTES_TYPE, DATETIME_TYPE, DECIMAL_TYPE, DossSerializer};
+
+use crate::error::GateError;
+use crate::value::Scalar;
+
+/// key of the type name when types are written as JSON objects
+pub const TYPE_KEY: &str = "$type";
+/// key of the content when types are written as JSON objects
+pub const ARGS_KEY: &str = "$args";
+
+/// Receives the events a transform passes on
+pub trait EventSink {
+    fn write(&mut self, event: TypedStreamEvent) -> Result<(), GateError>;
+}
+
+impl EventSink for Vec<TypedStreamEvent> {
+    fn write(&mut self, event: TypedStreamEvent) -> Result<(), GateError> {
+        self.push(event);
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Kind {
+    Object,
+    Array,
+    Type,
+}
+
+#[derive(Debug)]
+struct Level {
+    kind: Kind,
+    items: usize,
+}
+
+/// Writes events as streamablejson or JSON text while they arrive, one top level value per line.
+/// JSON has no types, they are written as objects `{"$type": name, "$args": [...]}`. Date times and
+/// binaries become such objects with RFC 3339 and hex text, in streamablejson `datetime("...")` and `bytes("...")`
+#[derive(Debug)]
+pub struct TextWriter<W: Write> {
+    out: W,
+    json: bool,
+    stack: Vec<Level>,
+}
+
+impl<W: Write> TextWriter<W> {
+    pub fn sjson(out: W) -> Self {
+        TextWriter { out, json: false, stack: Vec::new() }
+    }
+
+    pub fn json(out: W) -> Self {
+        TextWriter { out, json: true, stack: Vec::new() }
+    }
+
+    /// The output, all top level values must be complete
+    pub fn finish(mut self) -> Result<W, GateError> {
+        if !self.stack.is_empty() {
+            return Err(GateError::UnbalancedStructure);
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    //separates the item from the one before, returns true for keys
+    fn separate(&mut self) -> Result<bool, GateError> {
+        let Some(level) = self.stack.last_mut() else {
+            return Ok(false);
+        };
+        let key = level.kind == Kind::Object && level.items % 2 == 0;
+        let separator: &[u8] = match (key, level.items) {
+            (_, 0) => b"",
+            (true, _) => b",",
+            (false, _) if level.kind == Kind::Object => b":",
+            _ => b",",
+        };
+        level.items += 1;
+        self.out.write_all(separator)?;
+        Ok(key)
+    }
+
+    //a top level value is complete
+    fn completed(&mut self) -> Result<(), GateError> {
+        if self.stack.is_empty() {
+            self.out.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    fn open(&mut self, kind: Kind, text: &str) -> Result<(), GateError> {
+        if self.separate()? && self.json {
+            return Err(GateError::Unsupported(String::from("a key that is no scalar"), "JSON"));
+        }
+        self.out.write_all(text.as_bytes())?;
+        self.stack.push(Level { kind, items: 0 });
+        Ok(())
+    }
+
+    fn close(&mut self, kind: Kind, text: &str) -> Result<(), GateError> {
+        match self.stack.pop() {
+            Some(level) if level.kind == kind && (kind != Kind::Object || level.items % 2 == 0) => {}
+            _ => return Err(GateError::UnbalancedStructure),
+        }
+        self.out.write_all(text.as_bytes())?;
+        self.completed()
+    }
+
+    fn scalar(&mut self, value: &Scalar) -> Result<(), GateError> {
+        let key = self.separate()?;
+        let text = match value {
+            Scalar::String(s) => quote(s),
+            //JSON keys are strings
+            value if key && self.json => quote(&value.to_string()),
+            Scalar::Float(v) if self.json && !v.is_finite() => String::from("null"),
+            Scalar::Decimal(d) if !self.json => typed(DECIMAL_TYPE, &d.to_string(), false),
+            Scalar::DateTime(d) => typed(DATETIME_TYPE, &d.to_string(), self.json),
+            Scalar::Bytes(_) => typed(BYTES_TYPE, &value.to_string(), self.json),
+            value => value.to_string(),
+        };
+        self.out.write_all(text.as_bytes())?;
+        self.completed()
+    }
+}
+
+impl<W: Write> EventSink for TextWriter<W> {
+    fn write(&mut self, event: TypedStreamEvent) -> Result<(), GateError> {
+        match event {
+            TypedStreamEvent::STARTOBJECT => self.open(Kind::Object, "{"),
+            TypedStreamEvent::STARTARRAY => self.open(Kind::Array, "["),
+            TypedStreamEvent::STARTTYPE(name) if self.json => self.open(Kind::Type, &format!("{{{}:{},{}:[", quote(TYPE_KEY), quote(&name), quote(ARGS_KEY))),
+            TypedStreamEvent::STARTTYPE(name) => self.open(Kind::Type, &format!("{name}(")),
+            TypedStreamEvent::ENDOBJECT => self.close(Kind::Object, "}"),
+            TypedStreamEvent::ENDARRAY => self.close(Kind::Array, "]"),
+            TypedStreamEvent::ENDTYPE => self.close(Kind::Type, if self.json { "]}" } else { ")" }),
+            TypedStreamEvent::INIT | TypedStreamEvent::FINISH | TypedStreamEvent::HINT(_) => Ok(()),
+            TypedStreamEvent::ERROR(e) => Err(GateError::Input(e.to_string())),
+            event => match Scalar::from_event(&event) {
+                Some(value) => self.scalar(&value),
+                None => Err(GateError::Unsupported(format!("{event:?}"), if self.json { "JSON" } else { "streamablejson" })),
+            },
+        }
+    }
+}
+
+fn typed(name: &str, text: &str, json: bool) -> String {
+    match json {
+        true => format!("{{{}:{},{}:[{}]}}", quote(TYPE_KEY), quote(name), quote(ARGS_KEY), quote(text)),
+        false => format!("{name}({})", quote(text)),
+    }
+}
+
+fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Encodes events as DOSS
+#[derive(Debug)]
+pub struct DossWriter<W: Write> {
+    serializer: DossSerializer<W>,
+}
+
+impl<W: Write> DossWriter<W> {
+    pub fn new(serializer: DossSerializer<W>) -> Self {
+        DossWriter { serializer }
+    }
+
+    pub fn finish(self) -> Result<W, GateError> {
+        Ok(self.serializer.finish()?)
+    }
+}
+
+impl<W: Write> EventSink for DossWriter<W> {
+    fn write(&mut self, event: TypedStreamEvent) -> Result<(), GateError> {
+        Ok(self.serializer.write_event(&event)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_writer() {
+        use TypedStreamEvent::*;
+        let datetime = "2025-01-01T00:00:00Z".parse().unwrap();
+        let events = || {
+            vec![
+                STARTOBJECT,
+                STRING(String::from("a\"b")),
+                STARTARRAY,
+                DECIMAL(1),
+                ANY(Box::new(-2i64)),
+                FLOAT(0.5),
+                ENDARRAY,
+                DECIMAL(3),
+                STARTTYPE(String::from("ObjectId")),
+                STRING(String::from("5f")),
+                ENDTYPE,
+                STRING(String::from("when")),
+                DATETIME(datetime),
+                ENDOBJECT,
+                TRUE,
+            ]
+        };
+        let mut sjson = TextWriter::sjson(Vec::new());
+        let mut json = TextWriter::json(Vec::new());
+        for (a, b) in events().into_iter().zip(events()) {
+            sjson.write(a).unwrap();
+            json.write(b).unwrap();
+        }
+        assert_eq!(
+            String::from_utf8(sjson.finish().unwrap()).unwrap(),
+            "{\"a\\\"b\":[1,-2,0.5],3:ObjectId(\"5f\"),\"when\":datetime(\"2025-01-01T00:00:00Z\")}\ntrue\n"
+        );
+        assert_eq!(
+            String::from_utf8(json.finish().unwrap()).unwrap(),
+            concat!(
+                r#"{"a\"b":[1,-2,0.5],"3":{"$type":"ObjectId","$args":["5f"]},"when":{"$type":"datetime","$args":["2025-01-01T00:00:00Z"]}}"#,
+                "\ntrue\n"
+            )
+        );
+        let mut json = TextWriter::json(Vec::new());
+        json.write(STARTOBJECT).unwrap();
+        assert!(json.write(STARTARRAY).is_err());
+    }
+}
@@ -0,0 +1,310 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::fmt;
+use std::str::FromStr;
+
+use dataflowgrid_commons::typedstream::TypedStreamEvent;
+
+use crate::error::GateError;
+use crate::value::Scalar;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Segment {
+    Key(String),
+    /// every element of an array
+    Elements,
+}
+
+/// The location of values within a record: keys separated by dots, `[]` after a key stands for the
+/// elements of its array, like `items[].product`. The empty path is the record itself
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Path(Vec<Segment>);
+
+impl Path {
+    pub fn root() -> Path {
+        Path(Vec::new())
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.0
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn child(&self, segment: Segment) -> Path {
+        let mut segments = self.0.clone();
+        segments.push(segment);
+        Path(segments)
+    }
+
+    pub fn parent(&self) -> Option<Path> {
+        self.0.split_last().map(|(_, parent)| Path(parent.to_vec()))
+    }
+
+    /// The key of the last segment
+    pub fn key(&self) -> Option<&str> {
+        match self.0.last() {
+            Some(Segment::Key(key)) => Some(key),
+            _ => None,
+        }
+    }
+
+    pub fn starts_with(&self, prefix: &Path) -> bool {
+        self.0.starts_with(&prefix.0)
+    }
+
+    /// True if values at the path can repeat within a record
+    pub fn has_elements(&self) -> bool {
+        self.0.contains(&Segment::Elements)
+    }
+}
+
+impl FromStr for Path {
+    type Err = GateError;
+
+    fn from_str(s: &str) -> Result<Path, GateError> {
+        let mut segments = Vec::new();
+        if s.is_empty() {
+            return Ok(Path(segments));
+        }
+        for part in s.split('.') {
+            let mut key = part;
+            let mut elements = 0;
+            while let Some(rest) = key.strip_suffix("[]") {
+                key = rest;
+                elements += 1;
+            }
+            if key.is_empty() && (elements == 0 || !segments.is_empty()) {
+                return Err(GateError::Definition(format!("empty key in path {s}")));
+            }
+            if !key.is_empty() {
+                segments.push(Segment::Key(key.to_string()));
+            }
+            segments.extend(std::iter::repeat_n(Segment::Elements, elements));
+        }
+        Ok(Path(segments))
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.0.iter().enumerate() {
+            match segment {
+                Segment::Key(key) if i > 0 => write!(f, ".{key}")?,
+                Segment::Key(key) => write!(f, "{key}")?,
+                Segment::Elements => write!(f, "[]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Where an event is within the records of a stream, see [`Cursor::push`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Position {
+    /// between records: the array holding them, hints, INIT and FINISH
+    Outside,
+    /// the start of a key, with the path of its field if the key is a scalar
+    Key(Option<Path>),
+    /// the start of a value, None inside types and keys that are no scalars
+    Value(Option<Path>),
+    /// the end of an object, array or type
+    End,
+}
+
+#[derive(Debug)]
+enum Field {
+    Key,
+    //a key that is an object, array or type
+    ComplexKey,
+    //the value of the field comes next
+    Value(Option<Path>),
+}
+
+#[derive(Debug)]
+enum Frame {
+    //the top level array whose elements are the records
+    Records,
+    Array(Option<Path>),
+    Object(Option<Path>, Field),
+    Type,
+}
+
+/// Follows the structure of a typed stream. Records are top level values, a top level array holds
+/// a record in each element like the JSON export of a collection
+#[derive(Debug, Default)]
+pub struct Cursor {
+    stack: Vec<Frame>,
+    record_ended: bool,
+}
+
+impl Cursor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Levels open, the array of records included
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// True if the last event pushed completed a record
+    pub fn record_ended(&self) -> bool {
+        self.record_ended
+    }
+
+    /// Records the event and returns where it is
+    pub fn push(&mut self, event: &TypedStreamEvent) -> Result<Position, GateError> {
+        self.record_ended = false;
+        match event {
+            TypedStreamEvent::INIT | TypedStreamEvent::FINISH | TypedStreamEvent::HINT(_) | TypedStreamEvent::ERROR(_) => Ok(Position::Outside),
+            TypedStreamEvent::ENDOBJECT | TypedStreamEvent::ENDARRAY | TypedStreamEvent::ENDTYPE => {
+                let frame = self.stack.pop();
+                match (event, &frame) {
+                    (TypedStreamEvent::ENDOBJECT, Some(Frame::Object(_, Field::Key)))
+                    | (TypedStreamEvent::ENDARRAY, Some(Frame::Array(_)))
+                    | (TypedStreamEvent::ENDTYPE, Some(Frame::Type)) => {
+                        self.completed();
+                        Ok(Position::End)
+                    }
+                    (TypedStreamEvent::ENDARRAY, Some(Frame::Records)) => Ok(Position::Outside),
+                    _ => Err(GateError::UnbalancedStructure),
+                }
+            }
+            TypedStreamEvent::STARTARRAY if self.stack.is_empty() => {
+                self.stack.push(Frame::Records);
+                Ok(Position::Outside)
+            }
+            _ => {
+                let position = self.position(event);
+                let path = match &position {
+                    Position::Value(path) => path.clone(),
+                    _ => None,
+                };
+                match event {
+                    TypedStreamEvent::STARTOBJECT | TypedStreamEvent::STARTARRAY | TypedStreamEvent::STARTTYPE(_) => {
+                        if let Some(Frame::Object(_, field @ Field::Key)) = self.stack.last_mut() {
+                            *field = Field::ComplexKey;
+                        }
+                        self.stack.push(match event {
+                            TypedStreamEvent::STARTOBJECT => Frame::Object(path, Field::Key),
+                            TypedStreamEvent::STARTARRAY => Frame::Array(path),
+                            _ => Frame::Type,
+                        });
+                    }
+                    _ => match (self.stack.last_mut(), &position) {
+                        (Some(Frame::Object(_, field @ Field::Key)), Position::Key(path)) => *field = Field::Value(path.clone()),
+                        _ => self.completed(),
+                    },
+                }
+                Ok(position)
+            }
+        }
+    }
+
+    fn position(&self, event: &TypedStreamEvent) -> Position {
+        match self.stack.last() {
+            None | Some(Frame::Records) => Position::Value(Some(Path::root())),
+            Some(Frame::Array(path)) => Position::Value(path.as_ref().map(|p| p.child(Segment::Elements))),
+            Some(Frame::Object(path, Field::Key)) => Position::Key(match (path, Scalar::from_event(event)) {
+                (Some(path), Some(Scalar::String(key))) => Some(path.child(Segment::Key(key))),
+                (Some(path), Some(key)) => Some(path.child(Segment::Key(key.to_string()))),
+                _ => None,
+            }),
+            Some(Frame::Object(_, Field::Value(path))) => Position::Value(path.clone()),
+            Some(Frame::Object(_, Field::ComplexKey) | Frame::Type) => Position::Value(None),
+        }
+    }
+
+    //a key or value is complete at the top of the stack
+    fn completed(&mut self) {
+        match self.stack.last_mut() {
+            None | Some(Frame::Records) => self.record_ended = true,
+            Some(Frame::Object(_, field)) => {
+                *field = match field {
+                    Field::ComplexKey => Field::Value(None),
+                    _ => Field::Key,
+                }
+            }
+            Some(Frame::Array(_) | Frame::Type) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_path() {
+        for text in ["", "a", "a.b", "items[].product", "matrix[][]", "[]"] {
+            assert_eq!(text.parse::<Path>().unwrap().to_string(), text);
+        }
+        assert_eq!("a.b".parse::<Path>().unwrap().parent(), Some("a".parse().unwrap()));
+        assert!("a..b".parse::<Path>().is_err());
+        assert!("a.[]".parse::<Path>().is_err());
+    }
+
+    #[test]
+    fn test_positions() {
+        use TypedStreamEvent::*;
+        let events = [
+            STARTARRAY,
+            STARTOBJECT,
+            STRING(String::from("a")),
+            DECIMAL(1),
+            STRING(String::from("items")),
+            STARTARRAY,
+            STARTOBJECT,
+            STRING(String::from("b")),
+            STARTTYPE(String::from("ObjectId")),
+            STRING(String::from("5f")),
+            ENDTYPE,
+            ENDOBJECT,
+            ENDARRAY,
+            STARTTYPE(String::from("Key")),
+            NULL,
+            ENDTYPE,
+            TRUE,
+            ENDOBJECT,
+            DECIMAL(2),
+            ENDARRAY,
+        ];
+        let path = |p: &str| Some(p.parse::<Path>().unwrap());
+        let expected = [
+            Position::Outside,
+            Position::Value(path("")),
+            Position::Key(path("a")),
+            Position::Value(path("a")),
+            Position::Key(path("items")),
+            Position::Value(path("items")),
+            Position::Value(path("items[]")),
+            Position::Key(path("items[].b")),
+            Position::Value(path("items[].b")),
+            Position::Value(None),
+            Position::End,
+            Position::End,
+            Position::End,
+            Position::Key(None),
+            Position::Value(None),
+            Position::End,
+            Position::Value(None),
+            Position::End,
+            Position::Value(path("")),
+            Position::Outside,
+        ];
+        let mut cursor = Cursor::new();
+        let mut ended = Vec::new();
+        for (i, (event, expected)) in events.iter().zip(expected).enumerate() {
+            assert_eq!(cursor.push(event).unwrap(), expected, "event {i}");
+            if cursor.record_ended() {
+                ended.push(i);
+            }
+        }
+        assert_eq!(ended, [17, 18]);
+        assert_eq!(cursor.depth(), 0);
+        assert!(cursor.push(&ENDOBJECT).is_err());
+    }
+}
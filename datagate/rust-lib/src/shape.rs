@@ -0,0 +1,190 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::collections::HashSet;
+
+use dataflowgrid_commons::typedstream::TypedStreamEvent;
+
+use crate::error::GateError;
+use crate::path::{Cursor, Path, Position};
+use crate::transform::Stage;
+use crate::value::Scalar;
+
+/// [`Step::Flatten`](crate::definition::Step::Flatten). Nested objects dissolve into the object at the path,
+/// their keys joined. Arrays and types stay values, objects without fields disappear
+#[derive(Debug)]
+pub(crate) struct Flatten {
+    cursor: Cursor,
+    path: Path,
+    separator: String,
+    //the depth inside the flattened object and the objects dissolved into it, with the prefix of their keys
+    levels: Vec<(usize, String)>,
+    //a key waiting for its value, as it came and joined with the prefix
+    key: Option<(TypedStreamEvent, String)>,
+}
+
+impl Flatten {
+    pub fn new(path: Path, separator: String) -> Self {
+        Flatten { cursor: Cursor::new(), path, separator, levels: Vec::new(), key: None }
+    }
+}
+
+impl Stage for Flatten {
+    fn push(&mut self, event: TypedStreamEvent, out: &mut Vec<TypedStreamEvent>) -> Result<(), GateError> {
+        let depth = self.cursor.depth();
+        let position = self.cursor.push(&event)?;
+        let Some((level, prefix)) = self.levels.last().filter(|(level, _)| *level == depth).cloned() else {
+            if self.levels.is_empty() && matches!(event, TypedStreamEvent::STARTOBJECT) && position == Position::Value(Some(self.path.clone())) {
+                self.levels.push((self.cursor.depth(), String::new()));
+            }
+            out.push(event);
+            return Ok(());
+        };
+        match position {
+            Position::Key(Some(_)) => {
+                let key = match Scalar::from_event(&event) {
+                    Some(Scalar::String(key)) => key,
+                    key => key.map(|key| key.to_string()).unwrap_or_default(),
+                };
+                let joined = match prefix.is_empty() {
+                    true => key,
+                    false => format!("{prefix}{}{key}", self.separator),
+                };
+                self.key = Some((event, joined));
+            }
+            Position::Value(_) if let Some((key, joined)) = self.key.take() => match event {
+                TypedStreamEvent::STARTOBJECT => self.levels.push((self.cursor.depth(), joined)),
+                event => {
+                    out.push(if level == self.levels[0].0 { key } else { TypedStreamEvent::STRING(joined) });
+                    out.push(event);
+                }
+            },
+            Position::End => {
+                self.levels.pop();
+                //the ends of dissolved objects go with them
+                if self.levels.is_empty() {
+                    out.push(event);
+                }
+            }
+            _ => out.push(event),
+        }
+        Ok(())
+    }
+}
+
+/// [`Step::Nest`](crate::definition::Step::Nest). Only the fields moved are held back, the new object
+/// follows once they are all there or before the end of their object
+#[derive(Debug)]
+pub(crate) struct Nest {
+    cursor: Cursor,
+    parent: Path,
+    name: String,
+    keys: Vec<(String, String)>,
+    //the depth inside the object the fields are moved from
+    inside: Option<usize>,
+    found: HashSet<String>,
+    fields: Vec<TypedStreamEvent>,
+    //the events are part of a field moved
+    moving: bool,
+    done: bool,
+}
+
+impl Nest {
+    pub fn new(path: Path, keys: Vec<(String, String)>) -> Self {
+        Nest {
+            cursor: Cursor::new(),
+            parent: path.parent().unwrap_or_default(),
+            name: path.key().unwrap_or_default().to_string(),
+            keys,
+            inside: None,
+            found: HashSet::new(),
+            fields: Vec::new(),
+            moving: false,
+            done: false,
+        }
+    }
+
+    fn emit(&mut self, out: &mut Vec<TypedStreamEvent>) {
+        if self.done || self.fields.is_empty() {
+            return;
+        }
+        out.push(TypedStreamEvent::STRING(self.name.clone()));
+        out.push(TypedStreamEvent::STARTOBJECT);
+        out.append(&mut self.fields);
+        out.push(TypedStreamEvent::ENDOBJECT);
+        self.done = true;
+    }
+}
+
+impl Stage for Nest {
+    fn push(&mut self, event: TypedStreamEvent, out: &mut Vec<TypedStreamEvent>) -> Result<(), GateError> {
+        let depth = self.cursor.depth();
+        let position = self.cursor.push(&event)?;
+        let Some(inside) = self.inside else {
+            if matches!(event, TypedStreamEvent::STARTOBJECT) && position == Position::Value(Some(self.parent.clone())) {
+                self.inside = Some(self.cursor.depth());
+                self.found.clear();
+                self.done = false;
+            }
+            out.push(event);
+            return Ok(());
+        };
+        if self.moving {
+            self.fields.push(event);
+            if self.cursor.depth() == inside {
+                self.moving = false;
+                if self.found.len() == self.keys.len() {
+                    self.emit(out);
+                }
+            }
+            return Ok(());
+        }
+        if depth == inside {
+            match &position {
+                Position::Key(Some(path)) if !self.done => {
+                    let key = path.key().unwrap_or_default();
+                    if let Some((from, to)) = self.keys.iter().find(|(from, _)| from == key)
+                        && self.found.insert(from.clone())
+                    {
+                        self.fields.push(TypedStreamEvent::STRING(to.clone()));
+                        self.moving = true;
+                        return Ok(());
+                    }
+                }
+                Position::End => {
+                    self.emit(out);
+                    self.inside = None;
+                }
+                _ => {}
+            }
+        }
+        out.push(event);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transform::tests::apply;
+
+    #[test]
+    fn test_flatten() {
+        let input = r#"{"id": 1, "address": {"city": "Graz", "geo": {"lat": 47, "tags": [{"a": 1}]}, "empty": {}}, "n": null}"#;
+        assert_eq!(
+            apply(r#"flatten({"path": "address", "separator": "."})"#, input),
+            "{\"id\":1,\"address\":{\"city\":\"Graz\",\"geo.lat\":47,\"geo.tags\":[{\"a\":1}]},\"n\":null}\n"
+        );
+        assert_eq!(
+            apply(r#"flatten("")"#, input),
+            "{\"id\":1,\"address_city\":\"Graz\",\"address_geo_lat\":47,\"address_geo_tags\":[{\"a\":1}],\"n\":null}\n"
+        );
+    }
+
+    #[test]
+    fn test_nest() {
+        let input = r#"[{"name": "Ada", "email": "a@x", "id": 1}, {"id": 2, "email": "b@x"}, {"id": 3}]"#;
+        assert_eq!(
+            apply(r#"nest({"contact": {"name": "n", "email": "e"}})"#, input),
+            "[{\"contact\":{\"n\":\"Ada\",\"e\":\"a@x\"},\"id\":1},{\"id\":2,\"contact\":{\"e\":\"b@x\"}},{\"id\":3}]\n"
+        );
+    }
+}
@@ -0,0 +1,123 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::fmt;
+
+use dataflowgrid_commons::typedstream::TypedStreamEvent;
+
+use crate::definition::{Step, parse_steps};
+use crate::error::GateError;
+use crate::fields::{Rename, Select};
+use crate::filter::Filter;
+use crate::map::{Convert, Map};
+use crate::output::EventSink;
+use crate::path::Cursor;
+use crate::shape::{Flatten, Nest};
+
+/// One step working on the events as they pass
+pub(crate) trait Stage: fmt::Debug {
+    fn push(&mut self, event: TypedStreamEvent, out: &mut Vec<TypedStreamEvent>) -> Result<(), GateError>;
+}
+
+/// Drops the rest of a value after its first event
+#[derive(Debug, Default)]
+pub(crate) struct Skip(Option<usize>);
+
+impl Skip {
+    /// Skips the level the last event opened, `depth` is the depth of the cursor before that event
+    pub fn start(&mut self, cursor: &Cursor, depth: usize) {
+        if cursor.depth() > depth {
+            self.0 = Some(depth);
+        }
+    }
+
+    /// True if the event belongs to the value being skipped
+    pub fn skipping(&mut self, cursor: &mut Cursor, event: &TypedStreamEvent) -> Result<bool, GateError> {
+        let Some(depth) = self.0 else {
+            return Ok(false);
+        };
+        cursor.push(event)?;
+        if cursor.depth() == depth {
+            self.0 = None;
+        }
+        Ok(true)
+    }
+}
+
+/// The steps of a transform applied to a typed stream while it passes. Only filter, nest and convert hold events
+/// back: a record until its filter is decided, the fields to nest until they are complete and a type until it ends.
+#[derive(Debug)]
+pub struct Transform {
+    stages: Vec<Box<dyn Stage>>,
+    events: Vec<TypedStreamEvent>,
+    next: Vec<TypedStreamEvent>,
+}
+
+impl Transform {
+    pub fn new(steps: Vec<Step>) -> Self {
+        let stages = steps
+            .into_iter()
+            .map(|step| -> Box<dyn Stage> {
+                match step {
+                    Step::Rename(names) => Box::new(Rename::new(names)),
+                    Step::Drop(paths) => Box::new(Select::drop(paths)),
+                    Step::Keep(paths) => Box::new(Select::keep(paths)),
+                    Step::Map(functions) => Box::new(Map::new(functions)),
+                    Step::Filter(predicate) => Box::new(Filter::new(predicate)),
+                    Step::Flatten { path, separator } => Box::new(Flatten::new(path, separator)),
+                    Step::Nest { path, keys } => Box::new(Nest::new(path, keys)),
+                    Step::Convert(conversions) => Box::new(Convert::new(conversions)),
+                }
+            })
+            .collect();
+        Transform { stages, events: Vec::new(), next: Vec::new() }
+    }
+
+    /// A transform written in streamablejson, see [`parse_steps`]
+    pub fn parse(definition: &str) -> Result<Self, GateError> {
+        Ok(Self::new(parse_steps(definition)?))
+    }
+
+    /// Passes one event through all steps, what comes out goes to `sink`
+    pub fn push(&mut self, event: TypedStreamEvent, sink: &mut dyn EventSink) -> Result<(), GateError> {
+        self.events.push(event);
+        for stage in &mut self.stages {
+            for event in self.events.drain(..) {
+                stage.push(event, &mut self.next)?;
+            }
+            std::mem::swap(&mut self.events, &mut self.next);
+        }
+        for event in self.events.drain(..) {
+            sink.write(event)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::input::read_text;
+    use crate::output::TextWriter;
+
+    use super::*;
+
+    /// The records of `input` transformed, as streamablejson text
+    pub fn apply(definition: &str, input: &str) -> String {
+        let mut transform = Transform::parse(definition).unwrap();
+        let mut writer = TextWriter::sjson(Vec::new());
+        read_text(input.as_bytes(), &mut |event| transform.push(event, &mut writer)).unwrap();
+        String::from_utf8(writer.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_steps_in_order() {
+        let definition = r#"[
+            convert({"ObjectId": "string"}),
+            rename({"_id": "id"}),
+            filter(eq({"status": "paid"})),
+            drop("status")
+        ]"#;
+        let input = r#"[{"_id": ObjectId("5f1"), "status": "paid"}, {"_id": ObjectId("5f2"), "status": "open"}, {"status": "paid", "_id": 3}]"#;
+        assert_eq!(apply(definition, input), "[{\"id\":\"5f1\"},{\"id\":3}]\n");
+        assert_eq!(apply("[]", input), apply("[]", &apply("[]", input)));
+    }
+}
@@ -0,0 +1,179 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use dataflowgrid_commons::typedstream::{DateTime, TypedStreamEvent};
+use doss::{Decimal, DossFloat};
+
+/// An owned scalar of a typed stream, the values predicates compare and functions produce
+#[derive(Debug, Clone, PartialEq)]
+pub enum Scalar {
+    Null,
+    Bool(bool),
+    UInt(u64),
+    Int(i64),
+    Float(f64),
+    Decimal(Decimal),
+    String(String),
+    DateTime(DateTime),
+    Bytes(Vec<u8>),
+}
+
+impl Scalar {
+    /// None for events that open or close a level and for events without a value
+    pub fn from_event(event: &TypedStreamEvent) -> Option<Scalar> {
+        Some(match event {
+            TypedStreamEvent::NULL => Scalar::Null,
+            TypedStreamEvent::TRUE => Scalar::Bool(true),
+            TypedStreamEvent::FALSE => Scalar::Bool(false),
+            TypedStreamEvent::DECIMAL(v) => Scalar::UInt(*v as u64),
+            TypedStreamEvent::FLOAT(v) => Scalar::Float(*v),
+            TypedStreamEvent::STRING(s) => Scalar::String(s.clone()),
+            TypedStreamEvent::DATETIME(d) => Scalar::DateTime(*d),
+            TypedStreamEvent::BYTEARRAY(b) => Scalar::Bytes(b.clone()),
+            TypedStreamEvent::ANY(v) => {
+                if let Some(v) = v.downcast_ref::<i64>() {
+                    Scalar::Int(*v)
+                } else if let Some(v) = v.downcast_ref::<u64>() {
+                    Scalar::UInt(*v)
+                } else {
+                    Scalar::Decimal(v.downcast_ref::<Decimal>()?.clone())
+                }
+            }
+            _ => return None,
+        })
+    }
+
+    /// The event as [`DossEvent::to_typed_stream_event`](doss::DossEvent::to_typed_stream_event) creates it
+    pub fn to_event(&self) -> TypedStreamEvent {
+        match self {
+            Scalar::Null => TypedStreamEvent::NULL,
+            Scalar::Bool(true) => TypedStreamEvent::TRUE,
+            Scalar::Bool(false) => TypedStreamEvent::FALSE,
+            Scalar::UInt(v) => match usize::try_from(*v) {
+                Ok(v) => TypedStreamEvent::DECIMAL(v),
+                Err(_) => TypedStreamEvent::ANY(Box::new(*v)),
+            },
+            Scalar::Int(v) => match usize::try_from(*v) {
+                Ok(v) => TypedStreamEvent::DECIMAL(v),
+                Err(_) => TypedStreamEvent::ANY(Box::new(*v)),
+            },
+            Scalar::Float(v) => TypedStreamEvent::FLOAT(*v),
+            Scalar::Decimal(d) => match d.get_usize() {
+                Ok(v) => TypedStreamEvent::DECIMAL(v),
+                Err(_) => TypedStreamEvent::ANY(Box::new(d.clone())),
+            },
+            Scalar::String(s) => TypedStreamEvent::STRING(s.clone()),
+            Scalar::DateTime(d) => TypedStreamEvent::DATETIME(*d),
+            Scalar::Bytes(b) => TypedStreamEvent::BYTEARRAY(b.clone()),
+        }
+    }
+
+    /// A constant of streamablejson text: `true`, `false`, `null` or a number
+    pub fn parse_constant(text: &str) -> Option<Scalar> {
+        Some(match text {
+            "true" => Scalar::Bool(true),
+            "false" => Scalar::Bool(false),
+            "null" => Scalar::Null,
+            _ => Scalar::parse_number(text)?,
+        })
+    }
+
+    /// Integers as exact as possible, decimals with a fraction stay exact as well
+    pub fn parse_number(text: &str) -> Option<Scalar> {
+        if let Ok(v) = text.parse::<u64>() {
+            Some(Scalar::UInt(v))
+        } else if let Ok(v) = text.parse::<i64>() {
+            Some(Scalar::Int(v))
+        } else if !text.contains(['e', 'E']) && let Ok(d) = text.parse::<Decimal>() {
+            Some(Scalar::Decimal(d))
+        } else {
+            text.parse::<f64>().ok().filter(|v| v.is_finite()).map(Scalar::Float)
+        }
+    }
+
+    fn decimal(&self) -> Option<Decimal> {
+        match self {
+            Scalar::UInt(v) => Some(Decimal::from(*v)),
+            Scalar::Int(v) => Some(Decimal::from(*v)),
+            Scalar::Decimal(d) => Some(d.clone()),
+            _ => None,
+        }
+    }
+
+    fn float(&self) -> Option<f64> {
+        match self {
+            Scalar::Float(v) => Some(*v),
+            _ => self.decimal().map(|d| d.to_f64()),
+        }
+    }
+
+    /// Numbers compare by value whatever their kind, other values only with values of their own kind
+    pub fn compare(&self, other: &Scalar) -> Option<Ordering> {
+        match (self, other) {
+            (Scalar::Null, Scalar::Null) => Some(Ordering::Equal),
+            (Scalar::Bool(a), Scalar::Bool(b)) => Some(a.cmp(b)),
+            (Scalar::String(a), Scalar::String(b)) => Some(a.cmp(b)),
+            (Scalar::DateTime(a), Scalar::DateTime(b)) => Some(a.unix_nanos().cmp(&b.unix_nanos())),
+            (Scalar::Bytes(a), Scalar::Bytes(b)) => Some(a.cmp(b)),
+            (a, b) => match (a.decimal(), b.decimal()) {
+                (Some(a), Some(b)) => Some(a.cmp(&b)),
+                _ => a.float()?.partial_cmp(&b.float()?),
+            },
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        *self == Scalar::Null
+    }
+}
+
+/// Strings without quotes, date times as RFC 3339 and bytes as hex
+impl fmt::Display for Scalar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scalar::Null => write!(f, "null"),
+            Scalar::Bool(v) => write!(f, "{v}"),
+            Scalar::UInt(v) => write!(f, "{v}"),
+            Scalar::Int(v) => write!(f, "{v}"),
+            Scalar::Float(v) => write!(f, "{}", DossFloat::shortest(*v)),
+            Scalar::Decimal(d) => write!(f, "{d}"),
+            Scalar::String(s) => write!(f, "{s}"),
+            Scalar::DateTime(d) => write!(f, "{d}"),
+            Scalar::Bytes(b) => b.iter().try_for_each(|b| write!(f, "{b:02x}")),
+        }
+    }
+}
+
+/// Bytes of hex text, None if it is no valid hex
+pub fn parse_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len()).step_by(2).map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_and_events() {
+        let one = [Scalar::UInt(1), Scalar::Int(1), Scalar::Float(1.0), Scalar::parse_number("1.00").unwrap()];
+        for a in &one {
+            for b in &one {
+                assert_eq!(a.compare(b), Some(Ordering::Equal), "{a:?} {b:?}");
+            }
+        }
+        assert_eq!(Scalar::Int(-2).compare(&Scalar::Float(0.5)), Some(Ordering::Less));
+        assert_eq!(Scalar::String(String::from("1")).compare(&Scalar::UInt(1)), None);
+
+        for scalar in [Scalar::Int(-2), Scalar::UInt(u64::MAX), Scalar::parse_number("-1.25").unwrap(), Scalar::Bytes(vec![0, 255])] {
+            assert_eq!(Scalar::from_event(&scalar.to_event()), Some(scalar));
+        }
+        assert_eq!(Scalar::Bytes(vec![0, 255]).to_string(), "00ff");
+        assert_eq!(parse_hex("00ff"), Some(vec![0, 255]));
+        assert_eq!(Scalar::parse_constant("1e3"), Some(Scalar::Float(1000.0)));
+    }
+}
@@ -0,0 +1,108 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::io::{Read, Write};
+use std::sync::Arc;
+
+use dataflowgrid_commons::readers::io::ReadReadable;
+use doss::{DossDictionaryRegistry, DossEvent, DossReader, DossReaderCallback, DossReaderCallbackReturn, DossReaderPushResult};
+
+use crate::error::CliError;
+use crate::sjson::SjsonWriter;
+
+/// Reads a whole stream from `input` and hands the resolved events to `callback`
+pub fn read(input: impl Read, callback: &mut dyn DossReaderCallback, registry: Option<Arc<DossDictionaryRegistry>>) -> Result<(), CliError> {
+    let mut reader = DossReader::new(callback);
+    if let Some(registry) = registry {
+        reader.set_registry(registry);
+    }
+    let mut data = ReadReadable::new(input);
+    loop {
+        match reader.pushdata(&mut data)? {
+            DossReaderPushResult::NeedMoreData => continue,
+            DossReaderPushResult::EndOfData => return Ok(reader.finish()?),
+            DossReaderPushResult::Stopped => return Ok(()),
+        }
+    }
+}
+
+impl<W: Write> DossReaderCallback for SjsonWriter<W> {
+    fn on_doss_event(&mut self, event: DossEvent) -> DossReaderCallbackReturn {
+        match self.write_event(&event) {
+            Ok(()) => DossReaderCallbackReturn::Continue,
+            Err(e) => DossReaderCallbackReturn::StopErr(Box::new(e)),
+        }
+    }
+}
+
+/// Writes the content of a stream as streamablejson
+pub fn decode<W: Write>(input: impl Read, output: W, pretty: bool, registry: Option<Arc<DossDictionaryRegistry>>) -> Result<W, CliError> {
+    let mut writer = if pretty { SjsonWriter::pretty(output) } else { SjsonWriter::compact(output) };
+    read(input, &mut writer, registry)?;
+    writer.flush()?;
+    Ok(writer.into_inner())
+}
+
+/// What `validate` found in a valid stream
+#[derive(Debug, Default, PartialEq)]
+pub struct Summary {
+    pub values: u64, //top level values
+    pub events: u64,
+    pub files: u64,
+    pub max_depth: usize,
+    depth: usize,
+}
+
+impl DossReaderCallback for Summary {
+    fn on_doss_event(&mut self, event: DossEvent) -> DossReaderCallbackReturn {
+        self.events += 1;
+        match event.depth_change() {
+            1 => {
+                self.depth += 1;
+                self.max_depth = self.max_depth.max(self.depth);
+            }
+            -1 => {
+                self.depth -= 1;
+                if self.depth == 0 {
+                    self.values += 1;
+                }
+            }
+            _ => match event {
+                DossEvent::FileStart(_) => self.files += 1,
+                DossEvent::Config { .. } | DossEvent::Hint { .. } | DossEvent::ImportDict(_) | DossEvent::StackStart | DossEvent::StackEnd => {}
+                _ if self.depth == 0 => self.values += 1,
+                _ => {}
+            },
+        }
+        DossReaderCallbackReturn::Continue
+    }
+}
+
+/// Decodes the whole stream without output
+pub fn validate(input: impl Read, registry: Option<Arc<DossDictionaryRegistry>>) -> Result<Summary, CliError> {
+    let mut summary = Summary::default();
+    read(input, &mut summary, registry)?;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use doss::{DossError, DossSerializer};
+
+    use super::*;
+
+    #[test]
+    fn test_decode_and_validate() {
+        let mut serializer = DossSerializer::new(Vec::new());
+        for event in [DossEvent::BlockStart, DossEvent::String(String::from("a")), DossEvent::ArrayStart, DossEvent::UInt(1), DossEvent::ArrayEnd, DossEvent::BlockEnd, DossEvent::Null] {
+            serializer.write_doss_event(&event).unwrap();
+        }
+        let encoded = serializer.finish().unwrap();
+        let decoded = decode(encoded.as_slice(), Vec::new(), true, None).unwrap();
+        assert_eq!(String::from_utf8(decoded).unwrap(), "{\n  \"a\": [\n    1\n  ]\n}\nnull\n");
+
+        let summary = validate(encoded.as_slice(), None).unwrap();
+        assert_eq!((summary.values, summary.files, summary.max_depth), (2, 0, 2));
+        assert!(matches!(validate(&encoded[..encoded.len() - 2], None), Err(CliError::Doss(DossError::UnexpectedEof))));
+        assert!(matches!(validate([9, 5].as_slice(), None), Err(CliError::Doss(DossError::InvalidReference(5)))));
+    }
+}
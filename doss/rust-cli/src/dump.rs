@@ -0,0 +1,115 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::io::{Read, Write};
+use std::sync::Arc;
+
+use doss::{DossDictionaryRegistry, DossFloat, DossLowLevelStreamEvent, DossResolver};
+
+use crate::error::CliError;
+use crate::scan::{MAX_OPERANDS, Scanner};
+use crate::sjson::{item_text, quote};
+
+const MAX_TEXT: usize = 40;
+
+/// Lists every opcode with its offset, operand bytes and meaning, indented by nesting level.
+/// The listing stops at the first error, after the line of the opcode that caused it
+pub fn dump(input: impl Read, mut output: impl Write, registry: Option<Arc<DossDictionaryRegistry>>) -> Result<(), CliError> {
+    let mut scanner = Scanner::new(input, registry);
+    let mut depth = 0;
+    writeln!(output, "offset    op  operands                   description")?;
+    while let Some(scanned) = scanner.next_event()? {
+        let event = &scanned.event;
+        if matches!(event, DossLowLevelStreamEvent::BlockEnd | DossLowLevelStreamEvent::ArrayEnd | DossLowLevelStreamEvent::TypeEnd) {
+            depth = usize::saturating_sub(depth, 1);
+        }
+        let mut operands = scanned.operands.iter().map(|b| format!("{b:02x}")).collect::<Vec<_>>().join(" ");
+        if scanned.len > MAX_OPERANDS + 1 {
+            operands.push_str(" ..");
+        }
+        let description = describe(event, scanner.resolver());
+        writeln!(output, "{:08x}  {:02}  {operands:<26} {}{description}", scanned.offset, event.opcode(), "  ".repeat(depth))?;
+        if matches!(event, DossLowLevelStreamEvent::BlockStart | DossLowLevelStreamEvent::ArrayStart | DossLowLevelStreamEvent::TypeStart) {
+            depth += 1;
+        }
+        scanner.resolve(scanned.event)?;
+    }
+    scanner.finish()?;
+    output.flush()?;
+    Ok(())
+}
+
+//the wording of docs/opcodes.md, with the dictionary state before the event
+fn describe(event: &DossLowLevelStreamEvent, resolver: &DossResolver) -> String {
+    let pointer = resolver.dictionary().pointer();
+    match event {
+        DossLowLevelStreamEvent::NoOp => String::from("noop"),
+        DossLowLevelStreamEvent::True => String::from("constant true"),
+        DossLowLevelStreamEvent::False => String::from("constant false"),
+        DossLowLevelStreamEvent::Varint(v) => format!("signed varint value {v}"),
+        DossLowLevelStreamEvent::Decimal(d) => format!("decimal value {d}"),
+        DossLowLevelStreamEvent::Float(f @ DossFloat::F32(_)) => format!("float value {f} (f32)"),
+        DossLowLevelStreamEvent::Float(f @ DossFloat::F64(_)) => format!("float value {f} (f64)"),
+        DossLowLevelStreamEvent::DateTime(d) => format!("datetime value {d}"),
+        DossLowLevelStreamEvent::String(s) => format!("{} string {}", resolver.settings().string_encoding().name(), shorten(&quote(s))),
+        DossLowLevelStreamEvent::Binary(b) => format!("binary string of {} bytes", b.len()),
+        DossLowLevelStreamEvent::Reference(index) => match resolver.dictionary().get(*index) {
+            Ok(item) => format!("dict reference {index} --> {}", item_text(&item, MAX_TEXT)),
+            Err(_) => format!("dict reference {index} --> empty entry"),
+        },
+        DossLowLevelStreamEvent::BlockStart => String::from("start object"),
+        DossLowLevelStreamEvent::BlockEnd => String::from("end object"),
+        DossLowLevelStreamEvent::ArrayStart => String::from("start array"),
+        DossLowLevelStreamEvent::ArrayEnd => String::from("end array"),
+        DossLowLevelStreamEvent::Null => String::from("constant null"),
+        DossLowLevelStreamEvent::UnsignedVarint(v) => format!("unsigned varint value {v}"),
+        DossLowLevelStreamEvent::TypeStart => String::from("start type"),
+        DossLowLevelStreamEvent::TypeEnd => String::from("end type"),
+        DossLowLevelStreamEvent::SetConfig => String::from("set config"),
+        DossLowLevelStreamEvent::StoreInDict => format!("store next item in dict: {pointer}"),
+        DossLowLevelStreamEvent::StoreButDontUse => format!("store next item in dict but don't use: {pointer}"),
+        DossLowLevelStreamEvent::SetDictPointer(p) => format!("set dict pointer {p}"),
+        DossLowLevelStreamEvent::ClearDictEntries { from, count } => format!("clear {count} dict entries from {from}"),
+        DossLowLevelStreamEvent::StackStart => String::from("start stack"),
+        DossLowLevelStreamEvent::StackEnd => String::from("leave stack"),
+        DossLowLevelStreamEvent::SetHint => String::from("set hint"),
+        DossLowLevelStreamEvent::SkipBytes16le(n) => format!("skip {n} bytes"),
+        DossLowLevelStreamEvent::SkipBytes32le(n) => format!("skip {n} bytes"),
+        DossLowLevelStreamEvent::ImportDict => String::from("import into dictionary"),
+        DossLowLevelStreamEvent::FileStart => String::from("start file"),
+    }
+}
+
+fn shorten(text: &str) -> String {
+    match text.char_indices().nth(MAX_TEXT) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dump_hello_world_with_dict() {
+        //second example of docs/examples.md
+        let serialized = [10_u8, 21, 7, 5, b'h', b'e', b'l', b'l', b'o', 7, 5, b'w', b'o', b'r', b'l', b'd', 7, 3, b's', b'a', b'y', 9, 0, 11];
+        let mut out = Vec::new();
+        dump(serialized.as_slice(), &mut out, None).unwrap();
+        let expected = [
+            "offset    op  operands                   description",
+            "00000000  10                             start object",
+            "00000001  21                               store next item in dict: 0",
+            "00000002  07  05 68 65 6c 6c 6f            utf8 string \"hello\"",
+            "00000009  07  05 77 6f 72 6c 64            utf8 string \"world\"",
+            "00000010  07  03 73 61 79                  utf8 string \"say\"",
+            "00000015  09  00                           dict reference 0 --> \"hello\"",
+            "00000017  11                             end object",
+        ];
+        assert_eq!(String::from_utf8(out).unwrap().lines().collect::<Vec<_>>(), expected);
+
+        let mut out = Vec::new();
+        assert!(dump([10_u8, 9, 3].as_slice(), &mut out, None).is_err());
+        assert!(String::from_utf8(out).unwrap().ends_with("dict reference 3 --> empty entry\n"));
+    }
+}
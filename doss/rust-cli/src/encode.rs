@@ -0,0 +1,56 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::io::{Read, Write};
+
+use dataflowgrid_commons::readers::io::TextReadable;
+use doss::{DossPredefinedDictionary, DossSerializer};
+use streamablejson::parser::{StreamableJSONReader, StreamableJSONReaderCallback, StreamableJSONReaderCallbackReturn, StreamableJSONReaderEvent};
+
+use crate::error::CliError;
+
+struct EncodeCallback<W: Write> {
+    serializer: DossSerializer<W>,
+}
+
+impl<W: Write> StreamableJSONReaderCallback for EncodeCallback<W> {
+    fn on_streamablejson_event(&mut self, event: StreamableJSONReaderEvent) -> StreamableJSONReaderCallbackReturn {
+        match self.serializer.write_reader_event(event) {
+            Ok(()) => StreamableJSONReaderCallbackReturn::Continue,
+            Err(e) => StreamableJSONReaderCallbackReturn::StopErr(Box::new(e)),
+        }
+    }
+}
+
+/// Parses streamablejson or JSON text from `input` chunk by chunk and writes it with `serializer`
+pub fn encode<R: Read, W: Write>(input: R, mut serializer: DossSerializer<W>, dictionaries: &[DossPredefinedDictionary]) -> Result<W, CliError> {
+    for dict in dictionaries {
+        serializer.import_dictionary(dict)?;
+    }
+    let mut callback = EncodeCallback { serializer };
+    let mut reader = StreamableJSONReader::new(&mut callback);
+    reader.pushdata(&mut TextReadable::utf8(input))?;
+    reader.finish()?;
+    drop(reader);
+    Ok(callback.serializer.finish()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use doss::{DossSerializerOptions, decode_entries};
+    use streamablejson::deserializer::deserialize_orderedbag_from_string;
+
+    use super::*;
+
+    #[test]
+    fn test_encode_across_chunks() {
+        let json = format!("[{}]", vec![r#"{"name": "grüße", "kind": "repeated", "n": 12}"#; 3000].join(","));
+        assert!(json.len() > 2 * 64 * 1024);
+        let serializer = DossSerializer::with_options(Vec::new(), DossSerializerOptions::default());
+        let encoded = encode(json.as_bytes(), serializer, &[]).unwrap();
+        assert_eq!(decode_entries(&encoded).unwrap(), vec![deserialize_orderedbag_from_string(json.clone()).unwrap()]);
+        assert!(encoded.len() * 3 < json.len());
+
+        let serializer = DossSerializer::new(Vec::new());
+        assert!(matches!(encode([b'"', 0xff, b'"'].as_slice(), serializer, &[]), Err(CliError::Input(_))));
+    }
+}
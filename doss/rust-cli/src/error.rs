@@ -0,0 +1,53 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::io::ErrorKind;
+
+use dataflowgrid_commons::readers::reader::ReaderError;
+use derive_more::{Display, Error, From};
+use doss::{DossError, DossReaderError};
+use streamablejson::parser::StreamableJSONReaderError;
+
+#[derive(Debug, Display, Error, From)]
+pub enum CliError {
+    #[display("{_0}")]
+    Io(std::io::Error),
+    #[display("{_0}")]
+    Doss(DossError),
+    #[display("invalid input: {_0}")]
+    #[from(ignore)]
+    Input(#[error(not(source))] String),
+}
+
+impl From<DossReaderError> for CliError {
+    fn from(e: DossReaderError) -> Self {
+        match e {
+            DossReaderError::DossError(e) => CliError::Doss(e),
+            DossReaderError::CallbackError(e) => callback_error(e),
+            DossReaderError::ReaderError(e) => CliError::Input(format!("{e:?}")),
+        }
+    }
+}
+
+impl From<StreamableJSONReaderError> for CliError {
+    fn from(e: StreamableJSONReaderError) -> Self {
+        match e {
+            StreamableJSONReaderError::CallbackError(e) => callback_error(e),
+            //text that is not utf8 is invalid input, other read errors are I/O errors
+            StreamableJSONReaderError::ReaderError(ReaderError::IO(e)) if e.kind() == ErrorKind::InvalidData => CliError::Input(e.to_string()),
+            StreamableJSONReaderError::ReaderError(ReaderError::IO(e)) => CliError::Io(e),
+            e => CliError::Input(format!("{e:?}")),
+        }
+    }
+}
+
+//callbacks stop with the error of the writer they feed
+fn callback_error(e: Box<dyn std::error::Error>) -> CliError {
+    let e = match e.downcast::<std::io::Error>() {
+        Ok(e) => return CliError::Io(*e),
+        Err(e) => e,
+    };
+    match e.downcast::<DossError>() {
+        Ok(e) => CliError::Doss(*e),
+        Err(e) => CliError::Input(e.to_string()),
+    }
+}
@@ -0,0 +1,242 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+//! `doss` converts between streamablejson and DOSS and inspects DOSS streams.
+//! Every command streams from stdin (or a file) to stdout (or a file).
+
+mod decode;
+mod dump;
+mod encode;
+mod error;
+mod scan;
+mod schema;
+mod sjson;
+mod stats;
+
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use clap::{Args, Parser, Subcommand};
+use doss::{Compression, DossDictionaryRegistry, DossPredefinedDictionary, DossSerializer, DossSerializerOptions};
+
+use crate::error::CliError;
+
+#[derive(Debug, Parser)]
+#[command(name = "doss", version, about = "Converts between streamablejson and DOSS and inspects DOSS streams")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Encodes streamablejson or JSON to DOSS
+    Encode {
+        #[command(flatten)]
+        io: IoArgs,
+        #[command(flatten)]
+        dict: DictArgs,
+        /// Number of dictionary entries to use, 0 disables the dictionary
+        #[arg(long, value_name = "N")]
+        max_dict_entries: Option<usize>,
+        /// Store a string in the dictionary once it was seen this often
+        #[arg(long, value_name = "N")]
+        min_occurrences: Option<usize>,
+        /// Don't announce the dictionary size with a hint
+        #[arg(long)]
+        no_dict_hint: bool,
+        /// Blocks and arrays larger than this get a skip opcode
+        #[arg(long, value_name = "BYTES", conflicts_with = "no_skip")]
+        skip_threshold: Option<usize>,
+        /// Don't write skip opcodes
+        #[arg(long)]
+        no_skip: bool,
+        /// Compress everything after the dictionary hint: zstd, lz4 or none
+        #[arg(long, value_name = "NAME", value_parser = compression)]
+        compression: Option<Compression>,
+    },
+    /// Decodes DOSS to streamablejson, one top level value per line
+    Decode {
+        #[command(flatten)]
+        io: IoArgs,
+        #[command(flatten)]
+        dict: DictArgs,
+        /// Without indentation
+        #[arg(long)]
+        compact: bool,
+    },
+    /// Lists the opcodes of a DOSS stream with offsets and meaning
+    Dump {
+        #[command(flatten)]
+        io: IoArgs,
+        #[command(flatten)]
+        dict: DictArgs,
+    },
+    /// Shows the dictionary hit rate and the size compared to JSON
+    Stats {
+        #[command(flatten)]
+        io: IoArgs,
+        #[command(flatten)]
+        dict: DictArgs,
+    },
+    /// Checks that a DOSS stream decodes completely, exits with 1 otherwise
+    Validate {
+        #[command(flatten)]
+        io: IoArgs,
+        #[command(flatten)]
+        dict: DictArgs,
+    },
+    /// Infers field paths, types, nullability and distinct values of the records
+    Schema {
+        #[command(flatten)]
+        io: IoArgs,
+        #[command(flatten)]
+        dict: DictArgs,
+        /// Count distinct values exactly up to this number per field
+        #[arg(long, value_name = "N", default_value_t = doss::DEFAULT_MAX_DISTINCT)]
+        max_distinct: usize,
+    },
+    /// Writes the records of a DOSS file to Parquet, the file is read twice
+    #[cfg(feature = "parquet")]
+    Parquet {
+        /// Input file
+        input: PathBuf,
+        /// Output file
+        #[arg(short, long)]
+        output: PathBuf,
+        #[command(flatten)]
+        dict: DictArgs,
+        /// Records per batch and row group
+        #[arg(long, value_name = "N", default_value_t = 65536)]
+        batch_size: usize,
+    },
+}
+
+#[derive(Debug, Args)]
+struct IoArgs {
+    /// Input file, stdin if missing or `-`
+    input: Option<PathBuf>,
+    /// Output file, stdout if missing or `-`
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+struct DictArgs {
+    /// Predefined dictionary file, imported by `encode` and available to imports otherwise. Can be repeated
+    #[arg(long = "dictionary", value_name = "FILE")]
+    files: Vec<PathBuf>,
+}
+
+impl IoArgs {
+    fn input(&self) -> Result<Box<dyn Read>, CliError> {
+        Ok(match file_arg(&self.input) {
+            Some(path) => Box::new(File::open(path)?),
+            None => Box::new(io::stdin().lock()),
+        })
+    }
+
+    fn output(&self) -> Result<Box<dyn Write>, CliError> {
+        Ok(match file_arg(&self.output) {
+            Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+            None => Box::new(BufWriter::new(io::stdout().lock())),
+        })
+    }
+}
+
+impl DictArgs {
+    fn dictionaries(&self) -> Result<Vec<DossPredefinedDictionary>, CliError> {
+        Ok(self.files.iter().map(DossPredefinedDictionary::load).collect::<Result<_, _>>()?)
+    }
+
+    fn registry(&self) -> Result<Option<Arc<DossDictionaryRegistry>>, CliError> {
+        if self.files.is_empty() {
+            return Ok(None);
+        }
+        let mut registry = DossDictionaryRegistry::new();
+        for file in &self.files {
+            registry.load_file(file)?;
+        }
+        Ok(Some(Arc::new(registry)))
+    }
+}
+
+fn compression(name: &str) -> Result<Compression, String> {
+    Compression::from_name(name).ok_or_else(|| format!("unknown compression {name}"))
+}
+
+//None for stdin and stdout
+fn file_arg(path: &Option<PathBuf>) -> Option<&Path> {
+    path.as_deref().filter(|p| *p != Path::new("-"))
+}
+
+fn run(command: Command) -> Result<(), CliError> {
+    match command {
+        Command::Encode { io, dict, max_dict_entries, min_occurrences, no_dict_hint, skip_threshold, no_skip, compression } => {
+            let defaults = DossSerializerOptions::default();
+            let options = DossSerializerOptions {
+                max_dict_entries: max_dict_entries.unwrap_or(defaults.max_dict_entries),
+                min_occurrences: min_occurrences.unwrap_or(defaults.min_occurrences),
+                emit_dict_hint: !no_dict_hint,
+                skip_threshold: if no_skip { None } else { skip_threshold.or(defaults.skip_threshold) },
+                compression: compression.unwrap_or(defaults.compression),
+                ..defaults
+            };
+            let dictionaries = dict.dictionaries()?;
+            //files are patched in place, streams get skip targets only for blocks that fit into the buffer
+            match file_arg(&io.output) {
+                Some(path) => {
+                    let serializer = DossSerializer::seekable(BufWriter::new(File::create(path)?), options);
+                    encode::encode(io.input()?, serializer, &dictionaries)?.flush()?;
+                }
+                None => {
+                    let serializer = DossSerializer::with_options(io.output()?, options);
+                    encode::encode(io.input()?, serializer, &dictionaries)?.flush()?;
+                }
+            }
+        }
+        Command::Decode { io, dict, compact } => {
+            decode::decode(io.input()?, io.output()?, !compact, dict.registry()?)?;
+        }
+        Command::Dump { io, dict } => dump::dump(io.input()?, io.output()?, dict.registry()?)?,
+        Command::Stats { io, dict } => {
+            let stats = stats::stats(io.input()?, dict.registry()?)?;
+            let mut output = io.output()?;
+            write!(output, "{stats}")?;
+            output.flush()?;
+        }
+        Command::Validate { io, dict } => {
+            let summary = decode::validate(io.input()?, dict.registry()?)?;
+            let mut output = io.output()?;
+            writeln!(output, "valid: {} values, {} events, {} files, nesting depth {}", summary.values, summary.events, summary.files, summary.max_depth)?;
+            output.flush()?;
+        }
+        Command::Schema { io, dict, max_distinct } => {
+            let schema = schema::schema(io.input()?, &dict.registry()?, max_distinct)?;
+            let mut output = io.output()?;
+            write!(output, "{schema}")?;
+            output.flush()?;
+        }
+        #[cfg(feature = "parquet")]
+        Command::Parquet { input, output, dict, batch_size } => {
+            let (schema, output) = schema::parquet(&input, BufWriter::new(File::create(output)?), &dict.registry()?, batch_size)?;
+            output.into_inner().map_err(io::IntoInnerError::into_error)?.sync_all()?;
+            eprintln!("{} records", schema.records);
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse().command) {
+        Ok(()) => ExitCode::SUCCESS,
+        //a closed pipe like `doss decode big.doss | head` is no error
+        Err(CliError::Io(e)) if e.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("doss: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,105 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::io::{ErrorKind, Read};
+use std::sync::Arc;
+
+use doss::{Compression, DossDictionaryRegistry, DossError, DossEvent, DossLowLevelStreamEvent, DossResolver};
+
+use crate::error::CliError;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// One opcode of the stream
+#[derive(Debug)]
+pub struct Scanned {
+    pub offset: u64,
+    pub len: usize,
+    /// the operand bytes, at most [`MAX_OPERANDS`] of them
+    pub operands: Vec<u8>,
+    pub event: DossLowLevelStreamEvent,
+}
+
+pub const MAX_OPERANDS: usize = 8;
+
+/// Reads the low level events of a stream with their offsets, without skipping anything.
+/// The events are resolved on request to follow the settings and the dictionary.
+#[derive(Debug)]
+pub struct Scanner<R: Read> {
+    input: R,
+    buffer: Vec<u8>,
+    pos: usize,
+    offset: u64, //stream offset of buffer[pos]
+    eof: bool,
+    resolver: DossResolver,
+    resolved: Vec<DossEvent>,
+}
+
+impl<R: Read> Scanner<R> {
+    pub fn new(input: R, registry: Option<Arc<DossDictionaryRegistry>>) -> Self {
+        let mut resolver = DossResolver::new();
+        if let Some(registry) = registry {
+            resolver.set_registry(registry);
+        }
+        Scanner {
+            input,
+            buffer: Vec::new(),
+            pos: 0,
+            offset: 0,
+            eof: false,
+            resolver,
+            resolved: Vec::new(),
+        }
+    }
+
+    pub fn resolver(&self) -> &DossResolver {
+        &self.resolver
+    }
+
+    pub fn next_event(&mut self) -> Result<Option<Scanned>, CliError> {
+        loop {
+            if let Some((event, used)) = self.resolver.decode(&self.buffer[self.pos..])? {
+                let operands = self.buffer[self.pos + 1..self.pos + used.min(MAX_OPERANDS + 1)].to_vec();
+                let scanned = Scanned { offset: self.offset, len: used, operands, event };
+                self.pos += used;
+                self.offset += used as u64;
+                return Ok(Some(scanned));
+            }
+            if self.eof {
+                return match self.pos < self.buffer.len() {
+                    true => Err(DossError::UnexpectedEof.into()),
+                    false => Ok(None),
+                };
+            }
+            self.buffer.drain(..self.pos);
+            self.pos = 0;
+            let len = self.buffer.len();
+            self.buffer.resize(len + CHUNK_SIZE, 0);
+            let read = loop {
+                match self.input.read(&mut self.buffer[len..]) {
+                    Ok(read) => break read,
+                    Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e.into()),
+                }
+            };
+            self.buffer.truncate(len + read);
+            self.eof = read == 0;
+        }
+    }
+
+    /// Applies an event to the dictionary and settings and returns the resolved events.
+    /// Compressed frames have no opcodes at stream offsets, scanning stops at the compression setting
+    pub fn resolve(&mut self, event: DossLowLevelStreamEvent) -> Result<&[DossEvent], CliError> {
+        self.resolved.clear();
+        self.resolver.push(event, &mut self.resolved)?;
+        let compression = self.resolver.settings().compression();
+        if compression != Compression::None {
+            return Err(CliError::Input(format!("{} compressed stream, decode it instead", compression.name())));
+        }
+        Ok(&self.resolved)
+    }
+
+    /// Checks that the stream did not end inside a structure
+    pub fn finish(&self) -> Result<(), CliError> {
+        Ok(self.resolver.finish()?)
+    }
+}
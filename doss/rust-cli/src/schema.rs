@@ -0,0 +1,77 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::io::Read;
+use std::sync::Arc;
+
+use doss::{DossDictionaryRegistry, DossPullParser, DossSchema, DossSchemaInference};
+
+use crate::error::CliError;
+
+fn parser<R: Read>(input: R, registry: &Option<Arc<DossDictionaryRegistry>>) -> DossPullParser<R> {
+    let mut parser = DossPullParser::new(input);
+    if let Some(registry) = registry {
+        parser.set_registry(registry.clone());
+    }
+    parser
+}
+
+/// Infers the schema of the records of a stream
+pub fn schema(input: impl Read, registry: &Option<Arc<DossDictionaryRegistry>>, max_distinct: usize) -> Result<DossSchema, CliError> {
+    let mut inference = DossSchemaInference::with_max_distinct(max_distinct);
+    inference.push_all(parser(input, registry))?;
+    Ok(inference.schema())
+}
+
+/// Writes the records of a file to Parquet, the file is read twice to infer the schema first
+#[cfg(feature = "parquet")]
+pub fn parquet<W: std::io::Write + Send>(
+    path: &std::path::Path,
+    output: W,
+    registry: &Option<Arc<DossDictionaryRegistry>>,
+    batch_size: usize,
+) -> Result<(DossSchema, W), CliError> {
+    use std::fs::File;
+    use std::io::BufReader;
+
+    let schema = schema(BufReader::new(File::open(path)?), registry, doss::DEFAULT_MAX_DISTINCT)?;
+    let mut writer = doss::DossParquetWriter::new(output, &schema, batch_size)?;
+    writer.push_all(parser(BufReader::new(File::open(path)?), registry))?;
+    let output = writer.finish()?;
+    Ok((schema, output))
+}
+
+#[cfg(test)]
+mod tests {
+    use doss::{DossEvent, DossFieldType, DossSerializer};
+
+    use super::*;
+
+    fn records() -> Vec<u8> {
+        let mut serializer = DossSerializer::new(Vec::new());
+        for value in [DossEvent::UInt(1), DossEvent::String(String::from("two"))] {
+            for event in [DossEvent::BlockStart, DossEvent::String(String::from("a")), value, DossEvent::BlockEnd] {
+                serializer.write_doss_event(&event).unwrap();
+            }
+        }
+        serializer.finish().unwrap()
+    }
+
+    #[test]
+    fn test_schema() {
+        let schema = schema(records().as_slice(), &None, 10).unwrap();
+        assert_eq!(schema.records, 2);
+        assert_eq!(schema.field("a").unwrap().data_type, DossFieldType::Mixed);
+        assert_eq!(schema.to_string(), "2 records\na: mixed required, 2 values\n");
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_parquet() {
+        let path = std::env::temp_dir().join(format!("doss-cli-parquet-{}.doss", std::process::id()));
+        std::fs::write(&path, records()).unwrap();
+        let (schema, parquet) = parquet(&path, Vec::new(), &None, 1).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(schema.records, 2);
+        assert_eq!(&parquet[..4], b"PAR1");
+    }
+}
@@ -0,0 +1,214 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::io::{self, Write};
+
+use doss::{BYTES_TYPE, DATETIME_TYPE, DECIMAL_TYPE, DossEvent};
+
+#[derive(Debug, PartialEq)]
+enum Kind {
+    Block,
+    Array,
+    Type,
+}
+
+#[derive(Debug)]
+struct Level {
+    kind: Kind,
+    items: usize,
+}
+
+/// Writes resolved DOSS events as streamablejson text while they arrive, one top level value per line.
+/// Values without a JSON counterpart are written as types like [`DossTreeBuilder`](doss::DossTreeBuilder) does,
+/// settings, hints, file starts and stacks are left out.
+#[derive(Debug)]
+pub struct SjsonWriter<W: Write> {
+    out: W,
+    pretty: bool,
+    stack: Vec<Level>,
+}
+
+impl<W: Write> SjsonWriter<W> {
+    /// Indents nested blocks and arrays by two spaces
+    pub fn pretty(out: W) -> Self {
+        SjsonWriter { out, pretty: true, stack: Vec::new() }
+    }
+
+    /// Without any whitespace
+    pub fn compact(out: W) -> Self {
+        SjsonWriter { out, pretty: false, stack: Vec::new() }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    pub fn write_event(&mut self, event: &DossEvent) -> io::Result<()> {
+        match event {
+            DossEvent::BlockStart => self.open(Kind::Block, "{"),
+            DossEvent::ArrayStart => self.open(Kind::Array, "["),
+            DossEvent::TypeStart(name) => self.open(Kind::Type, &format!("{name}(")),
+            DossEvent::BlockEnd => self.close("}"),
+            DossEvent::ArrayEnd => self.close("]"),
+            DossEvent::TypeEnd => self.close(")"),
+            DossEvent::True => self.value("true"),
+            DossEvent::False => self.value("false"),
+            DossEvent::Null => self.value("null"),
+            DossEvent::Int(v) => self.value(&v.to_string()),
+            DossEvent::UInt(v) => self.value(&v.to_string()),
+            DossEvent::Float(v) => self.value(&v.to_string()),
+            DossEvent::Decimal(d) => self.value(&typed(DECIMAL_TYPE, &d.to_string())),
+            DossEvent::DateTime(d) => self.value(&typed(DATETIME_TYPE, &d.to_string())),
+            DossEvent::Binary(b) => self.value(&typed(BYTES_TYPE, &b.iter().map(|b| format!("{b:02x}")).collect::<String>())),
+            DossEvent::String(s) => self.value(&quote(s)),
+            DossEvent::Config { .. }
+            | DossEvent::Hint { .. }
+            | DossEvent::ImportDict(_)
+            | DossEvent::FileStart(_)
+            | DossEvent::StackStart
+            | DossEvent::StackEnd => Ok(()),
+        }
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    fn open(&mut self, kind: Kind, text: &str) -> io::Result<()> {
+        self.separate()?;
+        self.out.write_all(text.as_bytes())?;
+        self.stack.push(Level { kind, items: 0 });
+        Ok(())
+    }
+
+    fn close(&mut self, text: &str) -> io::Result<()> {
+        let level = self.stack.pop().ok_or_else(|| io::Error::other("unbalanced end"))?;
+        if self.pretty && level.items > 0 && level.kind != Kind::Type {
+            self.newline()?;
+        }
+        self.out.write_all(text.as_bytes())?;
+        self.end_value()
+    }
+
+    fn value(&mut self, text: &str) -> io::Result<()> {
+        self.separate()?;
+        self.out.write_all(text.as_bytes())?;
+        self.end_value()
+    }
+
+    //writes what goes before the next item of the current level
+    fn separate(&mut self) -> io::Result<()> {
+        let pretty = self.pretty;
+        let Some(level) = self.stack.last_mut() else {
+            return Ok(());
+        };
+        let first = level.items == 0;
+        let is_value = level.kind == Kind::Block && level.items % 2 == 1;
+        level.items += 1;
+        match level.kind {
+            _ if is_value => self.out.write_all(if pretty { b": " } else { b":" }),
+            Kind::Block | Kind::Array => {
+                if !first {
+                    self.out.write_all(b",")?;
+                }
+                match pretty {
+                    true => self.newline(),
+                    false => Ok(()),
+                }
+            }
+            Kind::Type if first => Ok(()),
+            Kind::Type => self.out.write_all(if pretty { b", " } else { b"," }),
+        }
+    }
+
+    fn newline(&mut self) -> io::Result<()> {
+        write!(self.out, "\n{}", "  ".repeat(self.stack.len()))
+    }
+
+    fn end_value(&mut self) -> io::Result<()> {
+        match self.stack.is_empty() {
+            true => self.out.write_all(b"\n"),
+            false => Ok(()),
+        }
+    }
+}
+
+fn typed(name: &str, text: &str) -> String {
+    format!("{name}({})", quote(text))
+}
+
+/// A JSON string literal
+pub fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Compact text of a dictionary entry, shortened to about `max_len` characters
+pub fn item_text(item: &[DossEvent], max_len: usize) -> String {
+    let mut writer = SjsonWriter::compact(Vec::new());
+    for event in item {
+        //writing into memory does not fail
+        let _ = writer.write_event(event);
+    }
+    let text = String::from_utf8_lossy(&writer.into_inner()).trim_end().to_string();
+    match text.char_indices().nth(max_len) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(events: &[DossEvent], pretty: bool) -> String {
+        let mut writer = if pretty { SjsonWriter::pretty(Vec::new()) } else { SjsonWriter::compact(Vec::new()) };
+        for event in events {
+            writer.write_event(event).unwrap();
+        }
+        String::from_utf8(writer.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn test_pretty_and_compact() {
+        let s = |v: &str| DossEvent::String(v.to_string());
+        let events = [
+            DossEvent::BlockStart,
+            s("hello"), s("wo\"rld\n"),
+            s("list"), DossEvent::ArrayStart, DossEvent::UInt(1), DossEvent::Int(-2), DossEvent::ArrayEnd,
+            s("empty"), DossEvent::BlockStart, DossEvent::BlockEnd,
+            s("when"), DossEvent::TypeStart(String::from("date")), s("2025-01-01"), DossEvent::TypeEnd,
+            s("raw"), DossEvent::Binary(vec![0, 255]),
+            DossEvent::BlockEnd,
+            DossEvent::FileStart(vec![DossEvent::Null]),
+            DossEvent::Null,
+        ];
+        assert_eq!(write(&events, true), concat!(
+            "{\n",
+            "  \"hello\": \"wo\\\"rld\\n\",\n",
+            "  \"list\": [\n",
+            "    1,\n",
+            "    -2\n",
+            "  ],\n",
+            "  \"empty\": {},\n",
+            "  \"when\": date(\"2025-01-01\"),\n",
+            "  \"raw\": bytes(\"00ff\")\n",
+            "}\n",
+            "null\n",
+        ));
+        assert_eq!(write(&events, false), "{\"hello\":\"wo\\\"rld\\n\",\"list\":[1,-2],\"empty\":{},\"when\":date(\"2025-01-01\"),\"raw\":bytes(\"00ff\")}\nnull\n");
+        assert_eq!(item_text(&events[..5], 12), "{\"hello\":\"wo...");
+    }
+}
@@ -0,0 +1,129 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+use doss::{DossDictionaryRegistry, DossLowLevelStreamEvent};
+
+use crate::error::CliError;
+use crate::scan::Scanner;
+use crate::sjson::SjsonWriter;
+
+/// Counts the bytes written to it
+#[derive(Debug, Default)]
+struct Counter(u64);
+
+impl Write for Counter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Stats {
+    pub bytes: u64,
+    /// size of the content as compact streamablejson
+    pub json_bytes: u64,
+    pub opcodes: u64,
+    /// strings and binaries written out
+    pub literals: u64,
+    pub literal_bytes: u64,
+    pub references: u64,
+    pub stores: u64,
+    pub imports: u64,
+    pub skips: u64,
+    pub settings: u64,
+    pub files: u64,
+}
+
+impl Stats {
+    /// Share of strings, binaries and stored items read from the dictionary
+    pub fn hit_rate(&self) -> f64 {
+        match self.references + self.literals {
+            0 => 0.0,
+            total => self.references as f64 / total as f64,
+        }
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let percent = |part: u64, total: u64| if total == 0 { 0.0 } else { part as f64 * 100.0 / total as f64 };
+        let rows = [
+            ("size", format!("{} bytes", self.bytes)),
+            ("json size", format!("{} bytes (doss is {:.1}%)", self.json_bytes, percent(self.bytes, self.json_bytes))),
+            ("opcodes", self.opcodes.to_string()),
+            ("literals", format!("{} strings and binaries, {} bytes", self.literals, self.literal_bytes)),
+            ("dict references", self.references.to_string()),
+            ("dict hit rate", format!("{:.1}%", self.hit_rate() * 100.0)),
+            ("dict stores", self.stores.to_string()),
+            ("dict imports", self.imports.to_string()),
+            ("skips", self.skips.to_string()),
+            ("settings and hints", self.settings.to_string()),
+            ("files", self.files.to_string()),
+        ];
+        for (name, value) in rows {
+            writeln!(f, "{:<20}{value}", format!("{name}:"))?;
+        }
+        Ok(())
+    }
+}
+
+pub fn stats(input: impl Read, registry: Option<Arc<DossDictionaryRegistry>>) -> Result<Stats, CliError> {
+    let mut scanner = Scanner::new(input, registry);
+    let mut json = SjsonWriter::compact(Counter::default());
+    let mut stats = Stats::default();
+    while let Some(scanned) = scanner.next_event()? {
+        stats.bytes += scanned.len as u64;
+        stats.opcodes += 1;
+        match &scanned.event {
+            DossLowLevelStreamEvent::String(_) | DossLowLevelStreamEvent::Binary(_) => {
+                stats.literals += 1;
+                stats.literal_bytes += scanned.len as u64;
+            }
+            DossLowLevelStreamEvent::Reference(_) => stats.references += 1,
+            DossLowLevelStreamEvent::StoreInDict | DossLowLevelStreamEvent::StoreButDontUse => stats.stores += 1,
+            DossLowLevelStreamEvent::ImportDict => stats.imports += 1,
+            DossLowLevelStreamEvent::SkipBytes16le(_) | DossLowLevelStreamEvent::SkipBytes32le(_) => stats.skips += 1,
+            DossLowLevelStreamEvent::SetConfig | DossLowLevelStreamEvent::SetHint => stats.settings += 1,
+            DossLowLevelStreamEvent::FileStart => stats.files += 1,
+            _ => {}
+        }
+        for event in scanner.resolve(scanned.event)? {
+            json.write_event(event)?;
+        }
+    }
+    scanner.finish()?;
+    stats.json_bytes = json.into_inner().0;
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        //{"hello": "world", "say": "hello"} with the dictionary, see docs/examples.md
+        let serialized = [10_u8, 21, 7, 5, b'h', b'e', b'l', b'l', b'o', 7, 5, b'w', b'o', b'r', b'l', b'd', 7, 3, b's', b'a', b'y', 9, 0, 11];
+        let stats = stats(serialized.as_slice(), None).unwrap();
+        assert_eq!(stats, Stats {
+            bytes: 24,
+            json_bytes: r#"{"hello":"world","say":"hello"}"#.len() as u64 + 1,
+            opcodes: 7,
+            literals: 3,
+            literal_bytes: 19,
+            references: 1,
+            stores: 1,
+            ..Stats::default()
+        });
+        assert_eq!(stats.hit_rate(), 0.25);
+        assert!(stats.to_string().contains("dict hit rate:      25.0%"));
+    }
+}
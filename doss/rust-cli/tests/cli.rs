@@ -0,0 +1,33 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+//! Runs the `doss` binary on the fixtures in tests/fixtures: `<name>.json` is encoded and decoded again,
+//! `<name>.sjson` is the expected output of `doss decode --compact`.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+}
+
+fn doss(args: &[&str], input: &[u8]) -> Vec<u8> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_doss"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "doss {args:?}: {}", String::from_utf8_lossy(&output.stderr));
+    output.stdout
+}
+
+#[test]
+fn test_encode_numbers() {
+    let encoded = doss(&["encode"], &std::fs::read(fixture("numbers.json")).unwrap());
+    let decoded = doss(&["decode", "--compact"], &encoded);
+    assert_eq!(String::from_utf8(decoded).unwrap(), std::fs::read_to_string(fixture("numbers.sjson")).unwrap());
+}
@@ -0,0 +1,107 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+//Compares plain DOSS, DOSS with zstd or lz4 frames and JSON with zstd on generated data.
+//Run with `cargo bench -p doss --bench compression`, the sizes are printed before the timings.
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use doss::{Compression, DossSerializer, DossSerializerOptions, decode_entries};
+use streamablejson::StreamableJSONEntry;
+use streamablejson::deserializer::deserialize_orderedbag_from_string;
+
+const LEVELS: [&str; 4] = ["debug", "info", "info", "warn"];
+const SERVICES: [&str; 5] = ["gateway", "orders", "billing", "inventory", "search"];
+const PRODUCTS: [&str; 6] = ["keyboard", "monitor", "cable", "laptop", "headset", "dock"];
+
+//a deterministic pseudo random sequence, so every run compares the same bytes
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self, n: usize) -> usize {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (self.0 >> 33) as usize % n
+    }
+}
+
+//an array of flat log records with repeated keys and values
+fn logs(records: usize) -> String {
+    let mut rng = Lcg(1);
+    let records: Vec<String> = (0..records).map(|i| {
+        format!(
+            r#"{{"timestamp": {}, "level": "{}", "service": "{}", "request_id": "req-{:08x}", "duration_ms": {}, "message": "handled request for user {}"}}"#,
+            1_700_000_000_000_u64 + i as u64 * 17,
+            LEVELS[rng.next(LEVELS.len())],
+            SERVICES[rng.next(SERVICES.len())],
+            rng.next(1 << 30),
+            rng.next(2000),
+            rng.next(500),
+        )
+    }).collect();
+    format!("[{}]", records.join(", "))
+}
+
+//nested orders like an API response
+fn orders(orders: usize) -> String {
+    let mut rng = Lcg(2);
+    let orders: Vec<String> = (0..orders).map(|i| {
+        let items: Vec<String> = (0..1 + rng.next(5)).map(|_| {
+            format!(r#"{{"product": "{}", "quantity": {}, "price": {}}}"#, PRODUCTS[rng.next(PRODUCTS.len())], 1 + rng.next(3), 5 + rng.next(500))
+        }).collect();
+        format!(
+            r#"{{"id": {i}, "customer": {{"name": "customer {}", "country": "{}"}}, "status": "{}", "items": [{}]}}"#,
+            rng.next(300),
+            ["DE", "AT", "CH", "FR"][rng.next(4)],
+            ["open", "shipped", "delivered"][rng.next(3)],
+            items.join(", "),
+        )
+    }).collect();
+    format!(r#"{{"orders": [{}], "next_page": null}}"#, orders.join(", "))
+}
+
+fn encode(entry: &StreamableJSONEntry, compression: Compression) -> Vec<u8> {
+    let options = DossSerializerOptions { compression, ..DossSerializerOptions::default() };
+    let mut serializer = DossSerializer::with_options(Vec::new(), options);
+    serializer.write_entry(entry).unwrap();
+    serializer.finish().unwrap()
+}
+
+fn bench_compression(c: &mut Criterion) {
+    let compressions = [Compression::None, Compression::Zstd, Compression::Lz4];
+    for (name, json) in [("logs", logs(1000)), ("orders", orders(250))] {
+        let entry = deserialize_orderedbag_from_string(json.clone()).unwrap();
+        let json_zstd = zstd::bulk::compress(json.as_bytes(), zstd::DEFAULT_COMPRESSION_LEVEL).unwrap();
+        println!("{name}: JSON {} bytes, JSON+zstd {} bytes", json.len(), json_zstd.len());
+        for compression in compressions {
+            println!("{name}: DOSS+{} {} bytes", compression.name(), encode(&entry, compression).len());
+        }
+
+        let mut group = c.benchmark_group(format!("encode {name}"));
+        group.throughput(Throughput::Bytes(json.len() as u64));
+        for compression in compressions {
+            group.bench_with_input(BenchmarkId::new("DOSS", compression.name()), &entry, |b, entry| b.iter(|| encode(entry, compression)));
+        }
+        //the JSON text is already there, only compressing it is measured
+        group.bench_with_input(BenchmarkId::new("JSON", "zstd"), &json, |b, json| {
+            b.iter(|| zstd::bulk::compress(json.as_bytes(), zstd::DEFAULT_COMPRESSION_LEVEL).unwrap())
+        });
+        group.finish();
+
+        let mut group = c.benchmark_group(format!("decode {name}"));
+        group.throughput(Throughput::Bytes(json.len() as u64));
+        for compression in compressions {
+            let serialized = encode(&entry, compression);
+            group.bench_with_input(BenchmarkId::new("DOSS", compression.name()), &serialized, |b, serialized| {
+                b.iter(|| decode_entries(serialized).unwrap())
+            });
+        }
+        group.bench_with_input(BenchmarkId::new("JSON", "zstd"), &json_zstd, |b, compressed| {
+            b.iter(|| {
+                let text = zstd::bulk::decompress(compressed, json.len()).unwrap();
+                deserialize_orderedbag_from_string(String::from_utf8(text).unwrap()).unwrap()
+            })
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_compression);
+criterion_main!(benches);
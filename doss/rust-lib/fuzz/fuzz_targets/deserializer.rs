@@ -0,0 +1,24 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+#![no_main]
+
+use doss::{DossLimits, DossLowLevelStreamEvent, DossPullParser, decode_entries};
+use libfuzzer_sys::fuzz_target;
+
+//untrusted bytes fail with errors and stay within the limits, they never panic
+fuzz_target!(|data: &[u8]| {
+    //a decoded event encodes to bytes that decode to the same bytes again
+    if let Ok(Some((event, _))) = DossLowLevelStreamEvent::decode(data) {
+        let mut encoded = Vec::new();
+        event.encode(&mut encoded);
+        let (decoded, used) = DossLowLevelStreamEvent::decode(&encoded).unwrap().unwrap();
+        let mut reencoded = Vec::new();
+        decoded.encode(&mut reencoded);
+        assert_eq!((reencoded, used), (encoded.clone(), encoded.len()));
+    }
+
+    let mut parser = DossPullParser::new(data);
+    parser.set_limits(DossLimits { max_depth: 64, max_string_len: 1 << 16, max_memory: 1 << 20 });
+    while let Some(Ok(_)) = parser.next() {}
+    let _ = decode_entries(data);
+});
@@ -0,0 +1,48 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+#![no_main]
+
+use doss::{DossDictionary, DossEvent, DossPredefinedDictionary};
+use libfuzzer_sys::fuzz_target;
+
+const MAX_ENTRIES: usize = 64;
+
+//operations of three bytes each: opcode and two operands
+fuzz_target!(|data: &[u8]| {
+    let _ = DossPredefinedDictionary::from_bytes(data);
+
+    let mut dict = DossDictionary::with_limits(MAX_ENTRIES, 256);
+    for op in data.chunks(3) {
+        let a = *op.get(1).unwrap_or(&0);
+        let b = *op.get(2).unwrap_or(&0);
+        match op[0] % 7 {
+            0 => {
+                let _ = dict.allocate();
+            }
+            1 => {
+                let item = vec![DossEvent::String("x".repeat(b as usize)); 1 + a as usize % 4];
+                let _ = dict.store(a as usize, item);
+            }
+            2 => {
+                let _ = dict.set_pointer(a as u64);
+            }
+            3 => {
+                let _ = dict.get(a as u64);
+            }
+            4 => dict.clear(a as u64, b as u64),
+            5 => dict.start_stack(),
+            _ => {
+                let _ = dict.leave_stack();
+            }
+        }
+        assert!(dict.pointer() <= MAX_ENTRIES);
+        assert!(dict.len() <= MAX_ENTRIES);
+    }
+    //the counters of the dictionary go back to 0 with the entries
+    while dict.stack_depth() > 0 {
+        dict.leave_stack().unwrap();
+    }
+    dict.clear(0, u64::MAX);
+    assert!(dict.is_empty());
+    assert_eq!(dict.memory(), 0);
+});
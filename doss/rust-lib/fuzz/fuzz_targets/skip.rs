@@ -0,0 +1,62 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+#![no_main]
+
+use dataflowgrid_commons::readers::reader::IteratorReadable;
+use doss::{DossEvent, DossPullParser, DossReader, DossReaderCallback, DossReaderCallbackReturn, DossReaderPushResult};
+use libfuzzer_sys::fuzz_target;
+
+//skip after the event with this index
+fn skip_after(decisions: &[u8], index: usize) -> bool {
+    decisions.get(index % decisions.len().max(1)).is_some_and(|d| d % 8 == 0)
+}
+
+struct Collector<'a> {
+    decisions: &'a [u8],
+    events: Vec<DossEvent>,
+}
+
+impl DossReaderCallback for Collector<'_> {
+    fn on_doss_event(&mut self, event: DossEvent) -> DossReaderCallbackReturn {
+        let skip = skip_after(self.decisions, self.events.len());
+        self.events.push(event);
+        match skip {
+            true => DossReaderCallbackReturn::Skip,
+            false => DossReaderCallbackReturn::Continue,
+        }
+    }
+}
+
+//the pull parser and the push reader with a small chunk size skip the same way
+fuzz_target!(|data: &[u8]| {
+    let Some((&chunk, rest)) = data.split_first() else {
+        return;
+    };
+    let (decisions, stream) = rest.split_at(rest.len().min(8));
+
+    let mut parser = DossPullParser::new(stream);
+    let mut pulled = Vec::new();
+    let pull_result = loop {
+        match parser.next_doss_event() {
+            Ok(Some(event)) => {
+                pulled.push(event);
+                if skip_after(decisions, pulled.len() - 1) {
+                    parser.skip_level();
+                }
+            }
+            Ok(None) => break Ok(()),
+            Err(e) => break Err(e.to_string()),
+        }
+    };
+
+    let mut collector = Collector { decisions, events: Vec::new() };
+    let mut reader = DossReader::new(&mut collector);
+    let mut readable = IteratorReadable::with_chunk_size(Box::new(stream.to_vec().into_iter()), 1 + chunk as usize % 16);
+    let push_result = match reader.pushdata(&mut readable) {
+        Ok(DossReaderPushResult::EndOfData) => reader.finish().map_err(|e| format!("{e:?}")),
+        Ok(r) => panic!("unexpected {r:?}"),
+        Err(e) => Err(format!("{e:?}")),
+    };
+    assert_eq!(pulled, collector.events);
+    assert_eq!(pull_result.is_ok(), push_result.is_ok());
+});
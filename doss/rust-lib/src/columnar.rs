@@ -0,0 +1,746 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+use arrow::array::{
+    ArrayBuilder, ArrayRef, BinaryBuilder, BooleanBuilder, Decimal128Builder, Float64Builder, Int64Builder, ListArray, NullArray, RecordBatch,
+    StringBuilder, StructArray, TimestampMicrosecondBuilder,
+};
+use arrow::buffer::{NullBuffer, OffsetBuffer, ScalarBuffer};
+use arrow::datatypes::{DataType, Field, FieldRef, Fields, Schema, SchemaRef, TimeUnit};
+use dataflowgrid_commons::typedstream::TypedStreamEvent;
+
+use crate::error::DossError;
+use crate::schema::{DossField, DossFieldType, DossSchema, depth_change, precision, scalar};
+use crate::types::Decimal;
+
+/// Field metadata key with the name of a DOSS type, `sjson` for mixed fields and `decimal` for decimals beyond 38 digits
+pub const DOSS_TYPE_METADATA: &str = "doss.type";
+
+const MAX_DECIMAL128_DIGITS: u32 = 38;
+
+impl DossSchema {
+    /// The Arrow schema of the records, see [`DossArrowProjector`] for the mapping of the types
+    pub fn to_arrow(&self) -> Schema {
+        Schema::new(self.fields.iter().map(arrow_field).collect::<Vec<_>>())
+    }
+}
+
+fn arrow_field(field: &DossField) -> Field {
+    let (data_type, doss_type) = arrow_type(&field.data_type);
+    //arrow requires null columns to be nullable
+    let nullable = field.nullable || data_type == DataType::Null;
+    let arrow = Field::new(&field.name, data_type, nullable);
+    match doss_type {
+        Some(name) => arrow.with_metadata(HashMap::from([(String::from(DOSS_TYPE_METADATA), name)])),
+        None => arrow,
+    }
+}
+
+fn arrow_type(data_type: &DossFieldType) -> (DataType, Option<String>) {
+    match data_type {
+        DossFieldType::Null => (DataType::Null, None),
+        DossFieldType::Boolean => (DataType::Boolean, None),
+        DossFieldType::Integer => (DataType::Int64, None),
+        DossFieldType::Float => (DataType::Float64, None),
+        DossFieldType::Decimal { digits, scale } if precision(*digits, *scale) <= MAX_DECIMAL128_DIGITS => {
+            (DataType::Decimal128(precision(*digits, *scale) as u8, *scale as i8), None)
+        }
+        DossFieldType::Decimal { .. } => (DataType::Utf8, Some(String::from("decimal"))),
+        DossFieldType::String => (DataType::Utf8, None),
+        DossFieldType::Binary => (DataType::Binary, None),
+        DossFieldType::DateTime => (DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())), None),
+        DossFieldType::Typed(name, content) => (arrow_type(content).0, Some(name.clone())),
+        DossFieldType::Struct(fields) => (DataType::Struct(fields.iter().map(arrow_field).collect::<Fields>()), None),
+        DossFieldType::List(item) => (DataType::List(Arc::new(arrow_field(item))), None),
+        DossFieldType::Mixed => (DataType::Utf8, Some(String::from("sjson"))),
+    }
+}
+
+#[derive(Debug)]
+enum Builder {
+    Null(usize),
+    Boolean(BooleanBuilder),
+    Integer(Int64Builder),
+    Float(Float64Builder),
+    Decimal(Decimal128Builder, u16),
+    DecimalText(StringBuilder),
+    String(StringBuilder),
+    Binary(BinaryBuilder),
+    DateTime(TimestampMicrosecondBuilder),
+    Mixed(StringBuilder),
+    Struct { fields: Fields, children: Vec<Column>, validity: Vec<bool> },
+    List { field: FieldRef, item: Box<Column>, offsets: Vec<i32>, validity: Vec<bool> },
+}
+
+#[derive(Debug)]
+struct Column {
+    name: String,
+    typed: Option<String>, //name of the DOSS type around the values
+    builder: Builder,
+}
+
+impl Column {
+    fn new(field: &DossField) -> Column {
+        let (typed, data_type) = match &field.data_type {
+            DossFieldType::Typed(name, content) => (Some(name.clone()), content.as_ref()),
+            data_type => (None, data_type),
+        };
+        let builder = match data_type {
+            DossFieldType::Null | DossFieldType::Typed(..) => Builder::Null(0),
+            DossFieldType::Boolean => Builder::Boolean(BooleanBuilder::new()),
+            DossFieldType::Integer => Builder::Integer(Int64Builder::new()),
+            DossFieldType::Float => Builder::Float(Float64Builder::new()),
+            DossFieldType::Decimal { digits, scale } if precision(*digits, *scale) <= MAX_DECIMAL128_DIGITS => Builder::Decimal(
+                Decimal128Builder::new().with_precision_and_scale(precision(*digits, *scale) as u8, *scale as i8).expect("precision checked above"),
+                *scale,
+            ),
+            DossFieldType::Decimal { .. } => Builder::DecimalText(StringBuilder::new()),
+            DossFieldType::String => Builder::String(StringBuilder::new()),
+            DossFieldType::Binary => Builder::Binary(BinaryBuilder::new()),
+            DossFieldType::DateTime => Builder::DateTime(TimestampMicrosecondBuilder::new().with_timezone("UTC")),
+            DossFieldType::Mixed => Builder::Mixed(StringBuilder::new()),
+            DossFieldType::Struct(fields) => Builder::Struct {
+                fields: fields.iter().map(arrow_field).collect(),
+                children: fields.iter().map(Column::new).collect(),
+                validity: Vec::new(),
+            },
+            DossFieldType::List(item) => Builder::List {
+                field: Arc::new(arrow_field(item)),
+                item: Box::new(Column::new(item)),
+                offsets: vec![0],
+                validity: Vec::new(),
+            },
+        };
+        Column { name: field.name.clone(), typed, builder }
+    }
+
+    fn append_null(&mut self) {
+        match &mut self.builder {
+            Builder::Null(len) => *len += 1,
+            Builder::Boolean(b) => b.append_null(),
+            Builder::Integer(b) => b.append_null(),
+            Builder::Float(b) => b.append_null(),
+            Builder::Decimal(b, _) => b.append_null(),
+            Builder::DecimalText(b) | Builder::String(b) | Builder::Mixed(b) => b.append_null(),
+            Builder::Binary(b) => b.append_null(),
+            Builder::DateTime(b) => b.append_null(),
+            Builder::Struct { children, validity, .. } => {
+                validity.push(false);
+                children.iter_mut().for_each(Column::append_null);
+            }
+            Builder::List { offsets, validity, .. } => {
+                offsets.push(*offsets.last().expect("offsets start with 0"));
+                validity.push(false);
+            }
+        }
+    }
+
+    /// Appends a scalar, false if it doesn't fit the column
+    fn append_scalar(&mut self, event: &TypedStreamEvent) -> bool {
+        match (&mut self.builder, event) {
+            (_, TypedStreamEvent::NULL) => self.append_null(),
+            (Builder::Boolean(b), TypedStreamEvent::TRUE | TypedStreamEvent::FALSE) => b.append_value(matches!(event, TypedStreamEvent::TRUE)),
+            (Builder::Integer(b), event) => match integer(event).and_then(|v| i64::try_from(v).ok()) {
+                Some(v) => b.append_value(v),
+                None => return false,
+            },
+            (Builder::Float(b), TypedStreamEvent::FLOAT(f)) => b.append_value(*f),
+            (Builder::Float(b), event) => match integer(event).map(|v| v as f64).or_else(|| decimal(event).and_then(|d| d.to_string().parse().ok())) {
+                Some(f) => b.append_value(f),
+                None => return false,
+            },
+            (Builder::Decimal(b, scale), event) => {
+                let unscaled = match integer(event) {
+                    Some(v) => 10_i128.checked_pow(*scale as u32).and_then(|p| v.checked_mul(p)),
+                    None => decimal(event).and_then(|d| decimal_i128(d, *scale)),
+                };
+                match unscaled {
+                    Some(v) => b.append_value(v),
+                    None => return false,
+                }
+            }
+            (Builder::DecimalText(b), event) => match integer(event).map(|v| v.to_string()).or_else(|| decimal(event).map(Decimal::to_string)) {
+                Some(text) => b.append_value(text),
+                None => return false,
+            },
+            (Builder::String(b), TypedStreamEvent::STRING(s)) => b.append_value(s),
+            (Builder::Binary(b), TypedStreamEvent::BYTEARRAY(bytes)) => b.append_value(bytes),
+            (Builder::DateTime(b), TypedStreamEvent::DATETIME(d)) => match i64::try_from(d.unix_nanos().div_euclid(1000)) {
+                Ok(micros) => b.append_value(micros),
+                Err(_) => return false,
+            },
+            (Builder::Mixed(b), event) => match scalar(event) {
+                Ok((_, text)) => b.append_value(text),
+                Err(_) => return false,
+            },
+            _ => return false,
+        }
+        true
+    }
+
+    fn finish(&mut self) -> Result<ArrayRef, DossError> {
+        Ok(match &mut self.builder {
+            Builder::Null(len) => Arc::new(NullArray::new(std::mem::take(len))),
+            Builder::Boolean(b) => Arc::new(b.finish()),
+            Builder::Integer(b) => Arc::new(b.finish()),
+            Builder::Float(b) => Arc::new(b.finish()),
+            Builder::Decimal(b, _) => Arc::new(b.finish()),
+            Builder::DecimalText(b) | Builder::String(b) | Builder::Mixed(b) => Arc::new(b.finish()),
+            Builder::Binary(b) => Arc::new(b.finish()),
+            Builder::DateTime(b) => Arc::new(b.finish()),
+            Builder::Struct { fields, children, validity } => {
+                let nulls = Some(NullBuffer::from(std::mem::take(validity)));
+                if fields.is_empty() {
+                    Arc::new(StructArray::new_empty_fields(nulls.as_ref().map_or(0, NullBuffer::len), nulls))
+                } else {
+                    let arrays = children.iter_mut().map(Column::finish).collect::<Result<Vec<_>, _>>()?;
+                    Arc::new(StructArray::try_new(fields.clone(), arrays, nulls).map_err(columnar)?)
+                }
+            }
+            Builder::List { field, item, offsets, validity } => {
+                let offsets = OffsetBuffer::new(ScalarBuffer::from(std::mem::replace(offsets, vec![0])));
+                let nulls = Some(NullBuffer::from(std::mem::take(validity)));
+                Arc::new(ListArray::try_new(field.clone(), offsets, item.finish()?, nulls).map_err(columnar)?)
+            }
+        })
+    }
+
+    fn len(&self) -> usize {
+        match &self.builder {
+            Builder::Null(len) => *len,
+            Builder::Boolean(b) => b.len(),
+            Builder::Integer(b) => b.len(),
+            Builder::Float(b) => b.len(),
+            Builder::Decimal(b, _) => b.len(),
+            Builder::DecimalText(b) | Builder::String(b) | Builder::Mixed(b) => b.len(),
+            Builder::Binary(b) => b.len(),
+            Builder::DateTime(b) => b.len(),
+            Builder::Struct { validity, .. } | Builder::List { validity, .. } => validity.len(),
+        }
+    }
+}
+
+fn integer(event: &TypedStreamEvent) -> Option<i128> {
+    match event {
+        TypedStreamEvent::DECIMAL(v) => Some(*v as i128),
+        TypedStreamEvent::ANY(v) => v.downcast_ref::<i64>().map(|v| *v as i128).or_else(|| v.downcast_ref::<u64>().map(|v| *v as i128)),
+        _ => None,
+    }
+}
+
+fn decimal(event: &TypedStreamEvent) -> Option<&Decimal> {
+    match event {
+        TypedStreamEvent::ANY(v) => v.downcast_ref::<Decimal>(),
+        _ => None,
+    }
+}
+
+/// The unscaled value of a decimal with `scale` digits after the point, None if it doesn't fit or digits would be lost
+fn decimal_i128(d: &Decimal, scale: u16) -> Option<i128> {
+    let mut magnitude: i128 = 0;
+    for limb in d.values().iter().rev() {
+        magnitude = magnitude.checked_mul(1_i128 << usize::BITS)?.checked_add(*limb as i128)?;
+    }
+    let shift = d.extension() as i32 + scale as i32;
+    let unscaled = if shift >= 0 {
+        magnitude.checked_mul(10_i128.checked_pow(shift as u32)?)?
+    } else {
+        let divisor = 10_i128.checked_pow(shift.unsigned_abs())?;
+        if magnitude % divisor != 0 {
+            return None;
+        }
+        magnitude / divisor
+    };
+    Some(if d.is_negative() { -unscaled } else { unscaled })
+}
+
+fn columnar(e: impl std::fmt::Display) -> DossError {
+    DossError::Columnar(e.to_string())
+}
+
+/// streamablejson text of a value in a mixed column
+#[derive(Debug, Default)]
+struct SjsonText {
+    text: String,
+    stack: Vec<(char, usize)>, //closing character and the number of items so far
+}
+
+impl SjsonText {
+    /// Adds an event, true when the value is complete
+    fn push(&mut self, event: &TypedStreamEvent) -> Result<bool, DossError> {
+        match event {
+            TypedStreamEvent::ENDOBJECT | TypedStreamEvent::ENDARRAY | TypedStreamEvent::ENDTYPE => {
+                let (close, _) = self.stack.pop().ok_or(DossError::UnbalancedStructure)?;
+                self.text.push(close);
+            }
+            event => {
+                if let Some((close, n)) = self.stack.last_mut() {
+                    if *n > 0 {
+                        self.text.push_str(if *close == '}' && *n % 2 == 1 { ": " } else { ", " });
+                    }
+                    *n += 1;
+                }
+                match event {
+                    TypedStreamEvent::STARTOBJECT => self.open('{', '}'),
+                    TypedStreamEvent::STARTARRAY => self.open('[', ']'),
+                    TypedStreamEvent::STARTTYPE(name) => {
+                        self.text.push_str(name);
+                        self.open('(', ')');
+                    }
+                    event => self.text.push_str(&scalar(event)?.1),
+                }
+            }
+        }
+        Ok(self.stack.is_empty())
+    }
+
+    fn open(&mut self, open: char, close: char) {
+        self.text.push(open);
+        self.stack.push((close, 0));
+    }
+}
+
+#[derive(Debug)]
+enum Frame {
+    Records,
+    Object { path: Vec<usize>, key: Option<usize>, seen: Vec<bool> },
+    Array { path: Vec<usize> },
+    Typed { path: Vec<usize>, value: Option<TypedStreamEvent> },
+    Text { path: Vec<usize>, text: SjsonText },
+    Ignore(usize), //a repeated key, the number of open levels
+}
+
+/// Projects the records of an event stream into Arrow record batches of a [`DossSchema`].
+///
+/// Types map to Int64, Float64, Decimal128 (decimals beyond 38 digits become Utf8), Utf8, Binary, Timestamp in
+/// microseconds UTC, Struct and List. A typed value like `ObjectId("...")` is stored as its content, the type name is
+/// kept in the field metadata [`DOSS_TYPE_METADATA`], and mixed fields hold the streamablejson text of their values.
+/// Missing fields are null and the first of repeated keys wins. Only the records of the current batch are held in memory.
+#[derive(Debug)]
+pub struct DossArrowProjector {
+    schema: SchemaRef,
+    root: Column,
+    stack: Vec<Frame>,
+    batch_size: usize,
+    batches: VecDeque<RecordBatch>,
+}
+
+impl DossArrowProjector {
+    pub fn new(schema: &DossSchema, batch_size: usize) -> Self {
+        let root = DossField {
+            name: String::new(),
+            data_type: DossFieldType::Struct(schema.fields.clone()),
+            nullable: false,
+            count: schema.records,
+            cardinality: None,
+        };
+        DossArrowProjector {
+            schema: Arc::new(schema.to_arrow()),
+            root: Column::new(&root),
+            stack: Vec::new(),
+            batch_size: batch_size.max(1),
+            batches: VecDeque::new(),
+        }
+    }
+
+    pub fn arrow_schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    /// Adds all events, e.g. of a [`DossPullParser`](crate::DossPullParser)
+    pub fn push_all(&mut self, events: impl IntoIterator<Item = Result<TypedStreamEvent, DossError>>) -> Result<(), DossError> {
+        for event in events {
+            self.push(event?)?;
+        }
+        Ok(())
+    }
+
+    pub fn push(&mut self, event: TypedStreamEvent) -> Result<(), DossError> {
+        match event {
+            TypedStreamEvent::INIT | TypedStreamEvent::FINISH | TypedStreamEvent::HINT(_) => return Ok(()),
+            TypedStreamEvent::ERROR(e) => return Err(DossError::UnsupportedValue(e.to_string())),
+            _ => {}
+        }
+        match self.stack.last_mut() {
+            Some(Frame::Ignore(depth)) => {
+                *depth = depth.saturating_add_signed(depth_change(&event));
+                if *depth == 0 {
+                    self.stack.pop();
+                }
+                return Ok(());
+            }
+            Some(Frame::Text { text, .. }) => {
+                if text.push(&event)? {
+                    let Some(Frame::Text { path, text }) = self.stack.pop() else { unreachable!("matched above") };
+                    match &mut self.column(&path).builder {
+                        Builder::Mixed(b) => b.append_value(text.text),
+                        _ => unreachable!("text is only collected for mixed columns"),
+                    }
+                }
+                return Ok(());
+            }
+            Some(Frame::Typed { value, .. }) => {
+                return match (event, value.is_some()) {
+                    (TypedStreamEvent::ENDTYPE, _) => {
+                        let Some(Frame::Typed { path, value }) = self.stack.pop() else { unreachable!("matched above") };
+                        self.append_scalar(&path, &value.unwrap_or(TypedStreamEvent::NULL))
+                    }
+                    (event, false) if depth_change(&event) == 0 => {
+                        *value = Some(event);
+                        Ok(())
+                    }
+                    _ => Err(self.mismatch()),
+                };
+            }
+            Some(Frame::Object { key: None, .. }) if !matches!(event, TypedStreamEvent::ENDOBJECT) => return self.key(event),
+            _ => {}
+        }
+        match event {
+            TypedStreamEvent::ENDOBJECT => match self.stack.pop() {
+                Some(Frame::Object { path, seen, .. }) => {
+                    if let Builder::Struct { children, .. } = &mut self.column(&path).builder {
+                        children.iter_mut().zip(seen).filter(|(_, seen)| !seen).for_each(|(c, _)| c.append_null());
+                    }
+                    if path.is_empty() && self.root.len() >= self.batch_size {
+                        let batch = self.batch()?;
+                        self.batches.push_back(batch);
+                    }
+                    Ok(())
+                }
+                _ => Err(DossError::UnbalancedStructure),
+            },
+            TypedStreamEvent::ENDARRAY => match self.stack.pop() {
+                Some(Frame::Array { path }) => {
+                    if let Builder::List { item, offsets, validity, .. } = &mut self.column(&path).builder {
+                        offsets.push(i32::try_from(item.len()).map_err(columnar)?);
+                        validity.push(true);
+                    }
+                    Ok(())
+                }
+                Some(Frame::Records) => Ok(()),
+                _ => Err(DossError::UnbalancedStructure),
+            },
+            TypedStreamEvent::ENDTYPE => Err(DossError::UnbalancedStructure),
+            event => {
+                let path = match self.stack.last_mut() {
+                    None if matches!(event, TypedStreamEvent::STARTARRAY) => {
+                        self.stack.push(Frame::Records);
+                        return Ok(());
+                    }
+                    None | Some(Frame::Records) if matches!(event, TypedStreamEvent::STARTOBJECT) => {
+                        if let Builder::Struct { validity, .. } = &mut self.root.builder {
+                            validity.push(true);
+                        }
+                        self.stack.push(Frame::Object { path: Vec::new(), key: None, seen: vec![false; self.schema.fields().len()] });
+                        return Ok(());
+                    }
+                    None | Some(Frame::Records) => return Err(DossError::SchemaMismatch(String::from("records must be objects"))),
+                    Some(Frame::Object { path, key, .. }) => {
+                        let mut path = path.clone();
+                        path.push(key.take().ok_or(DossError::UnbalancedStructure)?);
+                        path
+                    }
+                    Some(Frame::Array { path }) => {
+                        let mut path = path.clone();
+                        path.push(0);
+                        path
+                    }
+                    Some(_) => unreachable!("handled above"),
+                };
+                self.value(path, event)
+            }
+        }
+    }
+
+    /// The next complete batch of `batch_size` records
+    pub fn pop_batch(&mut self) -> Option<RecordBatch> {
+        self.batches.pop_front()
+    }
+
+    /// The remaining records, None if there are none
+    pub fn finish(&mut self) -> Result<Option<RecordBatch>, DossError> {
+        if !self.stack.is_empty() {
+            return Err(DossError::UnexpectedEof);
+        }
+        if self.root.len() == 0 {
+            return Ok(None);
+        }
+        self.batch().map(Some)
+    }
+
+    fn batch(&mut self) -> Result<RecordBatch, DossError> {
+        let Builder::Struct { children, validity, .. } = &mut self.root.builder else { unreachable!("the root is a struct") };
+        let columns = children.iter_mut().map(Column::finish).collect::<Result<Vec<_>, _>>()?;
+        let rows = std::mem::take(validity).len();
+        RecordBatch::try_new_with_options(self.schema.clone(), columns, &arrow::array::RecordBatchOptions::new().with_row_count(Some(rows)))
+            .map_err(columnar)
+    }
+
+    fn key(&mut self, event: TypedStreamEvent) -> Result<(), DossError> {
+        let name = match &event {
+            TypedStreamEvent::STRING(s) => s.clone(),
+            event => scalar(event)?.1,
+        };
+        let Some(Frame::Object { path, .. }) = self.stack.last() else { unreachable!("only called for object keys") };
+        let Builder::Struct { children, .. } = &self.column(&path.clone()).builder else { unreachable!("objects are projected into structs") };
+        let child = children.iter().position(|c| c.name == name);
+        let Some(Frame::Object { path, key, seen }) = self.stack.last_mut() else { unreachable!("matched above") };
+        match child {
+            Some(child) if !seen[child] => {
+                seen[child] = true;
+                *key = Some(child);
+            }
+            Some(_) => self.stack.push(Frame::Ignore(0)),
+            None => {
+                let path = path.clone();
+                return Err(DossError::SchemaMismatch(format!("unknown field {name} in {}", self.path_name(&path))));
+            }
+        }
+        Ok(())
+    }
+
+    fn value(&mut self, path: Vec<usize>, event: TypedStreamEvent) -> Result<(), DossError> {
+        let column = self.column(&path);
+        match (&mut column.builder, event) {
+            (Builder::Struct { children, validity, .. }, TypedStreamEvent::STARTOBJECT) => {
+                validity.push(true);
+                let seen = vec![false; children.len()];
+                self.stack.push(Frame::Object { path, key: None, seen });
+            }
+            (Builder::List { .. }, TypedStreamEvent::STARTARRAY) => self.stack.push(Frame::Array { path }),
+            (Builder::Mixed(_), event) if depth_change(&event) > 0 => {
+                let mut text = SjsonText::default();
+                text.push(&event)?;
+                self.stack.push(Frame::Text { path, text });
+            }
+            (_, TypedStreamEvent::STARTTYPE(name)) if column.typed.as_ref() == Some(&name) => self.stack.push(Frame::Typed { path, value: None }),
+            (_, event) if depth_change(&event) == 0 && column.typed.is_none() => return self.append_scalar(&path, &event),
+            (_, TypedStreamEvent::NULL) => column.append_null(),
+            _ => return Err(DossError::SchemaMismatch(self.path_name(&path))),
+        }
+        Ok(())
+    }
+
+    fn append_scalar(&mut self, path: &[usize], event: &TypedStreamEvent) -> Result<(), DossError> {
+        if self.column(path).append_scalar(event) {
+            Ok(())
+        } else {
+            Err(DossError::SchemaMismatch(self.path_name(path)))
+        }
+    }
+
+    fn mismatch(&self) -> DossError {
+        match self.stack.last() {
+            Some(Frame::Typed { path, .. }) => DossError::SchemaMismatch(self.path_name(path)),
+            _ => DossError::UnbalancedStructure,
+        }
+    }
+
+    fn column(&mut self, path: &[usize]) -> &mut Column {
+        let mut column = &mut self.root;
+        for index in path {
+            column = match &mut column.builder {
+                Builder::Struct { children, .. } => &mut children[*index],
+                Builder::List { item, .. } => item,
+                _ => unreachable!("paths only lead through structs and lists"),
+            };
+        }
+        column
+    }
+
+    /// A path like `items[].price` for errors
+    fn path_name(&self, path: &[usize]) -> String {
+        let mut name = String::new();
+        let mut column = &self.root;
+        for index in path {
+            column = match &column.builder {
+                Builder::Struct { children, .. } => {
+                    let child = &children[*index];
+                    if !name.is_empty() {
+                        name.push('.');
+                    }
+                    name.push_str(&child.name);
+                    child
+                }
+                Builder::List { item, .. } => {
+                    name.push_str("[]");
+                    item
+                }
+                _ => break,
+            };
+        }
+        name
+    }
+}
+
+#[cfg(feature = "parquet")]
+pub use parquet_writer::{DossParquetWriter, doss_to_parquet};
+
+#[cfg(feature = "parquet")]
+mod parquet_writer {
+    use std::io::{Read, Seek, SeekFrom, Write};
+
+    use dataflowgrid_commons::typedstream::TypedStreamEvent;
+    use parquet::arrow::ArrowWriter;
+    use parquet::basic::{Compression, ZstdLevel};
+    use parquet::file::properties::WriterProperties;
+
+    use super::{DossArrowProjector, columnar};
+    use crate::error::DossError;
+    use crate::parser::DossPullParser;
+    use crate::schema::{DossSchema, DossSchemaInference};
+
+    /// Writes the records of an event stream to a zstd compressed Parquet file, a row group per batch
+    pub struct DossParquetWriter<W: Write + Send> {
+        projector: DossArrowProjector,
+        writer: ArrowWriter<W>,
+    }
+
+    impl<W: Write + Send> DossParquetWriter<W> {
+        pub fn new(out: W, schema: &DossSchema, batch_size: usize) -> Result<Self, DossError> {
+            let projector = DossArrowProjector::new(schema, batch_size);
+            let properties = WriterProperties::builder()
+                .set_compression(Compression::ZSTD(ZstdLevel::default()))
+                .set_max_row_group_size(batch_size.max(1))
+                .build();
+            let writer = ArrowWriter::try_new(out, projector.arrow_schema(), Some(properties)).map_err(columnar)?;
+            Ok(DossParquetWriter { projector, writer })
+        }
+
+        pub fn push_all(&mut self, events: impl IntoIterator<Item = Result<TypedStreamEvent, DossError>>) -> Result<(), DossError> {
+            for event in events {
+                self.push(event?)?;
+            }
+            Ok(())
+        }
+
+        pub fn push(&mut self, event: TypedStreamEvent) -> Result<(), DossError> {
+            self.projector.push(event)?;
+            while let Some(batch) = self.projector.pop_batch() {
+                self.writer.write(&batch).map_err(columnar)?;
+            }
+            Ok(())
+        }
+
+        /// Writes the remaining records and the footer
+        pub fn finish(mut self) -> Result<W, DossError> {
+            if let Some(batch) = self.projector.finish()? {
+                self.writer.write(&batch).map_err(columnar)?;
+            }
+            self.writer.into_inner().map_err(columnar)
+        }
+    }
+
+    /// Converts a DOSS stream to Parquet in two passes, the first one infers the schema
+    pub fn doss_to_parquet<R: Read + Seek, W: Write + Send>(mut input: R, out: W, batch_size: usize) -> Result<DossSchema, DossError> {
+        let start = input.stream_position()?;
+        let mut inference = DossSchemaInference::new();
+        inference.push_all(DossPullParser::new(&mut input))?;
+        let schema = inference.schema();
+        input.seek(SeekFrom::Start(start))?;
+        let mut writer = DossParquetWriter::new(out, &schema, batch_size)?;
+        writer.push_all(DossPullParser::new(&mut input))?;
+        writer.finish()?;
+        Ok(schema)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::{Array, AsArray};
+    use arrow::datatypes::{Decimal128Type, Int64Type, TimestampMicrosecondType};
+    use streamablejson::deserializer::deserialize_orderedbag_from_string;
+
+    use super::*;
+    use crate::parser::DossPullParser;
+    use crate::schema::DossSchemaInference;
+    use crate::serializer::DossSerializer;
+
+    const ORDERS: &str = r#"[
+        {"id": 1, "customer": {"name": "a", "vip": true}, "items": [{"product": "cable", "price": decimal("1.50")}, {"product": "dock", "price": 20}], "at": datetime("2025-01-01T00:00:01Z")},
+        {"id": 2, "customer": null, "items": [], "oid": ObjectId("5f1"), "extra": "x"},
+        {"id": 3, "customer": {"name": "b"}, "items": [{"product": "cable"}], "extra": [1, {"a": "b"}]}
+    ]"#;
+
+    fn doss(sjson: &str) -> Vec<u8> {
+        let mut serializer = DossSerializer::new(Vec::new());
+        serializer.write_entry(&deserialize_orderedbag_from_string(sjson.to_string()).unwrap()).unwrap();
+        serializer.finish().unwrap()
+    }
+
+    fn project(serialized: &[u8], batch_size: usize) -> (DossSchema, Vec<RecordBatch>) {
+        let mut inference = DossSchemaInference::new();
+        inference.push_all(DossPullParser::new(serialized)).unwrap();
+        let schema = inference.schema();
+        let mut projector = DossArrowProjector::new(&schema, batch_size);
+        let mut batches = Vec::new();
+        for event in DossPullParser::new(serialized) {
+            projector.push(event.unwrap()).unwrap();
+            batches.extend(std::iter::from_fn(|| projector.pop_batch()));
+        }
+        batches.extend(projector.finish().unwrap());
+        (schema, batches)
+    }
+
+    #[test]
+    fn test_project_orders() {
+        let (schema, batches) = project(&doss(ORDERS), 2);
+        assert_eq!(batches.iter().map(RecordBatch::num_rows).collect::<Vec<_>>(), [2, 1]);
+        let arrow = schema.to_arrow();
+        assert_eq!(arrow.field_with_name("id").unwrap().data_type(), &DataType::Int64);
+        assert_eq!(arrow.field_with_name("oid").unwrap().metadata()[DOSS_TYPE_METADATA], "ObjectId");
+        assert_eq!(arrow.field_with_name("extra").unwrap().metadata()[DOSS_TYPE_METADATA], "sjson");
+
+        let first = &batches[0];
+        assert_eq!(first.column_by_name("id").unwrap().as_primitive::<Int64Type>().values(), &[1, 2]);
+        let customer = first.column_by_name("customer").unwrap().as_struct();
+        assert!(customer.is_valid(0) && customer.is_null(1));
+        assert_eq!(customer.column_by_name("name").unwrap().as_string::<i32>().value(0), "a");
+        let items = first.column_by_name("items").unwrap().as_list::<i32>();
+        assert_eq!(items.value_offsets(), &[0, 2, 2]);
+        let item = items.values().as_struct();
+        let prices = item.column_by_name("price").unwrap().as_primitive::<Decimal128Type>();
+        assert_eq!((prices.value(0), prices.value(1), prices.scale()), (150, 2000, 2));
+        let at = first.column_by_name("at").unwrap().as_primitive::<TimestampMicrosecondType>();
+        assert_eq!((at.value(0), at.is_null(1)), (1_735_689_601_000_000, true));
+        assert_eq!(first.column_by_name("oid").unwrap().as_string::<i32>().value(1), "5f1");
+
+        let last = &batches[1];
+        let extra = last.column_by_name("extra").unwrap().as_string::<i32>();
+        assert_eq!(extra.value(0), r#"[1, {"a": "b"}]"#);
+        assert_eq!(batches[0].column_by_name("extra").unwrap().as_string::<i32>().value(1), r#""x""#);
+        let price = last.column_by_name("items").unwrap().as_list::<i32>().values().as_struct().column_by_name("price").unwrap().clone();
+        assert!(price.is_null(0));
+    }
+
+    #[test]
+    fn test_schema_mismatch() {
+        let (schema, _) = project(&doss(r#"{"id": 1, "id": "first wins"}"#), 10);
+        let mut projector = DossArrowProjector::new(&schema, 10);
+        let serialized = doss(r#"{"id": "one"}"#);
+        assert!(matches!(projector.push_all(DossPullParser::new(serialized.as_slice())), Err(DossError::SchemaMismatch(path)) if path == "id"));
+        let mut projector = DossArrowProjector::new(&schema, 10);
+        let serialized = doss(r#"{"name": 1}"#);
+        assert!(matches!(projector.push_all(DossPullParser::new(serialized.as_slice())), Err(DossError::SchemaMismatch(_))));
+        assert_eq!(decimal_i128(&"-1.25".parse().unwrap(), 3), Some(-1250));
+        assert_eq!(decimal_i128(&"1.25".parse().unwrap(), 1), None);
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_doss_to_parquet() {
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let serialized = doss(ORDERS);
+        let mut out = Vec::new();
+        let schema = doss_to_parquet(std::io::Cursor::new(&serialized), &mut out, 2).unwrap();
+        assert_eq!(schema.records, 3);
+        let reader = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(out)).unwrap().with_batch_size(2);
+        assert_eq!(reader.metadata().num_row_groups(), 2);
+        let batches: Vec<_> = reader.build().unwrap().collect::<Result<_, _>>().unwrap();
+        let (_, expected) = project(&serialized, 2);
+        assert_eq!(batches, expected);
+    }
+}
@@ -0,0 +1,451 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::io::{Result as IoResult, Seek, Write};
+
+use crate::error::DossError;
+use crate::skip::{PatchFn, seek_patch};
+use crate::varint;
+
+/// Compression of the bytes following setting 2, see settings_and_hints.md
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    #[default]
+    None,
+    #[cfg(feature = "zstd")]
+    Zstd,
+    #[cfg(feature = "lz4")]
+    Lz4,
+}
+
+impl Compression {
+    /// Accepts the names of settings_and_hints.md case insensitive. Compressions whose feature is disabled are unknown
+    pub fn from_name(name: &str) -> Option<Compression> {
+        match name.to_ascii_lowercase().as_str() {
+            "none" => Some(Compression::None),
+            #[cfg(feature = "zstd")]
+            "zstd" => Some(Compression::Zstd),
+            #[cfg(feature = "lz4")]
+            "lz4" => Some(Compression::Lz4),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Compression::None => "none",
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => "zstd",
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => "lz4",
+        }
+    }
+
+    fn compress(&self, data: &[u8]) -> IoResult<Vec<u8>> {
+        match self {
+            Compression::None => Ok(data.to_vec()),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => zstd::bulk::compress(data, zstd::DEFAULT_COMPRESSION_LEVEL),
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => Ok(lz4_flex::block::compress(data)),
+        }
+    }
+
+    fn decompress(&self, data: &[u8], len: usize) -> Result<Vec<u8>, DossError> {
+        let decompressed = match self {
+            Compression::None => data.to_vec(),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => zstd::bulk::decompress(data, len).map_err(|e| DossError::InvalidFrame(e.to_string()))?,
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => lz4_flex::block::decompress(data, len).map_err(|e| DossError::InvalidFrame(e.to_string()))?,
+        };
+        match decompressed.len() == len {
+            true => Ok(decompressed),
+            false => Err(DossError::InvalidFrame(format!("{} bytes instead of {len}", decompressed.len()))),
+        }
+    }
+}
+
+/// Writes a DOSS stream as it is or, once compression is switched on, as frames of `frame_size` bytes
+/// compressed independently. Each frame is written as the uncompressed length, the compressed length
+/// (both unsigned varints) and the compressed bytes. Flushing ends the current frame.
+pub(crate) struct FrameWriter<W: Write> {
+    out: W,
+    seek: Option<PatchFn<W>>,
+    compression: Compression,
+    frame_size: usize,
+    frame: Vec<u8>, //uncompressed bytes of the current frame
+    position: u64, //uncompressed bytes written
+    start: u64, //position the compression was set at
+}
+
+impl<W: Write> std::fmt::Debug for FrameWriter<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FrameWriter")
+            .field("seekable", &self.seek.is_some())
+            .field("compression", &self.compression)
+            .field("frame_size", &self.frame_size)
+            .field("frame", &self.frame.len())
+            .finish()
+    }
+}
+
+impl<W: Write> FrameWriter<W> {
+    pub(crate) fn new(out: W, frame_size: usize) -> Self {
+        FrameWriter { out, seek: None, compression: Compression::None, frame_size: frame_size.max(1), frame: Vec::new(), position: 0, start: 0 }
+    }
+
+    /// Uncompressed bytes written before can be patched by seeking back
+    pub(crate) fn seekable(out: W, frame_size: usize) -> Self where W: Seek {
+        FrameWriter { seek: Some(seek_patch::<W>), ..Self::new(out, frame_size) }
+    }
+
+    /// Ends the current frame, the following bytes use the new compression
+    pub(crate) fn set_compression(&mut self, compression: Compression) -> IoResult<()> {
+        self.end_frame()?;
+        self.compression = compression;
+        self.start = self.position;
+        Ok(())
+    }
+
+    /// Overwrites bytes written `back` bytes before the end. Compressed bytes can only be changed
+    /// while their frame is collected, uncompressed ones by seeking back if they follow the last switch.
+    /// Returns false for other bytes
+    pub(crate) fn patch(&mut self, back: u64, bytes: &[u8]) -> IoResult<bool> {
+        if self.compression == Compression::None {
+            return match self.seek {
+                Some(seek) if back <= self.position - self.start => seek(&mut self.out, back, bytes),
+                _ => Ok(false),
+            };
+        }
+        if back > self.frame.len() as u64 {
+            return Ok(false);
+        }
+        let at = self.frame.len() - back as usize;
+        self.frame[at..at + bytes.len()].copy_from_slice(bytes);
+        Ok(true)
+    }
+
+    pub(crate) fn finish(mut self) -> IoResult<W> {
+        self.flush()?;
+        Ok(self.out)
+    }
+
+    fn end_frame(&mut self) -> IoResult<()> {
+        if self.frame.is_empty() {
+            return Ok(());
+        }
+        let compressed = self.compression.compress(&self.frame)?;
+        let mut header = Vec::new();
+        varint::encode_unsigned(self.frame.len() as u64, &mut header);
+        varint::encode_unsigned(compressed.len() as u64, &mut header);
+        self.out.write_all(&header)?;
+        self.out.write_all(&compressed)?;
+        self.frame.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for FrameWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        if self.compression == Compression::None {
+            let n = self.out.write(buf)?;
+            self.position += n as u64;
+            return Ok(n);
+        }
+        let n = buf.len().min(self.frame_size - self.frame.len());
+        self.frame.extend_from_slice(&buf[..n]);
+        self.position += n as u64;
+        if self.frame.len() == self.frame_size {
+            self.end_frame()?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        self.end_frame()?;
+        self.out.flush()
+    }
+}
+
+/// What [`FrameReader::next`] found
+#[derive(Debug)]
+pub(crate) enum Frame {
+    Data(Vec<u8>), //the uncompressed bytes of the next frame
+    Dropped(usize), //a frame of this many uncompressed bytes was skipped
+    Jump(usize), //jump over this many bytes of the input or append them
+    NeedData,
+}
+
+/// Splits the input into the frames written by [`FrameWriter`]
+#[derive(Debug)]
+pub(crate) struct FrameReader {
+    compression: Compression,
+    input: Vec<u8>,
+    jump: usize, //compressed bytes of a dropped frame that are not buffered
+}
+
+impl FrameReader {
+    pub(crate) fn new(compression: Compression, input: Vec<u8>) -> Self {
+        FrameReader { compression, input, jump: 0 }
+    }
+
+    pub(crate) fn set_compression(&mut self, compression: Compression) {
+        self.compression = compression;
+    }
+
+    /// The buffer to append input to
+    pub(crate) fn input_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.input
+    }
+
+    /// Input that is not part of a frame yet
+    pub(crate) fn into_input(self) -> Vec<u8> {
+        self.input
+    }
+
+    /// The caller jumped over `n` bytes after [`Frame::Jump`]
+    pub(crate) fn jumped(&mut self, n: usize) {
+        self.jump -= n;
+    }
+
+    /// True if the input ends between two frames
+    pub(crate) fn is_complete(&self) -> bool {
+        self.input.is_empty() && self.jump == 0
+    }
+
+    /// Decompresses the next frame, a frame of at most `skip` bytes is dropped without decompressing it
+    pub(crate) fn next(&mut self, skip: usize, max_len: usize) -> Result<Frame, DossError> {
+        if self.jump > 0 {
+            let buffered = self.jump.min(self.input.len());
+            self.input.drain(..buffered);
+            self.jump -= buffered;
+            if self.jump > 0 {
+                return Ok(Frame::Jump(self.jump));
+            }
+        }
+        let Some((len, used)) = varint::decode_unsigned(&self.input)? else {
+            return Ok(Frame::NeedData);
+        };
+        let Some((compressed, compressed_used)) = varint::decode_unsigned(&self.input[used..])? else {
+            return Ok(Frame::NeedData);
+        };
+        let header = used + compressed_used;
+        let len = frame_len(len, max_len)?;
+        let compressed = frame_len(compressed, max_len)?;
+        if len <= skip {
+            let buffered = compressed.min(self.input.len() - header);
+            self.input.drain(..header + buffered);
+            self.jump = compressed - buffered;
+            return Ok(Frame::Dropped(len));
+        }
+        if self.input.len() < header + compressed {
+            return Ok(Frame::NeedData);
+        }
+        let data = self.compression.decompress(&self.input[header..header + compressed], len)?;
+        self.input.drain(..header + compressed);
+        Ok(Frame::Data(data))
+    }
+}
+
+fn frame_len(len: u64, max_len: usize) -> Result<usize, DossError> {
+    match usize::try_from(len) {
+        Ok(len) if len <= max_len => Ok(len),
+        _ => Err(DossError::TooLong(len)),
+    }
+}
+
+#[cfg(all(test, feature = "zstd", feature = "lz4"))]
+mod tests {
+    use std::cell::RefCell;
+    use std::io::{Cursor, Read};
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::limits::DossLimits;
+    use crate::parser::DossPullParser;
+    use crate::resolver::DossEvent;
+    use crate::serializer::{DossSerializer, DossSerializerOptions};
+
+    fn s(v: &str) -> DossEvent {
+        DossEvent::String(v.to_string())
+    }
+
+    //{"records": [{"id": 0, "name": "record 0"}, ...], "end": true}
+    fn events() -> Vec<DossEvent> {
+        let mut events = vec![DossEvent::BlockStart, s("records"), DossEvent::ArrayStart];
+        for i in 0..200 {
+            events.extend([DossEvent::BlockStart, s("id"), DossEvent::UInt(i), s("name"), s(&format!("record {i}")), DossEvent::BlockEnd]);
+        }
+        events.extend([DossEvent::ArrayEnd, s("end"), DossEvent::True, DossEvent::BlockEnd]);
+        events
+    }
+
+    fn options(compression: Compression) -> DossSerializerOptions {
+        DossSerializerOptions { compression, frame_size: 64, skip_threshold: Some(64), ..DossSerializerOptions::default() }
+    }
+
+    fn serialize(options: DossSerializerOptions) -> Vec<u8> {
+        let mut serializer = DossSerializer::seekable(Cursor::new(Vec::new()), options);
+        for e in &events() {
+            serializer.write_doss_event(e).unwrap();
+        }
+        serializer.finish().unwrap().into_inner()
+    }
+
+    fn decode(input: impl Read) -> Result<Vec<DossEvent>, DossError> {
+        let mut parser = DossPullParser::new(input);
+        let mut events = Vec::new();
+        while let Some(event) = parser.next_doss_event()? {
+            events.push(event);
+        }
+        Ok(events.into_iter().filter(|e| !matches!(e, DossEvent::Config { .. } | DossEvent::Hint { .. })).collect())
+    }
+
+    //hands out a few bytes per read, so frames arrive in pieces
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+            let n = buf.len().min(self.0.len()).min(5);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    //start and length of the compressed bytes of every frame following the setting at `start`
+    fn frames(serialized: &[u8], mut pos: usize) -> Vec<(usize, usize)> {
+        let mut frames = Vec::new();
+        while pos < serialized.len() {
+            let (_, used) = varint::decode_unsigned(&serialized[pos..]).unwrap().unwrap();
+            let (len, len_used) = varint::decode_unsigned(&serialized[pos + used..]).unwrap().unwrap();
+            pos += used + len_used;
+            frames.push((pos, len as usize));
+            pos += len as usize;
+        }
+        frames
+    }
+
+    #[derive(Clone, Default)]
+    struct Shared(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> IoResult<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let plain = serialize(options(Compression::None));
+        for compression in [Compression::Zstd, Compression::Lz4] {
+            let serialized = serialize(options(compression));
+            assert!(serialized.len() < plain.len(), "{compression:?}");
+            assert_eq!(decode(serialized.as_slice()).unwrap(), events());
+            assert_eq!(decode(Trickle(&serialized)).unwrap(), events());
+            let mut parser = DossPullParser::new(serialized.as_slice());
+            while parser.next_doss_event().unwrap().is_some() {}
+            assert_eq!(parser.settings().compression(), compression);
+            //positions count uncompressed bytes, the streams only differ in the setting
+            assert_eq!(parser.position(), (plain.len() + 5 + compression.name().len()) as u64);
+        }
+    }
+
+    #[test]
+    fn test_skip_drops_frames() {
+        let header = [20_u8, 15, 2, 7, 3, b'l', b'z', b'4'];
+        let options = DossSerializerOptions { max_dict_entries: 0, ..options(Compression::Lz4) };
+        let mut serialized = serialize(options);
+        assert_eq!(serialized[..header.len()], header);
+        let frames = frames(&serialized, header.len());
+        assert!(frames.len() > 10);
+        //a broken frame in the middle of the array, which is skipped without decompressing it
+        let (start, len) = frames[frames.len() / 2];
+        serialized[start..start + len].fill(0xff);
+        assert!(matches!(decode(serialized.as_slice()), Err(DossError::InvalidFrame(_))));
+
+        let mut parser = DossPullParser::new(Trickle(&serialized));
+        let mut events = Vec::new();
+        while let Some(event) = parser.next_doss_event().unwrap() {
+            if event == DossEvent::ArrayStart {
+                parser.skip_level();
+            }
+            events.push(event);
+        }
+        assert_eq!(events[1..], [DossEvent::BlockStart, s("records"), DossEvent::ArrayStart, DossEvent::ArrayEnd, s("end"), DossEvent::True, DossEvent::BlockEnd]);
+    }
+
+    #[test]
+    fn test_flush_ends_frame() {
+        let out = Shared::default();
+        let options = DossSerializerOptions { frame_size: 1 << 16, ..options(Compression::Zstd) };
+        let mut serializer = DossSerializer::with_options(out.clone(), options);
+        let events = events();
+        for e in &events[..50] {
+            serializer.write_doss_event(e).unwrap();
+        }
+        serializer.flush().unwrap();
+        let prefix = out.0.borrow().clone();
+        let mut parser = DossPullParser::new(prefix.as_slice());
+        let mut decoded = 0;
+        while let Ok(Some(event)) = parser.next_doss_event() {
+            decoded += !matches!(event, DossEvent::Config { .. } | DossEvent::Hint { .. }) as usize;
+        }
+        assert_eq!(decoded, 50);
+
+        for e in &events[50..] {
+            serializer.write_doss_event(e).unwrap();
+        }
+        serializer.finish().unwrap();
+        assert_eq!(decode(out.0.borrow().as_slice()).unwrap(), events);
+    }
+
+    #[test]
+    fn test_switch_compression() {
+        let first = serialize(options(Compression::Zstd));
+        let mut parser = DossPullParser::new(first.as_slice());
+        while parser.next_doss_event().unwrap().is_some() {}
+        let settings = parser.settings().clone();
+
+        for compression in [Compression::None, Compression::Lz4, Compression::Zstd] {
+            let mut serializer = DossSerializer::appending(Vec::new(), &settings, options(compression)).unwrap();
+            for e in &events() {
+                serializer.write_doss_event(e).unwrap();
+            }
+            let serialized = [first.clone(), serializer.finish().unwrap()].concat();
+            assert_eq!(decode(Trickle(&serialized)).unwrap(), [events(), events()].concat());
+        }
+
+        //the setting can be written as event as well, but not inside a block
+        let mut serializer = DossSerializer::new(Vec::new());
+        let setting = DossEvent::Config { key: vec![DossEvent::UInt(2)], value: vec![s("lz4")] };
+        serializer.write_doss_event(&setting).unwrap();
+        serializer.write_doss_event(&s("compressed")).unwrap();
+        serializer.write_doss_event(&DossEvent::BlockStart).unwrap();
+        assert!(matches!(serializer.write_doss_event(&setting), Err(DossError::InvalidSetting(_))));
+        let unknown = DossEvent::Config { key: vec![DossEvent::UInt(2)], value: vec![s("brotli")] };
+        assert!(matches!(DossSerializer::new(Vec::new()).write_doss_event(&unknown), Err(DossError::InvalidSetting(_))));
+    }
+
+    #[test]
+    fn test_invalid_frames() {
+        let serialized = serialize(options(Compression::Zstd));
+        assert!(matches!(decode(&serialized[..serialized.len() - 1]), Err(DossError::UnexpectedEof)));
+
+        let mut parser = DossPullParser::new(serialized.as_slice());
+        parser.set_limits(DossLimits { max_string_len: 32, ..DossLimits::default() });
+        assert!(matches!(parser.by_ref().last(), Some(Err(DossError::TooLong(_)))));
+
+        //a frame that decompresses to more bytes than announced
+        let mut frame = vec![20_u8, 15, 2, 7, 3, b'l', b'z', b'4'];
+        let compressed = Compression::Lz4.compress(&[9; 10]).unwrap();
+        frame.push(5);
+        varint::encode_unsigned(compressed.len() as u64, &mut frame);
+        frame.extend(compressed);
+        assert!(matches!(decode(frame.as_slice()), Err(DossError::InvalidFrame(_))));
+    }
+}
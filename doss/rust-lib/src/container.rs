@@ -0,0 +1,403 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use dataflowgrid_commons::readers::reader::Readable;
+use streamablejson::StreamableJSONEntry;
+
+use crate::error::DossError;
+use crate::reader::{DossReader, DossReaderCallback, DossReaderCallbackReturn, DossReaderError};
+use crate::resolver::{DossEvent, DossItem};
+use crate::serializer::{DossSerializer, DossSerializerOptions};
+use crate::tree::DossTreeBuilder;
+
+/// Name of a file in a DOSS stream, see "File names could be anything" in docs/features.md
+#[derive(Debug, Clone, PartialEq)]
+pub enum DossFileName {
+    /// Data before the first start file opcode and files named null, numbered from 0 in stream order
+    Assigned(u64),
+    /// Any other name item
+    Named(DossItem),
+}
+
+impl DossFileName {
+    /// The name item to write with the start file opcode (50)
+    pub fn to_item(&self) -> DossItem {
+        match self {
+            DossFileName::Assigned(_) => vec![DossEvent::Null],
+            DossFileName::Named(item) => item.clone(),
+        }
+    }
+}
+
+pub trait DossFileCallback {
+    /// A file starts, the previous one ended. `Skip` leaves out all events of this file
+    fn on_file_start(&mut self, name: &DossFileName) -> DossReaderCallbackReturn;
+    /// An event of the current file. Settings, hints and imports before the first file are handed to the first file
+    fn on_file_event(&mut self, event: DossEvent) -> DossReaderCallbackReturn;
+    /// The current file ended, called at the next file start and by [`DossFiles::finish`]
+    fn on_file_end(&mut self) {}
+}
+
+/// Splits the events of a [`DossReader`] into files. Files share the dictionary and the settings,
+/// so every file is read in stream order and nothing of earlier files is kept.
+#[derive(Debug)]
+pub struct DossFiles<C: DossFileCallback> {
+    callback: C,
+    assigned: u64,
+    in_file: bool,
+    skipping: bool,
+    pending: Vec<DossEvent>,
+}
+
+impl<C: DossFileCallback> DossFiles<C> {
+    pub fn new(callback: C) -> Self {
+        DossFiles {
+            callback,
+            assigned: 0,
+            in_file: false,
+            skipping: false,
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn callback(&mut self) -> &mut C {
+        &mut self.callback
+    }
+
+    /// Ends the last file, call it after [`DossReader::finish`]
+    pub fn finish(&mut self) {
+        if self.in_file {
+            self.in_file = false;
+            self.callback.on_file_end();
+        }
+    }
+
+    pub fn into_callback(self) -> C {
+        self.callback
+    }
+
+    fn name(&mut self, item: DossItem) -> DossFileName {
+        match item.as_slice() {
+            [DossEvent::Null] => self.assign(),
+            _ => DossFileName::Named(item),
+        }
+    }
+
+    fn assign(&mut self) -> DossFileName {
+        self.assigned += 1;
+        DossFileName::Assigned(self.assigned - 1)
+    }
+
+    fn start(&mut self, name: DossFileName) -> DossReaderCallbackReturn {
+        self.finish();
+        self.in_file = true;
+        self.skipping = false;
+        match self.callback.on_file_start(&name) {
+            DossReaderCallbackReturn::Continue => {}
+            DossReaderCallbackReturn::Skip => {
+                self.skipping = true;
+                self.pending.clear();
+            }
+            stop => return stop,
+        }
+        for event in std::mem::take(&mut self.pending) {
+            match self.callback.on_file_event(event) {
+                DossReaderCallbackReturn::Continue | DossReaderCallbackReturn::Skip => {}
+                stop => return stop,
+            }
+        }
+        DossReaderCallbackReturn::Continue
+    }
+}
+
+impl<C: DossFileCallback> DossReaderCallback for DossFiles<C> {
+    fn on_doss_event(&mut self, event: DossEvent) -> DossReaderCallbackReturn {
+        match event {
+            DossEvent::FileStart(item) => {
+                let name = self.name(item);
+                self.start(name)
+            }
+            DossEvent::Config { .. } | DossEvent::Hint { .. } | DossEvent::ImportDict(_) if !self.in_file => {
+                //stream level events before the first file, there might be no data without start file
+                self.pending.push(event);
+                DossReaderCallbackReturn::Continue
+            }
+            event => {
+                if !self.in_file {
+                    let name = self.assign();
+                    match self.start(name) {
+                        DossReaderCallbackReturn::Continue => {}
+                        stop => return stop,
+                    }
+                }
+                match self.skipping {
+                    true => DossReaderCallbackReturn::Continue,
+                    false => self.callback.on_file_event(event),
+                }
+            }
+        }
+    }
+}
+
+/// Collects the trees of every file, a file replaces an earlier file with the same name at its position
+#[derive(Debug, Default)]
+struct TreeCollector {
+    files: Vec<(DossFileName, Vec<StreamableJSONEntry>)>,
+    current: Option<(DossFileName, DossTreeBuilder)>,
+    error: Option<DossError>,
+}
+
+impl DossFileCallback for TreeCollector {
+    fn on_file_start(&mut self, name: &DossFileName) -> DossReaderCallbackReturn {
+        self.current = Some((name.clone(), DossTreeBuilder::new()));
+        DossReaderCallbackReturn::Continue
+    }
+
+    fn on_file_event(&mut self, event: DossEvent) -> DossReaderCallbackReturn {
+        let (_, builder) = self.current.as_mut().expect("events follow a file start");
+        match builder.push(event) {
+            Ok(()) => DossReaderCallbackReturn::Continue,
+            Err(e) => {
+                self.error = Some(e);
+                DossReaderCallbackReturn::StopOk
+            }
+        }
+    }
+
+    fn on_file_end(&mut self) {
+        let Some((name, mut builder)) = self.current.take() else {
+            return;
+        };
+        let entries = builder.take_results();
+        match self.files.iter_mut().find(|(n, _)| matches!(n, DossFileName::Named(_)) && *n == name) {
+            Some(file) => file.1 = entries,
+            None => self.files.push((name, entries)),
+        }
+    }
+}
+
+/// Collects the events of the last file with the given name
+#[derive(Debug)]
+struct FileExtractor<'a> {
+    name: &'a DossFileName,
+    events: Option<Vec<DossEvent>>,
+}
+
+impl DossFileCallback for FileExtractor<'_> {
+    fn on_file_start(&mut self, name: &DossFileName) -> DossReaderCallbackReturn {
+        match name == self.name {
+            true => {
+                self.events = Some(Vec::new());
+                DossReaderCallbackReturn::Continue
+            }
+            false => DossReaderCallbackReturn::Skip,
+        }
+    }
+
+    fn on_file_event(&mut self, event: DossEvent) -> DossReaderCallbackReturn {
+        if let Some(events) = &mut self.events {
+            events.push(event);
+        }
+        DossReaderCallbackReturn::Continue
+    }
+}
+
+/// Reads a complete stream into its files. A file replaces an earlier file with the same name
+/// (except assigned names) but keeps the earlier position
+pub fn read_files(data: &mut dyn Readable<u8>) -> Result<Vec<(DossFileName, Vec<StreamableJSONEntry>)>, DossReaderError> {
+    let mut files = DossFiles::new(TreeCollector::default());
+    read_all(data, &mut files)?;
+    let mut collector = files.into_callback();
+    match collector.error.take() {
+        Some(e) => Err(e.into()),
+        None => Ok(collector.files),
+    }
+}
+
+/// Writes the last file with the given name as a stream of its own, settings and imports it depends on
+/// are already applied. Returns None if there is no such file
+pub fn extract_file<W: std::io::Write>(data: &mut dyn Readable<u8>, name: &DossFileName, out: W, options: DossSerializerOptions) -> Result<Option<W>, DossReaderError> {
+    let mut files = DossFiles::new(FileExtractor { name, events: None });
+    read_all(data, &mut files)?;
+    let Some(events) = files.into_callback().events else {
+        return Ok(None);
+    };
+    let mut serializer = DossSerializer::with_options(out, options);
+    if let DossFileName::Named(item) = name {
+        serializer.write_doss_event(&DossEvent::FileStart(item.clone()))?;
+    }
+    for event in events.iter().filter(|e| !matches!(e, DossEvent::Config { .. } | DossEvent::ImportDict(_))) {
+        serializer.write_doss_event(event)?;
+    }
+    Ok(Some(serializer.finish()?))
+}
+
+fn read_all<C: DossFileCallback>(data: &mut dyn Readable<u8>, files: &mut DossFiles<C>) -> Result<(), DossReaderError> {
+    let mut reader = DossReader::new(files);
+    //without more data the stream ends here, finish reports a cut off item
+    reader.pushdata(data)?;
+    reader.finish()?;
+    files.finish();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use dataflowgrid_commons::readers::reader::IteratorReadable;
+    use streamablejson::deserializer::deserialize_orderedbag_from_string;
+
+    use super::*;
+    use crate::settings::DossSettings;
+    use crate::tree::decode_entries;
+
+    fn s(v: &str) -> DossEvent {
+        DossEvent::String(v.to_string())
+    }
+
+    fn readable(serialized: Vec<u8>) -> IteratorReadable<u8> {
+        IteratorReadable::with_chunk_size(Box::new(serialized.into_iter()), 5)
+    }
+
+    fn named(name: &str) -> DossFileName {
+        DossFileName::Named(vec![s(name)])
+    }
+
+    fn hello_world() -> StreamableJSONEntry {
+        deserialize_orderedbag_from_string(String::from(r#"{"hello": "world"}"#)).unwrap()
+    }
+
+    fn serialize(events: &[DossEvent]) -> Vec<u8> {
+        let mut serializer = DossSerializer::new(Vec::new());
+        for e in events {
+            serializer.write_doss_event(e).unwrap();
+        }
+        serializer.finish().unwrap()
+    }
+
+    //"Multiple files" in docs/examples.md
+    const EXAMPLE: &[u8] = &[10, 21, 7, 5, b'h', b'e', b'l', b'l', b'o', 21, 7, 5, b'w', b'o', b'r', b'l', b'd', 11,
+        50, 7, 8, b'e', b'x', b'a', b'm', b'p', b'l', b'e', b'2', 10, 9, 0, 9, 1, 11];
+
+    #[test]
+    fn test_example() {
+        let files = read_files(&mut readable(EXAMPLE.to_vec())).unwrap();
+        assert_eq!(files, vec![(DossFileName::Assigned(0), vec![hello_world()]), (named("example2"), vec![hello_world()])]);
+    }
+
+    #[test]
+    fn test_names() {
+        let file = |name: DossEvent, value: &str| vec![DossEvent::FileStart(vec![name]), s(value)];
+        let events = [
+            vec![s("first")],
+            file(DossEvent::Null, "second"),
+            file(s("a"), "third"),
+            file(DossEvent::UInt(7), "fourth"),
+            file(DossEvent::Null, "fifth"),
+            file(s("a"), "sixth"),
+        ].concat();
+        let files = read_files(&mut readable(serialize(&events))).unwrap();
+        let string = |v: &str| vec![StreamableJSONEntry::String(v.to_string())];
+        assert_eq!(files, vec![
+            (DossFileName::Assigned(0), string("first")),
+            (DossFileName::Assigned(1), string("second")),
+            (named("a"), string("sixth")),
+            (DossFileName::Named(vec![DossEvent::UInt(7)]), string("fourth")),
+            (DossFileName::Assigned(2), string("fifth")),
+        ]);
+    }
+
+    struct Recorder {
+        log: Vec<String>,
+        skip: &'static str,
+    }
+
+    impl DossFileCallback for Recorder {
+        fn on_file_start(&mut self, name: &DossFileName) -> DossReaderCallbackReturn {
+            self.log.push(format!("start {name:?}"));
+            match *name == named(self.skip) {
+                true => DossReaderCallbackReturn::Skip,
+                false => DossReaderCallbackReturn::Continue,
+            }
+        }
+
+        fn on_file_event(&mut self, event: DossEvent) -> DossReaderCallbackReturn {
+            self.log.push(format!("{event:?}"));
+            DossReaderCallbackReturn::Continue
+        }
+
+        fn on_file_end(&mut self) {
+            self.log.push(String::from("end"));
+        }
+    }
+
+    #[test]
+    fn test_streaming_and_skip() {
+        let events = [DossEvent::FileStart(vec![s("skipped")]), s("repeated"), s("repeated"), DossEvent::FileStart(vec![s("b")]), s("repeated")];
+        let mut files = DossFiles::new(Recorder { log: Vec::new(), skip: "skipped" });
+        let mut reader = DossReader::new(&mut files);
+        reader.pushdata(&mut readable(serialize(&events))).unwrap();
+        reader.finish().unwrap();
+        files.finish();
+        assert_eq!(files.into_callback().log, vec![
+            r#"start Named([String("skipped")])"#,
+            "end",
+            r#"start Named([String("b")])"#,
+            r#"String("repeated")"#,
+            "end",
+        ]);
+
+        //the dict size hint written before the first file start belongs to the first file
+        let mut files = DossFiles::new(Recorder { log: Vec::new(), skip: "" });
+        let mut reader = DossReader::new(&mut files);
+        reader.pushdata(&mut readable(serialize(&events[..3]))).unwrap();
+        reader.finish().unwrap();
+        files.finish();
+        let log = files.into_callback().log;
+        assert_eq!(log[0], r#"start Named([String("skipped")])"#);
+        assert!(log[1].starts_with("Hint"));
+    }
+
+    #[test]
+    fn test_extract() {
+        let extracted = extract_file(&mut readable(EXAMPLE.to_vec()), &named("example2"), Vec::new(), DossSerializerOptions::default()).unwrap().unwrap();
+        //the references to the first file were resolved
+        assert_eq!(decode_entries(&extracted).unwrap(), vec![hello_world()]);
+        assert_eq!(read_files(&mut readable(extracted)).unwrap(), vec![(named("example2"), vec![hello_world()])]);
+
+        let first = extract_file(&mut readable(EXAMPLE.to_vec()), &DossFileName::Assigned(0), Vec::new(), DossSerializerOptions::default()).unwrap().unwrap();
+        assert_eq!(decode_entries(&first).unwrap(), vec![hello_world()]);
+        assert!(extract_file(&mut readable(EXAMPLE.to_vec()), &named("missing"), Vec::new(), DossSerializerOptions::default()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_append() {
+        //an existing stream using utf16 and the dictionary
+        let config = DossEvent::Config { key: vec![DossEvent::UInt(1)], value: vec![s("utf16")] };
+        let mut existing = serialize(&[config]);
+        existing.extend([10, 21, 7, 10]);
+        existing.extend("hello".encode_utf16().flat_map(u16::to_le_bytes));
+        existing.extend([9, 0, 11]);
+
+        struct Ignore;
+        impl DossReaderCallback for Ignore {
+            fn on_doss_event(&mut self, _event: DossEvent) -> DossReaderCallbackReturn {
+                DossReaderCallbackReturn::Continue
+            }
+        }
+        let mut ignore = Ignore;
+        let mut reader = DossReader::new(&mut ignore);
+        reader.pushdata(&mut readable(existing.clone())).unwrap();
+        reader.finish().unwrap();
+        let settings: DossSettings = reader.settings().clone();
+
+        let mut serializer = DossSerializer::appending(Vec::new(), &settings, DossSerializerOptions::default()).unwrap();
+        for e in [DossEvent::FileStart(vec![s("grüße")]), DossEvent::ArrayStart, s("world"), s("world"), s("world"), DossEvent::ArrayEnd] {
+            serializer.write_doss_event(&e).unwrap();
+        }
+        existing.extend(serializer.finish().unwrap());
+
+        let files = read_files(&mut readable(existing.clone())).unwrap();
+        let appended = deserialize_orderedbag_from_string(String::from(r#"["world", "world", "world"]"#)).unwrap();
+        let first = deserialize_orderedbag_from_string(String::from(r#"{"hello": "hello"}"#)).unwrap();
+        assert_eq!(files, vec![(DossFileName::Assigned(0), vec![first]), (named("grüße"), vec![appended])]);
+    }
+}
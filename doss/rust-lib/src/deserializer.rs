@@ -1,128 +1,368 @@
 /* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
 
-use std::{collections::BTreeMap, io::Result};
-use tokio::io::AsyncReadExt;
-use dataflowgrid_commons::orderedbag::OrderedBag;
+use dataflowgrid_commons::typedstream::DateTime;
 
-#[derive(Debug)]
+use crate::error::DossError;
+use crate::limits::DossLimits;
+use crate::settings::StringEncoding;
+use crate::types::{Decimal, DossFloat};
+use crate::varint;
+
+/// One opcode together with its decoded operands, see docs/opcodes.md.
+/// Opcodes without operand that are followed by items (like StoreInDict or SetConfig) don't contain them,
+/// the items are decoded as events of their own.
+#[derive(Debug, Clone, PartialEq)]
+#[repr(u8)]
 pub enum DossLowLevelStreamEvent {
     NoOp = 0,
     True = 1,
     False = 2,
-    Varint = 3,
-    Decimal = 4,
-    Float = 5,
-    DateTime = 6,
-    String = 7,
-    Binary = 8,
-    Reference = 9,
+    Varint(i64) = 3,
+    Decimal(Decimal) = 4,
+    Float(DossFloat) = 5,
+    DateTime(DateTime) = 6,
+    String(String) = 7,
+    Binary(Vec<u8>) = 8,
+    Reference(u64) = 9,
     BlockStart = 10,
     BlockEnd = 11,
     ArrayStart = 12,
     ArrayEnd = 13,
-    NULL = 14,
-    UnsignedVarint = 15,
+    Null = 14,
+    UnsignedVarint(u64) = 15,
     TypeStart = 16,
     TypeEnd = 17,
 
     SetConfig = 20,
     StoreInDict = 21,
     StoreButDontUse = 22,
-    SetDictPointer = 23,
-    ClearDictEntries = 24,
+    SetDictPointer(u64) = 23,
+    ClearDictEntries { from: u64, count: u64 } = 24,
     StackStart = 25,
     StackEnd = 26,
     SetHint = 27,
 
-    SkipBytes16le = 30,
-    SkipBytes32le = 31,
+    SkipBytes16le(u16) = 30,
+    SkipBytes32le(u32) = 31,
 
     ImportDict = 40,
 
     FileStart = 50,
 }
 
-pub trait DossLowLevelStream {
-    async fn doss_event(&self, event: &DossLowLevelStreamEvent);
-}
-
-struct DossLowLevelStreamConsoleImpl {
-}
-impl DossLowLevelStream for DossLowLevelStreamConsoleImpl {
-    async fn doss_event(&self, event: &DossLowLevelStreamEvent) {
-        println!("Event: {:?}", event);
+/// Splits a varint length prefixed byte string from the start of `input`
+fn length_prefixed(input: &[u8], max_len: usize) -> Result<Option<(&[u8], usize)>, DossError> {
+    let Some((len, used)) = varint::decode_unsigned(input)? else {
+        return Ok(None);
+    };
+    check_len(len, max_len)?;
+    let end = usize::try_from(len).ok()
+        .and_then(|len| len.checked_add(used))
+        .ok_or(DossError::VarintOverflow)?;
+    if input.len() < end {
+        return Ok(None);
     }
+    Ok(Some((&input[used..end], end)))
 }
 
-struct Deserializer<'a> {
-    dict: BTreeMap<usize, &'a str>
+//fails before waiting for the data of a length that is too long anyway
+fn check_len(len: u64, max_len: usize) -> Result<(), DossError> {
+    match usize::try_from(len) {
+        Ok(l) if l <= max_len => Ok(()),
+        _ => Err(DossError::TooLong(len)),
+    }
 }
 
-impl<'a> Deserializer<'a> {
-    fn new() -> Deserializer<'a> {
-        Deserializer {
-            dict: BTreeMap::new()
+impl DossLowLevelStreamEvent {
+    pub fn opcode(&self) -> u8 {
+        match self {
+            DossLowLevelStreamEvent::NoOp => 0,
+            DossLowLevelStreamEvent::True => 1,
+            DossLowLevelStreamEvent::False => 2,
+            DossLowLevelStreamEvent::Varint(_) => 3,
+            DossLowLevelStreamEvent::Decimal(_) => 4,
+            DossLowLevelStreamEvent::Float(_) => 5,
+            DossLowLevelStreamEvent::DateTime(_) => 6,
+            DossLowLevelStreamEvent::String(_) => 7,
+            DossLowLevelStreamEvent::Binary(_) => 8,
+            DossLowLevelStreamEvent::Reference(_) => 9,
+            DossLowLevelStreamEvent::BlockStart => 10,
+            DossLowLevelStreamEvent::BlockEnd => 11,
+            DossLowLevelStreamEvent::ArrayStart => 12,
+            DossLowLevelStreamEvent::ArrayEnd => 13,
+            DossLowLevelStreamEvent::Null => 14,
+            DossLowLevelStreamEvent::UnsignedVarint(_) => 15,
+            DossLowLevelStreamEvent::TypeStart => 16,
+            DossLowLevelStreamEvent::TypeEnd => 17,
+            DossLowLevelStreamEvent::SetConfig => 20,
+            DossLowLevelStreamEvent::StoreInDict => 21,
+            DossLowLevelStreamEvent::StoreButDontUse => 22,
+            DossLowLevelStreamEvent::SetDictPointer(_) => 23,
+            DossLowLevelStreamEvent::ClearDictEntries { .. } => 24,
+            DossLowLevelStreamEvent::StackStart => 25,
+            DossLowLevelStreamEvent::StackEnd => 26,
+            DossLowLevelStreamEvent::SetHint => 27,
+            DossLowLevelStreamEvent::SkipBytes16le(_) => 30,
+            DossLowLevelStreamEvent::SkipBytes32le(_) => 31,
+            DossLowLevelStreamEvent::ImportDict => 40,
+            DossLowLevelStreamEvent::FileStart => 50,
         }
     }
 
-    async fn deserialize<T: AsyncReadExt+Unpin, P:DossLowLevelStream>(&self, mut reader: T, processor: P) -> Result<()>{
-        loop {
-            let b = reader.read_u8().await;
-            match b {
-                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
-                Err(e) => return Result::Err(e),
-                Ok(opcode) => {
-                    let event = match opcode {
-                        0 => DossLowLevelStreamEvent::NoOp,
-                        1 => DossLowLevelStreamEvent::True,
-                        2 => DossLowLevelStreamEvent::False,
-                        3 => DossLowLevelStreamEvent::Varint,
-
-                        4 => DossLowLevelStreamEvent::Decimal,
-                        5 => DossLowLevelStreamEvent::Float,
-                        6 => DossLowLevelStreamEvent::DateTime,
-                        7 => DossLowLevelStreamEvent::String,
-                        8 => DossLowLevelStreamEvent::Binary,
-                        9 => DossLowLevelStreamEvent::Reference,
-                        10 => DossLowLevelStreamEvent::BlockStart,
-                        11 => DossLowLevelStreamEvent::BlockEnd,
-                        12 => DossLowLevelStreamEvent::ArrayStart,
-                        13 => DossLowLevelStreamEvent::ArrayEnd,
-                        14 => DossLowLevelStreamEvent::NULL,
-                        15 => DossLowLevelStreamEvent::UnsignedVarint,
-                        20 => DossLowLevelStreamEvent::SetConfig,
-                        21 => DossLowLevelStreamEvent::StoreInDict,
-                        22 => DossLowLevelStreamEvent::StoreButDontUse,
-                        23 => DossLowLevelStreamEvent::SetDictPointer,
-                        24 => DossLowLevelStreamEvent::ClearDictEntries,
-                        25 => DossLowLevelStreamEvent::StackStart,
-                        26 => DossLowLevelStreamEvent::StackEnd,
-                        27 => DossLowLevelStreamEvent::SetHint,
-                        30 => DossLowLevelStreamEvent::SkipBytes16le,
-                        31 => DossLowLevelStreamEvent::SkipBytes32le,
-                        40 => DossLowLevelStreamEvent::ImportDict,
-                        50 => DossLowLevelStreamEvent::FileStart,
-                        _ => return Result::Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid event"))
-                    };
-                    processor.doss_event(&event).await;
+    /// Decodes one event from the start of `input`, strings are expected as utf8.
+    /// Returns the event and the number of bytes used or None if `input` ends before the event is complete.
+    pub fn decode(input: &[u8]) -> Result<Option<(DossLowLevelStreamEvent, usize)>, DossError> {
+        Self::decode_with(input, StringEncoding::Utf8)
+    }
+
+    /// Like [`DossLowLevelStreamEvent::decode`] with strings in the encoding selected by the stream settings
+    pub fn decode_with(input: &[u8], encoding: StringEncoding) -> Result<Option<(DossLowLevelStreamEvent, usize)>, DossError> {
+        Self::decode_limited(input, encoding, &DossLimits::unlimited())
+    }
+
+    /// Like [`DossLowLevelStreamEvent::decode_with`], strings, binaries and decimals longer than `limits` allow are an error
+    pub fn decode_limited(input: &[u8], encoding: StringEncoding, limits: &DossLimits) -> Result<Option<(DossLowLevelStreamEvent, usize)>, DossError> {
+        let Some(&opcode) = input.first() else {
+            return Ok(None);
+        };
+        let operands = &input[1..];
+        let (event, used) = match opcode {
+            0 => (DossLowLevelStreamEvent::NoOp, 0),
+            1 => (DossLowLevelStreamEvent::True, 0),
+            2 => (DossLowLevelStreamEvent::False, 0),
+            3 => {
+                let Some((v, used)) = varint::decode_signed(operands)? else { return Ok(None) };
+                (DossLowLevelStreamEvent::Varint(v), used)
+            }
+            4 => {
+                let Some((extension, used_extension)) = varint::decode_signed(operands)? else { return Ok(None) };
+                let Some((header, used_header)) = varint::decode_unsigned(&operands[used_extension..])? else { return Ok(None) };
+                let extension = i16::try_from(extension).map_err(|_| DossError::InvalidOperand("decimal"))?;
+                check_len(header >> 1, limits.max_string_len)?;
+                let start = used_extension + used_header;
+                let end = usize::try_from(header >> 1).ok()
+                    .and_then(|len| len.checked_add(start))
+                    .ok_or(DossError::VarintOverflow)?;
+                let Some(magnitude) = operands.get(start..end) else { return Ok(None) };
+                (DossLowLevelStreamEvent::Decimal(Decimal::from_magnitude_le_bytes(header & 1 == 1, extension, magnitude)), end)
+            }
+            5 => match operands.split_first() {
+                None => return Ok(None),
+                Some((4, rest)) => {
+                    let Some(b) = rest.first_chunk::<4>() else { return Ok(None) };
+                    (DossLowLevelStreamEvent::Float(DossFloat::F32(f32::from_le_bytes(*b))), 5)
                 }
+                Some((8, rest)) => {
+                    let Some(b) = rest.first_chunk::<8>() else { return Ok(None) };
+                    (DossLowLevelStreamEvent::Float(DossFloat::F64(f64::from_le_bytes(*b))), 9)
+                }
+                Some(_) => return Err(DossError::InvalidOperand("float width")),
+            },
+            6 => {
+                let Some((&precision, rest)) = operands.split_first() else { return Ok(None) };
+                let Some((value, used_value)) = varint::decode_signed(rest)? else { return Ok(None) };
+                let Some((offset, used_offset)) = varint::decode_signed(&rest[used_value..])? else { return Ok(None) };
+                let offset = i16::try_from(offset).map_err(|_| DossError::InvalidOperand("datetime"))?;
+                let datetime = DateTime::new(value, precision, offset).map_err(|_| DossError::InvalidOperand("datetime"))?;
+                (DossLowLevelStreamEvent::DateTime(datetime), 1 + used_value + used_offset)
+            }
+            7 => {
+                let Some((bytes, used)) = length_prefixed(operands, limits.max_string_len)? else { return Ok(None) };
+                (DossLowLevelStreamEvent::String(encoding.decode(bytes)?), used)
+            }
+            8 => {
+                let Some((bytes, used)) = length_prefixed(operands, limits.max_string_len)? else { return Ok(None) };
+                (DossLowLevelStreamEvent::Binary(bytes.to_vec()), used)
+            }
+            9 => {
+                let Some((v, used)) = varint::decode_unsigned(operands)? else { return Ok(None) };
+                (DossLowLevelStreamEvent::Reference(v), used)
+            }
+            10 => (DossLowLevelStreamEvent::BlockStart, 0),
+            11 => (DossLowLevelStreamEvent::BlockEnd, 0),
+            12 => (DossLowLevelStreamEvent::ArrayStart, 0),
+            13 => (DossLowLevelStreamEvent::ArrayEnd, 0),
+            14 => (DossLowLevelStreamEvent::Null, 0),
+            15 => {
+                let Some((v, used)) = varint::decode_unsigned(operands)? else { return Ok(None) };
+                (DossLowLevelStreamEvent::UnsignedVarint(v), used)
+            }
+            16 => (DossLowLevelStreamEvent::TypeStart, 0),
+            17 => (DossLowLevelStreamEvent::TypeEnd, 0),
+            20 => (DossLowLevelStreamEvent::SetConfig, 0),
+            21 => (DossLowLevelStreamEvent::StoreInDict, 0),
+            22 => (DossLowLevelStreamEvent::StoreButDontUse, 0),
+            23 => {
+                let Some((v, used)) = varint::decode_unsigned(operands)? else { return Ok(None) };
+                (DossLowLevelStreamEvent::SetDictPointer(v), used)
             }
+            24 => {
+                let Some((from, used_from)) = varint::decode_unsigned(operands)? else { return Ok(None) };
+                let Some((count, used_count)) = varint::decode_unsigned(&operands[used_from..])? else { return Ok(None) };
+                (DossLowLevelStreamEvent::ClearDictEntries { from, count }, used_from + used_count)
+            }
+            25 => (DossLowLevelStreamEvent::StackStart, 0),
+            26 => (DossLowLevelStreamEvent::StackEnd, 0),
+            27 => (DossLowLevelStreamEvent::SetHint, 0),
+            30 => {
+                let Some(b) = operands.first_chunk::<2>() else { return Ok(None) };
+                (DossLowLevelStreamEvent::SkipBytes16le(u16::from_le_bytes(*b)), 2)
+            }
+            31 => {
+                let Some(b) = operands.first_chunk::<4>() else { return Ok(None) };
+                (DossLowLevelStreamEvent::SkipBytes32le(u32::from_le_bytes(*b)), 4)
+            }
+            40 => (DossLowLevelStreamEvent::ImportDict, 0),
+            50 => (DossLowLevelStreamEvent::FileStart, 0),
+            _ => return Err(DossError::InvalidOpcode(opcode)),
+        };
+        Ok(Some((event, used + 1)))
+    }
+
+    /// Appends the opcode and its operands to `out`
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        out.push(self.opcode());
+        match self {
+            DossLowLevelStreamEvent::Varint(v) => varint::encode_signed(*v, out),
+            DossLowLevelStreamEvent::String(s) => {
+                varint::encode_unsigned(s.len() as u64, out);
+                out.extend_from_slice(s.as_bytes());
+            }
+            DossLowLevelStreamEvent::Binary(b) => {
+                varint::encode_unsigned(b.len() as u64, out);
+                out.extend_from_slice(b);
+            }
+            DossLowLevelStreamEvent::Decimal(d) => {
+                let magnitude = d.magnitude_le_bytes();
+                varint::encode_signed(d.extension() as i64, out);
+                varint::encode_unsigned((magnitude.len() as u64) << 1 | d.is_negative() as u64, out);
+                out.extend_from_slice(&magnitude);
+            }
+            DossLowLevelStreamEvent::Float(DossFloat::F32(v)) => {
+                out.push(4);
+                out.extend_from_slice(&v.to_le_bytes());
+            }
+            DossLowLevelStreamEvent::Float(DossFloat::F64(v)) => {
+                out.push(8);
+                out.extend_from_slice(&v.to_le_bytes());
+            }
+            DossLowLevelStreamEvent::DateTime(d) => {
+                out.push(d.precision());
+                varint::encode_signed(d.value(), out);
+                varint::encode_signed(d.offset_minutes() as i64, out);
+            }
+            DossLowLevelStreamEvent::Reference(v)
+            | DossLowLevelStreamEvent::UnsignedVarint(v)
+            | DossLowLevelStreamEvent::SetDictPointer(v) => varint::encode_unsigned(*v, out),
+            DossLowLevelStreamEvent::ClearDictEntries { from, count } => {
+                varint::encode_unsigned(*from, out);
+                varint::encode_unsigned(*count, out);
+            }
+            DossLowLevelStreamEvent::SkipBytes16le(v) => out.extend_from_slice(&v.to_le_bytes()),
+            DossLowLevelStreamEvent::SkipBytes32le(v) => out.extend_from_slice(&v.to_le_bytes()),
+            _ => {}
         }
-        Ok(())
     }
 
+    /// Like [`DossLowLevelStreamEvent::encode`] with strings in the encoding selected by the stream settings
+    pub fn encode_with(&self, encoding: StringEncoding, out: &mut Vec<u8>) -> Result<(), DossError> {
+        match self {
+            DossLowLevelStreamEvent::String(s) if encoding != StringEncoding::Utf8 => {
+                let bytes = encoding.encode(s)?;
+                out.push(self.opcode());
+                varint::encode_unsigned(bytes.len() as u64, out);
+                out.extend_from_slice(&bytes);
+            }
+            _ => self.encode(out),
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[tokio::test]
-    async fn deserialize_empty() {
-        let deserializer = Deserializer::new();
-        let serialized = [1_u8,2,3,4].as_slice();
-        let logger = DossLowLevelStreamConsoleImpl {};
-        let deserialized = deserializer.deserialize(serialized, logger).await;
-        deserialized.unwrap()
+    fn all_events() -> Vec<DossLowLevelStreamEvent> {
+        vec![
+            DossLowLevelStreamEvent::NoOp,
+            DossLowLevelStreamEvent::True,
+            DossLowLevelStreamEvent::False,
+            DossLowLevelStreamEvent::Varint(-300),
+            DossLowLevelStreamEvent::Decimal("-1234567890123456789012345.67890".parse().unwrap()),
+            DossLowLevelStreamEvent::Decimal("0.000".parse().unwrap()),
+            DossLowLevelStreamEvent::Float(DossFloat::F32(1.5)),
+            DossLowLevelStreamEvent::Float(DossFloat::F64(-0.1)),
+            DossLowLevelStreamEvent::DateTime(DateTime::new(-1_700_000_000_123, 3, -330).unwrap()),
+            DossLowLevelStreamEvent::String(String::from("grüße")),
+            DossLowLevelStreamEvent::Binary(vec![0, 1, 255]),
+            DossLowLevelStreamEvent::Reference(1000),
+            DossLowLevelStreamEvent::BlockStart,
+            DossLowLevelStreamEvent::BlockEnd,
+            DossLowLevelStreamEvent::ArrayStart,
+            DossLowLevelStreamEvent::ArrayEnd,
+            DossLowLevelStreamEvent::Null,
+            DossLowLevelStreamEvent::UnsignedVarint(u64::MAX),
+            DossLowLevelStreamEvent::TypeStart,
+            DossLowLevelStreamEvent::TypeEnd,
+            DossLowLevelStreamEvent::SetConfig,
+            DossLowLevelStreamEvent::StoreInDict,
+            DossLowLevelStreamEvent::StoreButDontUse,
+            DossLowLevelStreamEvent::SetDictPointer(7),
+            DossLowLevelStreamEvent::ClearDictEntries { from: 2, count: 200 },
+            DossLowLevelStreamEvent::StackStart,
+            DossLowLevelStreamEvent::StackEnd,
+            DossLowLevelStreamEvent::SetHint,
+            DossLowLevelStreamEvent::SkipBytes16le(0x1234),
+            DossLowLevelStreamEvent::SkipBytes32le(0x12345678),
+            DossLowLevelStreamEvent::ImportDict,
+            DossLowLevelStreamEvent::FileStart,
+        ]
+    }
+
+    #[test]
+    fn test_hello_world() {
+        //first example of docs/examples.md
+        let serialized = [10_u8, 7, 5, b'h', b'e', b'l', b'l', b'o', 7, 5, b'w', b'o', b'r', b'l', b'd', 11];
+        let mut pos = 0;
+        let mut events = Vec::new();
+        while let Some((event, used)) = DossLowLevelStreamEvent::decode(&serialized[pos..]).unwrap() {
+            events.push(event);
+            pos += used;
+        }
+        assert_eq!(pos, serialized.len());
+        assert_eq!(events, vec![
+            DossLowLevelStreamEvent::BlockStart,
+            DossLowLevelStreamEvent::String(String::from("hello")),
+            DossLowLevelStreamEvent::String(String::from("world")),
+            DossLowLevelStreamEvent::BlockEnd,
+        ]);
+    }
+
+    #[test]
+    fn test_roundtrip_and_incomplete() {
+        for event in all_events() {
+            let mut out = Vec::new();
+            event.encode(&mut out);
+            assert_eq!(out[0], event.opcode());
+            assert_eq!(DossLowLevelStreamEvent::decode(&out).unwrap(), Some((event.clone(), out.len())));
+            //every prefix is reported as incomplete instead of failing or guessing
+            for i in 0..out.len() {
+                assert_eq!(DossLowLevelStreamEvent::decode(&out[..i]).unwrap(), None, "{event:?} cut at {i}");
+            }
+        }
+        //example of docs/opcodes.md
+        let mut out = Vec::new();
+        DossLowLevelStreamEvent::Decimal("-1.50".parse().unwrap()).encode(&mut out);
+        assert_eq!(out, [4, 0x7e, 0x03, 150]);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(matches!(DossLowLevelStreamEvent::decode(&[18]), Err(DossError::InvalidOpcode(18))));
+        assert!(matches!(DossLowLevelStreamEvent::decode(&[4, 0x80, 0x80, 0x02, 0]), Err(DossError::InvalidOperand(_))));
+        assert!(matches!(DossLowLevelStreamEvent::decode(&[5, 2, 0, 0]), Err(DossError::InvalidOperand(_))));
+        assert!(matches!(DossLowLevelStreamEvent::decode(&[6, 10, 0, 0]), Err(DossError::InvalidOperand(_))));
+        assert!(matches!(DossLowLevelStreamEvent::decode(&[7, 2, 0xc3, 0x28]), Err(DossError::InvalidUtf8)));
     }
 }
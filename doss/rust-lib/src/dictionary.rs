@@ -0,0 +1,276 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::sync::Arc;
+
+use crate::error::DossError;
+use crate::resolver::{DossEvent, item_memory};
+
+/// default maximum number of dict entries
+pub const DEFAULT_MAX_ENTRIES: usize = 1 << 20;
+/// default maximum number of events stored in all dict entries together
+pub const DEFAULT_MAX_EVENTS: usize = 1 << 24;
+
+//a stored item, shared with the undo logs of the stacks
+type Entry = Option<Arc<Vec<DossEvent>>>;
+
+/// The reference dictionary of a DOSS stream. Entries are complete items (scalars or whole subtrees)
+/// stored as the resolved events they expand to.
+#[derive(Debug, Clone)]
+pub struct DossDictionary {
+    entries: Vec<Entry>,
+    pointer: usize,
+    max_entries: usize,
+    max_events: usize,
+    events: usize,
+    memory: usize, //approximate bytes of all entries
+    logged: usize, //approximate bytes of the replaced entries kept by the stacks
+    scopes: Vec<Scope>,
+}
+
+/// State at the start of a stack (25), changes are undone when leaving it
+#[derive(Debug, Clone)]
+struct Scope {
+    pointer: usize,
+    len: usize,
+    undo: Vec<(usize, Entry)>, //index and the entry before the change
+    memory: usize, //of the entries in undo
+}
+
+impl Default for DossDictionary {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DossDictionary {
+    pub fn new() -> Self {
+        Self::with_limits(DEFAULT_MAX_ENTRIES, DEFAULT_MAX_EVENTS)
+    }
+
+    /// Limits protect against hostile inputs: `max_entries` bounds the highest index,
+    /// `max_events` the size of all entries together (references in stored items are expanded)
+    pub fn with_limits(max_entries: usize, max_events: usize) -> Self {
+        DossDictionary {
+            entries: Vec::new(),
+            pointer: 0,
+            max_entries,
+            max_events,
+            events: 0,
+            memory: 0,
+            logged: 0,
+            scopes: Vec::new(),
+        }
+    }
+
+    pub fn max_entries(&self) -> usize {
+        self.max_entries
+    }
+
+    pub fn max_events(&self) -> usize {
+        self.max_events
+    }
+
+    /// Approximate bytes held by the entries and the stacks, see [`DossLimits::max_memory`](crate::DossLimits::max_memory)
+    pub fn memory(&self) -> usize {
+        self.memory + self.logged
+    }
+
+    /// The index the next stored item goes to
+    pub fn pointer(&self) -> usize {
+        self.pointer
+    }
+
+    /// Returns the index for the next stored item and moves the pointer behind it
+    pub fn allocate(&mut self) -> Result<usize, DossError> {
+        if self.pointer >= self.max_entries {
+            return Err(DossError::DictFull);
+        }
+        self.pointer += 1;
+        Ok(self.pointer - 1)
+    }
+
+    pub fn set_pointer(&mut self, pointer: u64) -> Result<(), DossError> {
+        match usize::try_from(pointer) {
+            Ok(pointer) if pointer < self.max_entries => {
+                self.pointer = pointer;
+                Ok(())
+            }
+            _ => Err(DossError::DictFull)
+        }
+    }
+
+    pub fn store(&mut self, index: usize, item: Vec<DossEvent>) -> Result<(), DossError> {
+        if index >= self.max_entries {
+            return Err(DossError::DictFull);
+        }
+        let old = self.entries.get(index).and_then(|e| e.as_ref());
+        let events = self.events - old.map_or(0, |e| e.len()) + item.len();
+        let memory = self.memory - old.map_or(0, |e| item_memory(e)) + item_memory(&item);
+        if events > self.max_events {
+            return Err(DossError::DictFull);
+        }
+        if self.entries.len() <= index {
+            self.entries.resize(index + 1, None);
+        }
+        let old = self.entries[index].replace(Arc::new(item));
+        self.log(index, old);
+        self.events = events;
+        self.memory = memory;
+        Ok(())
+    }
+
+    pub fn get(&self, index: u64) -> Result<Arc<Vec<DossEvent>>, DossError> {
+        usize::try_from(index).ok()
+            .and_then(|i| self.entries.get(i))
+            .and_then(|e| e.clone())
+            .ok_or(DossError::InvalidReference(index))
+    }
+
+    /// Removes `count` entries starting at `from`, the pointer is not changed
+    pub fn clear(&mut self, from: u64, count: u64) {
+        let from = usize::try_from(from).unwrap_or(usize::MAX).min(self.entries.len());
+        let to = usize::try_from(count).unwrap_or(usize::MAX).saturating_add(from).min(self.entries.len());
+        for index in from..to {
+            if let Some(e) = self.entries[index].take() {
+                self.events -= e.len();
+                self.memory -= item_memory(&e);
+                self.log(index, Some(e));
+            }
+        }
+    }
+
+    /// Starts a stack (25): entries stored, overwritten or cleared until the matching
+    /// [`DossDictionary::leave_stack`] are restored then, so is the pointer
+    pub fn start_stack(&mut self) {
+        self.scopes.push(Scope { pointer: self.pointer, len: self.entries.len(), undo: Vec::new(), memory: 0 });
+    }
+
+    /// Leaves the innermost stack (26)
+    pub fn leave_stack(&mut self) -> Result<(), DossError> {
+        let scope = self.scopes.pop().ok_or(DossError::UnbalancedStructure)?;
+        self.logged -= scope.memory;
+        for (index, entry) in scope.undo.into_iter().rev() {
+            let new = std::mem::replace(&mut self.entries[index], entry);
+            let old = self.entries[index].as_ref();
+            self.events = self.events - new.as_ref().map_or(0, |e| e.len()) + old.map_or(0, |e| e.len());
+            self.memory = self.memory - new.as_ref().map_or(0, |e| item_memory(e)) + old.map_or(0, |e| item_memory(e));
+        }
+        self.entries.truncate(scope.len);
+        self.pointer = scope.pointer;
+        Ok(())
+    }
+
+    /// Number of stacks not left yet
+    pub fn stack_depth(&self) -> usize {
+        self.scopes.len()
+    }
+
+    /// The pointer and the entries at the start of every open stack from the outermost on, followed by the current ones
+    pub(crate) fn scope_states(&self) -> Vec<(usize, Vec<Entry>)> {
+        let mut states = vec![(self.pointer, self.entries.clone())];
+        for scope in self.scopes.iter().rev() {
+            let mut entries = states.last().unwrap().1.clone(); //never empty
+            for (index, entry) in scope.undo.iter().rev() {
+                entries[*index] = entry.clone();
+            }
+            entries.truncate(scope.len);
+            states.push((scope.pointer, entries));
+        }
+        states.reverse();
+        states
+    }
+
+    fn log(&mut self, index: usize, old: Entry) {
+        if let Some(scope) = self.scopes.last_mut() {
+            let memory = old.as_ref().map_or(0, |e| item_memory(e));
+            scope.memory += memory;
+            self.logged += memory;
+            scope.undo.push((index, old));
+        }
+    }
+
+    /// Number of entries currently set
+    pub fn len(&self) -> usize {
+        self.entries.iter().filter(|e| e.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(s: &str) -> Vec<DossEvent> {
+        vec![DossEvent::String(s.to_string())]
+    }
+
+    #[test]
+    fn test_store_and_clear() {
+        let mut dict = DossDictionary::new();
+        let i = dict.allocate().unwrap();
+        dict.store(i, item("hello")).unwrap();
+        dict.set_pointer(5).unwrap();
+        let j = dict.allocate().unwrap();
+        assert_eq!(j, 5);
+        dict.store(j, item("world")).unwrap();
+        assert_eq!(dict.len(), 2);
+        assert_eq!(*dict.get(0).unwrap(), item("hello"));
+        assert!(matches!(dict.get(1), Err(DossError::InvalidReference(1))));
+        dict.clear(0, 3);
+        assert!(dict.get(0).is_err());
+        assert_eq!(dict.len(), 1);
+        dict.clear(4, u64::MAX);
+        assert!(dict.is_empty());
+        assert_eq!(dict.pointer(), 6);
+    }
+
+    #[test]
+    fn test_limits() {
+        let mut dict = DossDictionary::with_limits(2, 3);
+        dict.allocate().unwrap();
+        dict.allocate().unwrap();
+        assert!(matches!(dict.allocate(), Err(DossError::DictFull)));
+        assert!(matches!(dict.set_pointer(2), Err(DossError::DictFull)));
+        dict.store(0, vec![DossEvent::Null; 3]).unwrap();
+        assert!(matches!(dict.store(1, item("x")), Err(DossError::DictFull)));
+        //overwriting frees the old size
+        dict.store(0, item("x")).unwrap();
+        dict.store(1, item("y")).unwrap();
+    }
+
+    #[test]
+    fn test_nested_stacks() {
+        let mut dict = DossDictionary::with_limits(100, 100);
+        dict.store(0, item("global")).unwrap();
+        dict.set_pointer(1).unwrap();
+        dict.start_stack();
+        let i = dict.allocate().unwrap();
+        dict.store(i, item("outer")).unwrap();
+        dict.store(0, item("overwritten")).unwrap();
+        dict.start_stack();
+        dict.clear(0, 2);
+        dict.set_pointer(50).unwrap();
+        let j = dict.allocate().unwrap();
+        dict.store(j, item("inner")).unwrap();
+        assert_eq!(dict.stack_depth(), 2);
+        assert_eq!(dict.len(), 1);
+
+        dict.leave_stack().unwrap();
+        assert_eq!(dict.pointer(), 2);
+        assert_eq!(*dict.get(0).unwrap(), item("overwritten"));
+        assert_eq!(*dict.get(1).unwrap(), item("outer"));
+        assert!(dict.get(50).is_err());
+
+        dict.leave_stack().unwrap();
+        assert_eq!(dict.pointer(), 1);
+        assert_eq!(*dict.get(0).unwrap(), item("global"));
+        assert!(dict.get(1).is_err());
+        assert_eq!(dict.len(), 1);
+        assert_eq!(dict.events, 1);
+        assert_eq!(dict.memory(), item_memory(&item("global")));
+        assert!(matches!(dict.leave_stack(), Err(DossError::UnbalancedStructure)));
+    }
+}
@@ -0,0 +1,85 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use dataflowgrid_commons::typedstream::TypedStreamEventError;
+use derive_more::{Display, Error, From};
+
+#[derive(Debug, Display, Error, From)]
+pub enum DossError {
+    #[display("invalid opcode {_0}")]
+    #[from(ignore)]
+    InvalidOpcode(#[error(not(source))] u8),
+    #[display("opcode {_0} is not supported")]
+    #[from(ignore)]
+    UnsupportedOpcode(#[error(not(source))] u8),
+    #[display("varint does not fit into 64 bits")]
+    VarintOverflow,
+    #[display("invalid operand of {_0}")]
+    #[from(ignore)]
+    InvalidOperand(#[error(not(source))] &'static str),
+    #[display("string is not valid utf8")]
+    InvalidUtf8,
+    #[display("string is not valid {_0}")]
+    #[from(ignore)]
+    InvalidString(#[error(not(source))] &'static str),
+    #[display("stream ended in the middle of an item")]
+    UnexpectedEof,
+    #[display("dictionary is full")]
+    DictFull,
+    #[display("reference to empty dict entry {_0}")]
+    #[from(ignore)]
+    InvalidReference(#[error(not(source))] u64),
+    #[display("end of block, array or type does not match its start")]
+    UnbalancedStructure,
+    #[display("expected an item")]
+    ItemExpected,
+    #[display("type name must be a string")]
+    InvalidTypeName,
+    #[display("value {_0} can not be serialized")]
+    #[from(ignore)]
+    UnsupportedValue(#[error(not(source))] String),
+    #[display("unknown setting {_0}")]
+    #[from(ignore)]
+    UnknownSetting(#[error(not(source))] String),
+    #[display("invalid setting {_0}")]
+    #[from(ignore)]
+    InvalidSetting(#[error(not(source))] String),
+    #[display("stream requires DOSS version {_0}")]
+    #[from(ignore)]
+    UnsupportedVersion(#[error(not(source))] u64),
+    #[display("unknown predefined dictionary {_0}")]
+    #[from(ignore)]
+    UnknownDictionary(#[error(not(source))] String),
+    #[display("predefined dictionary {_0} has different content")]
+    #[from(ignore)]
+    DictionaryMismatch(#[error(not(source))] String),
+    #[display("invalid predefined dictionary: {_0}")]
+    #[from(ignore)]
+    InvalidDictionary(#[error(not(source))] String),
+    #[display("nesting deeper than {_0} levels")]
+    #[from(ignore)]
+    TooDeep(#[error(not(source))] usize),
+    #[display("item of {_0} bytes is longer than allowed")]
+    #[from(ignore)]
+    TooLong(#[error(not(source))] u64),
+    #[display("decoding needs more than {_0} bytes of memory")]
+    #[from(ignore)]
+    MemoryLimit(#[error(not(source))] usize),
+    #[display("invalid compressed frame: {_0}")]
+    #[from(ignore)]
+    InvalidFrame(#[error(not(source))] String),
+    #[display("invalid index: {_0}")]
+    #[from(ignore)]
+    InvalidIndex(#[error(not(source))] String),
+    #[display("schema mismatch: {_0}")]
+    #[from(ignore)]
+    SchemaMismatch(#[error(not(source))] String),
+    #[display("columnar output failed: {_0}")]
+    #[from(ignore)]
+    Columnar(#[error(not(source))] String),
+    #[display("event handler failed: {_0:?}")]
+    Handler(#[error(not(source))] TypedStreamEventError),
+    #[display("event handler stopped with an error")]
+    HandlerStopped,
+    #[display("io error: {_0}")]
+    Io(std::io::Error),
+}
@@ -0,0 +1,676 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::io::{ErrorKind, Read, Seek, Write};
+use std::sync::Arc;
+
+use crate::compression::Compression;
+use crate::container::DossFileName;
+use crate::deserializer::DossLowLevelStreamEvent;
+use crate::error::DossError;
+use crate::limits::DossLimits;
+use crate::parser::DossPullParser;
+use crate::registry::DossDictionaryRegistry;
+use crate::resolver::{DossEvent, DossItem, DossResolver};
+use crate::serializer::MAX_DICT_ENTRIES_HINT;
+use crate::settings::{DossSettings, StringEncoding};
+
+/// type name of the index written by [`DossIndex::write`]
+pub const INDEX_TYPE: &str = "doss_index";
+const INDEX_VERSION: u64 = 1;
+
+/// Where an index entry leads to
+#[derive(Debug, Clone, PartialEq)]
+pub enum DossIndexTarget {
+    /// The start of a file, named like [`DossFiles`](crate::DossFiles) does
+    File(DossFileName),
+    /// An element of the array whose start opcode is at byte offset `array`
+    Element { array: u64, element: u64 },
+}
+
+/// A position to continue decoding at, see [`DossPullParser::at_entry`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DossIndexEntry {
+    pub target: DossIndexTarget,
+    /// Byte offset of the first opcode of the target
+    pub offset: u64,
+    pub(crate) state: Vec<u8>, //opcodes restoring the dictionary, the settings and the open levels at offset
+}
+
+/// An array with at least [`DossIndex::stride`] elements
+#[derive(Debug, Clone, PartialEq)]
+pub struct DossIndexArray {
+    /// Byte offset of the start array opcode
+    pub offset: u64,
+    pub len: u64,
+    /// Nesting level of the array, 1 for arrays at the top level of a file
+    pub depth: usize,
+}
+
+/// Byte offsets of the files and of every `stride`th element of large arrays in a DOSS stream, together with
+/// the dictionary and settings in effect there. Stored as a sidecar stream with [`DossIndex::write`].
+///
+/// The index belongs to exactly the bytes it was built from, it has to be rebuilt when the stream changes.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DossIndex {
+    stride: u64,
+    entries: Vec<DossIndexEntry>, //ordered by offset
+    arrays: Vec<DossIndexArray>, //ordered by their end
+}
+
+impl DossIndex {
+    /// Reads a whole stream, see [`DossIndexBuilder`]
+    pub fn build<R: Read>(mut reader: R, stride: u64) -> Result<Self, DossError> {
+        let mut builder = DossIndexBuilder::new(stride);
+        let mut chunk = vec![0; 64 * 1024];
+        loop {
+            let read = match reader.read(&mut chunk) {
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                read => read?,
+            };
+            if read == 0 {
+                return builder.finish();
+            }
+            builder.push(&chunk[..read])?;
+        }
+    }
+
+    /// Elements between two entries of the same array
+    pub fn stride(&self) -> u64 {
+        self.stride
+    }
+
+    pub fn entries(&self) -> &[DossIndexEntry] {
+        &self.entries
+    }
+
+    pub fn arrays(&self) -> &[DossIndexArray] {
+        &self.arrays
+    }
+
+    /// The large array starting at `offset`
+    pub fn array(&self, offset: u64) -> Option<&DossIndexArray> {
+        self.arrays.iter().find(|a| a.offset == offset)
+    }
+
+    /// The entry of the last file with this name
+    pub fn file(&self, name: &DossFileName) -> Option<&DossIndexEntry> {
+        self.entries.iter().rev().find(|e| matches!(&e.target, DossIndexTarget::File(n) if n == name))
+    }
+
+    /// A parser whose first event is the start of the file, None if there is no such file
+    pub fn open_file<R: Read + Seek>(&self, reader: R, name: &DossFileName) -> Result<Option<DossPullParser<R>>, DossError> {
+        self.file(name).map(|entry| DossPullParser::at_entry(reader, entry)).transpose()
+    }
+
+    /// A parser whose next value is the element of a large array, None if the array is not indexed or shorter.
+    /// Decoding starts at the nearest entry before the element and skips the elements in between
+    pub fn open_element<R: Read + Seek>(&self, reader: R, array: u64, element: u64) -> Result<Option<DossPullParser<R>>, DossError> {
+        if self.array(array).is_none_or(|a| element >= a.len) {
+            return Ok(None);
+        }
+        let nearest = self.entries.iter().rev().find_map(|e| match e.target {
+            DossIndexTarget::Element { array: a, element: n } if a == array && n <= element => Some((e, n)),
+            _ => None,
+        });
+        let (mut parser, mut remaining) = match nearest {
+            Some((entry, n)) => (DossPullParser::at_entry(reader, entry)?, element - n),
+            None => {
+                //the first elements have no entry, continue at the last entry before the array
+                let entry = self.entries.iter().rev().find(|e| e.offset <= array).ok_or_else(|| invalid("no entry before the array"))?;
+                let mut parser = DossPullParser::at_entry(reader, entry)?;
+                loop {
+                    match parser.next_doss_event()? {
+                        Some(DossEvent::ArrayStart) if parser.position() == array + 1 => break,
+                        Some(_) if parser.position() <= array => {}
+                        _ => return Err(invalid("array not found")),
+                    }
+                }
+                (parser, element)
+            }
+        };
+        while remaining > 0 {
+            match parser.next_doss_event()? {
+                Some(event) if !is_value(&event) => {}
+                Some(event) if event.depth_change() > 0 => {
+                    parser.skip_level();
+                    parser.next_doss_event()?;
+                    remaining -= 1;
+                }
+                Some(event) if event.depth_change() == 0 => remaining -= 1,
+                _ => return Err(invalid("array ended before the element")),
+            }
+        }
+        Ok(Some(parser))
+    }
+
+    /// Writes the index as a DOSS stream of its own, a value of type [`INDEX_TYPE`]
+    pub fn write<W: Write>(&self, mut out: W) -> Result<W, DossError> {
+        let mut events = vec![
+            DossEvent::TypeStart(String::from(INDEX_TYPE)),
+            DossEvent::UInt(INDEX_VERSION),
+            DossEvent::UInt(self.stride),
+            DossEvent::ArrayStart,
+        ];
+        for array in &self.arrays {
+            events.extend([DossEvent::ArrayStart, DossEvent::UInt(array.offset), DossEvent::UInt(array.len), DossEvent::UInt(array.depth as u64), DossEvent::ArrayEnd]);
+        }
+        events.extend([DossEvent::ArrayEnd, DossEvent::ArrayStart]);
+        for entry in &self.entries {
+            events.extend([DossEvent::ArrayStart, DossEvent::UInt(entry.offset), DossEvent::Binary(entry.state.clone())]);
+            match &entry.target {
+                DossIndexTarget::File(DossFileName::Assigned(n)) => events.extend([DossEvent::UInt(0), DossEvent::UInt(*n)]),
+                DossIndexTarget::File(DossFileName::Named(item)) => {
+                    events.push(DossEvent::UInt(1));
+                    events.extend(item.iter().cloned());
+                }
+                DossIndexTarget::Element { array, element } => events.extend([DossEvent::UInt(2), DossEvent::UInt(*array), DossEvent::UInt(*element)]),
+            }
+            events.push(DossEvent::ArrayEnd);
+        }
+        events.extend([DossEvent::ArrayEnd, DossEvent::TypeEnd]);
+        let mut bytes = Vec::new();
+        encode_item(&events, &mut bytes);
+        out.write_all(&bytes)?;
+        Ok(out)
+    }
+
+    /// Reads an index written by [`DossIndex::write`]
+    pub fn read<R: Read>(reader: R) -> Result<Self, DossError> {
+        let mut parser = DossPullParser::new(reader);
+        let mut events = Vec::new();
+        while let Some(event) = parser.next_doss_event()? {
+            events.push(event);
+        }
+        let mut events = events.into_iter();
+        expect(&mut events, DossEvent::TypeStart(String::from(INDEX_TYPE)))?;
+        if uint(&mut events)? != INDEX_VERSION {
+            return Err(invalid("unknown version"));
+        }
+        let mut index = DossIndex { stride: uint(&mut events)?, ..Default::default() };
+        expect(&mut events, DossEvent::ArrayStart)?;
+        while next_in_list(&mut events)? {
+            let offset = uint(&mut events)?;
+            let len = uint(&mut events)?;
+            let depth = usize::try_from(uint(&mut events)?).map_err(|_| invalid("depth"))?;
+            index.arrays.push(DossIndexArray { offset, len, depth });
+            expect(&mut events, DossEvent::ArrayEnd)?;
+        }
+        expect(&mut events, DossEvent::ArrayStart)?;
+        while next_in_list(&mut events)? {
+            let offset = uint(&mut events)?;
+            let Some(DossEvent::Binary(state)) = events.next() else {
+                return Err(invalid("state expected"));
+            };
+            let target = match uint(&mut events)? {
+                0 => DossIndexTarget::File(DossFileName::Assigned(uint(&mut events)?)),
+                1 => DossIndexTarget::File(DossFileName::Named(item(&mut events)?)),
+                2 => DossIndexTarget::Element { array: uint(&mut events)?, element: uint(&mut events)? },
+                _ => return Err(invalid("unknown target")),
+            };
+            index.entries.push(DossIndexEntry { target, offset, state });
+            expect(&mut events, DossEvent::ArrayEnd)?;
+        }
+        expect(&mut events, DossEvent::TypeEnd)?;
+        match events.next() {
+            None => Ok(index),
+            Some(_) => Err(invalid("data after the index")),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Level {
+    Block,
+    Type(String),
+    Array(Option<ArrayScan>), //None for arrays that can't be indexed, e.g. stored ones
+}
+
+#[derive(Debug)]
+struct ArrayScan {
+    offset: u64,
+    elements: u64,
+}
+
+/// Builds a [`DossIndex`] from the bytes of a stream pushed in order.
+///
+/// Every file gets an entry. Arrays get an entry for every `stride`th element, arrays with fewer elements are
+/// not indexed. Each entry holds a copy of the dictionary, a larger stride keeps the index small.
+/// Compressed streams can't be indexed, offsets within frames can't be opened.
+#[derive(Debug)]
+pub struct DossIndexBuilder {
+    resolver: DossResolver,
+    stride: u64,
+    buffer: Vec<u8>,
+    offset: u64, //stream offset of the start of the buffer
+    resolved: Vec<DossEvent>,
+    levels: Vec<Level>,
+    assigned: u64,
+    in_file: bool,
+    file_start: Option<(u64, Vec<u8>)>, //offset and state of the start file opcode being read
+    element_start: Option<(u64, Vec<u8>)>, //offset and state before the next element to index
+    index: DossIndex,
+}
+
+impl DossIndexBuilder {
+    pub fn new(stride: u64) -> Self {
+        DossIndexBuilder {
+            resolver: DossResolver::new(),
+            stride: stride.max(1),
+            buffer: Vec::new(),
+            offset: 0,
+            resolved: Vec::new(),
+            levels: Vec::new(),
+            assigned: 0,
+            in_file: false,
+            file_start: None,
+            element_start: None,
+            index: DossIndex { stride: stride.max(1), ..Default::default() },
+        }
+    }
+
+    /// Predefined dictionaries for imports, see [`DossResolver::set_registry`]
+    pub fn set_registry(&mut self, registry: Arc<DossDictionaryRegistry>) {
+        self.resolver.set_registry(registry);
+    }
+
+    /// See [`DossResolver::set_limits`]
+    pub fn set_limits(&mut self, limits: DossLimits) {
+        self.resolver.set_limits(limits);
+    }
+
+    pub fn push(&mut self, data: &[u8]) -> Result<(), DossError> {
+        self.buffer.extend_from_slice(data);
+        let mut pos = 0;
+        while let Some((event, used)) = self.resolver.decode(&self.buffer[pos..])? {
+            let offset = self.offset + pos as u64;
+            pos += used;
+            self.push_event(event, offset)?;
+        }
+        self.buffer.drain(..pos);
+        self.offset += pos as u64;
+        Ok(())
+    }
+
+    /// Checks that the stream is complete and returns the index
+    pub fn finish(self) -> Result<DossIndex, DossError> {
+        if !self.buffer.is_empty() {
+            return Err(DossError::UnexpectedEof);
+        }
+        self.resolver.finish()?;
+        Ok(self.index)
+    }
+
+    fn push_event(&mut self, event: DossLowLevelStreamEvent, offset: u64) -> Result<(), DossError> {
+        let capturing = self.resolver.is_capturing();
+        if !capturing {
+            if event == DossLowLevelStreamEvent::FileStart {
+                self.file_start = Some((offset, self.state()));
+            } else if starts_item(&event)
+                && let Some(Level::Array(Some(scan))) = self.levels.last()
+                && scan.elements > 0
+                && scan.elements % self.stride == 0 {
+                self.element_start = Some((offset, self.state()));
+            }
+        }
+        let opens_array = !capturing && event == DossLowLevelStreamEvent::ArrayStart;
+        self.resolver.push(event, &mut self.resolved)?;
+        if self.resolver.settings().compression() != Compression::None {
+            return Err(invalid("compressed streams can't be indexed"));
+        }
+        for event in std::mem::take(&mut self.resolved) {
+            self.on_event(&event, opens_array.then_some(offset));
+        }
+        Ok(())
+    }
+
+    fn on_event(&mut self, event: &DossEvent, array_offset: Option<u64>) {
+        match event {
+            DossEvent::FileStart(name) => {
+                let name = match name.as_slice() {
+                    [DossEvent::Null] => self.assign(),
+                    _ => DossFileName::Named(name.clone()),
+                };
+                let (offset, state) = self.file_start.take().unwrap_or_default();
+                self.in_file = true;
+                self.index.entries.push(DossIndexEntry { target: DossIndexTarget::File(name), offset, state });
+            }
+            //settings before the first file belong to it, like in DossFiles
+            DossEvent::Config { .. } | DossEvent::Hint { .. } | DossEvent::ImportDict(_) if !self.in_file => {}
+            _ if !self.in_file => {
+                let name = self.assign();
+                self.in_file = true;
+                self.index.entries.push(DossIndexEntry { target: DossIndexTarget::File(name), offset: 0, state: Vec::new() });
+            }
+            _ => {}
+        }
+        if is_value(event)
+            && event.depth_change() >= 0
+            && let Some(Level::Array(Some(scan))) = self.levels.last_mut() {
+            if scan.elements > 0 && scan.elements % self.stride == 0
+                && let Some((offset, state)) = self.element_start.take() {
+                let target = DossIndexTarget::Element { array: scan.offset, element: scan.elements };
+                self.index.entries.push(DossIndexEntry { target, offset, state });
+            }
+            scan.elements += 1;
+        }
+        match event {
+            DossEvent::BlockStart => self.levels.push(Level::Block),
+            DossEvent::TypeStart(name) => self.levels.push(Level::Type(name.clone())),
+            DossEvent::ArrayStart => self.levels.push(Level::Array(array_offset.map(|offset| ArrayScan { offset, elements: 0 }))),
+            DossEvent::BlockEnd | DossEvent::TypeEnd => {
+                self.levels.pop();
+            }
+            DossEvent::ArrayEnd => {
+                if let Some(Level::Array(Some(scan))) = self.levels.pop()
+                    && scan.elements >= self.stride {
+                    let depth = self.levels.len() + 1;
+                    self.index.arrays.push(DossIndexArray { offset: scan.offset, len: scan.elements, depth });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn assign(&mut self) -> DossFileName {
+        self.assigned += 1;
+        DossFileName::Assigned(self.assigned - 1)
+    }
+
+    /// Opcodes that bring a new resolver into the current state: the dictionary with its stacks,
+    /// the open levels and the settings, which come last as they may change the string encoding
+    fn state(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut entries = Vec::new();
+        let mut pointer = 0;
+        for (level, (level_pointer, level_entries)) in self.resolver.dictionary().scope_states().into_iter().enumerate() {
+            if level > 0 {
+                DossLowLevelStreamEvent::StackStart.encode(&mut out);
+            }
+            for index in 0..entries.len().max(level_entries.len()) {
+                let old = entries.get(index).cloned().flatten();
+                match level_entries.get(index).cloned().flatten() {
+                    new if new == old => {}
+                    Some(item) => {
+                        if pointer != index {
+                            DossLowLevelStreamEvent::SetDictPointer(index as u64).encode(&mut out);
+                        }
+                        DossLowLevelStreamEvent::StoreButDontUse.encode(&mut out);
+                        encode_item(&item, &mut out);
+                        pointer = index + 1;
+                    }
+                    None => DossLowLevelStreamEvent::ClearDictEntries { from: index as u64, count: 1 }.encode(&mut out),
+                }
+            }
+            if pointer != level_pointer {
+                DossLowLevelStreamEvent::SetDictPointer(level_pointer as u64).encode(&mut out);
+                pointer = level_pointer;
+            }
+            entries = level_entries;
+        }
+        let levels: Vec<_> = self.levels.iter().map(|level| match level {
+            Level::Block => DossEvent::BlockStart,
+            Level::Type(name) => DossEvent::TypeStart(name.clone()),
+            Level::Array(_) => DossEvent::ArrayStart,
+        }).collect();
+        encode_item(&levels, &mut out);
+        encode_settings(self.resolver.settings(), &mut out);
+        out
+    }
+}
+
+fn encode_settings(settings: &DossSettings, out: &mut Vec<u8>) {
+    let mut set = |opcode: DossLowLevelStreamEvent, key: DossEvent, value: DossEvent| {
+        opcode.encode(out);
+        encode_item(&[key, value], out);
+    };
+    if settings.minimum_version() > 0 {
+        set(DossLowLevelStreamEvent::SetConfig, DossEvent::UInt(0), DossEvent::UInt(settings.minimum_version()));
+    }
+    if settings.recommended_version() > 0 {
+        set(DossLowLevelStreamEvent::SetHint, DossEvent::UInt(0), DossEvent::UInt(settings.recommended_version()));
+    }
+    if let Some(generated_with) = settings.generated_with() {
+        set(DossLowLevelStreamEvent::SetHint, DossEvent::String(String::from("generated_with")), DossEvent::String(generated_with.to_string()));
+    }
+    if let Some(max) = settings.max_dict_entries() {
+        set(DossLowLevelStreamEvent::SetHint, DossEvent::String(String::from(MAX_DICT_ENTRIES_HINT)), DossEvent::UInt(max));
+    }
+    if settings.string_encoding() != StringEncoding::Utf8 {
+        set(DossLowLevelStreamEvent::SetConfig, DossEvent::UInt(1), DossEvent::String(settings.string_encoding().name().to_string()));
+    }
+}
+
+/// Writes value events as they are, without the dictionary and in utf8
+fn encode_item(item: &[DossEvent], out: &mut Vec<u8>) {
+    for event in item {
+        let event = match event {
+            DossEvent::BlockStart => DossLowLevelStreamEvent::BlockStart,
+            DossEvent::BlockEnd => DossLowLevelStreamEvent::BlockEnd,
+            DossEvent::ArrayStart => DossLowLevelStreamEvent::ArrayStart,
+            DossEvent::ArrayEnd => DossLowLevelStreamEvent::ArrayEnd,
+            DossEvent::TypeStart(name) => {
+                DossLowLevelStreamEvent::TypeStart.encode(out);
+                DossLowLevelStreamEvent::String(name.clone())
+            }
+            DossEvent::TypeEnd => DossLowLevelStreamEvent::TypeEnd,
+            DossEvent::True => DossLowLevelStreamEvent::True,
+            DossEvent::False => DossLowLevelStreamEvent::False,
+            DossEvent::Null => DossLowLevelStreamEvent::Null,
+            DossEvent::Int(v) => DossLowLevelStreamEvent::Varint(*v),
+            DossEvent::UInt(v) => DossLowLevelStreamEvent::UnsignedVarint(*v),
+            DossEvent::Decimal(d) => DossLowLevelStreamEvent::Decimal(d.clone()),
+            DossEvent::Float(f) => DossLowLevelStreamEvent::Float(*f),
+            DossEvent::DateTime(d) => DossLowLevelStreamEvent::DateTime(*d),
+            DossEvent::String(s) => DossLowLevelStreamEvent::String(s.clone()),
+            DossEvent::Binary(b) => DossLowLevelStreamEvent::Binary(b.clone()),
+            DossEvent::Config { .. }
+            | DossEvent::Hint { .. }
+            | DossEvent::ImportDict(_)
+            | DossEvent::FileStart(_)
+            | DossEvent::StackStart
+            | DossEvent::StackEnd => unreachable!("items consist of values"),
+        };
+        event.encode(out);
+    }
+}
+
+//false for settings, imports, file starts and stacks
+fn is_value(event: &DossEvent) -> bool {
+    !matches!(event, DossEvent::Config { .. } | DossEvent::Hint { .. } | DossEvent::ImportDict(_) | DossEvent::FileStart(_) | DossEvent::StackStart | DossEvent::StackEnd)
+}
+
+//opcodes an element of an array can start with
+fn starts_item(event: &DossLowLevelStreamEvent) -> bool {
+    !matches!(event, DossLowLevelStreamEvent::NoOp
+        | DossLowLevelStreamEvent::BlockEnd
+        | DossLowLevelStreamEvent::ArrayEnd
+        | DossLowLevelStreamEvent::TypeEnd
+        | DossLowLevelStreamEvent::SetConfig
+        | DossLowLevelStreamEvent::StoreButDontUse
+        | DossLowLevelStreamEvent::SetDictPointer(_)
+        | DossLowLevelStreamEvent::ClearDictEntries { .. }
+        | DossLowLevelStreamEvent::StackStart
+        | DossLowLevelStreamEvent::StackEnd
+        | DossLowLevelStreamEvent::SetHint
+        | DossLowLevelStreamEvent::SkipBytes16le(_)
+        | DossLowLevelStreamEvent::SkipBytes32le(_)
+        | DossLowLevelStreamEvent::ImportDict
+        | DossLowLevelStreamEvent::FileStart)
+}
+
+fn invalid(reason: &str) -> DossError {
+    DossError::InvalidIndex(reason.to_string())
+}
+
+fn expect(events: &mut impl Iterator<Item = DossEvent>, expected: DossEvent) -> Result<(), DossError> {
+    match events.next() {
+        Some(event) if event == expected => Ok(()),
+        _ => Err(invalid(&format!("{expected:?} expected"))),
+    }
+}
+
+fn uint(events: &mut impl Iterator<Item = DossEvent>) -> Result<u64, DossError> {
+    match events.next() {
+        Some(DossEvent::UInt(v)) => Ok(v),
+        _ => Err(invalid("number expected")),
+    }
+}
+
+//true if another array of the list follows, consumes the end of the list otherwise
+fn next_in_list(events: &mut impl Iterator<Item = DossEvent>) -> Result<bool, DossError> {
+    match events.next() {
+        Some(DossEvent::ArrayStart) => Ok(true),
+        Some(DossEvent::ArrayEnd) => Ok(false),
+        _ => Err(invalid("list expected")),
+    }
+}
+
+fn item(events: &mut impl Iterator<Item = DossEvent>) -> Result<DossItem, DossError> {
+    let mut item = Vec::new();
+    let mut depth = 0_isize;
+    for event in events.by_ref() {
+        depth += event.depth_change();
+        item.push(event);
+        if depth <= 0 {
+            break;
+        }
+    }
+    match depth {
+        0 if !item.is_empty() => Ok(item),
+        _ => Err(invalid("item expected")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::serializer::{DossSerializer, DossSerializerOptions};
+
+    fn s(v: &str) -> DossEvent {
+        DossEvent::String(v.to_string())
+    }
+
+    //an unnamed file, a file with an array of 100 records partly inside a stack and a file with an array of strings
+    fn stream() -> Vec<u8> {
+        let mut events = vec![s("first"), DossEvent::FileStart(vec![s("rows")]), DossEvent::BlockStart, s("rows"), DossEvent::ArrayStart];
+        for i in 0..100 {
+            match i {
+                50 => events.push(DossEvent::StackStart),
+                70 => events.push(DossEvent::StackEnd),
+                _ => {}
+            }
+            events.extend([DossEvent::BlockStart, s("id"), DossEvent::UInt(i), s("name"), s(&format!("name {}", i % 7))]);
+            events.extend([s("tags"), DossEvent::ArrayStart, s("a"), s(&format!("tag {}", i % 3)), DossEvent::ArrayEnd, DossEvent::BlockEnd]);
+        }
+        events.extend([DossEvent::ArrayEnd, DossEvent::BlockEnd, DossEvent::FileStart(vec![DossEvent::Null]), DossEvent::ArrayStart]);
+        events.extend((0..30).map(|i| s(&format!("name {}", i % 5))));
+        events.extend([DossEvent::ArrayEnd, DossEvent::FileStart(vec![s("rows")]), s("last")]);
+
+        //a small dictionary, so entries are replaced all the time
+        let options = DossSerializerOptions { max_dict_entries: 4, min_occurrences: 1, skip_threshold: Some(8), ..Default::default() };
+        let mut serializer = DossSerializer::seekable(Cursor::new(Vec::new()), options);
+        events.iter().for_each(|e| serializer.write_doss_event(e).unwrap());
+        serializer.finish().unwrap().into_inner()
+    }
+
+    //all events of a stream with the position after each
+    fn read_all(serialized: &[u8]) -> Vec<(DossEvent, u64)> {
+        let mut parser = DossPullParser::new(serialized);
+        std::iter::from_fn(|| parser.next_doss_event().unwrap().map(|e| (e, parser.position()))).collect()
+    }
+
+    //the next value, settings and stacks before it are dropped
+    fn next_item(events: &mut impl Iterator<Item = DossEvent>) -> DossItem {
+        let mut item = Vec::new();
+        let mut depth = 0;
+        for event in events.filter(is_value) {
+            depth += event.depth_change();
+            item.push(event);
+            if depth == 0 {
+                break;
+            }
+        }
+        item
+    }
+
+    #[test]
+    fn test_entries_continue_the_stream() {
+        let serialized = stream();
+        let index = DossIndex::build(serialized.as_slice(), 10).unwrap();
+        let all = read_all(&serialized);
+        let files: Vec<_> = index.entries.iter().filter_map(|e| match &e.target {
+            DossIndexTarget::File(name) => Some(name.clone()),
+            _ => None,
+        }).collect();
+        let rows = DossFileName::Named(vec![s("rows")]);
+        assert_eq!(files, vec![DossFileName::Assigned(0), rows.clone(), DossFileName::Assigned(1), rows.clone()]);
+        assert_eq!(index.arrays.iter().map(|a| (a.len, a.depth)).collect::<Vec<_>>(), vec![(100, 2), (30, 1)]);
+        assert_eq!(index.entries.len(), 4 + 9 + 2);
+
+        //from every entry on, decoding yields the same events as reading everything
+        for entry in &index.entries {
+            let mut parser = DossPullParser::at_entry(Cursor::new(&serialized), entry).unwrap();
+            let events: Vec<_> = std::iter::from_fn(|| parser.next_doss_event().unwrap()).collect();
+            let expected: Vec<_> = all.iter().filter(|(_, p)| *p > entry.offset).map(|(e, _)| e.clone()).collect();
+            assert_eq!(events, expected, "{entry:?}");
+        }
+
+        let mut parser = index.open_file(Cursor::new(&serialized), &rows).unwrap().unwrap();
+        assert_eq!(parser.next_doss_event().unwrap(), Some(DossEvent::FileStart(vec![s("rows")])));
+        assert_eq!(parser.next_doss_event().unwrap(), Some(s("last")));
+        assert!(index.open_file(Cursor::new(&serialized), &DossFileName::Assigned(2)).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_open_element() {
+        let serialized = stream();
+        let index = DossIndex::build(serialized.as_slice(), 10).unwrap();
+        let all = read_all(&serialized);
+        for array in index.arrays() {
+            let start = all.iter().position(|(e, p)| *e == DossEvent::ArrayStart && *p == array.offset + 1).unwrap();
+            let mut events = all[start + 1..].iter().map(|(e, _)| e.clone());
+            for element in 0..array.len {
+                let expected = next_item(&mut events);
+                let mut parser = index.open_element(Cursor::new(&serialized), array.offset, element).unwrap().unwrap();
+                let item = next_item(&mut std::iter::from_fn(|| parser.next_doss_event().unwrap()));
+                assert_eq!(item, expected, "element {element} of {array:?}");
+            }
+            assert!(index.open_element(Cursor::new(&serialized), array.offset, array.len).unwrap().is_none());
+        }
+        assert!(index.open_element(Cursor::new(&serialized), 0, 0).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_settings_in_state() {
+        //utf16 strings, the setting must be part of the state of the entries
+        let mut serialized = vec![20, 15, 1, 7, 7];
+        serialized.extend(b"utf16le");
+        serialized.push(12);
+        for i in 0..10 {
+            let utf16: Vec<u8> = format!("grüße {i}").encode_utf16().flat_map(u16::to_le_bytes).collect();
+            serialized.extend([7, utf16.len() as u8]);
+            serialized.extend(utf16);
+        }
+        serialized.push(13);
+        let index = DossIndex::build(serialized.as_slice(), 3).unwrap();
+        assert_eq!(index.arrays(), [DossIndexArray { offset: 12, len: 10, depth: 1 }]);
+        let mut parser = index.open_element(Cursor::new(&serialized), 12, 7).unwrap().unwrap();
+        assert_eq!(parser.settings().string_encoding(), StringEncoding::Utf16Le);
+        assert_eq!(parser.next_doss_event().unwrap(), Some(s("grüße 7")));
+        assert_eq!(parser.next_doss_event().unwrap(), Some(s("grüße 8")));
+    }
+
+    #[test]
+    fn test_write_and_read() {
+        let serialized = stream();
+        let index = DossIndex::build(serialized.as_slice(), 10).unwrap();
+        let written = index.write(Vec::new()).unwrap();
+        assert_eq!(DossIndex::read(written.as_slice()).unwrap(), index);
+
+        assert!(matches!(DossIndex::read(&written[..written.len() - 1]), Err(DossError::UnexpectedEof)));
+        assert!(matches!(DossIndex::read(&serialized[..]), Err(DossError::InvalidIndex(_))));
+        assert!(matches!(DossIndex::build(&serialized[..serialized.len() - 1], 10), Err(DossError::UnexpectedEof)));
+    }
+}
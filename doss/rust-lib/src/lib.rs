@@ -2,6 +2,46 @@
 
 #![allow(dead_code)]
 mod types;
+#[cfg(feature = "arrow")]
+mod columnar;
+mod compression;
+mod container;
+mod error;
+mod varint;
 mod deserializer;
+mod dictionary;
+mod index;
+mod limits;
+mod parser;
+mod resolver;
+mod reader;
+mod registry;
+mod schema;
+mod serializer;
+mod settings;
+mod skip;
+mod text;
+mod tree;
 
+#[cfg(feature = "arrow")]
+pub use columnar::{DOSS_TYPE_METADATA, DossArrowProjector};
+#[cfg(feature = "parquet")]
+pub use columnar::{DossParquetWriter, doss_to_parquet};
+pub use compression::Compression;
+pub use deserializer::DossLowLevelStreamEvent;
+pub use dictionary::DossDictionary;
+pub use container::{DossFileCallback, DossFileName, DossFiles, extract_file, read_files};
+pub use error::DossError;
+pub use index::{DossIndex, DossIndexArray, DossIndexBuilder, DossIndexEntry, DossIndexTarget, INDEX_TYPE};
+pub use limits::DossLimits;
+pub use reader::{DossReader, DossReaderCallback, DossReaderCallbackReturn, DossReaderError, DossReaderPushResult};
+pub use parser::{DossAsyncEventHandler, DossAsyncParser, DossEventHandler, DossEventHandlerFuture, DossPullParser};
+pub use registry::{DossDictionaryRegistry, DossDictionaryTrainer, DossPredefinedDictionary};
+pub use schema::{DEFAULT_MAX_DISTINCT, DossCardinality, DossField, DossFieldType, DossSchema, DossSchemaInference};
+pub use resolver::{DossEvent, DossItem, DossResolver};
+pub use serializer::{DossSerializer, DossSerializerOptions};
+pub use settings::{DOSS_VERSION, DossSettings, StringEncoding};
+pub use text::DossTextEvents;
+pub use tree::{BYTES_TYPE, DATETIME_TYPE, DECIMAL_TYPE, DossTreeBuilder, decode_entries, decode_entries_with_registry};
+pub use types::{Decimal, DossFloat, TypesError};
 
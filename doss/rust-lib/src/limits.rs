@@ -0,0 +1,35 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+/// Bounds for decoding untrusted streams. Exceeding one fails with a [`DossError`](crate::DossError)
+/// instead of allocating without end. The size of the dictionary is bounded by
+/// [`DossDictionary::with_limits`](crate::DossDictionary::with_limits).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DossLimits {
+    /// Blocks, arrays and types open at the same time, items collected for stores, settings and similar count as well
+    pub max_depth: usize,
+    /// Bytes of a single string, binary or decimal magnitude, and of a compressed frame
+    pub max_string_len: usize,
+    /// Approximate bytes held by the dictionary and the items being collected
+    pub max_memory: usize,
+}
+
+impl Default for DossLimits {
+    fn default() -> Self {
+        DossLimits {
+            max_depth: 512,
+            max_string_len: 64 << 20,
+            max_memory: 1 << 30,
+        }
+    }
+}
+
+impl DossLimits {
+    /// No limits, for trusted input
+    pub fn unlimited() -> Self {
+        DossLimits {
+            max_depth: usize::MAX,
+            max_string_len: usize::MAX,
+            max_memory: usize::MAX,
+        }
+    }
+}
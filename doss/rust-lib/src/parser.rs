@@ -0,0 +1,670 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+use std::pin::Pin;
+use std::sync::Arc;
+
+use dataflowgrid_commons::typedstream::{TypeStream2OrderedMultiDictProcessor, TypedStreamEvent, TypedStreamEventError, TypedStreamEventReturn};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+
+use crate::compression::{Compression, Frame, FrameReader};
+use crate::deserializer::DossLowLevelStreamEvent;
+use crate::dictionary::DossDictionary;
+use crate::error::DossError;
+use crate::index::DossIndexEntry;
+use crate::limits::DossLimits;
+use crate::registry::DossDictionaryRegistry;
+use crate::resolver::{DossEvent, DossResolver};
+use crate::settings::DossSettings;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// What [`ParserCore::next`] needs to continue
+#[derive(Debug)]
+pub(crate) enum Step {
+    Event(DossEvent),
+    NeedData, //append more bytes to the buffer, the stream may end here
+    Skip(usize), //jump over this many bytes of the input or append them
+}
+
+/// Decodes and resolves buffered bytes and applies skip requests, the input is read by the caller.
+///
+/// When skipping, the end event of the skipped level is still delivered. A skip opcode of that level
+/// is used to jump ahead, otherwise the content is decoded without delivering it, so dictionary
+/// changes inside the skipped part are applied.
+///
+/// Once a compression is set, the input goes to a [`FrameReader`] and the buffer holds decompressed bytes.
+/// Positions and skips count decompressed bytes then.
+#[derive(Debug)]
+pub(crate) struct ParserCore {
+    resolver: DossResolver,
+    buffer: Vec<u8>,
+    pos: usize,
+    offset: u64, //input position of the start of the buffer
+    resolved: Vec<DossEvent>,
+    events: VecDeque<DossEvent>,
+    depth: usize, //nesting depth of the delivered events
+    skip_level: Option<usize>, //events are dropped until the depth falls below this level
+    pending_skip: usize, //bytes still to jump over
+    frames: Option<FrameReader>, //while a compression is set
+}
+
+impl ParserCore {
+    pub(crate) fn new(dict: DossDictionary) -> Self {
+        Self::with_resolver(DossResolver::with_dictionary(dict))
+    }
+
+    pub(crate) fn with_resolver(resolver: DossResolver) -> Self {
+        ParserCore {
+            resolver,
+            buffer: Vec::new(),
+            pos: 0,
+            offset: 0,
+            resolved: Vec::new(),
+            events: VecDeque::new(),
+            depth: 0,
+            skip_level: None,
+            pending_skip: 0,
+            frames: None,
+        }
+    }
+
+    pub(crate) fn resolver(&self) -> &DossResolver {
+        &self.resolver
+    }
+
+    pub(crate) fn resolver_mut(&mut self) -> &mut DossResolver {
+        &mut self.resolver
+    }
+
+    /// The buffer to append input to, bytes already decoded are removed
+    pub(crate) fn buffer_mut(&mut self) -> &mut Vec<u8> {
+        if let Some(frames) = &mut self.frames {
+            return frames.input_mut();
+        }
+        self.offset += self.pos as u64;
+        self.buffer.drain(..self.pos);
+        self.pos = 0;
+        &mut self.buffer
+    }
+
+    /// The caller jumped over `n` bytes after [`Step::Skip`]
+    pub(crate) fn skipped(&mut self, n: usize) {
+        if let Some(frames) = &mut self.frames {
+            return frames.jumped(n);
+        }
+        self.pending_skip -= n;
+        self.offset += n as u64;
+    }
+
+    /// Input position of the next byte to decode, compressed bytes count uncompressed
+    pub(crate) fn position(&self) -> u64 {
+        self.offset + self.pos as u64
+    }
+
+    /// Continues at `offset` of the input with the state of an index entry, see [`DossIndexEntry`]
+    pub(crate) fn restore(&mut self, entry: &DossIndexEntry) -> Result<(), DossError> {
+        let mut pos = 0;
+        while let Some((event, used)) = self.resolver.decode(&entry.state[pos..])? {
+            pos += used;
+            self.resolver.push(event, &mut self.resolved)?;
+            self.resolved.clear();
+        }
+        if pos < entry.state.len() || self.resolver.is_capturing() {
+            return Err(DossError::InvalidIndex(String::from("incomplete state")));
+        }
+        self.depth = self.resolver.depth();
+        self.offset = entry.offset;
+        Ok(())
+    }
+
+    /// Drops the rest of the level of the event delivered last, or its content if it started a level
+    pub(crate) fn skip(&mut self) {
+        //at the top level there is nothing to skip
+        if self.depth > 0 {
+            self.skip_level = Some(self.depth);
+        }
+    }
+
+    pub(crate) fn next(&mut self) -> Result<Step, DossError> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                self.depth = self.depth.saturating_add_signed(event.depth_change());
+                if let Some(level) = self.skip_level {
+                    if self.depth >= level {
+                        continue;
+                    }
+                    //the end event of the skipped level
+                    self.skip_level = None;
+                }
+                return Ok(Step::Event(event));
+            }
+            if self.pending_skip > 0 {
+                let buffered = (self.buffer.len() - self.pos).min(self.pending_skip);
+                self.pos += buffered;
+                self.pending_skip -= buffered;
+                if self.pending_skip > 0 {
+                    match self.next_frame()? {
+                        Some(step) => return Ok(step),
+                        None => continue,
+                    }
+                }
+            }
+            let Some((event, used)) = self.resolver.decode(&self.buffer[self.pos..])? else {
+                match self.next_frame()? {
+                    Some(step) => return Ok(step),
+                    None => continue,
+                }
+            };
+            self.pos += used;
+            //a skip opcode directly inside the skipped level jumps to its end, unless an item is being captured
+            if self.skip_level == Some(self.depth) && !self.resolver.is_capturing() {
+                match event {
+                    DossLowLevelStreamEvent::SkipBytes16le(n) if n > 0 => {
+                        self.pending_skip = n as usize;
+                        continue;
+                    }
+                    DossLowLevelStreamEvent::SkipBytes32le(n) if n > 0 => {
+                        self.pending_skip = n as usize;
+                        continue;
+                    }
+                    _ => {}
+                }
+            }
+            self.resolver.push(event, &mut self.resolved)?;
+            self.events.extend(self.resolved.drain(..));
+            self.switch_compression();
+        }
+    }
+
+    //buffers the next frame or drops it while skipping, None to continue decoding
+    fn next_frame(&mut self) -> Result<Option<Step>, DossError> {
+        let Some(frames) = &mut self.frames else {
+            return Ok(Some(match self.pending_skip {
+                0 => Step::NeedData,
+                n => Step::Skip(n),
+            }));
+        };
+        match frames.next(self.pending_skip, self.resolver.limits().max_string_len)? {
+            Frame::Data(data) => {
+                self.offset += self.pos as u64;
+                self.buffer.drain(..self.pos);
+                self.pos = 0;
+                self.buffer.extend_from_slice(&data);
+                Ok(None)
+            }
+            Frame::Dropped(n) => {
+                self.pending_skip -= n;
+                self.offset += n as u64;
+                Ok(None)
+            }
+            Frame::Jump(n) => Ok(Some(Step::Skip(n))),
+            Frame::NeedData => Ok(Some(Step::NeedData)),
+        }
+    }
+
+    //the bytes following a compression setting are frames
+    fn switch_compression(&mut self) {
+        let compression = self.resolver.settings().compression();
+        match (&mut self.frames, compression == Compression::None) {
+            (None, true) => {}
+            (None, false) => self.frames = Some(FrameReader::new(compression, self.buffer.split_off(self.pos))),
+            (Some(_), true) => {
+                let input = self.frames.take().unwrap().into_input();
+                self.buffer.extend(input);
+            }
+            (Some(frames), false) => frames.set_compression(compression),
+        }
+    }
+
+    /// Checks that the input did not end inside an item
+    pub(crate) fn finish(&self) -> Result<(), DossError> {
+        if self.pending_skip > 0 || self.pos < self.buffer.len() || self.frames.as_ref().is_some_and(|f| !f.is_complete()) {
+            return Err(DossError::UnexpectedEof);
+        }
+        self.resolver.finish()
+    }
+}
+
+/// Receives the events of a DOSS stream, see [`DossPullParser::run`].
+/// The return value decides whether reading continues, skips the current level or stops.
+pub trait DossEventHandler {
+    fn on_event(&mut self, event: TypedStreamEvent) -> Result<TypedStreamEventReturn, TypedStreamEventError>;
+}
+
+impl<F: FnMut(TypedStreamEvent) -> Result<TypedStreamEventReturn, TypedStreamEventError>> DossEventHandler for F {
+    fn on_event(&mut self, event: TypedStreamEvent) -> Result<TypedStreamEventReturn, TypedStreamEventError> {
+        self(event)
+    }
+}
+
+impl DossEventHandler for TypeStream2OrderedMultiDictProcessor {
+    fn on_event(&mut self, event: TypedStreamEvent) -> Result<TypedStreamEventReturn, TypedStreamEventError> {
+        self.process(event)
+    }
+}
+
+/// The future returned by [`DossAsyncEventHandler::on_event`]
+pub type DossEventHandlerFuture<'a> = Pin<Box<dyn Future<Output = Result<TypedStreamEventReturn, TypedStreamEventError>> + 'a>>;
+
+/// Receives the events of a DOSS stream and may await before deciding, see [`DossAsyncParser::run_async`]
+pub trait DossAsyncEventHandler {
+    fn on_event(&mut self, event: TypedStreamEvent) -> DossEventHandlerFuture<'_>;
+}
+
+//true if the handler stopped reading
+fn apply(core: &mut ParserCore, decision: Result<TypedStreamEventReturn, TypedStreamEventError>) -> Result<bool, DossError> {
+    match decision {
+        Ok(TypedStreamEventReturn::CONTINUE) => Ok(false),
+        Ok(TypedStreamEventReturn::SKIP) => {
+            core.skip();
+            Ok(false)
+        }
+        Ok(TypedStreamEventReturn::STOP) => Ok(true),
+        Ok(TypedStreamEventReturn::ERROR) => Err(DossError::HandlerStopped),
+        Err(e) => Err(DossError::Handler(e)),
+    }
+}
+
+/// Pulls the events of a DOSS stream from a [`Read`].
+///
+/// As an iterator it returns the events as [`TypedStreamEvent`]s, settings, imports, stacks and file
+/// starts are applied without an event of their own. [`next_doss_event`](Self::next_doss_event) returns
+/// all of them. Call [`skip_level`](Self::skip_level) after an event to drop the rest of its level.
+#[derive(Debug)]
+pub struct DossPullParser<R> {
+    reader: R,
+    core: ParserCore,
+    done: bool,
+}
+
+impl<R: Read> DossPullParser<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_dictionary(reader, DossDictionary::new())
+    }
+
+    /// Use a dictionary with custom limits or predefined entries
+    pub fn with_dictionary(reader: R, dict: DossDictionary) -> Self {
+        DossPullParser { reader, core: ParserCore::new(dict), done: false }
+    }
+
+    /// Predefined dictionaries for imports, see [`DossResolver::set_registry`]
+    pub fn set_registry(&mut self, registry: Arc<DossDictionaryRegistry>) {
+        self.core.resolver_mut().set_registry(registry);
+    }
+
+    /// See [`DossResolver::set_limits`]
+    pub fn set_limits(&mut self, limits: DossLimits) {
+        self.core.resolver_mut().set_limits(limits);
+    }
+
+    pub fn dictionary(&self) -> &DossDictionary {
+        self.core.resolver().dictionary()
+    }
+
+    pub fn settings(&self) -> &DossSettings {
+        self.core.resolver().settings()
+    }
+
+    /// Bytes of the input decoded or jumped over so far, the offset in the stream for parsers opened at an index entry
+    pub fn position(&self) -> u64 {
+        self.core.position()
+    }
+
+    /// Drops the content of the block, array or type just started or the rest of the current one,
+    /// see [`DossReaderCallbackReturn::Skip`](crate::DossReaderCallbackReturn::Skip)
+    pub fn skip_level(&mut self) {
+        self.core.skip();
+    }
+
+    /// Returns the next event or None at the end of a complete stream
+    pub fn next_doss_event(&mut self) -> Result<Option<DossEvent>, DossError> {
+        if self.done {
+            return Ok(None);
+        }
+        let next = self.read_next();
+        self.done = !matches!(next, Ok(Some(_)));
+        next
+    }
+
+    /// Returns the next event that has a [`TypedStreamEvent`] or None at the end of a complete stream
+    pub fn next_event(&mut self) -> Result<Option<TypedStreamEvent>, DossError> {
+        while let Some(event) = self.next_doss_event()? {
+            if let Some(event) = event.to_typed_stream_event() {
+                return Ok(Some(event));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Hands all events to `handler`, framed by INIT and FINISH unless the handler stopped before
+    pub fn run(&mut self, handler: &mut dyn DossEventHandler) -> Result<(), DossError> {
+        if apply(&mut self.core, handler.on_event(TypedStreamEvent::INIT))? {
+            return Ok(());
+        }
+        while let Some(event) = self.next_event()? {
+            if apply(&mut self.core, handler.on_event(event))? {
+                return Ok(());
+            }
+        }
+        apply(&mut self.core, handler.on_event(TypedStreamEvent::FINISH))?;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn read_next(&mut self) -> Result<Option<DossEvent>, DossError> {
+        loop {
+            match self.core.next()? {
+                Step::Event(event) => return Ok(Some(event)),
+                Step::Skip(n) => {
+                    let skipped = std::io::copy(&mut (&mut self.reader).take(n as u64), &mut std::io::sink())?;
+                    if skipped == 0 {
+                        return Err(DossError::UnexpectedEof);
+                    }
+                    self.core.skipped(skipped as usize);
+                }
+                Step::NeedData => {
+                    if self.fill()? == 0 {
+                        self.core.finish()?;
+                        return Ok(None);
+                    }
+                }
+            }
+        }
+    }
+
+    fn fill(&mut self) -> Result<usize, DossError> {
+        let buffer = self.core.buffer_mut();
+        let len = buffer.len();
+        buffer.resize(len + CHUNK_SIZE, 0);
+        let read = loop {
+            match self.reader.read(&mut buffer[len..]) {
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                read => break read,
+            }
+        };
+        buffer.truncate(len + *read.as_ref().unwrap_or(&0));
+        Ok(read?)
+    }
+}
+
+impl<R: Read + Seek> DossPullParser<R> {
+    /// Continues decoding a stream at an index entry. The events of the enclosing levels are not repeated,
+    /// their ends follow once the entry's level is complete
+    pub fn at_entry(mut reader: R, entry: &DossIndexEntry) -> Result<Self, DossError> {
+        reader.seek(SeekFrom::Start(entry.offset))?;
+        let mut parser = Self::new(reader);
+        parser.core.restore(entry)?;
+        Ok(parser)
+    }
+}
+
+impl<R: Read> Iterator for DossPullParser<R> {
+    type Item = Result<TypedStreamEvent, DossError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+/// Reads the events of a DOSS stream from an [`AsyncRead`], the async counterpart of [`DossPullParser`].
+/// Events can be pulled with [`next_event`](Self::next_event) or pushed to a handler with [`run`](Self::run).
+#[derive(Debug)]
+pub struct DossAsyncParser<R> {
+    reader: R,
+    core: ParserCore,
+    done: bool,
+}
+
+impl<R: AsyncRead + Unpin> DossAsyncParser<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_dictionary(reader, DossDictionary::new())
+    }
+
+    /// Use a dictionary with custom limits or predefined entries
+    pub fn with_dictionary(reader: R, dict: DossDictionary) -> Self {
+        DossAsyncParser { reader, core: ParserCore::new(dict), done: false }
+    }
+
+    /// Predefined dictionaries for imports, see [`DossResolver::set_registry`]
+    pub fn set_registry(&mut self, registry: Arc<DossDictionaryRegistry>) {
+        self.core.resolver_mut().set_registry(registry);
+    }
+
+    /// See [`DossResolver::set_limits`]
+    pub fn set_limits(&mut self, limits: DossLimits) {
+        self.core.resolver_mut().set_limits(limits);
+    }
+
+    pub fn dictionary(&self) -> &DossDictionary {
+        self.core.resolver().dictionary()
+    }
+
+    pub fn settings(&self) -> &DossSettings {
+        self.core.resolver().settings()
+    }
+
+    /// See [`DossPullParser::position`]
+    pub fn position(&self) -> u64 {
+        self.core.position()
+    }
+
+    /// See [`DossPullParser::skip_level`]
+    pub fn skip_level(&mut self) {
+        self.core.skip();
+    }
+
+    /// See [`DossPullParser::next_doss_event`]
+    pub async fn next_doss_event(&mut self) -> Result<Option<DossEvent>, DossError> {
+        if self.done {
+            return Ok(None);
+        }
+        let next = self.read_next().await;
+        self.done = !matches!(next, Ok(Some(_)));
+        next
+    }
+
+    /// See [`DossPullParser::next_event`]
+    pub async fn next_event(&mut self) -> Result<Option<TypedStreamEvent>, DossError> {
+        while let Some(event) = self.next_doss_event().await? {
+            if let Some(event) = event.to_typed_stream_event() {
+                return Ok(Some(event));
+            }
+        }
+        Ok(None)
+    }
+
+    /// See [`DossPullParser::run`]
+    pub async fn run(&mut self, handler: &mut dyn DossEventHandler) -> Result<(), DossError> {
+        if apply(&mut self.core, handler.on_event(TypedStreamEvent::INIT))? {
+            return Ok(());
+        }
+        while let Some(event) = self.next_event().await? {
+            if apply(&mut self.core, handler.on_event(event))? {
+                return Ok(());
+            }
+        }
+        apply(&mut self.core, handler.on_event(TypedStreamEvent::FINISH))?;
+        Ok(())
+    }
+
+    /// Like [`run`](Self::run) with a handler that awaits
+    pub async fn run_async(&mut self, handler: &mut dyn DossAsyncEventHandler) -> Result<(), DossError> {
+        if apply(&mut self.core, handler.on_event(TypedStreamEvent::INIT).await)? {
+            return Ok(());
+        }
+        while let Some(event) = self.next_event().await? {
+            if apply(&mut self.core, handler.on_event(event).await)? {
+                return Ok(());
+            }
+        }
+        apply(&mut self.core, handler.on_event(TypedStreamEvent::FINISH).await)?;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    async fn read_next(&mut self) -> Result<Option<DossEvent>, DossError> {
+        loop {
+            match self.core.next()? {
+                Step::Event(event) => return Ok(Some(event)),
+                Step::Skip(n) => {
+                    let skipped = tokio::io::copy(&mut (&mut self.reader).take(n as u64), &mut tokio::io::sink()).await?;
+                    if skipped == 0 {
+                        return Err(DossError::UnexpectedEof);
+                    }
+                    self.core.skipped(skipped as usize);
+                }
+                Step::NeedData => {
+                    let buffer = self.core.buffer_mut();
+                    buffer.reserve(CHUNK_SIZE);
+                    if self.reader.read_buf(buffer).await? == 0 {
+                        self.core.finish()?;
+                        return Ok(None);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin> DossAsyncParser<R> {
+    /// See [`DossPullParser::at_entry`]
+    pub async fn at_entry(mut reader: R, entry: &DossIndexEntry) -> Result<Self, DossError> {
+        reader.seek(SeekFrom::Start(entry.offset)).await?;
+        let mut parser = Self::new(reader);
+        parser.core.restore(entry)?;
+        Ok(parser)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use dataflowgrid_commons::typedstream::TypedStreamElement;
+
+    use super::*;
+    use crate::serializer::{DossSerializer, DossSerializerOptions};
+
+    fn s(v: &str) -> DossEvent {
+        DossEvent::String(v.to_string())
+    }
+
+    //{"skip": [...100 values], "keep": "me"}
+    fn document() -> Vec<u8> {
+        let mut events = vec![DossEvent::BlockStart, s("skip"), DossEvent::ArrayStart];
+        events.extend((0..100).map(|i| s(&format!("value {i}"))));
+        events.extend([DossEvent::ArrayEnd, s("keep"), s("me"), DossEvent::BlockEnd]);
+        let options = DossSerializerOptions { max_dict_entries: 0, skip_threshold: Some(64), ..DossSerializerOptions::default() };
+        let mut serializer = DossSerializer::seekable(Cursor::new(Vec::new()), options);
+        for e in &events {
+            serializer.write_doss_event(e).unwrap();
+        }
+        serializer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_pull_and_skip() {
+        let serialized = document();
+        let mut parser = DossPullParser::new(serialized.as_slice());
+        let mut events = Vec::new();
+        while let Some(event) = parser.next_event().unwrap() {
+            if matches!(event, TypedStreamEvent::STARTARRAY) {
+                parser.skip_level();
+            }
+            events.push(format!("{event:?}"));
+        }
+        assert_eq!(events, ["STARTOBJECT", "STRING(\"skip\")", "STARTARRAY", "ENDARRAY", "STRING(\"keep\")", "STRING(\"me\")", "ENDOBJECT"]);
+        assert_eq!(DossPullParser::new(serialized.as_slice()).count(), 107);
+
+        let mut parser = DossPullParser::new(&serialized[..serialized.len() - 1]);
+        assert!(matches!(parser.by_ref().last(), Some(Err(DossError::UnexpectedEof))));
+        assert!(parser.next().is_none());
+    }
+
+    #[test]
+    fn test_pull_with_dict() {
+        //"Hello World with Dict" of docs/examples.md
+        let serialized = [10_u8, 21, 7, 5, b'h', b'e', b'l', b'l', b'o', 7, 5, b'w', b'o', b'r', b'l', b'd', 7, 3, b's', b'a', b'y', 9, 0, 11];
+        let mut parser = DossPullParser::new(serialized.as_slice());
+        let mut events = Vec::new();
+        while let Some(event) = parser.next_doss_event().unwrap() {
+            events.push(event);
+        }
+        assert_eq!(events, vec![DossEvent::BlockStart, s("hello"), s("world"), s("say"), s("hello"), DossEvent::BlockEnd]);
+        assert_eq!(parser.dictionary().len(), 1);
+    }
+
+    #[test]
+    fn test_run_into_commons_processor() {
+        let serialized = document();
+        let mut processor = TypeStream2OrderedMultiDictProcessor::new();
+        DossPullParser::new(serialized.as_slice()).run(&mut processor).unwrap();
+        let TypedStreamElement::Object(object) = processor.get_result().unwrap() else {
+            panic!("object expected");
+        };
+        assert_eq!(object.length(), 2);
+
+        //a handler that is used as trait object, stops at the second string and fails at the first array
+        let mut strings = 0;
+        let mut stopper = |event: TypedStreamEvent| {
+            strings += matches!(event, TypedStreamEvent::STRING(_)) as usize;
+            Ok(if strings == 2 { TypedStreamEventReturn::STOP } else { TypedStreamEventReturn::CONTINUE })
+        };
+        let handler: &mut dyn DossEventHandler = &mut stopper;
+        DossPullParser::new(serialized.as_slice()).run(handler).unwrap();
+        assert_eq!(strings, 2);
+        let mut failing = |event: TypedStreamEvent| match event {
+            TypedStreamEvent::STARTARRAY => Err(TypedStreamEventError::InvalidEvent),
+            _ => Ok(TypedStreamEventReturn::CONTINUE),
+        };
+        let r = DossPullParser::new(serialized.as_slice()).run(&mut failing);
+        assert!(matches!(r, Err(DossError::Handler(TypedStreamEventError::InvalidEvent))));
+    }
+
+    struct AsyncCollector {
+        events: Vec<String>,
+    }
+
+    impl DossAsyncEventHandler for AsyncCollector {
+        fn on_event(&mut self, event: TypedStreamEvent) -> DossEventHandlerFuture<'_> {
+            Box::pin(async move {
+                tokio::task::yield_now().await;
+                let skip = matches!(event, TypedStreamEvent::STARTARRAY);
+                self.events.push(format!("{event:?}"));
+                Ok(if skip { TypedStreamEventReturn::SKIP } else { TypedStreamEventReturn::CONTINUE })
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_async() {
+        let serialized = document();
+        //a tiny reader makes events cross read boundaries
+        let reader = tokio::io::BufReader::with_capacity(3, serialized.as_slice());
+        let mut parser = DossAsyncParser::new(reader);
+        let mut strings = 0;
+        while let Some(event) = parser.next_event().await.unwrap() {
+            strings += matches!(event, TypedStreamEvent::STRING(_)) as usize;
+        }
+        assert_eq!(strings, 103);
+
+        let mut collector = AsyncCollector { events: Vec::new() };
+        let handler: &mut dyn DossAsyncEventHandler = &mut collector;
+        DossAsyncParser::new(serialized.as_slice()).run_async(handler).await.unwrap();
+        assert_eq!(collector.events, [
+            "INIT", "STARTOBJECT", "STRING(\"skip\")", "STARTARRAY", "ENDARRAY", "STRING(\"keep\")", "STRING(\"me\")", "ENDOBJECT", "FINISH",
+        ]);
+
+        let r = DossAsyncParser::new([7_u8, 5, b'h', b'e'].as_slice()).next_event().await;
+        assert!(matches!(r, Err(DossError::UnexpectedEof)));
+    }
+}
@@ -0,0 +1,330 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::error::Error;
+use std::sync::Arc;
+
+use dataflowgrid_commons::readers::reader::{Readable, ReaderError};
+
+use crate::dictionary::DossDictionary;
+use crate::error::DossError;
+use crate::limits::DossLimits;
+use crate::parser::{ParserCore, Step};
+use crate::registry::DossDictionaryRegistry;
+use crate::resolver::DossEvent;
+use crate::settings::DossSettings;
+
+#[derive(Debug)]
+pub enum DossReaderError {
+    DossError(DossError),
+    CallbackError(Box<dyn Error>),
+    ReaderError(ReaderError),
+}
+
+impl From<DossError> for DossReaderError {
+    fn from(e: DossError) -> Self {
+        DossReaderError::DossError(e)
+    }
+}
+
+/// Why [`DossReader::pushdata`] returned
+#[derive(Debug, PartialEq)]
+pub enum DossReaderPushResult {
+    EndOfData, //the readable ended, call finish next
+    NeedMoreData, //the readable has no data right now, call pushdata again once more data arrived
+    Stopped, //a callback returned StopOk
+}
+
+#[derive(Debug)]
+pub enum DossReaderCallbackReturn {
+    Continue,
+    Skip, //skip the content of the block, array or type just started or the rest of the current one
+    StopOk, //stop reading and return OK
+    StopErr(Box<dyn Error>), //stop reading and return error
+}
+
+pub trait DossReaderCallback {
+    fn on_doss_event(&mut self, event: DossEvent) -> DossReaderCallbackReturn;
+}
+
+/// Reads a DOSS stream from a [`Readable`] and hands the resolved events to a callback.
+///
+/// When the callback asks to skip, the end event of the skipped level is still delivered. The reader
+/// jumps ahead if it finds a skip opcode of that level, otherwise it keeps decoding without calling the
+/// callback so dictionary changes inside the skipped part are applied.
+pub struct DossReader<'a> {
+    callback: &'a mut dyn DossReaderCallback,
+    core: ParserCore,
+    stopped: bool,
+}
+
+impl<'a> std::fmt::Debug for DossReader<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DossReader")
+            .field("core", &self.core)
+            .field("stopped", &self.stopped)
+            .finish()
+    }
+}
+
+impl<'a> DossReader<'a> {
+    pub fn new(callback: &'a mut dyn DossReaderCallback) -> DossReader<'a> {
+        Self::with_dictionary(callback, DossDictionary::new())
+    }
+
+    /// Use a dictionary with custom limits or predefined entries
+    pub fn with_dictionary(callback: &'a mut dyn DossReaderCallback, dict: DossDictionary) -> DossReader<'a> {
+        DossReader {
+            callback,
+            core: ParserCore::new(dict),
+            stopped: false,
+        }
+    }
+
+    pub fn dictionary(&self) -> &DossDictionary {
+        self.core.resolver().dictionary()
+    }
+
+    /// Settings of the stream read so far, e.g. to append to it with [`DossSerializer::appending`](crate::DossSerializer::appending)
+    pub fn settings(&self) -> &DossSettings {
+        self.core.resolver().settings()
+    }
+
+    /// Predefined dictionaries for imports, see [`DossResolver::set_registry`](crate::DossResolver::set_registry)
+    pub fn set_registry(&mut self, registry: Arc<DossDictionaryRegistry>) {
+        self.core.resolver_mut().set_registry(registry);
+    }
+
+    /// See [`DossResolver::set_limits`](crate::DossResolver::set_limits)
+    pub fn set_limits(&mut self, limits: DossLimits) {
+        self.core.resolver_mut().set_limits(limits);
+    }
+
+    /// Reads all available data, can be called again when more data arrived
+    pub fn pushdata(&mut self, data: &mut dyn Readable<u8>) -> Result<DossReaderPushResult, DossReaderError> {
+        loop {
+            if self.stopped {
+                return Ok(DossReaderPushResult::Stopped);
+            }
+            match self.core.next()? {
+                Step::Event(event) => {
+                    self.process(event)?;
+                    continue;
+                }
+                Step::Skip(n) => match data.skip(n) {
+                    Ok(0) => {} //nothing to skip right now, find out why by reading
+                    Ok(skipped) => {
+                        self.core.skipped(skipped);
+                        continue;
+                    }
+                    Err(e) => return Self::read_error(e),
+                },
+                Step::NeedData => {}
+            }
+            match data.read_chunk() {
+                Ok(chunk) => self.core.buffer_mut().extend_from_slice(chunk.as_slice()),
+                Err(e) => return Self::read_error(e),
+            }
+        }
+    }
+
+    /// Checks that the stream did not end inside an item
+    pub fn finish(&mut self) -> Result<(), DossReaderError> {
+        if self.stopped {
+            return Ok(());
+        }
+        Ok(self.core.finish()?)
+    }
+
+    fn read_error(e: ReaderError) -> Result<DossReaderPushResult, DossReaderError> {
+        match e {
+            ReaderError::EOF => Ok(DossReaderPushResult::EndOfData),
+            ReaderError::NeedMoreData => Ok(DossReaderPushResult::NeedMoreData),
+            e => Err(DossReaderError::ReaderError(e)),
+        }
+    }
+
+    fn process(&mut self, event: DossEvent) -> Result<(), DossReaderError> {
+        match self.callback.on_doss_event(event) {
+            DossReaderCallbackReturn::Continue => {}
+            DossReaderCallbackReturn::Skip => self.core.skip(),
+            DossReaderCallbackReturn::StopOk => self.stopped = true,
+            DossReaderCallbackReturn::StopErr(e) => {
+                self.stopped = true;
+                return Err(DossReaderError::CallbackError(e));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use dataflowgrid_commons::cursedbuffer::CursedBuffer;
+    use dataflowgrid_commons::readers::reader::{CursedBufferReadable, IteratorReadable, ReadableChunk};
+
+    use super::*;
+    use crate::serializer::{DossSerializer, DossSerializerOptions};
+
+    struct Collector {
+        events: Vec<DossEvent>,
+        skip: Box<dyn FnMut(&DossEvent) -> bool>,
+    }
+
+    impl DossReaderCallback for Collector {
+        fn on_doss_event(&mut self, event: DossEvent) -> DossReaderCallbackReturn {
+            let skip = (self.skip)(&event);
+            self.events.push(event);
+            match skip {
+                true => DossReaderCallbackReturn::Skip,
+                false => DossReaderCallbackReturn::Continue,
+            }
+        }
+    }
+
+    /// Counts the bytes the reader jumped over
+    struct CountingReadable {
+        inner: IteratorReadable<u8>,
+        skipped: usize,
+    }
+
+    impl Readable<u8> for CountingReadable {
+        fn read_next(&mut self) -> Result<u8, ReaderError> {
+            self.inner.read_next()
+        }
+
+        fn skip(&mut self, skipped: usize) -> Result<usize, ReaderError> {
+            let skipped = self.inner.skip(skipped)?;
+            self.skipped += skipped;
+            Ok(skipped)
+        }
+
+        fn read_chunk(&mut self) -> Result<ReadableChunk<u8>, ReaderError> {
+            self.inner.read_chunk()
+        }
+
+        fn pos(&self) -> Option<usize> {
+            self.inner.pos()
+        }
+
+        fn len(&self) -> Option<usize> {
+            self.inner.len()
+        }
+    }
+
+    fn s(v: &str) -> DossEvent {
+        DossEvent::String(v.to_string())
+    }
+
+    fn serialize(events: &[DossEvent], options: DossSerializerOptions) -> Vec<u8> {
+        let mut serializer = DossSerializer::seekable(Cursor::new(Vec::new()), options);
+        for e in events {
+            serializer.write_doss_event(e).unwrap();
+        }
+        serializer.finish().unwrap().into_inner()
+    }
+
+    fn read(serialized: Vec<u8>, skip: impl FnMut(&DossEvent) -> bool + 'static) -> (Vec<DossEvent>, usize) {
+        let mut collector = Collector { events: Vec::new(), skip: Box::new(skip) };
+        let mut readable = CountingReadable {
+            inner: IteratorReadable::with_chunk_size(Box::new(serialized.into_iter()), 7),
+            skipped: 0,
+        };
+        let mut reader = DossReader::new(&mut collector);
+        assert_eq!(reader.pushdata(&mut readable).unwrap(), DossReaderPushResult::EndOfData);
+        reader.finish().unwrap();
+        (collector.events, readable.skipped)
+    }
+
+    //{"skip": [...100 values], "keep": "me"}
+    fn document() -> Vec<DossEvent> {
+        let mut events = vec![DossEvent::BlockStart, s("skip"), DossEvent::ArrayStart];
+        events.extend((0..100).map(|i| s(&format!("value {i}"))));
+        events.extend([DossEvent::ArrayEnd, s("keep"), s("me"), DossEvent::BlockEnd]);
+        events
+    }
+
+    #[test]
+    fn test_skip_with_skip_opcode() {
+        let options = DossSerializerOptions { max_dict_entries: 0, skip_threshold: Some(64), ..DossSerializerOptions::default() };
+        let serialized = serialize(&document(), options);
+        let (events, skipped) = read(serialized, |e| *e == DossEvent::ArrayStart);
+        assert_eq!(events, vec![
+            DossEvent::BlockStart, s("skip"), DossEvent::ArrayStart, DossEvent::ArrayEnd, s("keep"), s("me"), DossEvent::BlockEnd,
+        ]);
+        //the array is jumped over except for the bytes already buffered
+        assert!(skipped > 600, "{skipped}");
+    }
+
+    #[test]
+    fn test_skip_rest_of_level() {
+        let options = DossSerializerOptions { max_dict_entries: 0, skip_threshold: None, ..DossSerializerOptions::default() };
+        let serialized = serialize(&document(), options);
+        let (events, skipped) = read(serialized, |e| *e == s("value 1"));
+        assert_eq!(events, vec![
+            DossEvent::BlockStart, s("skip"), DossEvent::ArrayStart, s("value 0"), s("value 1"), DossEvent::ArrayEnd,
+            s("keep"), s("me"), DossEvent::BlockEnd,
+        ]);
+        assert_eq!(skipped, 0);
+    }
+
+    #[test]
+    fn test_skip_applies_dictionary_changes() {
+        //values are stored in the skipped array and referenced afterwards
+        let mut doc = vec![DossEvent::ArrayStart, DossEvent::ArrayStart];
+        doc.extend((0..2).flat_map(|_| (0..5).map(|i| s(&format!("repeated {i}")))));
+        doc.extend([DossEvent::ArrayEnd, DossEvent::ArrayStart]);
+        doc.extend((0..5).map(|i| s(&format!("repeated {i}"))));
+        doc.extend([DossEvent::ArrayEnd, DossEvent::ArrayEnd]);
+        let options = DossSerializerOptions { emit_dict_hint: false, skip_threshold: Some(16), ..DossSerializerOptions::default() };
+        let serialized = serialize(&doc, options);
+
+        //skip the first inner array
+        let mut starts = 0;
+        let (events, skipped) = read(serialized, move |e| {
+            starts += (*e == DossEvent::ArrayStart) as usize;
+            *e == DossEvent::ArrayStart && starts == 2
+        });
+        let mut expected = vec![DossEvent::ArrayStart, DossEvent::ArrayStart, DossEvent::ArrayEnd, DossEvent::ArrayStart];
+        expected.extend((0..5).map(|i| s(&format!("repeated {i}"))));
+        expected.extend([DossEvent::ArrayEnd, DossEvent::ArrayEnd]);
+        assert_eq!(events, expected);
+        //the skipped array changed the dictionary, so it was not jumped over
+        assert_eq!(skipped, 0);
+    }
+
+    #[test]
+    fn test_stop_and_incremental() {
+        let serialized = serialize(&document(), DossSerializerOptions::default());
+        let buffer = CursedBuffer::<u8>::new();
+        let mut readable = CursedBufferReadable::new(buffer.reader(0));
+        let mut collector = Collector { events: Vec::new(), skip: Box::new(|_| false) };
+        let mut reader = DossReader::new(&mut collector);
+        let (a, b) = serialized.split_at(serialized.len() / 2);
+        buffer.write(a.to_vec()).unwrap();
+        assert_eq!(reader.pushdata(&mut readable).unwrap(), DossReaderPushResult::NeedMoreData);
+        buffer.write(b.to_vec()).unwrap();
+        buffer.close();
+        assert_eq!(reader.pushdata(&mut readable).unwrap(), DossReaderPushResult::EndOfData);
+        reader.finish().unwrap();
+        assert_eq!(collector.events.len(), 108); //the document and the dict size hint
+
+        struct Stopper(usize);
+        impl DossReaderCallback for Stopper {
+            fn on_doss_event(&mut self, _event: DossEvent) -> DossReaderCallbackReturn {
+                self.0 += 1;
+                match self.0 {
+                    3 => DossReaderCallbackReturn::StopOk,
+                    _ => DossReaderCallbackReturn::Continue,
+                }
+            }
+        }
+        let mut stopper = Stopper(0);
+        let mut reader = DossReader::new(&mut stopper);
+        let mut readable = IteratorReadable::new(Box::new(serialized.into_iter()));
+        assert_eq!(reader.pushdata(&mut readable).unwrap(), DossReaderPushResult::Stopped);
+        reader.finish().unwrap();
+        assert_eq!(stopper.0, 3);
+    }
+}
@@ -0,0 +1,425 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+
+use streamablejson::StreamableJSONEntry;
+
+use crate::error::DossError;
+use crate::resolver::{DossEvent, DossItem, DossResolver};
+use crate::serializer::{DossSerializer, DossSerializerOptions};
+use crate::varint;
+
+/// A named dictionary known to serializer and deserializer in advance, imported with opcode 40.
+///
+/// The content hash covers the entries, so a stream written with a different dictionary of the same
+/// name and version is rejected instead of being decoded with the wrong values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DossPredefinedDictionary {
+    name: String,
+    version: u64,
+    entries: Vec<DossItem>,
+    hash: u64,
+}
+
+impl DossPredefinedDictionary {
+    /// Every entry must be exactly one item: a scalar or a balanced block, array or type
+    pub fn new(name: impl Into<String>, version: u64, entries: Vec<DossItem>) -> Result<Self, DossError> {
+        let name = name.into();
+        let mut content = Vec::new();
+        for entry in &entries {
+            check_item(entry)?;
+            content.extend(plain_bytes(std::slice::from_ref(entry))?);
+        }
+        Ok(DossPredefinedDictionary { name, version, entries, hash: fnv1a(&content) })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn entries(&self) -> &[DossItem] {
+        &self.entries
+    }
+
+    /// FNV-1a hash of the entries written as plain DOSS
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// The item following opcode 40: `[name, version, hash]`
+    pub fn import_item(&self) -> DossItem {
+        vec![
+            DossEvent::ArrayStart,
+            DossEvent::String(self.name.clone()),
+            DossEvent::UInt(self.version),
+            DossEvent::UInt(self.hash),
+            DossEvent::ArrayEnd,
+        ]
+    }
+
+    /// Reads a dictionary file, a DOSS stream with the single item `[name, version, [entry, ...]]`
+    pub fn from_bytes(serialized: &[u8]) -> Result<Self, DossError> {
+        let mut resolver = DossResolver::new();
+        let mut events = Vec::new();
+        let mut pos = 0;
+        while pos < serialized.len() {
+            let (event, used) = resolver.decode(&serialized[pos..])?
+                .ok_or(DossError::UnexpectedEof)?;
+            pos += used;
+            resolver.push(event, &mut events)?;
+        }
+        resolver.finish()?;
+        //hints and settings of the file don't belong to the dictionary
+        events.retain(|e| !matches!(e, DossEvent::Hint { .. } | DossEvent::Config { .. }));
+
+        let invalid = || DossError::InvalidDictionary(String::from("expected [name, version, [entries]]"));
+        let [DossEvent::ArrayStart, DossEvent::String(name), version, DossEvent::ArrayStart, content @ .., DossEvent::ArrayEnd, DossEvent::ArrayEnd] = events.as_slice() else {
+            return Err(invalid());
+        };
+        let version = match version {
+            DossEvent::UInt(v) => *v,
+            DossEvent::Int(v) => u64::try_from(*v).map_err(|_| invalid())?,
+            _ => return Err(invalid()),
+        };
+        let mut entries = Vec::new();
+        let mut depth = 0;
+        for event in content {
+            if depth == 0 {
+                entries.push(Vec::new());
+            }
+            depth += event.depth_change();
+            entries.last_mut().unwrap().push(event.clone()); //pushed above for depth 0
+        }
+        Self::new(name.clone(), version, entries)
+    }
+
+    /// Writes the dictionary file read by [`DossPredefinedDictionary::from_bytes`]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, DossError> {
+        let mut item = vec![DossEvent::ArrayStart, DossEvent::String(self.name.clone()), DossEvent::UInt(self.version), DossEvent::ArrayStart];
+        item.extend(self.entries.iter().flatten().cloned());
+        item.extend([DossEvent::ArrayEnd, DossEvent::ArrayEnd]);
+        plain_bytes(&[item])
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, DossError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), DossError> {
+        Ok(std::fs::write(path, self.to_bytes()?)?)
+    }
+}
+
+/// Predefined dictionaries by name and version, shared by serializers and readers
+#[derive(Debug, Clone, Default)]
+pub struct DossDictionaryRegistry {
+    dictionaries: HashMap<String, BTreeMap<u64, Arc<DossPredefinedDictionary>>>,
+}
+
+impl DossDictionaryRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a dictionary, a registered dictionary with the same name and version must have the same content
+    pub fn register(&mut self, dict: DossPredefinedDictionary) -> Result<Arc<DossPredefinedDictionary>, DossError> {
+        let versions = self.dictionaries.entry(dict.name.clone()).or_default();
+        match versions.get(&dict.version) {
+            Some(known) if known.hash != dict.hash => Err(DossError::DictionaryMismatch(dict.name)),
+            Some(known) => Ok(known.clone()),
+            None => {
+                let dict = Arc::new(dict);
+                versions.insert(dict.version, dict.clone());
+                Ok(dict)
+            }
+        }
+    }
+
+    /// Loads and registers a dictionary file
+    pub fn load_file(&mut self, path: impl AsRef<Path>) -> Result<Arc<DossPredefinedDictionary>, DossError> {
+        self.register(DossPredefinedDictionary::load(path)?)
+    }
+
+    pub fn get(&self, name: &str, version: u64) -> Option<Arc<DossPredefinedDictionary>> {
+        self.dictionaries.get(name)?.get(&version).cloned()
+    }
+
+    /// The highest registered version
+    pub fn latest(&self, name: &str) -> Option<Arc<DossPredefinedDictionary>> {
+        self.dictionaries.get(name)?.last_key_value().map(|(_, d)| d.clone())
+    }
+
+    /// Finds the dictionary for the item of opcode 40. A plain name selects the latest version,
+    /// `[name, version, hash]` must match exactly
+    pub fn resolve_import(&self, item: &[DossEvent]) -> Result<Arc<DossPredefinedDictionary>, DossError> {
+        match item {
+            [DossEvent::String(name)] => self.latest(name).ok_or_else(|| DossError::UnknownDictionary(name.clone())),
+            [DossEvent::ArrayStart, DossEvent::String(name), DossEvent::UInt(version), DossEvent::UInt(hash), DossEvent::ArrayEnd] => {
+                let dict = self.get(name, *version).ok_or_else(|| DossError::UnknownDictionary(format!("{name} version {version}")))?;
+                match dict.hash == *hash {
+                    true => Ok(dict),
+                    false => Err(DossError::DictionaryMismatch(name.clone())),
+                }
+            }
+            _ => Err(DossError::InvalidDictionary(format!("invalid import {item:?}"))),
+        }
+    }
+}
+
+/// Builds a predefined dictionary from sample documents.
+///
+/// Strings, binaries and type names are scored by the bytes a reference saves over all documents
+/// containing them. Values repeated within a single document are left to the serializer's own dictionary,
+/// so only the number of documents counts.
+#[derive(Debug, Default)]
+pub struct DossDictionaryTrainer {
+    documents: usize,
+    values: HashMap<Vec<u8>, (DossEvent, usize)>, //encoded value -> value and number of documents
+}
+
+impl DossDictionaryTrainer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of documents added so far
+    pub fn documents(&self) -> usize {
+        self.documents
+    }
+
+    pub fn add_entry(&mut self, entry: &StreamableJSONEntry) {
+        let mut values = Vec::new();
+        collect_entry(entry, &mut values);
+        self.add_values(values);
+    }
+
+    /// Adds a document given as resolved events
+    pub fn add_events(&mut self, events: &[DossEvent]) {
+        self.add_values(events.iter().filter_map(|e| match e {
+            DossEvent::String(_) | DossEvent::Binary(_) => Some(e.clone()),
+            DossEvent::TypeStart(name) => Some(DossEvent::String(name.clone())),
+            _ => None,
+        }).collect());
+    }
+
+    /// The best `max_entries` values found in at least `min_documents` documents, most valuable first
+    /// so they get the shortest references
+    pub fn build(&self, name: impl Into<String>, version: u64, max_entries: usize, min_documents: usize) -> Result<DossPredefinedDictionary, DossError> {
+        let mut ranked: Vec<_> = self.values.iter()
+            .filter(|(_, (_, documents))| *documents >= min_documents.max(1))
+            .map(|(encoded, (value, documents))| (documents * encoded.len().saturating_sub(2), encoded, value))
+            .filter(|(score, _, _)| *score > 0)
+            .collect();
+        //ties are broken by the encoded value, so training is deterministic
+        ranked.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+        let entries = ranked.into_iter()
+            .map(|(_, encoded, value)| (encoded.len(), value))
+            .enumerate()
+            .take_while(|(index, _)| *index < max_entries)
+            .filter(|(index, (len, _))| *len > 1 + varint::unsigned_len(*index as u64))
+            .map(|(_, (_, value))| vec![value.clone()])
+            .collect();
+        DossPredefinedDictionary::new(name, version, entries)
+    }
+
+    fn add_values(&mut self, values: Vec<DossEvent>) {
+        self.documents += 1;
+        let mut seen = HashSet::new();
+        for value in values {
+            let encoded = plain_bytes(std::slice::from_ref(&vec![value.clone()])).expect("scalars can be written");
+            if seen.insert(encoded.clone()) {
+                self.values.entry(encoded).or_insert((value, 0)).1 += 1;
+            }
+        }
+    }
+}
+
+fn collect_entry(entry: &StreamableJSONEntry, out: &mut Vec<DossEvent>) {
+    match entry {
+        StreamableJSONEntry::Object(bag) => {
+            for (key, value) in bag.iter() {
+                collect_entry(key, out);
+                collect_entry(value, out);
+            }
+        }
+        StreamableJSONEntry::Array(items) => items.iter().for_each(|i| collect_entry(i, out)),
+        StreamableJSONEntry::Type(name, content) => {
+            out.push(DossEvent::String(name.clone()));
+            content.iter().for_each(|i| collect_entry(i, out));
+        }
+        StreamableJSONEntry::String(s) => out.push(DossEvent::String(s.clone())),
+        StreamableJSONEntry::Constant(_) => {}
+    }
+}
+
+/// An entry must be a single value, stream level events have no meaning inside a dictionary
+fn check_item(item: &[DossEvent]) -> Result<(), DossError> {
+    let mut depth = 0;
+    for (i, event) in item.iter().enumerate() {
+        if matches!(event, DossEvent::Config { .. } | DossEvent::Hint { .. } | DossEvent::ImportDict(_)
+            | DossEvent::FileStart(_) | DossEvent::StackStart | DossEvent::StackEnd) {
+            return Err(DossError::InvalidDictionary(format!("{event:?} in entry")));
+        }
+        depth += event.depth_change();
+        if depth < 0 || (depth == 0 && i + 1 < item.len()) {
+            return Err(DossError::InvalidDictionary(String::from("entry is not a single item")));
+        }
+    }
+    match (item.is_empty(), depth) {
+        (false, 0) => Ok(()),
+        _ => Err(DossError::InvalidDictionary(String::from("entry is not a single item"))),
+    }
+}
+
+/// The items without dictionary, hints or skips, the same bytes on every run
+fn plain_bytes(items: &[DossItem]) -> Result<Vec<u8>, DossError> {
+    let mut serializer = DossSerializer::with_options(Vec::new(), DossSerializerOptions {
+        max_dict_entries: 0,
+        emit_dict_hint: false,
+        skip_threshold: None,
+        ..DossSerializerOptions::default()
+    });
+    for event in items.iter().flatten() {
+        serializer.write_doss_event(event)?;
+    }
+    serializer.finish()
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| (hash ^ *b as u64).wrapping_mul(0x100000001b3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::{decode_entries, decode_entries_with_registry};
+    use streamablejson::deserializer::deserialize_orderedbag_from_string;
+
+    fn s(v: &str) -> DossEvent {
+        DossEvent::String(v.to_string())
+    }
+
+    fn hello_world(version: u64, second: &str) -> DossPredefinedDictionary {
+        DossPredefinedDictionary::new("hello_world", version, vec![vec![s("hello")], vec![s(second)]]).unwrap()
+    }
+
+    fn registry(dicts: Vec<DossPredefinedDictionary>) -> Arc<DossDictionaryRegistry> {
+        let mut registry = DossDictionaryRegistry::new();
+        for dict in dicts {
+            registry.register(dict).unwrap();
+        }
+        Arc::new(registry)
+    }
+
+    fn serialize(dict: Option<&DossPredefinedDictionary>, json: &[&str]) -> Vec<u8> {
+        let mut serializer = DossSerializer::with_options(Vec::new(), DossSerializerOptions { emit_dict_hint: false, ..DossSerializerOptions::default() });
+        if let Some(dict) = dict {
+            serializer.import_dictionary(dict).unwrap();
+        }
+        for json in json {
+            serializer.write_entry(&deserialize_orderedbag_from_string(json.to_string()).unwrap()).unwrap();
+        }
+        serializer.finish().unwrap()
+    }
+
+    #[test]
+    fn test_file_roundtrip() {
+        let entries = vec![
+            vec![s("hello")],
+            vec![DossEvent::Binary(vec![1, 2, 3])],
+            vec![DossEvent::BlockStart, s("unit"), s("celsius"), DossEvent::BlockEnd],
+            vec![DossEvent::TypeStart(String::from("date")), s("2025-01-01"), DossEvent::TypeEnd],
+        ];
+        let dict = DossPredefinedDictionary::new("telemetry", 3, entries).unwrap();
+        let loaded = DossPredefinedDictionary::from_bytes(&dict.to_bytes().unwrap()).unwrap();
+        assert_eq!(loaded, dict);
+        assert_eq!(loaded.hash(), dict.hash());
+
+        //the hash depends on the content only
+        assert_eq!(hello_world(1, "world").hash(), hello_world(2, "world").hash());
+        assert_ne!(hello_world(1, "world").hash(), hello_world(1, "there").hash());
+
+        assert!(matches!(DossPredefinedDictionary::new("x", 1, vec![vec![s("a"), s("b")]]), Err(DossError::InvalidDictionary(_))));
+        assert!(matches!(DossPredefinedDictionary::new("x", 1, vec![vec![DossEvent::BlockStart]]), Err(DossError::InvalidDictionary(_))));
+        assert!(matches!(DossPredefinedDictionary::new("x", 1, vec![vec![DossEvent::StackStart]]), Err(DossError::InvalidDictionary(_))));
+        assert!(matches!(DossPredefinedDictionary::from_bytes(&[14]), Err(DossError::InvalidDictionary(_))));
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut registry = DossDictionaryRegistry::new();
+        registry.register(hello_world(1, "world")).unwrap();
+        registry.register(hello_world(2, "there")).unwrap();
+        registry.register(hello_world(1, "world")).unwrap();
+        assert!(matches!(registry.register(hello_world(1, "there")), Err(DossError::DictionaryMismatch(_))));
+
+        assert_eq!(registry.resolve_import(&[s("hello_world")]).unwrap().version(), 2);
+        let v1 = hello_world(1, "world");
+        assert_eq!(*registry.resolve_import(&v1.import_item()).unwrap(), v1);
+        assert!(matches!(registry.resolve_import(&hello_world(3, "world").import_item()), Err(DossError::UnknownDictionary(_))));
+        assert!(matches!(registry.resolve_import(&hello_world(2, "world").import_item()), Err(DossError::DictionaryMismatch(_))));
+        assert!(matches!(registry.resolve_import(&[s("goodbye")]), Err(DossError::UnknownDictionary(_))));
+        assert!(matches!(registry.resolve_import(&[DossEvent::Null]), Err(DossError::InvalidDictionary(_))));
+    }
+
+    #[test]
+    fn test_import_example() {
+        //"Importing predefined dictionaries" in docs/examples.md
+        let serialized = [&[40, 7, 11][..], b"hello_world", &[10, 9, 0, 9, 1, 11]].concat();
+        let expected = deserialize_orderedbag_from_string(String::from(r#"{"hello": "world"}"#)).unwrap();
+        assert_eq!(decode_entries_with_registry(&serialized, registry(vec![hello_world(1, "world")])).unwrap(), vec![expected]);
+        assert!(matches!(decode_entries(&serialized), Err(DossError::InvalidReference(0))));
+        assert!(matches!(decode_entries_with_registry(&serialized, registry(vec![])), Err(DossError::UnknownDictionary(_))));
+    }
+
+    #[test]
+    fn test_serializer_uses_import() {
+        let dict = hello_world(1, "world");
+        let json = [r#"{"hello": "world", "say": "hello"}"#, r#"["world", "moon"]"#];
+        let serialized = serialize(Some(&dict), &json);
+        let plain = serialize(None, &json);
+        assert!(!serialized.windows(7).any(|w| w == b"\x07\x05world"));
+        assert_eq!(decode_entries_with_registry(&serialized, registry(vec![dict.clone()])).unwrap(), decode_entries(&plain).unwrap());
+
+        //a reader with another dictionary of the same name and version fails instead of decoding other values
+        let other = registry(vec![hello_world(1, "there")]);
+        assert!(matches!(decode_entries_with_registry(&serialized, other), Err(DossError::DictionaryMismatch(_))));
+    }
+
+    #[test]
+    fn test_trainer() {
+        let mut trainer = DossDictionaryTrainer::new();
+        let documents: Vec<_> = (0..20)
+            .map(|i| format!(r#"{{"device_id": "sensor-{i}", "temperature": {i}, "humidity": 40, "status": "operational", "firmware": "v1.2.3"}}"#))
+            .collect();
+        for doc in &documents {
+            trainer.add_entry(&deserialize_orderedbag_from_string(doc.clone()).unwrap());
+        }
+        assert_eq!(trainer.documents(), 20);
+        let dict = trainer.build("telemetry", 1, 3, 2).unwrap();
+        //the keys and values shared by all documents, most saved bytes first
+        assert_eq!(dict.entries(), &[vec![s("operational")], vec![s("temperature")], vec![s("device_id")]]);
+
+        let dict = trainer.build("telemetry", 1, 100, 2).unwrap();
+        assert_eq!(dict.entries().len(), 7);
+        assert!(!dict.entries().iter().any(|e| matches!(&e[..], [DossEvent::String(v)] if v.starts_with("sensor-"))));
+
+        //one message at a time, the serializer's own dictionary can't help here
+        let registry = registry(vec![dict.clone()]);
+        let (mut with, mut without) = (0, 0);
+        for doc in &documents {
+            let serialized = serialize(Some(&dict), &[doc]);
+            with += serialized.len();
+            without += serialize(None, &[doc]).len();
+            assert_eq!(decode_entries_with_registry(&serialized, registry.clone()).unwrap(), decode_entries(&serialize(None, &[doc])).unwrap());
+        }
+        //the import costs about 25 bytes per message
+        assert!(with * 3 < without * 2, "{with} {without}");
+    }
+}
@@ -0,0 +1,644 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::sync::Arc;
+
+use dataflowgrid_commons::typedstream::{DateTime, TypedStreamEvent};
+
+use crate::deserializer::DossLowLevelStreamEvent;
+use crate::dictionary::DossDictionary;
+use crate::error::DossError;
+use crate::limits::DossLimits;
+use crate::registry::DossDictionaryRegistry;
+use crate::settings::DossSettings;
+use crate::types::{Decimal, DossFloat};
+
+/// A complete item (scalar or whole subtree) as the sequence of events it consists of
+pub type DossItem = Vec<DossEvent>;
+
+/// Events of a DOSS stream after the dictionary was applied: references are expanded
+/// to the stored items, dictionary opcodes are consumed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DossEvent {
+    BlockStart,
+    BlockEnd,
+    ArrayStart,
+    ArrayEnd,
+    TypeStart(String),
+    TypeEnd,
+    True,
+    False,
+    Null,
+    Int(i64),
+    UInt(u64),
+    Decimal(Decimal),
+    Float(DossFloat),
+    DateTime(DateTime),
+    String(String),
+    Binary(Vec<u8>),
+
+    Config { key: DossItem, value: DossItem },
+    Hint { key: DossItem, value: DossItem },
+    ImportDict(DossItem),
+    FileStart(DossItem),
+    StackStart,
+    StackEnd,
+}
+
+impl DossEvent {
+    /// +1 for events opening a nesting level, -1 for events closing one
+    pub fn depth_change(&self) -> isize {
+        match self {
+            DossEvent::BlockStart | DossEvent::ArrayStart | DossEvent::TypeStart(_) => 1,
+            DossEvent::BlockEnd | DossEvent::ArrayEnd | DossEvent::TypeEnd => -1,
+            _ => 0
+        }
+    }
+
+    /// Approximate bytes the event occupies in memory
+    pub fn memory(&self) -> usize {
+        let heap = match self {
+            DossEvent::TypeStart(s) | DossEvent::String(s) => s.len(),
+            DossEvent::Binary(b) => b.len(),
+            DossEvent::Decimal(d) => std::mem::size_of_val(d.values()),
+            DossEvent::Config { key, value } | DossEvent::Hint { key, value } => item_memory(key) + item_memory(value),
+            DossEvent::ImportDict(item) | DossEvent::FileStart(item) => item_memory(item),
+            _ => 0,
+        };
+        std::mem::size_of::<DossEvent>() + heap
+    }
+
+    /// The event for typed stream consumers. Hints become `HINT("key=value")` or `HINT("key")` for null values,
+    /// settings, dictionary imports, file starts and stacks have no counterpart
+    pub fn to_typed_stream_event(&self) -> Option<TypedStreamEvent> {
+        Some(match self {
+            DossEvent::BlockStart => TypedStreamEvent::STARTOBJECT,
+            DossEvent::BlockEnd => TypedStreamEvent::ENDOBJECT,
+            DossEvent::ArrayStart => TypedStreamEvent::STARTARRAY,
+            DossEvent::ArrayEnd => TypedStreamEvent::ENDARRAY,
+            DossEvent::TypeStart(name) => TypedStreamEvent::STARTTYPE(name.clone()),
+            DossEvent::TypeEnd => TypedStreamEvent::ENDTYPE,
+            DossEvent::True => TypedStreamEvent::TRUE,
+            DossEvent::False => TypedStreamEvent::FALSE,
+            DossEvent::Null => TypedStreamEvent::NULL,
+            DossEvent::Int(v) => match usize::try_from(*v) {
+                Ok(v) => TypedStreamEvent::DECIMAL(v),
+                Err(_) => TypedStreamEvent::ANY(Box::new(*v)),
+            },
+            DossEvent::UInt(v) => match usize::try_from(*v) {
+                Ok(v) => TypedStreamEvent::DECIMAL(v),
+                Err(_) => TypedStreamEvent::ANY(Box::new(*v)),
+            },
+            DossEvent::Decimal(d) => match d.get_usize() {
+                Ok(v) => TypedStreamEvent::DECIMAL(v),
+                Err(_) => TypedStreamEvent::ANY(Box::new(d.clone())),
+            },
+            DossEvent::Float(f) => TypedStreamEvent::FLOAT(f.to_f64()),
+            DossEvent::DateTime(d) => TypedStreamEvent::DATETIME(*d),
+            DossEvent::String(s) => TypedStreamEvent::STRING(s.clone()),
+            DossEvent::Binary(b) => TypedStreamEvent::BYTEARRAY(b.clone()),
+            DossEvent::Hint { key, value } => TypedStreamEvent::HINT(match value.as_slice() {
+                [DossEvent::Null] => item_text(key),
+                _ => format!("{}={}", item_text(key), item_text(value)),
+            }),
+            DossEvent::Config { .. }
+            | DossEvent::ImportDict(_)
+            | DossEvent::FileStart(_)
+            | DossEvent::StackStart
+            | DossEvent::StackEnd => return None,
+        })
+    }
+}
+
+/// Approximate bytes an item occupies in memory
+pub(crate) fn item_memory(item: &[DossEvent]) -> usize {
+    item.iter().map(DossEvent::memory).sum()
+}
+
+fn item_text(item: &[DossEvent]) -> String {
+    match item {
+        [DossEvent::String(s)] => s.clone(),
+        [DossEvent::Int(v)] => v.to_string(),
+        [DossEvent::UInt(v)] => v.to_string(),
+        [DossEvent::Decimal(v)] => v.to_string(),
+        [DossEvent::Float(v)] => v.to_string(),
+        [DossEvent::DateTime(v)] => v.to_string(),
+        [DossEvent::True] => String::from("true"),
+        [DossEvent::False] => String::from("false"),
+        [DossEvent::Null] => String::from("null"),
+        _ => format!("{item:?}"),
+    }
+}
+
+#[derive(Debug)]
+enum CaptureKind {
+    Store { index: usize, emit: bool },
+    TypeName,
+    ConfigKey { hint: bool },
+    ConfigValue { hint: bool, key: DossItem },
+    ImportDict,
+    FileName,
+}
+
+/// Collects the next item following an opcode like StoreInDict or SetConfig
+#[derive(Debug)]
+struct Capture {
+    kind: CaptureKind,
+    depth: usize,
+    events: DossItem,
+    memory: usize, //of the events and the key of a setting
+}
+
+impl Capture {
+    fn new(kind: CaptureKind) -> Self {
+        let memory = match &kind {
+            CaptureKind::ConfigValue { key, .. } => item_memory(key),
+            _ => 0,
+        };
+        Capture { kind, depth: 0, events: Vec::new(), memory }
+    }
+
+    /// stored items (21) are also part of the stream, everything else is consumed
+    fn emits(&self) -> bool {
+        matches!(self.kind, CaptureKind::Store { emit: true, .. })
+    }
+
+    fn is_complete(&self) -> bool {
+        self.depth == 0 && !self.events.is_empty()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Nesting {
+    Block,
+    Array,
+    Type,
+}
+
+/// Applies the dictionary to low level events, see the dictionary section in docs/features.md.
+/// Resolved events are appended to an output vector, so the resolver works the same for sync and async input.
+#[derive(Debug, Default)]
+pub struct DossResolver {
+    dict: DossDictionary,
+    settings: DossSettings,
+    registry: Option<Arc<DossDictionaryRegistry>>,
+    captures: Vec<Capture>,
+    captured: usize, //memory of all captures
+    nesting: Vec<Nesting>,
+    limits: DossLimits,
+}
+
+impl DossResolver {
+    pub fn new() -> Self {
+        Self::with_dictionary(DossDictionary::new())
+    }
+
+    pub fn with_dictionary(dict: DossDictionary) -> Self {
+        DossResolver {
+            dict,
+            settings: DossSettings::new(),
+            registry: None,
+            captures: Vec::new(),
+            captured: 0,
+            nesting: Vec::new(),
+            limits: DossLimits::default(),
+        }
+    }
+
+    pub fn dictionary(&self) -> &DossDictionary {
+        &self.dict
+    }
+
+    pub fn dictionary_mut(&mut self) -> &mut DossDictionary {
+        &mut self.dict
+    }
+
+    /// Imports (40) load the predefined dictionary from the registry. Without a registry imports are
+    /// only passed on as [`DossEvent::ImportDict`] and references to the missing entries fail
+    pub fn set_registry(&mut self, registry: Arc<DossDictionaryRegistry>) {
+        self.registry = Some(registry);
+    }
+
+    /// Settings and hints of the stream so far, decoders must use its string encoding for the following events
+    pub fn settings(&self) -> &DossSettings {
+        &self.settings
+    }
+
+    pub fn limits(&self) -> &DossLimits {
+        &self.limits
+    }
+
+    /// Limits for untrusted input, the default limits are used otherwise
+    pub fn set_limits(&mut self, limits: DossLimits) {
+        self.limits = limits;
+    }
+
+    /// Decodes the next event of `input` with the string encoding and the limits of the stream
+    pub fn decode(&self, input: &[u8]) -> Result<Option<(DossLowLevelStreamEvent, usize)>, DossError> {
+        DossLowLevelStreamEvent::decode_limited(input, self.settings.string_encoding(), &self.limits)
+    }
+
+    /// Current nesting depth of the input, captured items included
+    pub fn depth(&self) -> usize {
+        self.nesting.len()
+    }
+
+    /// True while an item for a store, setting, type name or similar is collected
+    pub fn is_capturing(&self) -> bool {
+        !self.captures.is_empty()
+    }
+
+    pub fn push(&mut self, event: DossLowLevelStreamEvent, out: &mut Vec<DossEvent>) -> Result<(), DossError> {
+        match event {
+            DossLowLevelStreamEvent::NoOp
+            | DossLowLevelStreamEvent::SkipBytes16le(_)
+            | DossLowLevelStreamEvent::SkipBytes32le(_) => Ok(()),
+            DossLowLevelStreamEvent::True => self.deliver(DossEvent::True, out),
+            DossLowLevelStreamEvent::False => self.deliver(DossEvent::False, out),
+            DossLowLevelStreamEvent::Null => self.deliver(DossEvent::Null, out),
+            DossLowLevelStreamEvent::Varint(v) => self.deliver(DossEvent::Int(v), out),
+            DossLowLevelStreamEvent::UnsignedVarint(v) => self.deliver(DossEvent::UInt(v), out),
+            DossLowLevelStreamEvent::String(s) => self.deliver(DossEvent::String(s), out),
+            DossLowLevelStreamEvent::Binary(b) => self.deliver(DossEvent::Binary(b), out),
+            DossLowLevelStreamEvent::Float(f) => self.deliver(DossEvent::Float(f), out),
+            DossLowLevelStreamEvent::DateTime(d) => self.deliver(DossEvent::DateTime(d), out),
+            DossLowLevelStreamEvent::Decimal(d) => self.deliver(DossEvent::Decimal(d), out),
+            DossLowLevelStreamEvent::Reference(index) => {
+                let item = self.dict.get(index)?;
+                for e in item.iter() {
+                    self.deliver(e.clone(), out)?;
+                }
+                Ok(())
+            }
+            DossLowLevelStreamEvent::BlockStart => {
+                self.enter()?;
+                self.nesting.push(Nesting::Block);
+                self.deliver(DossEvent::BlockStart, out)
+            }
+            DossLowLevelStreamEvent::BlockEnd => {
+                self.leave(Nesting::Block)?;
+                self.deliver(DossEvent::BlockEnd, out)
+            }
+            DossLowLevelStreamEvent::ArrayStart => {
+                self.enter()?;
+                self.nesting.push(Nesting::Array);
+                self.deliver(DossEvent::ArrayStart, out)
+            }
+            DossLowLevelStreamEvent::ArrayEnd => {
+                self.leave(Nesting::Array)?;
+                self.deliver(DossEvent::ArrayEnd, out)
+            }
+            DossLowLevelStreamEvent::TypeStart => {
+                //TypeStart is delivered once the name is known
+                self.enter()?;
+                self.nesting.push(Nesting::Type);
+                self.captures.push(Capture::new(CaptureKind::TypeName));
+                Ok(())
+            }
+            DossLowLevelStreamEvent::TypeEnd => {
+                self.leave(Nesting::Type)?;
+                self.deliver(DossEvent::TypeEnd, out)
+            }
+            DossLowLevelStreamEvent::StoreInDict | DossLowLevelStreamEvent::StoreButDontUse => {
+                //the index is taken when storing starts, so nested stores get the following indexes
+                self.enter()?;
+                let index = self.dict.allocate()?;
+                let emit = event == DossLowLevelStreamEvent::StoreInDict;
+                self.captures.push(Capture::new(CaptureKind::Store { index, emit }));
+                Ok(())
+            }
+            DossLowLevelStreamEvent::SetDictPointer(p) => self.dict.set_pointer(p),
+            DossLowLevelStreamEvent::ClearDictEntries { from, count } => {
+                self.dict.clear(from, count);
+                Ok(())
+            }
+            DossLowLevelStreamEvent::SetConfig => {
+                self.enter()?;
+                self.captures.push(Capture::new(CaptureKind::ConfigKey { hint: false }));
+                Ok(())
+            }
+            DossLowLevelStreamEvent::SetHint => {
+                self.enter()?;
+                self.captures.push(Capture::new(CaptureKind::ConfigKey { hint: true }));
+                Ok(())
+            }
+            DossLowLevelStreamEvent::ImportDict => {
+                self.enter()?;
+                self.captures.push(Capture::new(CaptureKind::ImportDict));
+                Ok(())
+            }
+            DossLowLevelStreamEvent::FileStart => {
+                if !self.nesting.is_empty() || !self.captures.is_empty() {
+                    return Err(DossError::UnbalancedStructure);
+                }
+                self.captures.push(Capture::new(CaptureKind::FileName));
+                Ok(())
+            }
+            DossLowLevelStreamEvent::StackStart => {
+                self.enter()?;
+                self.dict.start_stack();
+                out.push(DossEvent::StackStart);
+                Ok(())
+            }
+            DossLowLevelStreamEvent::StackEnd => {
+                self.dict.leave_stack()?;
+                out.push(DossEvent::StackEnd);
+                Ok(())
+            }
+        }
+    }
+
+    /// Checks that the stream did not end inside an item
+    pub fn finish(&self) -> Result<(), DossError> {
+        if self.nesting.is_empty() && self.captures.is_empty() && self.dict.stack_depth() == 0 {
+            Ok(())
+        } else {
+            Err(DossError::UnexpectedEof)
+        }
+    }
+
+    //blocks, arrays, captures and stacks all take memory per level
+    fn levels(&self) -> usize {
+        self.nesting.len() + self.captures.len() + self.dict.stack_depth()
+    }
+
+    fn enter(&self) -> Result<(), DossError> {
+        match self.levels() < self.limits.max_depth {
+            true => Ok(()),
+            false => Err(DossError::TooDeep(self.limits.max_depth)),
+        }
+    }
+
+    fn check_memory(&self) -> Result<(), DossError> {
+        match self.captured.saturating_add(self.dict.memory()) <= self.limits.max_memory {
+            true => Ok(()),
+            false => Err(DossError::MemoryLimit(self.limits.max_memory)),
+        }
+    }
+
+    fn leave(&mut self, nesting: Nesting) -> Result<(), DossError> {
+        match self.nesting.pop() {
+            Some(n) if n == nesting => Ok(()),
+            _ => Err(DossError::UnbalancedStructure)
+        }
+    }
+
+    /// Hands an event to the open captures from the innermost outwards. Captures that consume their item
+    /// stop the event, otherwise it ends up in `out`.
+    fn deliver(&mut self, event: DossEvent, out: &mut Vec<DossEvent>) -> Result<(), DossError> {
+        let change = event.depth_change();
+        let mut emit = true;
+        for capture in self.captures.iter_mut().rev() {
+            if change < 0 && capture.depth == 0 {
+                //e.g. a BlockEnd directly after StoreInDict
+                return Err(DossError::ItemExpected);
+            }
+            capture.depth = capture.depth.saturating_add_signed(change);
+            if capture.events.len() >= self.dict.max_events() {
+                return Err(DossError::DictFull);
+            }
+            let memory = event.memory();
+            capture.memory += memory;
+            self.captured += memory;
+            capture.events.push(event.clone());
+            if !capture.emits() {
+                emit = false;
+                break;
+            }
+        }
+        self.check_memory()?;
+        if emit {
+            out.push(event);
+        }
+        while self.captures.last().is_some_and(|c| c.is_complete()) {
+            let capture = self.captures.pop().unwrap(); //checked in the loop condition
+            self.captured -= capture.memory;
+            self.complete(capture, out)?;
+            self.check_memory()?;
+        }
+        Ok(())
+    }
+
+    fn complete(&mut self, capture: Capture, out: &mut Vec<DossEvent>) -> Result<(), DossError> {
+        match capture.kind {
+            CaptureKind::Store { index, .. } => self.dict.store(index, capture.events),
+            CaptureKind::TypeName => match <[DossEvent; 1]>::try_from(capture.events) {
+                Ok([DossEvent::String(name)]) => self.deliver(DossEvent::TypeStart(name), out),
+                _ => Err(DossError::InvalidTypeName)
+            },
+            CaptureKind::ConfigKey { hint } => {
+                let value = Capture::new(CaptureKind::ConfigValue { hint, key: capture.events });
+                self.captured += value.memory;
+                self.captures.push(value);
+                Ok(())
+            }
+            CaptureKind::ConfigValue { hint, key } => {
+                match hint {
+                    true => self.settings.apply_hint(&key, &capture.events),
+                    false => self.settings.apply_config(&key, &capture.events)?,
+                }
+                out.push(match hint {
+                    true => DossEvent::Hint { key, value: capture.events },
+                    false => DossEvent::Config { key, value: capture.events },
+                });
+                Ok(())
+            }
+            CaptureKind::ImportDict => {
+                //the entries are stored from the dict pointer on, like items stored one after another
+                if let Some(registry) = &self.registry {
+                    let dict = registry.resolve_import(&capture.events)?;
+                    for entry in dict.entries() {
+                        let index = self.dict.allocate()?;
+                        self.dict.store(index, entry.clone())?;
+                    }
+                }
+                out.push(DossEvent::ImportDict(capture.events));
+                Ok(())
+            }
+            CaptureKind::FileName => {
+                out.push(DossEvent::FileStart(capture.events));
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve_with(resolver: &mut DossResolver, serialized: &[u8]) -> Result<Vec<DossEvent>, DossError> {
+        let mut out = Vec::new();
+        let mut pos = 0;
+        while let Some((event, used)) = resolver.decode(&serialized[pos..])? {
+            pos += used;
+            resolver.push(event, &mut out)?;
+        }
+        assert_eq!(pos, serialized.len());
+        resolver.finish()?;
+        Ok(out)
+    }
+
+    fn resolve(serialized: &[u8]) -> Result<Vec<DossEvent>, DossError> {
+        resolve_with(&mut DossResolver::new(), serialized)
+    }
+
+    fn s(s: &str) -> DossEvent {
+        DossEvent::String(s.to_string())
+    }
+
+    const HELLO: [u8; 7] = [7, 5, b'h', b'e', b'l', b'l', b'o'];
+    const WORLD: [u8; 7] = [7, 5, b'w', b'o', b'r', b'l', b'd'];
+    const SAY: [u8; 5] = [7, 3, b's', b'a', b'y'];
+
+    #[test]
+    fn test_hello_world_with_dict() {
+        let serialized = [&[10, 21][..], &HELLO, &WORLD, &SAY, &[9, 0, 11]].concat();
+        assert_eq!(resolve(&serialized).unwrap(), vec![
+            DossEvent::BlockStart, s("hello"), s("world"), s("say"), s("hello"), DossEvent::BlockEnd
+        ]);
+    }
+
+    #[test]
+    fn test_nested_and_arrays() {
+        let serialized = [&[10, 21][..], &HELLO, &[12], &SAY, &[9, 0, 13, 11]].concat();
+        assert_eq!(resolve(&serialized).unwrap(), vec![
+            DossEvent::BlockStart, s("hello"), DossEvent::ArrayStart, s("say"), s("hello"), DossEvent::ArrayEnd, DossEvent::BlockEnd
+        ]);
+    }
+
+    #[test]
+    fn test_store_subtree() {
+        //stores {"say": "hello"} without using it and references it twice, nested stores get the following indexes
+        let serialized = [&[22, 10][..], &SAY, &[21], &HELLO, &[11, 12, 9, 0, 9, 0, 9, 1, 13]].concat();
+        let inner = vec![DossEvent::BlockStart, s("say"), s("hello"), DossEvent::BlockEnd];
+        let expected = [vec![DossEvent::ArrayStart], inner.clone(), inner, vec![s("hello"), DossEvent::ArrayEnd]].concat();
+        assert_eq!(resolve(&serialized).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_pointer_and_clear() {
+        let serialized = [&[23, 5, 21][..], &HELLO, &[21], &WORLD, &[9, 6, 9, 5, 24, 5, 1, 9, 6]].concat();
+        assert_eq!(resolve(&serialized).unwrap(), vec![s("hello"), s("world"), s("world"), s("hello"), s("world")]);
+
+        let serialized = [&[21][..], &HELLO, &[24, 0, 1, 9, 0]].concat();
+        assert!(matches!(resolve(&serialized), Err(DossError::InvalidReference(0))));
+    }
+
+    #[test]
+    fn test_stacks() {
+        //entries stored in a stack are dropped and the pointer restored when leaving it
+        let serialized = [&[21][..], &HELLO, &[25, 21], &WORLD, &[25, 21], &SAY, &[9, 2, 26, 9, 1, 26, 21], &SAY, &[9, 1, 9, 0]].concat();
+        assert_eq!(resolve(&serialized).unwrap(), vec![
+            s("hello"), DossEvent::StackStart, s("world"), DossEvent::StackStart, s("say"), s("say"), DossEvent::StackEnd,
+            s("world"), DossEvent::StackEnd, s("say"), s("say"), s("hello"),
+        ]);
+
+        let serialized = [&[25, 21][..], &HELLO, &[26, 9, 0]].concat();
+        assert!(matches!(resolve(&serialized), Err(DossError::InvalidReference(0))));
+        assert!(matches!(resolve(&[26]), Err(DossError::UnbalancedStructure)));
+        assert!(matches!(resolve(&[25]), Err(DossError::UnexpectedEof)));
+    }
+
+    #[test]
+    fn test_types_config_and_files() {
+        let serialized = [&[20, 3, 0, 3, 1, 27][..], &SAY, &[14, 21, 16], &HELLO, &[3, 2, 17, 50, 14, 9, 0]].concat();
+        assert_eq!(resolve(&serialized).unwrap(), vec![
+            DossEvent::Config { key: vec![DossEvent::Int(0)], value: vec![DossEvent::Int(1)] },
+            DossEvent::Hint { key: vec![s("say")], value: vec![DossEvent::Null] },
+            DossEvent::TypeStart(String::from("hello")), DossEvent::Int(2), DossEvent::TypeEnd,
+            DossEvent::FileStart(vec![DossEvent::Null]),
+            DossEvent::TypeStart(String::from("hello")), DossEvent::Int(2), DossEvent::TypeEnd,
+        ]);
+    }
+
+    #[test]
+    fn test_invalid_structure() {
+        assert!(matches!(resolve(&[10, 13]), Err(DossError::UnbalancedStructure)));
+        assert!(matches!(resolve(&[10, 21, 11]), Err(DossError::ItemExpected)));
+        assert!(matches!(resolve(&[10]), Err(DossError::UnexpectedEof)));
+        assert!(matches!(resolve(&[21]), Err(DossError::UnexpectedEof)));
+        assert!(matches!(resolve(&[16, 3, 1, 17]), Err(DossError::InvalidTypeName)));
+        assert!(matches!(resolve(&[10, 50, 14, 11]), Err(DossError::UnbalancedStructure)));
+    }
+
+    #[test]
+    fn test_dict_limits() {
+        let mut resolver = DossResolver::with_dictionary(DossDictionary::with_limits(1, 100));
+        let serialized = [&[21][..], &HELLO, &[21], &WORLD].concat();
+        assert!(matches!(resolve_with(&mut resolver, &serialized), Err(DossError::DictFull)));
+
+        //every entry doubles the previous one, the event limit stops this before it eats all memory
+        let mut serialized = vec![21, 14];
+        for i in 0..40 {
+            serialized.extend_from_slice(&[21, 12, 9, i, 9, i, 13]);
+        }
+        let mut resolver = DossResolver::with_dictionary(DossDictionary::with_limits(100, 10000));
+        let mut out = Vec::new();
+        let mut pos = 0;
+        let r = loop {
+            let (event, used) = DossLowLevelStreamEvent::decode(&serialized[pos..]).unwrap().unwrap();
+            pos += used;
+            out.clear();
+            if let Err(e) = resolver.push(event, &mut out) {
+                break e;
+            }
+        };
+        assert!(matches!(r, DossError::DictFull));
+    }
+
+    #[test]
+    fn test_limits() {
+        let limits = |max_depth, max_string_len, max_memory| DossLimits { max_depth, max_string_len, max_memory };
+        let mut resolver = DossResolver::new();
+        resolver.set_limits(limits(4, 10, 1000));
+        assert!(resolve_with(&mut resolver, &[12, 10, 21, 12, 13, 11, 13]).is_ok());
+        //open captures and stacks count as levels, so a run of opcodes that collect an item is bounded too
+        for serialized in [&[12, 10, 12][..], &[12, 10, 21], &[20, 20, 20], &[25, 25, 25]] {
+            let mut resolver = DossResolver::new();
+            resolver.set_limits(limits(2, 10, 1000));
+            assert!(matches!(resolve_with(&mut resolver, serialized), Err(DossError::TooDeep(2))), "{serialized:?}");
+        }
+
+        //the length is checked before the string arrives
+        let mut resolver = DossResolver::new();
+        resolver.set_limits(limits(3, 4, 1000));
+        assert!(matches!(resolver.decode(&[7, 5]), Err(DossError::TooLong(5))));
+        assert!(matches!(resolver.decode(&[8, 0xff, 0xff, 0xff, 0xff, 0x0f]), Err(DossError::TooLong(_))));
+        assert!(matches!(resolver.decode(&[4, 0, 10]), Err(DossError::TooLong(5))));
+        assert!(resolver.decode(&[7, 4]).unwrap().is_none());
+
+        //stored items and items replaced inside a stack are counted
+        let mut resolver = DossResolver::new();
+        let item = [21_u8, 8, 100].into_iter().chain([0; 100]);
+        resolver.set_limits(limits(10, 1000, 1000));
+        let serialized: Vec<u8> = [25].into_iter().chain((0..20).flat_map(|_| [23, 0].into_iter().chain(item.clone()))).collect();
+        assert!(matches!(resolve_with(&mut resolver, &serialized), Err(DossError::MemoryLimit(1000))));
+        assert!(resolver.dictionary().memory() <= 1000 + 200);
+    }
+
+    #[test]
+    fn test_settings_and_encoding() {
+        //the "Change string encoding" example of docs/examples.md
+        let ascii = [&[20, 3, 1, 7, 5][..], b"ASCII", &[10], &HELLO, &WORLD, &[11]].concat();
+        let mut resolver = DossResolver::new();
+        let events = resolve_with(&mut resolver, &ascii).unwrap();
+        assert_eq!(events[1..], [DossEvent::BlockStart, s("hello"), s("world"), DossEvent::BlockEnd]);
+        assert_eq!(resolver.settings().string_encoding(), crate::settings::StringEncoding::Ascii);
+
+        //strings following the setting are utf16, the name of the encoding is not
+        let utf16: Vec<u8> = "grüße".encode_utf16().flat_map(|u| u.to_le_bytes()).collect();
+        let serialized = [&[20, 15, 1, 7, 7][..], b"utf16le", &[7, utf16.len() as u8], &utf16].concat();
+        assert_eq!(resolve(&serialized).unwrap()[1..], [s("grüße")]);
+
+        assert!(matches!(resolve(&[20, 15, 0, 15, 9, 10, 11]), Err(DossError::UnsupportedVersion(9))));
+        assert!(matches!(resolve(&[20, 15, 7, 14, 10, 11]), Err(DossError::UnknownSetting(_))));
+        //unknown hints are passed on but don't fail
+        let events = resolve(&[27, 15, 7, 14, 27, 0x07, 14, b'g', b'e', b'n', b'e', b'r', b'a', b't', b'e', b'd', b'_', b'w', b'i', b't', b'h', 7, 1, b'x']).unwrap();
+        assert_eq!(resolve(&[27, 15, 0, 15, 1]).unwrap().len(), 1);
+        let hints: Vec<_> = events.iter().filter_map(|e| match e.to_typed_stream_event() {
+            Some(TypedStreamEvent::HINT(h)) => Some(h),
+            _ => None,
+        }).collect();
+        assert_eq!(hints, vec![String::from("7"), String::from("generated_with=x")]);
+    }
+}
@@ -0,0 +1,611 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use dataflowgrid_commons::typedstream::{TypedStreamEvent, TypedStreamEventError, TypedStreamEventReturn};
+
+use crate::error::DossError;
+use crate::parser::DossEventHandler;
+use crate::types::Decimal;
+
+/// default number of distinct values counted exactly per field
+pub const DEFAULT_MAX_DISTINCT: usize = 1 << 10;
+
+/// Type of a field, widened as records are added: integers and floats become floats,
+/// integers and decimals decimals, other combinations [`DossFieldType::Mixed`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum DossFieldType {
+    /// only nulls so far
+    Null,
+    Boolean,
+    /// fits into an i64
+    Integer,
+    Float,
+    /// digits before and after the decimal point
+    Decimal { digits: u16, scale: u16 },
+    String,
+    Binary,
+    DateTime,
+    /// a type like `ObjectId("...")` around a single scalar
+    Typed(String, Box<DossFieldType>),
+    Struct(Vec<DossField>),
+    /// the field of the items is named `item`
+    List(Box<DossField>),
+    /// values that don't share a type, projected as their streamablejson text
+    Mixed,
+}
+
+/// Distinct non null values of a scalar field, counted exactly up to a limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DossCardinality {
+    Exact(u64),
+    AtLeast(u64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DossField {
+    pub name: String,
+    pub data_type: DossFieldType,
+    /// null or missing in some values of the parent
+    pub nullable: bool,
+    /// number of non null values
+    pub count: u64,
+    /// None for structs, lists and mixed fields
+    pub cardinality: Option<DossCardinality>,
+}
+
+/// The inferred schema of a record stream, see [`DossSchemaInference`]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DossSchema {
+    pub records: u64,
+    pub fields: Vec<DossField>,
+}
+
+impl DossSchema {
+    /// The field at a path like `customer.name` or `items[].price`
+    pub fn field(&self, path: &str) -> Option<&DossField> {
+        self.paths().into_iter().find(|(p, _)| p == path).map(|(_, f)| f)
+    }
+
+    /// All fields with their paths, parents before their children. List items are marked with `[]`
+    pub fn paths(&self) -> Vec<(String, &DossField)> {
+        let mut paths = Vec::new();
+        for field in &self.fields {
+            add_paths(field.name.clone(), field, &mut paths);
+        }
+        paths
+    }
+}
+
+fn add_paths<'a>(path: String, field: &'a DossField, paths: &mut Vec<(String, &'a DossField)>) {
+    paths.push((path.clone(), field));
+    match &field.data_type {
+        DossFieldType::Struct(fields) => {
+            for child in fields {
+                add_paths(format!("{path}.{}", child.name), child, paths);
+            }
+        }
+        DossFieldType::List(item) => add_paths(format!("{path}[]"), item, paths),
+        _ => {}
+    }
+}
+
+impl fmt::Display for DossFieldType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DossFieldType::Null => write!(f, "null"),
+            DossFieldType::Boolean => write!(f, "boolean"),
+            DossFieldType::Integer => write!(f, "integer"),
+            DossFieldType::Float => write!(f, "float"),
+            DossFieldType::Decimal { digits, scale } => write!(f, "decimal({}, {scale})", precision(*digits, *scale)),
+            DossFieldType::String => write!(f, "string"),
+            DossFieldType::Binary => write!(f, "binary"),
+            DossFieldType::DateTime => write!(f, "datetime"),
+            DossFieldType::Typed(name, content) => write!(f, "{name}({content})"),
+            DossFieldType::Struct(_) => write!(f, "struct"),
+            DossFieldType::List(item) => write!(f, "list<{}>", item.data_type),
+            DossFieldType::Mixed => write!(f, "mixed"),
+        }
+    }
+}
+
+impl fmt::Display for DossCardinality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DossCardinality::Exact(n) => write!(f, "{n}"),
+            DossCardinality::AtLeast(n) => write!(f, ">={n}"),
+        }
+    }
+}
+
+/// One line per path with type, nullability, values and distinct values
+impl fmt::Display for DossSchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} records", self.records)?;
+        for (path, field) in self.paths() {
+            let nullable = if field.nullable { "nullable" } else { "required" };
+            let distinct = field.cardinality.map(|c| format!(", {c} distinct")).unwrap_or_default();
+            writeln!(f, "{path}: {} {nullable}, {} values{distinct}", field.data_type, field.count)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Kind {
+    Null,
+    Scalar(DossFieldType),
+    Struct,
+    List,
+    Mixed,
+}
+
+impl Kind {
+    fn merge(&mut self, other: Kind) {
+        *self = match (std::mem::replace(self, Kind::Null), other) {
+            (Kind::Null, other) => other,
+            (Kind::Scalar(a), Kind::Scalar(b)) => match widen(&a, &b) {
+                DossFieldType::Mixed => Kind::Mixed,
+                t => Kind::Scalar(t),
+            },
+            (Kind::Struct, Kind::Struct) => Kind::Struct,
+            (Kind::List, Kind::List) => Kind::List,
+            _ => Kind::Mixed,
+        }
+    }
+}
+
+fn widen(a: &DossFieldType, b: &DossFieldType) -> DossFieldType {
+    use DossFieldType::*;
+    match (a, b) {
+        _ if a == b => a.clone(),
+        (Integer, Float) | (Float, Integer) | (Decimal { .. }, Float) | (Float, Decimal { .. }) => Float,
+        (Decimal { digits, scale }, Integer) | (Integer, Decimal { digits, scale }) => Decimal { digits: (*digits).max(19), scale: *scale },
+        (Decimal { digits: d1, scale: s1 }, Decimal { digits: d2, scale: s2 }) => Decimal { digits: *d1.max(d2), scale: *s1.max(s2) },
+        (Typed(n1, c1), Typed(n2, c2)) if n1 == n2 => match widen(c1, c2) {
+            Mixed => Mixed,
+            c => Typed(n1.clone(), Box::new(c)),
+        },
+        _ => Mixed,
+    }
+}
+
+#[derive(Debug)]
+struct Node {
+    name: String,
+    kind: Kind,
+    children: Vec<usize>,
+    item: Option<usize>,
+    present: u64, //objects the field occurred in, list elements for items
+    nulls: u64,
+    count: u64,
+    objects: u64, //values that were objects
+    distinct: HashSet<u64>,
+    capped: bool,
+}
+
+impl Node {
+    fn new(name: String) -> Self {
+        Node {
+            name,
+            kind: Kind::Null,
+            children: Vec::new(),
+            item: None,
+            present: 0,
+            nulls: 0,
+            count: 0,
+            objects: 0,
+            distinct: HashSet::new(),
+            capped: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Frame {
+    Records, //a top level array of records
+    Object { node: usize, key: Option<usize>, seen: Vec<usize> },
+    Array { item: usize },
+    Type { node: usize, name: String, content: Vec<(DossFieldType, String)>, depth: usize },
+    Ignore(usize), //content of a mixed field or a repeated key, the number of open levels
+}
+
+/// Infers a [`DossSchema`] from the events of a record stream.
+///
+/// Records are the top level objects, elements of a top level array are records as well. Only the schema and
+/// up to `max_distinct` value hashes per field are kept, so streams of any size can be inferred.
+#[derive(Debug)]
+pub struct DossSchemaInference {
+    nodes: Vec<Node>, //the records are node 0
+    stack: Vec<Frame>,
+    max_distinct: usize,
+}
+
+impl Default for DossSchemaInference {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DossSchemaInference {
+    pub fn new() -> Self {
+        Self::with_max_distinct(DEFAULT_MAX_DISTINCT)
+    }
+
+    /// Distinct values are counted exactly up to `max_distinct` per field
+    pub fn with_max_distinct(max_distinct: usize) -> Self {
+        DossSchemaInference { nodes: vec![Node::new(String::new())], stack: Vec::new(), max_distinct }
+    }
+
+    /// Adds all events, e.g. of a [`DossPullParser`](crate::DossPullParser)
+    pub fn push_all(&mut self, events: impl IntoIterator<Item = Result<TypedStreamEvent, DossError>>) -> Result<(), DossError> {
+        for event in events {
+            self.push(event?)?;
+        }
+        Ok(())
+    }
+
+    pub fn push(&mut self, event: TypedStreamEvent) -> Result<(), DossError> {
+        match event {
+            TypedStreamEvent::INIT | TypedStreamEvent::FINISH | TypedStreamEvent::HINT(_) => return Ok(()),
+            TypedStreamEvent::ERROR(e) => return Err(DossError::UnsupportedValue(e.to_string())),
+            _ => {}
+        }
+        match self.stack.last_mut() {
+            Some(Frame::Ignore(depth)) => {
+                *depth = depth.saturating_add_signed(depth_change(&event));
+                if *depth == 0 {
+                    self.stack.pop();
+                }
+                return Ok(());
+            }
+            Some(Frame::Type { .. }) => return self.type_content(event),
+            Some(Frame::Object { key: None, .. }) if !matches!(event, TypedStreamEvent::ENDOBJECT) => return self.key(event),
+            _ => {}
+        }
+        if matches!(event, TypedStreamEvent::ENDOBJECT | TypedStreamEvent::ENDARRAY) {
+            return match self.stack.pop() {
+                Some(Frame::Object { .. }) if matches!(event, TypedStreamEvent::ENDOBJECT) => Ok(()),
+                Some(Frame::Array { .. } | Frame::Records) if matches!(event, TypedStreamEvent::ENDARRAY) => Ok(()),
+                _ => Err(DossError::UnbalancedStructure),
+            };
+        }
+        let node = match self.stack.last_mut() {
+            None if matches!(event, TypedStreamEvent::STARTARRAY) => {
+                self.stack.push(Frame::Records);
+                return Ok(());
+            }
+            None | Some(Frame::Records) => match event {
+                TypedStreamEvent::STARTOBJECT => {
+                    self.nodes[0].present += 1;
+                    0
+                }
+                _ => return Err(DossError::SchemaMismatch(String::from("records must be objects"))),
+            },
+            Some(Frame::Object { key, .. }) => key.take().ok_or(DossError::UnbalancedStructure)?,
+            Some(Frame::Array { item }) => {
+                let item = *item;
+                self.nodes[item].present += 1;
+                item
+            }
+            Some(Frame::Type { .. } | Frame::Ignore(_)) => unreachable!("handled above"),
+        };
+        self.value(node, event)
+    }
+
+    /// The schema of the records so far
+    pub fn schema(&self) -> DossSchema {
+        let root = &self.nodes[0];
+        DossSchema {
+            records: root.present,
+            fields: root.children.iter().map(|c| self.field(*c, root.objects)).collect(),
+        }
+    }
+
+    fn key(&mut self, event: TypedStreamEvent) -> Result<(), DossError> {
+        let name = match &event {
+            TypedStreamEvent::STRING(s) => s.clone(),
+            _ => scalar(&event)?.1,
+        };
+        let Some(Frame::Object { node, key, seen }) = self.stack.last_mut() else {
+            unreachable!("only called for object keys");
+        };
+        let parent = *node;
+        let child = match self.nodes[parent].children.iter().find(|c| self.nodes[**c].name == name) {
+            Some(child) => *child,
+            None => {
+                self.nodes.push(Node::new(name));
+                let child = self.nodes.len() - 1;
+                self.nodes[parent].children.push(child);
+                child
+            }
+        };
+        //the first of repeated keys wins
+        if seen.contains(&child) {
+            self.stack.push(Frame::Ignore(0));
+        } else {
+            seen.push(child);
+            self.nodes[child].present += 1;
+            *key = Some(child);
+        }
+        Ok(())
+    }
+
+    fn value(&mut self, node: usize, event: TypedStreamEvent) -> Result<(), DossError> {
+        match event {
+            TypedStreamEvent::NULL => self.nodes[node].nulls += 1,
+            TypedStreamEvent::STARTOBJECT => {
+                let n = &mut self.nodes[node];
+                n.count += 1;
+                n.objects += 1;
+                n.kind.merge(Kind::Struct);
+                self.stack.push(match n.kind {
+                    Kind::Struct => Frame::Object { node, key: None, seen: Vec::new() },
+                    _ => Frame::Ignore(1),
+                });
+            }
+            TypedStreamEvent::STARTARRAY => {
+                self.nodes[node].count += 1;
+                self.nodes[node].kind.merge(Kind::List);
+                if self.nodes[node].kind != Kind::List {
+                    self.stack.push(Frame::Ignore(1));
+                    return Ok(());
+                }
+                let item = match self.nodes[node].item {
+                    Some(item) => item,
+                    None => {
+                        self.nodes.push(Node::new(String::from("item")));
+                        self.nodes[node].item = Some(self.nodes.len() - 1);
+                        self.nodes.len() - 1
+                    }
+                };
+                self.stack.push(Frame::Array { item });
+            }
+            TypedStreamEvent::STARTTYPE(name) => self.stack.push(Frame::Type { node, name, content: Vec::new(), depth: 0 }),
+            event => {
+                let (data_type, text) = scalar(&event)?;
+                self.add_scalar(node, data_type, &text);
+            }
+        }
+        Ok(())
+    }
+
+    fn type_content(&mut self, event: TypedStreamEvent) -> Result<(), DossError> {
+        let Some(Frame::Type { node, name, content, depth }) = self.stack.last_mut() else {
+            unreachable!("only called inside types");
+        };
+        match event {
+            TypedStreamEvent::ENDTYPE if *depth == 0 => {
+                let (node, name, content) = (*node, std::mem::take(name), std::mem::take(content));
+                self.stack.pop();
+                match content.as_slice() {
+                    [(data_type, text)] if *data_type != DossFieldType::Mixed => self.add_scalar(node, DossFieldType::Typed(name.clone(), Box::new(data_type.clone())), &format!("{name}({text})")),
+                    _ => {
+                        self.nodes[node].count += 1;
+                        self.nodes[node].kind.merge(Kind::Mixed);
+                    }
+                }
+            }
+            TypedStreamEvent::STARTOBJECT | TypedStreamEvent::STARTARRAY | TypedStreamEvent::STARTTYPE(_) => {
+                //nested content is mixed
+                *depth += 1;
+                content.push((DossFieldType::Mixed, String::new()));
+            }
+            TypedStreamEvent::ENDOBJECT | TypedStreamEvent::ENDARRAY | TypedStreamEvent::ENDTYPE => *depth = depth.saturating_sub(1),
+            event if *depth == 0 => content.push(match event {
+                TypedStreamEvent::NULL => (DossFieldType::Null, String::from("null")),
+                event => scalar(&event)?,
+            }),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn add_scalar(&mut self, node: usize, data_type: DossFieldType, text: &str) {
+        let max_distinct = self.max_distinct;
+        let n = &mut self.nodes[node];
+        n.count += 1;
+        n.kind.merge(Kind::Scalar(data_type));
+        if !n.capped {
+            let mut hasher = DefaultHasher::new();
+            text.hash(&mut hasher);
+            n.distinct.insert(hasher.finish());
+            if n.distinct.len() > max_distinct {
+                //the set is not needed anymore
+                n.capped = true;
+                n.distinct = HashSet::new();
+            }
+        }
+    }
+
+    fn field(&self, node: usize, parent_objects: u64) -> DossField {
+        let n = &self.nodes[node];
+        let data_type = match &n.kind {
+            Kind::Null => DossFieldType::Null,
+            Kind::Scalar(t) => t.clone(),
+            Kind::Struct => DossFieldType::Struct(n.children.iter().map(|c| self.field(*c, n.objects)).collect()),
+            Kind::List => DossFieldType::List(Box::new(match n.item {
+                Some(item) => self.field(item, self.nodes[item].present),
+                None => DossField { name: String::from("item"), data_type: DossFieldType::Null, nullable: true, count: 0, cardinality: None },
+            })),
+            Kind::Mixed => DossFieldType::Mixed,
+        };
+        let cardinality = match (&n.kind, n.capped) {
+            (Kind::Null | Kind::Scalar(_), false) => Some(DossCardinality::Exact(n.distinct.len() as u64)),
+            (Kind::Null | Kind::Scalar(_), true) => Some(DossCardinality::AtLeast(self.max_distinct as u64 + 1)),
+            _ => None,
+        };
+        DossField {
+            name: n.name.clone(),
+            data_type,
+            nullable: n.nulls > 0 || n.present < parent_objects,
+            count: n.count,
+            cardinality,
+        }
+    }
+}
+
+impl DossEventHandler for DossSchemaInference {
+    fn on_event(&mut self, event: TypedStreamEvent) -> Result<TypedStreamEventReturn, TypedStreamEventError> {
+        match self.push(event) {
+            Ok(()) => Ok(TypedStreamEventReturn::CONTINUE),
+            Err(_) => Err(TypedStreamEventError::InvalidEvent),
+        }
+    }
+}
+
+pub(crate) fn depth_change(event: &TypedStreamEvent) -> isize {
+    match event {
+        TypedStreamEvent::STARTOBJECT | TypedStreamEvent::STARTARRAY | TypedStreamEvent::STARTTYPE(_) => 1,
+        TypedStreamEvent::ENDOBJECT | TypedStreamEvent::ENDARRAY | TypedStreamEvent::ENDTYPE => -1,
+        _ => 0,
+    }
+}
+
+/// Type and streamablejson text of a scalar event, numbers beyond i64 are decimals
+pub(crate) fn scalar(event: &TypedStreamEvent) -> Result<(DossFieldType, String), DossError> {
+    Ok(match event {
+        TypedStreamEvent::TRUE => (DossFieldType::Boolean, String::from("true")),
+        TypedStreamEvent::FALSE => (DossFieldType::Boolean, String::from("false")),
+        TypedStreamEvent::NULL => (DossFieldType::Null, String::from("null")),
+        TypedStreamEvent::DECIMAL(v) => (integer_type(*v as u64), v.to_string()),
+        TypedStreamEvent::FLOAT(f) => (DossFieldType::Float, f.to_string()),
+        TypedStreamEvent::DATETIME(d) => (DossFieldType::DateTime, format!("datetime(\"{d}\")")),
+        TypedStreamEvent::STRING(s) => (DossFieldType::String, quote(s)),
+        TypedStreamEvent::BYTEARRAY(b) => (DossFieldType::Binary, format!("bytes(\"{}\")", b.iter().map(|b| format!("{b:02x}")).collect::<String>())),
+        TypedStreamEvent::ANY(v) => {
+            if let Some(v) = v.downcast_ref::<i64>() {
+                (DossFieldType::Integer, v.to_string())
+            } else if let Some(v) = v.downcast_ref::<u64>() {
+                (integer_type(*v), v.to_string())
+            } else if let Some(d) = v.downcast_ref::<Decimal>() {
+                (decimal_type(d), format!("decimal(\"{d}\")"))
+            } else {
+                return Err(DossError::UnsupportedValue(String::from("any")));
+            }
+        }
+        _ => return Err(DossError::UnsupportedValue(String::from("scalar expected"))),
+    })
+}
+
+fn integer_type(v: u64) -> DossFieldType {
+    match i64::try_from(v) {
+        Ok(_) => DossFieldType::Integer,
+        Err(_) => DossFieldType::Decimal { digits: 20, scale: 0 },
+    }
+}
+
+/// Digits before and after the point of a decimal as written, `1.50` has a scale of 2
+pub(crate) fn decimal_type(d: &Decimal) -> DossFieldType {
+    let magnitude = Decimal::new(false, false, 0, d.values().into()).to_string().len() as i64;
+    let extension = d.extension() as i64;
+    let scale = (-extension).max(0);
+    let digits = (magnitude + extension).max(1);
+    DossFieldType::Decimal { digits: digits.min(u16::MAX as i64) as u16, scale: scale.min(u16::MAX as i64) as u16 }
+}
+
+/// Digits of a decimal type in total, more than a `u16` holds
+pub(crate) fn precision(digits: u16, scale: u16) -> u32 {
+    u32::from(digits) + u32::from(scale)
+}
+
+pub(crate) fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use streamablejson::deserializer::deserialize_orderedbag_from_string;
+
+    use super::*;
+    use crate::parser::DossPullParser;
+    use crate::serializer::DossSerializer;
+
+    fn doss(sjson: &str) -> Vec<u8> {
+        let mut serializer = DossSerializer::new(Vec::new());
+        serializer.write_entry(&deserialize_orderedbag_from_string(sjson.to_string()).unwrap()).unwrap();
+        serializer.finish().unwrap()
+    }
+
+    fn infer(sjson: &str) -> DossSchema {
+        let serialized = doss(sjson);
+        let mut inference = DossSchemaInference::with_max_distinct(3);
+        inference.push_all(DossPullParser::new(serialized.as_slice())).unwrap();
+        inference.schema()
+    }
+
+    #[test]
+    fn test_infer_records() {
+        let schema = infer(r#"[
+            {"id": 1, "name": "a", "price": decimal("1.50"), "tags": ["x", "y"], "customer": {"name": "c", "vip": true}},
+            {"id": 2, "name": null, "price": 3, "tags": [], "customer": {"name": "d"}, "oid": ObjectId("5f1")},
+            {"id": 3, "price": decimal("12.125"), "tags": ["x"], "customer": {"name": "c"}},
+            {"id": 4, "name": "b", "price": 1, "tags": ["z"], "customer": null}
+        ]"#);
+        assert_eq!(schema.records, 4);
+        let field = |path: &str| schema.field(path).unwrap();
+        assert_eq!(field("id").data_type, DossFieldType::Integer);
+        assert!(!field("id").nullable);
+        assert_eq!(field("id").cardinality, Some(DossCardinality::AtLeast(4)));
+        assert_eq!((&field("name").data_type, field("name").nullable, field("name").count), (&DossFieldType::String, true, 2));
+        assert_eq!(field("price").data_type, DossFieldType::Decimal { digits: 19, scale: 3 });
+        assert_eq!(field("tags[]").data_type, DossFieldType::String);
+        assert!(matches!(field("tags").data_type, DossFieldType::List(_)));
+        assert_eq!(field("tags[]").cardinality, Some(DossCardinality::Exact(3)));
+        assert!(field("customer").nullable);
+        assert!(!field("customer.name").nullable);
+        assert_eq!(field("customer.name").cardinality, Some(DossCardinality::Exact(2)));
+        assert!(field("customer.vip").nullable);
+        assert_eq!(field("oid").data_type, DossFieldType::Typed(String::from("ObjectId"), Box::new(DossFieldType::String)));
+        let paths: Vec<_> = schema.paths().into_iter().map(|(p, _)| p).collect();
+        assert_eq!(paths, ["id", "name", "price", "tags", "tags[]", "customer", "customer.name", "customer.vip", "oid"]);
+        assert!(schema.to_string().contains("price: decimal(22, 3) required, 4 values, >=4 distinct"));
+    }
+
+    #[test]
+    fn test_wide_decimals() {
+        let schema = infer(&format!(r#"[{{"d": decimal("1e-32000")}}, {{"d": decimal("{}e32000")}}]"#, "1".repeat(5000)));
+        assert_eq!(schema.field("d").unwrap().data_type, DossFieldType::Decimal { digits: 37000, scale: 32000 });
+        assert!(schema.to_string().contains("d: decimal(69000, 32000)"));
+    }
+
+    #[test]
+    fn test_mixed_and_nested_lists() {
+        let serialized = [doss(r#"{"v": 1, "m": [[1, 2], [3]], "t": Point([1, 2])}"#), doss(r#"{"v": "one", "m": [], "t": Point([3, 4])}"#)].concat();
+        let mut inference = DossSchemaInference::new();
+        inference.push_all(DossPullParser::new(serialized.as_slice())).unwrap();
+        let schema = inference.schema();
+        assert_eq!(schema.records, 2);
+        assert_eq!(schema.field("v").unwrap().data_type, DossFieldType::Mixed);
+        assert_eq!(schema.field("v").unwrap().cardinality, None);
+        assert_eq!(schema.field("m[]").unwrap().data_type.to_string(), "list<integer>");
+        assert_eq!(schema.field("t").unwrap().data_type, DossFieldType::Mixed);
+
+        let mut inference = DossSchemaInference::new();
+        for event in [TypedStreamEvent::STARTOBJECT, TypedStreamEvent::STRING(String::from("score")), TypedStreamEvent::DECIMAL(2), TypedStreamEvent::ENDOBJECT,
+            TypedStreamEvent::STARTOBJECT, TypedStreamEvent::STRING(String::from("score")), TypedStreamEvent::FLOAT(0.5), TypedStreamEvent::ENDOBJECT] {
+            inference.push(event).unwrap();
+        }
+        assert_eq!(inference.schema().field("score").unwrap().data_type, DossFieldType::Float);
+
+        let mut inference = DossSchemaInference::new();
+        let serialized = doss(r#"["not a record"]"#);
+        assert!(matches!(inference.push_all(DossPullParser::new(serialized.as_slice())), Err(DossError::SchemaMismatch(_))));
+    }
+}
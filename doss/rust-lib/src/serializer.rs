@@ -0,0 +1,921 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Seek, Write};
+
+use dataflowgrid_commons::typedstream::TypedStreamEvent;
+use streamablejson::StreamableJSONEntry;
+use streamablejson::parser::StreamableJSONReaderEvent;
+
+use crate::compression::{Compression, FrameWriter};
+use crate::deserializer::DossLowLevelStreamEvent;
+use crate::error::DossError;
+use crate::registry::DossPredefinedDictionary;
+use crate::resolver::DossEvent;
+use crate::settings::{DossSettings, StringEncoding};
+use crate::skip::SkipWriter;
+use crate::text::{DossTextEvents, constant_event, typed_value};
+use crate::types::{Decimal, DossFloat};
+use crate::varint;
+
+/// hint key announcing the highest number of dict entries the serializer uses
+pub const MAX_DICT_ENTRIES_HINT: &str = "max_dict_entries";
+
+/// Controls how the [`DossSerializer`] builds the dictionary
+#[derive(Debug, Clone)]
+pub struct DossSerializerOptions {
+    /// number of dict entries used, once full the least recently used entry is replaced. 0 disables the dictionary
+    pub max_dict_entries: usize,
+    /// a string or binary is stored once it was seen this often
+    pub min_occurrences: usize,
+    /// announce `max_dict_entries` with a hint at the start of the stream
+    pub emit_dict_hint: bool,
+    /// blocks and arrays with more content bytes get a skip opcode, None disables skipping
+    pub skip_threshold: Option<usize>,
+    /// bytes held back so skips of blocks up to this size are filled in without seeking
+    pub skip_buffer: usize,
+    /// compression of everything following the dict hint, see setting 2 in settings_and_hints.md
+    pub compression: Compression,
+    /// uncompressed bytes per compressed frame, [`DossSerializer::flush`] ends a frame early
+    pub frame_size: usize,
+}
+
+impl Default for DossSerializerOptions {
+    fn default() -> Self {
+        DossSerializerOptions {
+            max_dict_entries: 1 << 16,
+            min_occurrences: 2,
+            emit_dict_hint: true,
+            skip_threshold: Some(4096),
+            skip_buffer: 1 << 20,
+            compression: Compression::None,
+            frame_size: 1 << 16,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Candidate {
+    count: usize,
+    stored: Option<(usize, u64)>, //dict index and last use
+}
+
+/// Dictionary state at the start of a stack (25)
+#[derive(Debug)]
+struct Stack {
+    pointer: usize,
+    len: usize,
+    depth: usize, //nesting level the stack must end in
+    undo: Vec<(usize, Vec<u8>)>, //dict index and the value before the change
+}
+
+#[derive(Debug, PartialEq)]
+enum Nesting {
+    Block,
+    Array,
+    Type,
+}
+
+/// Writes DOSS opcode streams and fills the dictionary automatically.
+///
+/// Strings and binaries are the dictionary candidates. Streamed values are stored when seen for the
+/// `min_occurrences`th time, whole trees written with [`DossSerializer::write_entry`] are counted first
+/// so repeated values are stored on their first occurrence. Values are only stored if a reference is
+/// shorter than the value itself.
+///
+/// Large blocks and arrays start with a skip opcode, see [`DossSerializerOptions::skip_threshold`].
+/// Its target is filled in when the block ends, in memory for blocks that fit into
+/// [`DossSerializerOptions::skip_buffer`]. Larger blocks are patched by seeking back if the serializer was
+/// created with [`DossSerializer::seekable`], otherwise their target stays 0.
+///
+/// With a [`DossSerializerOptions::compression`] the bytes are written as compressed frames, skip targets
+/// are then only filled in for blocks that end within the skip buffer or the same frame.
+#[derive(Debug)]
+pub struct DossSerializer<W: Write> {
+    out: SkipWriter<FrameWriter<W>>,
+    options: DossSerializerOptions,
+    buffer: Vec<u8>,
+    candidates: HashMap<Vec<u8>, Candidate>,
+    planned: HashMap<Vec<u8>, usize>,
+    entries: Vec<Vec<u8>>, //encoded value of every dict index
+    lru: BTreeMap<u64, usize>, //last use -> dict index
+    tick: u64,
+    pointer: usize, //dict pointer of the reader
+    stacks: Vec<Stack>,
+    started: bool,
+    nesting: Vec<Nesting>,
+    encoding: StringEncoding, //of the string opcodes, setting 1
+    text: DossTextEvents,
+    text_buffer: Vec<DossEvent>, //events of the last reader event, kept for its allocation
+}
+
+impl<W: Write> DossSerializer<W> {
+    pub fn new(out: W) -> Self {
+        Self::with_options(out, DossSerializerOptions::default())
+    }
+
+    pub fn with_options(out: W, options: DossSerializerOptions) -> Self {
+        let out = SkipWriter::framed(FrameWriter::new(out, options.frame_size), options.skip_threshold, options.skip_buffer);
+        Self::build(out, options)
+    }
+
+    /// A serializer that seeks back to fill in skip targets, e.g. for files
+    pub fn seekable(out: W, options: DossSerializerOptions) -> Self where W: Seek {
+        let out = SkipWriter::framed(FrameWriter::seekable(out, options.frame_size), options.skip_threshold, options.skip_buffer);
+        Self::build(out, options)
+    }
+
+    /// Continues a stream that ended with `settings`, e.g. to add a file after the files written before.
+    /// The string encoding is switched back to utf8 and the dict pointer reset, so the existing
+    /// dictionary entries are overwritten from index 0. The dict size hint is not repeated, the compression
+    /// is switched if it differs from the options
+    pub fn appending(out: W, settings: &DossSettings, options: DossSerializerOptions) -> Result<Self, DossError> {
+        let mut serializer = Self::with_options(out, options);
+        serializer.started = true;
+        serializer.out.inner_mut().set_compression(settings.compression())?;
+        let encoding = settings.string_encoding();
+        if encoding != StringEncoding::Utf8 {
+            //the setting's own value is still decoded with the old encoding
+            serializer.encoding = encoding;
+            serializer.emit(&DossLowLevelStreamEvent::SetConfig)?;
+            serializer.emit(&DossLowLevelStreamEvent::UnsignedVarint(1))?;
+            serializer.emit(&DossLowLevelStreamEvent::String(String::from(StringEncoding::Utf8.name())))?;
+            serializer.encoding = StringEncoding::Utf8;
+        }
+        serializer.emit(&DossLowLevelStreamEvent::SetDictPointer(0))?;
+        if serializer.options.compression != settings.compression() {
+            serializer.set_compression(serializer.options.compression)?;
+        }
+        Ok(serializer)
+    }
+
+    fn build(out: SkipWriter<FrameWriter<W>>, options: DossSerializerOptions) -> Self {
+        DossSerializer {
+            out,
+            options,
+            buffer: Vec::new(),
+            candidates: HashMap::new(),
+            planned: HashMap::new(),
+            entries: Vec::new(),
+            lru: BTreeMap::new(),
+            tick: 0,
+            pointer: 0,
+            stacks: Vec::new(),
+            started: false,
+            nesting: Vec::new(),
+            encoding: StringEncoding::Utf8,
+            text: DossTextEvents::new(),
+            text_buffer: Vec::new(),
+        }
+    }
+
+    /// Writes one resolved event, the structure must be balanced by later calls
+    pub fn write_doss_event(&mut self, event: &DossEvent) -> Result<(), DossError> {
+        self.start()?;
+        match event {
+            DossEvent::BlockStart => self.open(Nesting::Block, DossLowLevelStreamEvent::BlockStart),
+            DossEvent::ArrayStart => self.open(Nesting::Array, DossLowLevelStreamEvent::ArrayStart),
+            DossEvent::TypeStart(name) => {
+                self.open(Nesting::Type, DossLowLevelStreamEvent::TypeStart)?;
+                self.write_scalar(DossLowLevelStreamEvent::String(name.clone()))
+            }
+            DossEvent::BlockEnd => self.close(Nesting::Block, DossLowLevelStreamEvent::BlockEnd),
+            DossEvent::ArrayEnd => self.close(Nesting::Array, DossLowLevelStreamEvent::ArrayEnd),
+            DossEvent::TypeEnd => self.close(Nesting::Type, DossLowLevelStreamEvent::TypeEnd),
+            DossEvent::True => self.emit(&DossLowLevelStreamEvent::True),
+            DossEvent::False => self.emit(&DossLowLevelStreamEvent::False),
+            DossEvent::Null => self.emit(&DossLowLevelStreamEvent::Null),
+            DossEvent::Int(v) if *v >= 0 => self.emit(&DossLowLevelStreamEvent::UnsignedVarint(*v as u64)),
+            DossEvent::Int(v) => self.emit(&DossLowLevelStreamEvent::Varint(*v)),
+            DossEvent::UInt(v) => self.emit(&DossLowLevelStreamEvent::UnsignedVarint(*v)),
+            DossEvent::Decimal(d) => self.emit(&DossLowLevelStreamEvent::Decimal(d.clone())),
+            //neither JSON nor streamablejson can write them
+            DossEvent::Float(f) if !f.to_f64().is_finite() => Err(DossError::UnsupportedValue(format!("float {f}"))),
+            DossEvent::Float(f) => self.emit(&DossLowLevelStreamEvent::Float(*f)),
+            DossEvent::DateTime(d) => self.emit(&DossLowLevelStreamEvent::DateTime(*d)),
+            DossEvent::String(s) => self.write_scalar(DossLowLevelStreamEvent::String(s.clone())),
+            DossEvent::Binary(b) => self.write_scalar(DossLowLevelStreamEvent::Binary(b.clone())),
+            DossEvent::Config { key, value } if let Some(compression) = compression_setting(key, value) => {
+                if !self.nesting.is_empty() {
+                    return Err(DossError::InvalidSetting(String::from("compression inside a block")));
+                }
+                self.set_compression(compression?)
+            }
+            DossEvent::Config { key, value } => {
+                let encoding = string_encoding_setting(key, value).transpose()?;
+                self.out.mark_stateful();
+                self.emit(&DossLowLevelStreamEvent::SetConfig)?;
+                //the setting's own value is still written with the old encoding
+                self.write_items(&[key, value])?;
+                if let Some(encoding) = encoding {
+                    self.encoding = encoding;
+                }
+                Ok(())
+            }
+            DossEvent::Hint { key, value } => {
+                self.emit(&DossLowLevelStreamEvent::SetHint)?;
+                self.write_items(&[key, value])
+            }
+            DossEvent::ImportDict(name) => {
+                self.out.mark_dict_changed();
+                self.emit(&DossLowLevelStreamEvent::ImportDict)?;
+                self.write_items(&[name])
+            }
+            DossEvent::FileStart(name) => {
+                if !self.nesting.is_empty() {
+                    return Err(DossError::UnbalancedStructure);
+                }
+                self.emit(&DossLowLevelStreamEvent::FileStart)?;
+                self.write_items(&[name])
+            }
+            DossEvent::StackStart => self.start_stack(),
+            DossEvent::StackEnd => self.leave_stack(),
+        }
+    }
+
+    /// Imports a predefined dictionary (40). Its entries are stored from the current dict pointer on and
+    /// strings and binaries among them are written as references from now on. Readers need the same
+    /// dictionary in their [`DossDictionaryRegistry`](crate::DossDictionaryRegistry)
+    pub fn import_dictionary(&mut self, dict: &DossPredefinedDictionary) -> Result<(), DossError> {
+        self.start()?;
+        let max = self.options.max_dict_entries;
+        if self.pointer + dict.entries().len() > max {
+            return Err(DossError::DictFull);
+        }
+        self.out.mark_dict_changed();
+        self.emit(&DossLowLevelStreamEvent::ImportDict)?;
+        //written without the dictionary, storing the name would move the pointer
+        for event in dict.import_item() {
+            self.emit(&match event {
+                DossEvent::ArrayStart => DossLowLevelStreamEvent::ArrayStart,
+                DossEvent::ArrayEnd => DossLowLevelStreamEvent::ArrayEnd,
+                DossEvent::String(s) => DossLowLevelStreamEvent::String(s),
+                DossEvent::UInt(v) => DossLowLevelStreamEvent::UnsignedVarint(v),
+                _ => unreachable!("import items consist of arrays, strings and numbers"),
+            })?;
+        }
+        for entry in dict.entries() {
+            let encoded = match entry.as_slice() {
+                [DossEvent::String(s)] => Some(event_bytes(&DossLowLevelStreamEvent::String(s.clone()))),
+                [DossEvent::Binary(b)] => Some(event_bytes(&DossLowLevelStreamEvent::Binary(b.clone()))),
+                _ => None,
+            };
+            self.tick += 1;
+            self.assign(self.pointer, encoded.unwrap_or_default(), self.tick);
+            self.pointer += 1;
+        }
+        Ok(())
+    }
+
+    /// Writes one event of a typed stream. `INIT` and `FINISH` are ignored, call [`DossSerializer::finish`] at the end
+    pub fn write_event(&mut self, event: &TypedStreamEvent) -> Result<(), DossError> {
+        match event {
+            TypedStreamEvent::INIT | TypedStreamEvent::FINISH => Ok(()),
+            TypedStreamEvent::STARTOBJECT => self.write_doss_event(&DossEvent::BlockStart),
+            TypedStreamEvent::ENDOBJECT => self.write_doss_event(&DossEvent::BlockEnd),
+            TypedStreamEvent::STARTARRAY => self.write_doss_event(&DossEvent::ArrayStart),
+            TypedStreamEvent::ENDARRAY => self.write_doss_event(&DossEvent::ArrayEnd),
+            TypedStreamEvent::STARTTYPE(name) => self.write_doss_event(&DossEvent::TypeStart(name.clone())),
+            TypedStreamEvent::ENDTYPE => self.write_doss_event(&DossEvent::TypeEnd),
+            TypedStreamEvent::STRING(s) => self.write_doss_event(&DossEvent::String(s.clone())),
+            TypedStreamEvent::DECIMAL(v) => self.write_doss_event(&DossEvent::UInt(*v as u64)),
+            TypedStreamEvent::FLOAT(v) => self.write_doss_event(&DossEvent::Float(DossFloat::shortest(*v))),
+            TypedStreamEvent::DATETIME(d) => self.write_doss_event(&DossEvent::DateTime(*d)),
+            TypedStreamEvent::NULL => self.write_doss_event(&DossEvent::Null),
+            TypedStreamEvent::TRUE => self.write_doss_event(&DossEvent::True),
+            TypedStreamEvent::FALSE => self.write_doss_event(&DossEvent::False),
+            TypedStreamEvent::BYTEARRAY(b) => self.write_doss_event(&DossEvent::Binary(b.clone())),
+            //the inverse of DossEvent::to_typed_stream_event
+            TypedStreamEvent::HINT(hint) => self.write_doss_event(&match hint.split_once('=') {
+                Some((key, value)) => DossEvent::Hint {
+                    key: vec![DossEvent::String(key.to_string())],
+                    value: vec![DossEvent::String(value.to_string())],
+                },
+                None => DossEvent::Hint {
+                    key: vec![DossEvent::String(hint.clone())],
+                    value: vec![DossEvent::Null],
+                },
+            }),
+            TypedStreamEvent::ANY(v) => {
+                if let Some(d) = v.downcast_ref::<Decimal>() {
+                    self.write_doss_event(&DossEvent::Decimal(d.clone()))
+                } else if let Some(v) = v.downcast_ref::<i64>() {
+                    self.write_doss_event(&DossEvent::Int(*v))
+                } else if let Some(v) = v.downcast_ref::<u64>() {
+                    self.write_doss_event(&DossEvent::UInt(*v))
+                } else {
+                    Err(DossError::UnsupportedValue(String::from("any")))
+                }
+            }
+            TypedStreamEvent::ERROR(e) => Err(DossError::UnsupportedValue(e.to_string())),
+        }
+    }
+
+    /// Writes a complete tree. Constants must be `true`, `false`, `null` or numbers
+    pub fn write_entry(&mut self, entry: &StreamableJSONEntry) -> Result<(), DossError> {
+        if self.options.max_dict_entries > 0 {
+            count_scalars(entry, &mut self.planned);
+        }
+        let r = self.write_entry_events(entry);
+        self.planned.clear();
+        r
+    }
+
+    fn write_entry_events(&mut self, entry: &StreamableJSONEntry) -> Result<(), DossError> {
+        match entry {
+            StreamableJSONEntry::Object(bag) => {
+                self.write_doss_event(&DossEvent::BlockStart)?;
+                for (key, value) in bag.iter() {
+                    self.write_entry_events(key)?;
+                    self.write_entry_events(value)?;
+                }
+                self.write_doss_event(&DossEvent::BlockEnd)
+            }
+            StreamableJSONEntry::Array(items) => {
+                self.write_doss_event(&DossEvent::ArrayStart)?;
+                for item in items {
+                    self.write_entry_events(item)?;
+                }
+                self.write_doss_event(&DossEvent::ArrayEnd)
+            }
+            StreamableJSONEntry::Type(name, content) if let Some(event) = typed_value(name, content) => self.write_doss_event(&event),
+            StreamableJSONEntry::Type(name, content) => {
+                self.write_doss_event(&DossEvent::TypeStart(name.clone()))?;
+                for item in content {
+                    self.write_entry_events(item)?;
+                }
+                self.write_doss_event(&DossEvent::TypeEnd)
+            }
+            StreamableJSONEntry::String(s) => self.write_doss_event(&DossEvent::String(s.clone())),
+            StreamableJSONEntry::Constant(c) => self.write_doss_event(&constant_event(c)?),
+        }
+    }
+
+    /// Writes one event of a [`StreamableJSONReader`](streamablejson::parser::StreamableJSONReader), so text is encoded while
+    /// it is parsed. Typed values like `decimal("1.50")` are held back until their type ends
+    pub fn write_reader_event(&mut self, event: StreamableJSONReaderEvent) -> Result<(), DossError> {
+        let mut events = std::mem::take(&mut self.text_buffer);
+        let mut r = self.text.push(event, &mut events);
+        for event in events.drain(..) {
+            if r.is_ok() {
+                r = self.write_doss_event(&event);
+            }
+        }
+        self.text_buffer = events;
+        r
+    }
+
+    /// Writes everything held back and ends the current compressed frame, so a reader can decode all events
+    /// written so far. Skips of blocks still open are not filled in unless they can be patched by seeking
+    pub fn flush(&mut self) -> Result<(), DossError> {
+        Ok(self.out.flush()?)
+    }
+
+    /// Checks that all structures are closed, flushes and returns the writer
+    pub fn finish(self) -> Result<W, DossError> {
+        if !self.nesting.is_empty() || !self.stacks.is_empty() || self.text.is_pending() {
+            return Err(DossError::UnbalancedStructure);
+        }
+        Ok(self.out.finish()?.finish()?)
+    }
+
+    fn start(&mut self) -> Result<(), DossError> {
+        if self.started {
+            return Ok(());
+        }
+        self.started = true;
+        if self.options.emit_dict_hint && self.options.max_dict_entries > 0 {
+            self.emit(&DossLowLevelStreamEvent::SetHint)?;
+            self.emit(&DossLowLevelStreamEvent::String(String::from(MAX_DICT_ENTRIES_HINT)))?;
+            self.emit(&DossLowLevelStreamEvent::UnsignedVarint(self.options.max_dict_entries as u64))?;
+        }
+        if self.options.compression != Compression::None {
+            self.set_compression(self.options.compression)?;
+        }
+        Ok(())
+    }
+
+    //writes setting 2, the bytes following it use the new compression
+    fn set_compression(&mut self, compression: Compression) -> Result<(), DossError> {
+        self.emit(&DossLowLevelStreamEvent::SetConfig)?;
+        self.emit(&DossLowLevelStreamEvent::UnsignedVarint(2))?;
+        self.emit(&DossLowLevelStreamEvent::String(String::from(compression.name())))?;
+        self.out.flush()?;
+        Ok(self.out.inner_mut().set_compression(compression)?)
+    }
+
+    fn write_items(&mut self, items: &[&Vec<DossEvent>]) -> Result<(), DossError> {
+        for item in items {
+            if item.is_empty() {
+                return Err(DossError::ItemExpected);
+            }
+            for event in item.iter() {
+                self.write_doss_event(event)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn open(&mut self, nesting: Nesting, event: DossLowLevelStreamEvent) -> Result<(), DossError> {
+        let skippable = nesting != Nesting::Type;
+        self.nesting.push(nesting);
+        self.emit(&event)?;
+        if skippable {
+            self.out.open();
+        }
+        Ok(())
+    }
+
+    fn close(&mut self, nesting: Nesting, event: DossLowLevelStreamEvent) -> Result<(), DossError> {
+        match self.nesting.pop() {
+            Some(n) if n == nesting => {
+                if nesting != Nesting::Type {
+                    self.out.close()?;
+                }
+                self.emit(&event)
+            }
+            _ => Err(DossError::UnbalancedStructure)
+        }
+    }
+
+    fn emit(&mut self, event: &DossLowLevelStreamEvent) -> Result<(), DossError> {
+        self.buffer.clear();
+        event.encode_with(self.encoding, &mut self.buffer)?;
+        self.out.write(&self.buffer)?;
+        Ok(())
+    }
+
+    /// Writes a string or binary either as reference, stored in the dict or plain
+    fn write_scalar(&mut self, event: DossLowLevelStreamEvent) -> Result<(), DossError> {
+        let max = self.options.max_dict_entries;
+        if max == 0 {
+            return self.emit(&event);
+        }
+        //the dictionary knows values by their utf8 bytes, written is what the stream gets
+        let encoded = event_bytes(&event);
+        let mut written = Vec::new();
+        event.encode_with(self.encoding, &mut written)?;
+        self.tick += 1;
+        let tick = self.tick;
+
+        let candidate = self.candidates.entry(encoded.clone()).or_default();
+        if let Some((index, last_use)) = &mut candidate.stored {
+            let index = *index;
+            self.lru.remove(last_use);
+            *last_use = tick;
+            self.lru.insert(tick, index);
+            return self.emit(&DossLowLevelStreamEvent::Reference(index as u64));
+        }
+        candidate.count += 1;
+        let count = candidate.count;
+
+        let planned = self.planned.get(&encoded).copied().unwrap_or(0);
+        let reference_len = 1 + varint::unsigned_len(max as u64 - 1);
+        if count.max(planned) < self.options.min_occurrences || written.len() <= reference_len {
+            self.trim_candidates();
+            return self.out.write(&written).map_err(DossError::from);
+        }
+
+        let index = if self.entries.len() < max {
+            self.entries.len()
+        } else if let Some((_, index)) = self.lru.first_key_value() {
+            //replace the least recently used entry
+            *index
+        } else {
+            //only imported subtrees, they are never replaced
+            return self.out.write(&written).map_err(DossError::from);
+        };
+        if index != self.pointer {
+            self.emit(&DossLowLevelStreamEvent::SetDictPointer(index as u64))?;
+        }
+        self.pointer = index + 1;
+        self.assign(index, encoded, tick);
+        self.out.mark_dict_changed();
+        self.emit(&DossLowLevelStreamEvent::StoreInDict)?;
+        self.out.write(&written)?;
+        Ok(())
+    }
+
+    /// Sets the value of a dict index. Empty values (imported subtrees) are never referenced or replaced.
+    /// Changes inside a stack are logged, so leaving it restores what the reader has
+    fn assign(&mut self, index: usize, value: Vec<u8>, tick: u64) {
+        if index == self.entries.len() {
+            self.entries.push(Vec::new());
+        }
+        let old = std::mem::replace(&mut self.entries[index], value.clone());
+        if let Some(Candidate { stored: Some((_, last_use)), .. }) = self.candidates.remove(&old) {
+            self.lru.remove(&last_use);
+        }
+        if let Some(stack) = self.stacks.last_mut() {
+            stack.undo.push((index, old));
+        }
+        if value.is_empty() {
+            return;
+        }
+        //a value stored twice is only referenced at its new index
+        if let Some(Candidate { stored: Some((other, _)), .. }) = self.candidates.get(&value)
+            && *other != index {
+            self.assign(*other, Vec::new(), tick);
+        }
+        self.lru.insert(tick, index);
+        self.candidates.entry(value).or_default().stored = Some((index, tick));
+    }
+
+    fn start_stack(&mut self) -> Result<(), DossError> {
+        self.out.start_stack();
+        self.stacks.push(Stack { pointer: self.pointer, len: self.entries.len(), depth: self.nesting.len(), undo: Vec::new() });
+        self.emit(&DossLowLevelStreamEvent::StackStart)
+    }
+
+    /// Leaves the innermost stack, it must end at the nesting level it started in
+    fn leave_stack(&mut self) -> Result<(), DossError> {
+        if self.stacks.last().is_none_or(|s| s.depth != self.nesting.len()) {
+            return Err(DossError::UnbalancedStructure);
+        }
+        let stack = self.stacks.pop().unwrap(); //checked above
+        self.emit(&DossLowLevelStreamEvent::StackEnd)?;
+        //the undo itself must not be logged in the outer stacks
+        let outer = std::mem::take(&mut self.stacks);
+        self.tick += 1;
+        for (index, value) in stack.undo.into_iter().rev() {
+            self.assign(index, value, self.tick);
+        }
+        self.stacks = outer;
+        self.entries.truncate(stack.len);
+        self.pointer = stack.pointer;
+        self.out.leave_stack();
+        Ok(())
+    }
+
+    /// Keeps the counting of values seen only once bounded
+    fn trim_candidates(&mut self) {
+        if self.candidates.len() > 4 * self.options.max_dict_entries + 1024 {
+            self.candidates.retain(|_, c| c.stored.is_some());
+        }
+    }
+}
+
+//the compression if the setting is setting 2, an error if its value is not a known compression
+fn compression_setting(key: &[DossEvent], value: &[DossEvent]) -> Option<Result<Compression, DossError>> {
+    match key {
+        [DossEvent::UInt(2)] | [DossEvent::Int(2)] => Some(match value {
+            [DossEvent::String(name)] => Compression::from_name(name).ok_or_else(|| DossError::InvalidSetting(format!("compression {name}"))),
+            _ => Err(DossError::InvalidSetting(format!("compression {value:?}"))),
+        }),
+        _ => None
+    }
+}
+
+//the encoding if the setting is setting 1, an error if its value is not a known encoding
+fn string_encoding_setting(key: &[DossEvent], value: &[DossEvent]) -> Option<Result<StringEncoding, DossError>> {
+    match key {
+        [DossEvent::UInt(1)] | [DossEvent::Int(1)] => Some(match value {
+            [DossEvent::String(name)] => StringEncoding::from_name(name).ok_or_else(|| DossError::InvalidSetting(format!("string encoding {name}"))),
+            _ => Err(DossError::InvalidSetting(format!("string encoding {value:?}"))),
+        }),
+        _ => None
+    }
+}
+
+fn event_bytes(event: &DossLowLevelStreamEvent) -> Vec<u8> {
+    let mut out = Vec::new();
+    event.encode(&mut out);
+    out
+}
+
+/// Counts the encoded strings of a tree, type names included
+fn count_scalars(entry: &StreamableJSONEntry, counts: &mut HashMap<Vec<u8>, usize>) {
+    match entry {
+        StreamableJSONEntry::Object(bag) => {
+            for (key, value) in bag.iter() {
+                count_scalars(key, counts);
+                count_scalars(value, counts);
+            }
+        }
+        StreamableJSONEntry::Array(items) => items.iter().for_each(|i| count_scalars(i, counts)),
+        StreamableJSONEntry::Type(name, content) => {
+            *counts.entry(event_bytes(&DossLowLevelStreamEvent::String(name.clone()))).or_default() += 1;
+            content.iter().for_each(|i| count_scalars(i, counts));
+        }
+        StreamableJSONEntry::String(s) => {
+            *counts.entry(event_bytes(&DossLowLevelStreamEvent::String(s.clone()))).or_default() += 1;
+        }
+        StreamableJSONEntry::Constant(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dataflowgrid_commons::typedstream::DateTime;
+    use crate::deserializer::DossLowLevelStreamEvent as E;
+    use crate::parser::DossPullParser;
+    use crate::resolver::DossResolver;
+    use crate::tree::decode_entries;
+    use dataflowgrid_commons::readers::reader::IteratorReadable;
+    use streamablejson::deserializer::deserialize_orderedbag_from_string;
+    use streamablejson::parser::{StreamableJSONReader, StreamableJSONReaderCallback, StreamableJSONReaderCallbackReturn};
+
+    fn plain() -> DossSerializerOptions {
+        DossSerializerOptions { emit_dict_hint: false, ..DossSerializerOptions::default() }
+    }
+
+    fn serialize(json: &str, options: DossSerializerOptions) -> Vec<u8> {
+        let entry = deserialize_orderedbag_from_string(json.to_string()).unwrap();
+        let mut serializer = DossSerializer::with_options(Vec::new(), options);
+        serializer.write_entry(&entry).unwrap();
+        serializer.finish().unwrap()
+    }
+
+    fn roundtrip(json: &str) -> Vec<u8> {
+        let serialized = serialize(json, DossSerializerOptions::default());
+        let decoded = decode_entries(&serialized).unwrap();
+        assert_eq!(decoded, vec![deserialize_orderedbag_from_string(json.to_string()).unwrap()], "{json}");
+        serialized
+    }
+
+    fn encode(events: &[E]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for e in events {
+            e.encode(&mut out);
+        }
+        out
+    }
+
+    fn s(v: &str) -> E {
+        E::String(v.to_string())
+    }
+
+    #[test]
+    fn test_examples_roundtrip() {
+        //the JSON examples of docs/examples.md
+        for json in [
+            r#"{ "hello": "world"}"#,
+            r#"{ "hello": "world", "say": "hello" }"#,
+            r#"{ "hello": true, "say": null }"#,
+            r#"{ "hello": { "say": "hello" } }"#,
+            r#"{ "hello": ["say", "hello"] }"#,
+            r#"{}"#,
+        ] {
+            roundtrip(json);
+        }
+        roundtrip(r#"{"n": [0, 1, 300, 18446744073709551615], "t": date("2025-01-01"), "u": date("2025-01-02"), "f": false}"#);
+        roundtrip(r#"[-1, -300, 1.5]"#);
+    }
+
+    #[test]
+    fn test_examples_bytes() {
+        assert_eq!(serialize(r#"{ "hello": "world", "say": "hello" }"#, plain()), encode(&[
+            E::BlockStart, E::StoreInDict, s("hello"), s("world"), s("say"), E::Reference(0), E::BlockEnd,
+        ]));
+        assert_eq!(serialize(r#"{ "hello": true, "say": null }"#, plain()), encode(&[
+            E::BlockStart, s("hello"), E::True, s("say"), E::Null, E::BlockEnd,
+        ]));
+        assert_eq!(serialize(r#"{ "hello": ["say", "hello"] }"#, plain()), encode(&[
+            E::BlockStart, E::StoreInDict, s("hello"), E::ArrayStart, s("say"), E::Reference(0), E::ArrayEnd, E::BlockEnd,
+        ]));
+        let hinted = serialize(r#"{}"#, DossSerializerOptions::default());
+        assert_eq!(hinted, encode(&[
+            E::SetHint, s(MAX_DICT_ENTRIES_HINT), E::UnsignedVarint(1 << 16), E::BlockStart, E::BlockEnd,
+        ]));
+    }
+
+    #[test]
+    fn test_dictionary_saves_space() {
+        let json = format!("[{}]", vec![r#"{"name": "some longer value", "kind": "repeated"}"#; 100].join(","));
+        let with_dict = roundtrip(&json);
+        let without = serialize(&json, DossSerializerOptions { max_dict_entries: 0, ..plain() });
+        assert_eq!(decode_entries(&without).unwrap(), decode_entries(&with_dict).unwrap());
+        assert!(with_dict.len() * 3 < without.len(), "{} vs {}", with_dict.len(), without.len());
+    }
+
+    #[test]
+    fn test_streamed_events_and_lru() {
+        let options = DossSerializerOptions { max_dict_entries: 2, ..DossSerializerOptions::default() };
+        let mut serializer = DossSerializer::with_options(Vec::new(), options);
+        let words = ["alpha", "beta", "alpha", "gamma", "gamma", "beta", "alpha", "gamma", "delta", "delta"];
+        serializer.write_event(&TypedStreamEvent::INIT).unwrap();
+        serializer.write_event(&TypedStreamEvent::STARTARRAY).unwrap();
+        for w in words {
+            serializer.write_event(&TypedStreamEvent::STRING(w.to_string())).unwrap();
+        }
+        serializer.write_event(&TypedStreamEvent::HINT(String::from("generated_with=test"))).unwrap();
+        serializer.write_event(&TypedStreamEvent::DECIMAL(7)).unwrap();
+        serializer.write_event(&TypedStreamEvent::BYTEARRAY(vec![1, 2])).unwrap();
+        serializer.write_event(&TypedStreamEvent::ENDARRAY).unwrap();
+        serializer.write_event(&TypedStreamEvent::FINISH).unwrap();
+        let serialized = serializer.finish().unwrap();
+
+        let mut resolver = DossResolver::new();
+        let mut events = Vec::new();
+        let mut pos = 0;
+        while pos < serialized.len() {
+            let (event, used) = E::decode(&serialized[pos..]).unwrap().unwrap();
+            pos += used;
+            resolver.push(event, &mut events).unwrap();
+            assert!(resolver.dictionary().len() <= 2);
+        }
+        assert_eq!(events[0], DossEvent::Hint {
+            key: vec![DossEvent::String(MAX_DICT_ENTRIES_HINT.to_string())],
+            value: vec![DossEvent::UInt(2)],
+        });
+        let mut expected = vec![DossEvent::ArrayStart];
+        expected.extend(words.iter().map(|w| DossEvent::String(w.to_string())));
+        expected.extend([DossEvent::UInt(7), DossEvent::Binary(vec![1, 2]), DossEvent::ArrayEnd]);
+        let hint = events.remove(1 + expected.len() - 3);
+        assert_eq!(events[1..], expected);
+        assert_eq!(resolver.settings().generated_with(), Some("test"));
+        assert!(matches!(hint.to_typed_stream_event(), Some(TypedStreamEvent::HINT(h)) if h == "generated_with=test"));
+    }
+
+    #[test]
+    fn test_value_types() {
+        let datetime = "2025-03-01T13:30:00.250+01:00".parse::<DateTime>().unwrap();
+        let typed = [
+            TypedStreamEvent::STARTARRAY,
+            TypedStreamEvent::FLOAT(1.5),
+            TypedStreamEvent::FLOAT(0.1),
+            TypedStreamEvent::FLOAT(f64::MAX),
+            TypedStreamEvent::DATETIME(datetime),
+            TypedStreamEvent::BYTEARRAY(vec![0, 255]),
+            TypedStreamEvent::ENDARRAY,
+        ];
+        let decimal = "-12345678901234567890.50".parse::<Decimal>().unwrap();
+        let mut serializer = DossSerializer::with_options(Vec::new(), plain());
+        for event in &typed[..typed.len() - 1] {
+            serializer.write_event(event).unwrap();
+        }
+        serializer.write_event(&TypedStreamEvent::ANY(Box::new(decimal.clone()))).unwrap();
+        serializer.write_event(&TypedStreamEvent::ANY(Box::new(-3i64))).unwrap();
+        serializer.write_event(&TypedStreamEvent::ENDARRAY).unwrap();
+        let serialized = serializer.finish().unwrap();
+        //1.5 fits into an f32, 0.1 does not
+        assert_eq!(serialized[1..7], [5, 4, 0, 0, 0xc0, 0x3f]);
+        assert_eq!(serialized[7..9], [5, 8]);
+
+        let mut resolver = DossResolver::new();
+        let mut events = Vec::new();
+        let mut pos = 0;
+        while let Some((event, used)) = E::decode(&serialized[pos..]).unwrap() {
+            pos += used;
+            resolver.push(event, &mut events).unwrap();
+        }
+        let mut decoded: Vec<_> = events.iter().filter_map(DossEvent::to_typed_stream_event).collect();
+        let TypedStreamEvent::ANY(negative) = decoded.remove(typed.len()) else { panic!("negative integer expected") };
+        assert_eq!(negative.downcast_ref::<i64>(), Some(&-3));
+        let TypedStreamEvent::ANY(any) = decoded.remove(typed.len() - 1) else { panic!("decimal expected") };
+        assert_eq!(any.downcast_ref::<Decimal>(), Some(&decimal));
+        assert_eq!(format!("{decoded:?}"), format!("{typed:?}"));
+
+        //trees keep floats as constants and decimals, date times and binaries as types
+        let entry = StreamableJSONEntry::Array(vec![
+            StreamableJSONEntry::Type(String::from("decimal"), vec![StreamableJSONEntry::String(decimal.to_string())]),
+            StreamableJSONEntry::Constant(String::from("1.5")),
+            StreamableJSONEntry::Constant(String::from("-0.1")),
+            StreamableJSONEntry::Type(String::from("datetime"), vec![StreamableJSONEntry::String(datetime.to_string())]),
+            StreamableJSONEntry::Type(String::from("bytes"), vec![StreamableJSONEntry::String(String::from("00ff"))]),
+        ]);
+        let mut serializer = DossSerializer::with_options(Vec::new(), plain());
+        serializer.write_entry(&entry).unwrap();
+        let serialized = serializer.finish().unwrap();
+        assert!(!serialized.windows(8).any(|w| w == b"datetime"));
+        assert_eq!(decode_entries(&serialized).unwrap(), vec![entry]);
+    }
+
+    #[test]
+    fn test_reader_events() {
+        struct Collector(Vec<StreamableJSONReaderEvent>);
+        impl StreamableJSONReaderCallback for Collector {
+            fn on_streamablejson_event(&mut self, event: StreamableJSONReaderEvent) -> StreamableJSONReaderCallbackReturn {
+                self.0.push(event);
+                StreamableJSONReaderCallbackReturn::Continue
+            }
+        }
+        let text = r#"{"a": decimal("1.50"), "b": [datetime("2025-01-01T00:00:00Z"), bytes("zz"), bytes(1), date("x"), "a", bytes()], "c": 7}"#;
+        let mut collector = Collector(Vec::new());
+        let mut reader = StreamableJSONReader::new(&mut collector);
+        reader.pushdata(&mut IteratorReadable::new(Box::new(text.chars().collect::<Vec<_>>().into_iter()))).unwrap();
+        reader.finish().unwrap();
+
+        let mut serializer = DossSerializer::new(Vec::new());
+        for event in collector.0 {
+            serializer.write_reader_event(event).unwrap();
+        }
+        let streamed = serializer.finish().unwrap();
+        assert_eq!(decode_entries(&streamed).unwrap(), decode_entries(&serialize(text, DossSerializerOptions::default())).unwrap());
+        assert!(!streamed.windows(7).any(|w| w == b"decimal"));
+    }
+
+    #[test]
+    fn test_string_encodings() {
+        let json = r#"[{"name": "hello", "kind": "repeated value"}, {"name": "hello", "kind": "repeated value"}, "world", decimal("1.5")]"#;
+        let serialized = serialize(json, DossSerializerOptions::default());
+        let expected: Vec<_> = DossPullParser::new(serialized.as_slice()).map(|e| format!("{:?}", e.unwrap())).collect();
+        for name in ["utf16le", "utf16be", "ascii"] {
+            let mut serializer = DossSerializer::new(Vec::new());
+            serializer.write_doss_event(&DossEvent::Config { key: vec![DossEvent::UInt(1)], value: vec![DossEvent::String(name.to_string())] }).unwrap();
+            serializer.write_entry(&deserialize_orderedbag_from_string(json.to_string()).unwrap()).unwrap();
+            let serialized = serializer.finish().unwrap();
+            let mut parser = DossPullParser::new(serialized.as_slice());
+            assert_eq!(parser.by_ref().map(|e| format!("{:?}", e.unwrap())).collect::<Vec<_>>(), expected, "{name}");
+            assert_eq!(Some(parser.settings().string_encoding()), StringEncoding::from_name(name));
+        }
+
+        let mut serializer = DossSerializer::new(Vec::new());
+        serializer.write_doss_event(&DossEvent::Config { key: vec![DossEvent::UInt(1)], value: vec![DossEvent::String(String::from("ascii"))] }).unwrap();
+        assert!(matches!(serializer.write_doss_event(&DossEvent::String(String::from("grüße"))), Err(DossError::InvalidString(_))));
+        let config = DossEvent::Config { key: vec![DossEvent::UInt(1)], value: vec![DossEvent::String(String::from("ebcdic"))] };
+        assert!(matches!(serializer.write_doss_event(&config), Err(DossError::InvalidSetting(_))));
+    }
+
+    #[test]
+    fn test_float_constants() {
+        let decoded = decode_entries(&serialize("[-0.1, 1.5, 1e-7, -2.5E+3, 1e300]", plain())).unwrap();
+        assert_eq!(decoded, vec![deserialize_orderedbag_from_string(String::from("[-0.1, 1.5, 1e-7, -2500.0, 1e300]")).unwrap()]);
+
+        //infinite and NaN floats are no valid JSON
+        for constant in ["Infinity", "-inf", "NaN", "1e400"] {
+            let entry = deserialize_orderedbag_from_string(format!("[{constant}]")).unwrap();
+            let mut serializer = DossSerializer::new(Vec::new());
+            assert!(matches!(serializer.write_entry(&entry), Err(DossError::UnsupportedValue(_))), "{constant}");
+        }
+        let mut serializer = DossSerializer::new(Vec::new());
+        assert!(matches!(serializer.write_event(&TypedStreamEvent::FLOAT(f64::NAN)), Err(DossError::UnsupportedValue(_))));
+    }
+
+    #[test]
+    fn test_big_integers() {
+        //integers beyond 64 bits stay exact as decimals instead of becoming floats
+        let decoded = decode_entries(&serialize("[12345678901234567890123, 18446744073709551616, -18446744073709551616, 18446744073709551615]", plain())).unwrap();
+        let expected = r#"[decimal("12345678901234567890123"), decimal("18446744073709551616"), decimal("-18446744073709551616"), 18446744073709551615]"#;
+        assert_eq!(decoded, vec![deserialize_orderedbag_from_string(expected.to_string()).unwrap()]);
+        assert_eq!(decode_entries(&serialize(expected, plain())).unwrap(), decoded);
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        let mut serializer = DossSerializer::new(Vec::new());
+        serializer.write_doss_event(&DossEvent::BlockStart).unwrap();
+        assert!(matches!(serializer.write_doss_event(&DossEvent::FileStart(vec![DossEvent::Null])), Err(DossError::UnbalancedStructure)));
+        assert!(matches!(serializer.write_doss_event(&DossEvent::ArrayEnd), Err(DossError::UnbalancedStructure)));
+
+        let mut serializer = DossSerializer::new(Vec::new());
+        serializer.write_doss_event(&DossEvent::ArrayStart).unwrap();
+        assert!(matches!(serializer.finish(), Err(DossError::UnbalancedStructure)));
+
+        let entry = StreamableJSONEntry::Array(vec![StreamableJSONEntry::Constant(String::from("undefined"))]);
+        let mut serializer = DossSerializer::new(Vec::new());
+        assert!(matches!(serializer.write_entry(&entry), Err(DossError::UnsupportedValue(_))));
+
+        //stacks end at the nesting level they started in
+        let mut serializer = DossSerializer::new(Vec::new());
+        assert!(matches!(serializer.write_doss_event(&DossEvent::StackEnd), Err(DossError::UnbalancedStructure)));
+        serializer.write_doss_event(&DossEvent::StackStart).unwrap();
+        serializer.write_doss_event(&DossEvent::ArrayStart).unwrap();
+        assert!(matches!(serializer.write_doss_event(&DossEvent::StackEnd), Err(DossError::UnbalancedStructure)));
+        serializer.write_doss_event(&DossEvent::ArrayEnd).unwrap();
+        assert!(matches!(serializer.finish(), Err(DossError::UnbalancedStructure)));
+    }
+
+    #[test]
+    fn test_nested_stacks() {
+        let words = |prefix: &str, n: usize| (0..n).flat_map(|_| (0..4).map(|i| DossEvent::String(format!("{prefix} value {i}")))).collect::<Vec<_>>();
+        let events = [
+            vec![DossEvent::ArrayStart],
+            words("global", 2),
+            vec![DossEvent::StackStart, DossEvent::ArrayStart],
+            words("outer", 2),
+            vec![DossEvent::StackStart],
+            words("inner", 2),
+            words("global", 1),
+            vec![DossEvent::StackEnd],
+            words("outer", 1),
+            vec![DossEvent::ArrayEnd, DossEvent::StackEnd],
+            words("global", 1),
+            words("outer", 2),
+            words("inner", 1),
+            vec![DossEvent::ArrayEnd],
+        ].concat();
+        let mut serializer = DossSerializer::with_options(Vec::new(), plain());
+        for e in &events {
+            serializer.write_doss_event(e).unwrap();
+        }
+        let serialized = serializer.finish().unwrap();
+
+        let mut resolver = DossResolver::new();
+        let mut resolved = Vec::new();
+        let mut pos = 0;
+        let mut max_len = 0;
+        while pos < serialized.len() {
+            let (event, used) = E::decode(&serialized[pos..]).unwrap().unwrap();
+            pos += used;
+            resolver.push(event, &mut resolved).unwrap();
+            max_len = max_len.max(resolver.dictionary().len());
+        }
+        resolver.finish().unwrap();
+        assert_eq!(resolved, events);
+        //the global values and the outer values stored again after the stacks
+        assert_eq!(max_len, 12);
+        assert_eq!(resolver.dictionary().len(), 8);
+    }
+}
@@ -0,0 +1,236 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use dataflowgrid_commons::decoders::decoders::{ASCIIDecoder, TextDecoder, UTF8Decoder, UTF16BEDecoder, UTF16LEDecoder, decode_to_string};
+
+use crate::compression::Compression;
+use crate::error::DossError;
+use crate::resolver::DossEvent;
+
+/// the DOSS version implemented by this library
+pub const DOSS_VERSION: u64 = 1;
+
+/// Encoding of the string opcode (7), selected with setting 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StringEncoding {
+    #[default]
+    Utf8,
+    Ascii,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl StringEncoding {
+    /// Accepts the names of settings_and_hints.md, case insensitive and with or without dash
+    pub fn from_name(name: &str) -> Option<StringEncoding> {
+        match name.to_ascii_lowercase().replace('-', "").as_str() {
+            "utf8" => Some(StringEncoding::Utf8),
+            "ascii" => Some(StringEncoding::Ascii),
+            "utf16" | "utf16le" => Some(StringEncoding::Utf16Le),
+            "utf16be" => Some(StringEncoding::Utf16Be),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StringEncoding::Utf8 => "utf8",
+            StringEncoding::Ascii => "ASCII",
+            StringEncoding::Utf16Le => "utf16le",
+            StringEncoding::Utf16Be => "utf16be",
+        }
+    }
+
+    pub fn decoder(&self) -> Box<dyn TextDecoder> {
+        match self {
+            StringEncoding::Utf8 => Box::new(UTF8Decoder::new()),
+            StringEncoding::Ascii => Box::new(ASCIIDecoder::new()),
+            StringEncoding::Utf16Le => Box::new(UTF16LEDecoder::new()),
+            StringEncoding::Utf16Be => Box::new(UTF16BEDecoder::new()),
+        }
+    }
+
+    /// Encodes the payload of a string opcode
+    pub fn encode(&self, s: &str) -> Result<Vec<u8>, DossError> {
+        match self {
+            StringEncoding::Utf8 => Ok(s.as_bytes().to_vec()),
+            StringEncoding::Ascii if s.is_ascii() => Ok(s.as_bytes().to_vec()),
+            StringEncoding::Ascii => Err(DossError::InvalidString(self.name())),
+            StringEncoding::Utf16Le => Ok(s.encode_utf16().flat_map(u16::to_le_bytes).collect()),
+            StringEncoding::Utf16Be => Ok(s.encode_utf16().flat_map(u16::to_be_bytes).collect()),
+        }
+    }
+
+    /// Decodes the payload of a string opcode
+    pub fn decode(&self, bytes: &[u8]) -> Result<String, DossError> {
+        match self {
+            //the common case doesn't need the char buffer of the generic decoders
+            StringEncoding::Utf8 => std::str::from_utf8(bytes).map(str::to_string).map_err(|_| DossError::InvalidUtf8),
+            //ASCIIDecoder reads bytes above 0x7f as latin-1, they couldn't be encoded again
+            StringEncoding::Ascii if !bytes.is_ascii() => Err(DossError::InvalidString(self.name())),
+            _ => decode_to_string(self.decoder().as_ref(), bytes).map_err(|_| DossError::InvalidString(self.name())),
+        }
+    }
+}
+
+/// Settings (opcode 20) and the hints (opcode 27) this library understands, see settings_and_hints.md.
+/// Unknown settings are rejected, unknown hints are ignored.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DossSettings {
+    minimum_version: u64,
+    string_encoding: StringEncoding,
+    compression: Compression,
+    recommended_version: u64,
+    generated_with: Option<String>,
+    max_dict_entries: Option<u64>,
+}
+
+impl DossSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn minimum_version(&self) -> u64 {
+        self.minimum_version
+    }
+
+    pub fn string_encoding(&self) -> StringEncoding {
+        self.string_encoding
+    }
+
+    /// Compression of the bytes following the setting
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    pub fn recommended_version(&self) -> u64 {
+        self.recommended_version
+    }
+
+    pub fn generated_with(&self) -> Option<&str> {
+        self.generated_with.as_deref()
+    }
+
+    pub fn max_dict_entries(&self) -> Option<u64> {
+        self.max_dict_entries
+    }
+
+    /// Applies a setting, settings stay valid until they are set again
+    pub fn apply_config(&mut self, key: &[DossEvent], value: &[DossEvent]) -> Result<(), DossError> {
+        match (number(key), value) {
+            (Some(0), _) => {
+                let version = number(value).ok_or_else(|| invalid(key, value))?;
+                if version > DOSS_VERSION {
+                    return Err(DossError::UnsupportedVersion(version));
+                }
+                self.minimum_version = version;
+            }
+            (Some(1), [DossEvent::String(name)]) => {
+                self.string_encoding = StringEncoding::from_name(name).ok_or_else(|| invalid(key, value))?;
+            }
+            (Some(1), _) => return Err(invalid(key, value)),
+            (Some(2), [DossEvent::String(name)]) => {
+                self.compression = Compression::from_name(name).ok_or_else(|| invalid(key, value))?;
+            }
+            (Some(2), _) => return Err(invalid(key, value)),
+            _ => return Err(DossError::UnknownSetting(format!("{key:?}"))),
+        }
+        Ok(())
+    }
+
+    /// Remembers the known hints, anything else including hints with unexpected values is ignored
+    pub fn apply_hint(&mut self, key: &[DossEvent], value: &[DossEvent]) {
+        match (key, number(key)) {
+            (_, Some(0)) => {
+                if let Some(version) = number(value) {
+                    self.recommended_version = version;
+                }
+            }
+            ([DossEvent::String(k)], _) if k == "generated_with" => {
+                if let [DossEvent::String(v)] = value {
+                    self.generated_with = Some(v.clone());
+                }
+            }
+            ([DossEvent::String(k)], _) if k == "max_dict_entries" => {
+                if let Some(max) = number(value) {
+                    self.max_dict_entries = Some(max);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn number(item: &[DossEvent]) -> Option<u64> {
+    match item {
+        [DossEvent::UInt(v)] => Some(*v),
+        [DossEvent::Int(v)] => u64::try_from(*v).ok(),
+        _ => None
+    }
+}
+
+fn invalid(key: &[DossEvent], value: &[DossEvent]) -> DossError {
+    DossError::InvalidSetting(format!("{key:?} = {value:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn s(v: &str) -> DossEvent {
+        DossEvent::String(v.to_string())
+    }
+
+    #[test]
+    fn test_settings() {
+        let mut settings = DossSettings::new();
+        settings.apply_config(&[DossEvent::Int(0)], &[DossEvent::Int(1)]).unwrap();
+        assert_eq!(settings.minimum_version(), 1);
+        assert!(matches!(settings.apply_config(&[DossEvent::UInt(0)], &[DossEvent::UInt(2)]), Err(DossError::UnsupportedVersion(2))));
+
+        settings.apply_config(&[DossEvent::UInt(1)], &[s("ASCII")]).unwrap();
+        assert_eq!(settings.string_encoding(), StringEncoding::Ascii);
+        settings.apply_config(&[DossEvent::UInt(1)], &[s("UTF-16BE")]).unwrap();
+        assert_eq!(settings.string_encoding(), StringEncoding::Utf16Be);
+        assert!(matches!(settings.apply_config(&[DossEvent::UInt(1)], &[s("EBCDIC")]), Err(DossError::InvalidSetting(_))));
+        assert!(matches!(settings.apply_config(&[DossEvent::UInt(1)], &[DossEvent::Null]), Err(DossError::InvalidSetting(_))));
+
+        #[cfg(feature = "zstd")]
+        {
+            settings.apply_config(&[DossEvent::UInt(2)], &[s("ZSTD")]).unwrap();
+            assert_eq!(settings.compression(), Compression::Zstd);
+        }
+        settings.apply_config(&[DossEvent::UInt(2)], &[s("none")]).unwrap();
+        assert_eq!(settings.compression(), Compression::None);
+        assert!(matches!(settings.apply_config(&[DossEvent::UInt(2)], &[s("gzip")]), Err(DossError::InvalidSetting(_))));
+        assert!(matches!(settings.apply_config(&[DossEvent::UInt(2)], &[DossEvent::Null]), Err(DossError::InvalidSetting(_))));
+        assert!(matches!(settings.apply_config(&[DossEvent::UInt(3)], &[DossEvent::Null]), Err(DossError::UnknownSetting(_))));
+        assert!(matches!(settings.apply_config(&[s("compression")], &[DossEvent::True]), Err(DossError::UnknownSetting(_))));
+    }
+
+    #[test]
+    fn test_hints() {
+        let mut settings = DossSettings::new();
+        settings.apply_hint(&[DossEvent::UInt(0)], &[DossEvent::UInt(1)]);
+        settings.apply_hint(&[s("generated_with")], &[s("dataflowgrid")]);
+        settings.apply_hint(&[s("max_dict_entries")], &[DossEvent::UInt(100)]);
+        settings.apply_hint(&[s("unknown")], &[DossEvent::True]);
+        settings.apply_hint(&[s("generated_with")], &[DossEvent::True]);
+        assert_eq!(settings.recommended_version(), 1);
+        assert_eq!(settings.generated_with(), Some("dataflowgrid"));
+        assert_eq!(settings.max_dict_entries(), Some(100));
+    }
+
+    #[test]
+    fn test_decode_strings() {
+        assert_eq!(StringEncoding::Ascii.decode(b"hello").unwrap(), "hello");
+        assert!(matches!(StringEncoding::Ascii.decode(b"gr\xfc\xdfe"), Err(DossError::InvalidString(_))));
+        let utf16: Vec<u8> = "grüße".encode_utf16().flat_map(|u| u.to_be_bytes()).collect();
+        assert_eq!(StringEncoding::Utf16Be.decode(&utf16).unwrap(), "grüße");
+        assert!(matches!(StringEncoding::Utf16Be.decode(&utf16[1..]), Err(DossError::InvalidString(_))));
+        assert!(matches!(StringEncoding::Utf8.decode(&[0xff]), Err(DossError::InvalidUtf8)));
+        for encoding in [StringEncoding::Utf8, StringEncoding::Utf16Le, StringEncoding::Utf16Be] {
+            assert_eq!(encoding.decode(&encoding.encode("grüße").unwrap()).unwrap(), "grüße");
+        }
+        assert!(matches!(StringEncoding::Ascii.encode("grüße"), Err(DossError::InvalidString(_))));
+    }
+}
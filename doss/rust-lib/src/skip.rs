@@ -0,0 +1,371 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::collections::VecDeque;
+use std::io::{Result as IoResult, Seek, SeekFrom, Write};
+
+use crate::compression::FrameWriter;
+use crate::deserializer::DossLowLevelStreamEvent;
+
+/// overwrites `bytes.len()` bytes written `back` bytes before the current end of the sink, returns false
+/// if they can't be changed anymore
+pub(crate) type PatchFn<W> = fn(&mut W, u64, &[u8]) -> IoResult<bool>;
+
+pub(crate) fn seek_patch<W: Write + Seek>(out: &mut W, back: u64, bytes: &[u8]) -> IoResult<bool> {
+    out.seek(SeekFrom::Current(-(back as i64)))?;
+    out.write_all(bytes)?;
+    out.seek(SeekFrom::Current(back as i64 - bytes.len() as i64))?;
+    Ok(true)
+}
+
+#[derive(Debug)]
+struct SkipFrame {
+    content_start: u64, //position after the start opcode
+    slot: Option<u64>, //position of the reserved 4 bytes
+    stateful: bool, //the content changes how the rest of the stream is parsed
+}
+
+/// Inserts backpatched skip opcodes (31) behind the start of blocks and arrays.
+///
+/// The output of a block is held back until the block either ends (no skip is needed) or grows
+/// beyond the threshold. Then the skip opcode is inserted with a 0 slot that is overwritten when the
+/// block ends: in memory if the slot is still within the `buffer` bytes held back, by seeking back if
+/// the sink supports it, otherwise the slot stays 0. Blocks that change the dictionary or settings keep a 0 slot as well,
+/// jumping over them would break later references. Dictionary changes inside a stack that ends within the block don't count.
+pub(crate) struct SkipWriter<W: Write> {
+    out: W,
+    patch: Option<PatchFn<W>>,
+    threshold: Option<usize>,
+    buffer: usize,
+    pending: VecDeque<u8>, //front bytes leave in O(1), the held back buffer isn't moved on every flush
+    flushed: u64,
+    frames: Vec<SkipFrame>,
+    stacks: Vec<usize>, //number of frames open when a stack started
+}
+
+impl<W: Write> std::fmt::Debug for SkipWriter<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SkipWriter")
+            .field("seekable", &self.patch.is_some())
+            .field("threshold", &self.threshold)
+            .field("buffer", &self.buffer)
+            .field("pending", &self.pending.len())
+            .field("flushed", &self.flushed)
+            .field("frames", &self.frames)
+            .field("stacks", &self.stacks)
+            .finish()
+    }
+}
+
+impl<W: Write> SkipWriter<W> {
+    /// A writer for pure streaming sinks, slots written before the block ends stay 0
+    pub(crate) fn new(out: W, threshold: Option<usize>, buffer: usize) -> Self {
+        SkipWriter {
+            out,
+            patch: None,
+            threshold,
+            buffer,
+            pending: VecDeque::new(),
+            flushed: 0,
+            frames: Vec::new(),
+            stacks: Vec::new(),
+        }
+    }
+
+    /// The sink, only to be changed while nothing is held back
+    pub(crate) fn inner_mut(&mut self) -> &mut W {
+        &mut self.out
+    }
+
+    pub(crate) fn position(&self) -> u64 {
+        self.flushed + self.pending.len() as u64
+    }
+
+    pub(crate) fn write(&mut self, bytes: &[u8]) -> IoResult<()> {
+        self.pending.extend(bytes);
+        self.reserve_slots();
+        self.flush_decided()
+    }
+
+    /// Called after the start opcode of a block or array was written
+    pub(crate) fn open(&mut self) {
+        if self.threshold.is_some() {
+            self.frames.push(SkipFrame { content_start: self.position(), slot: None, stateful: false });
+        }
+    }
+
+    /// Called before the end opcode of a block or array is written
+    pub(crate) fn close(&mut self) -> IoResult<()> {
+        let Some(frame) = self.frames.pop() else {
+            return Ok(());
+        };
+        if let Some(slot) = frame.slot {
+            let distance = self.position() - (slot + 4);
+            let value = match frame.stateful {
+                true => 0,
+                false => u32::try_from(distance).unwrap_or(0),
+            };
+            self.patch(slot, &value.to_le_bytes())?;
+        }
+        self.flush_decided()
+    }
+
+    /// The settings changed inside all open blocks
+    pub(crate) fn mark_stateful(&mut self) {
+        for frame in &mut self.frames {
+            frame.stateful = true;
+        }
+    }
+
+    /// The dictionary changed. Blocks opened before the current stack are not affected,
+    /// the stack ends within them and restores the dictionary
+    pub(crate) fn mark_dict_changed(&mut self) {
+        let from = self.stacks.last().copied().unwrap_or(0);
+        for frame in self.frames.iter_mut().skip(from) {
+            frame.stateful = true;
+        }
+    }
+
+    pub(crate) fn start_stack(&mut self) {
+        self.stacks.push(self.frames.len());
+    }
+
+    pub(crate) fn leave_stack(&mut self) {
+        self.stacks.pop();
+    }
+
+    /// Writes all bytes held back and flushes the sink. Slots already written are filled like in a pure streaming sink
+    pub(crate) fn flush(&mut self) -> IoResult<()> {
+        let n = self.pending.len();
+        let (front, back) = self.pending.as_slices();
+        self.out.write_all(front)?;
+        self.out.write_all(back)?;
+        self.pending.drain(..n);
+        self.flushed += n as u64;
+        self.out.flush()
+    }
+
+    pub(crate) fn finish(mut self) -> IoResult<W> {
+        self.frames.clear();
+        self.flush_decided()?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn reserve_slots(&mut self) {
+        let Some(threshold) = self.threshold else {
+            return;
+        };
+        let mut skip = Vec::new();
+        DossLowLevelStreamEvent::SkipBytes32le(0).encode(&mut skip);
+        //outer blocks first, their content contains the content of the inner ones
+        for i in 0..self.frames.len() {
+            let frame = &self.frames[i];
+            //a block whose start was flushed can't get a skip anymore
+            if frame.slot.is_some() || frame.content_start < self.flushed || self.position() - frame.content_start <= threshold as u64 {
+                continue;
+            }
+            //undecided blocks are never flushed, so the start is still pending
+            let at = (frame.content_start - self.flushed) as usize;
+            let slot = frame.content_start + 1;
+            for (i, b) in skip.iter().enumerate() {
+                self.pending.insert(at + i, *b);
+            }
+            self.frames[i].slot = Some(slot);
+            for inner in &mut self.frames[i + 1..] {
+                inner.content_start += skip.len() as u64;
+                if let Some(slot) = &mut inner.slot {
+                    *slot += skip.len() as u64;
+                }
+            }
+        }
+    }
+
+    fn flush_decided(&mut self) -> IoResult<()> {
+        let undecided = self.frames.iter()
+            .filter(|f| f.slot.is_none())
+            .map(|f| f.content_start)
+            .min()
+            .unwrap_or(self.position());
+        //while slots are open the last `buffer` bytes are kept, so slots of small blocks are patched in memory
+        let held = match self.frames.iter().any(|f| f.slot.is_some()) {
+            true => self.position().saturating_sub(self.buffer as u64),
+            false => self.position(),
+        };
+        let barrier = undecided.min(held);
+        if barrier > self.flushed {
+            let n = (barrier - self.flushed) as usize;
+            let (front, back) = self.pending.as_slices();
+            let split = n.min(front.len());
+            self.out.write_all(&front[..split])?;
+            self.out.write_all(&back[..n - split])?;
+            self.pending.drain(..n);
+            self.flushed = barrier;
+        }
+        Ok(())
+    }
+
+    fn patch(&mut self, slot: u64, bytes: &[u8]) -> IoResult<()> {
+        //a slot can be partly flushed, it is changed completely or not at all
+        let flushed = (self.flushed.saturating_sub(slot) as usize).min(bytes.len());
+        if flushed > 0 {
+            let patched = match self.patch {
+                Some(patch) => patch(&mut self.out, self.flushed - slot, &bytes[..flushed])?,
+                None => false, //pure streaming: the slot stays 0
+            };
+            if !patched || flushed == bytes.len() {
+                return Ok(());
+            }
+        }
+        let at = (slot + flushed as u64 - self.flushed) as usize;
+        for (i, b) in bytes[flushed..].iter().enumerate() {
+            self.pending[at + i] = *b;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> SkipWriter<FrameWriter<W>> {
+    /// A writer that fills slots already written if the [`FrameWriter`] can
+    pub(crate) fn framed(out: FrameWriter<W>, threshold: Option<usize>, buffer: usize) -> Self {
+        SkipWriter {
+            patch: Some(|out, back, bytes| out.patch(back, bytes)),
+            ..Self::new(out, threshold, buffer)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::deserializer::DossLowLevelStreamEvent as E;
+    use crate::resolver::DossEvent;
+    use crate::serializer::{DossSerializer, DossSerializerOptions};
+    use crate::tree::decode_entries;
+
+    fn options(threshold: usize, dict: bool) -> DossSerializerOptions {
+        DossSerializerOptions {
+            max_dict_entries: if dict { 1 << 16 } else { 0 },
+            emit_dict_hint: false,
+            skip_threshold: Some(threshold),
+            skip_buffer: 0,
+            ..DossSerializerOptions::default()
+        }
+    }
+
+    fn write(serializer: &mut DossSerializer<impl std::io::Write>, events: &[DossEvent]) {
+        for e in events {
+            serializer.write_doss_event(e).unwrap();
+        }
+    }
+
+    //an array of `n` distinct strings within a block
+    fn content(n: usize) -> Vec<DossEvent> {
+        let mut events = vec![DossEvent::BlockStart, DossEvent::String("list".to_string()), DossEvent::ArrayStart];
+        events.extend((0..n).map(|i| DossEvent::String(format!("value {i}"))));
+        events.extend([DossEvent::ArrayEnd, DossEvent::BlockEnd]);
+        events
+    }
+
+    /// Returns the targets of all skips and checks that each one lands on an end opcode
+    fn skips(serialized: &[u8]) -> Vec<u32> {
+        let mut targets = Vec::new();
+        let mut pos = 0;
+        while pos < serialized.len() {
+            let (event, used) = E::decode(&serialized[pos..]).unwrap().unwrap();
+            pos += used;
+            if let E::SkipBytes32le(n) = event {
+                if n > 0 {
+                    assert!([11, 13].contains(&serialized[pos + n as usize]));
+                }
+                targets.push(n);
+            }
+        }
+        targets
+    }
+
+    #[test]
+    fn test_small_blocks_have_no_skips() {
+        let mut serializer = DossSerializer::with_options(Vec::new(), options(1000, false));
+        write(&mut serializer, &content(10));
+        let serialized = serializer.finish().unwrap();
+        assert!(skips(&serialized).is_empty());
+
+        let mut serializer = DossSerializer::with_options(Vec::new(), DossSerializerOptions { skip_threshold: None, ..options(1000, false) });
+        write(&mut serializer, &content(10));
+        assert_eq!(serializer.finish().unwrap(), serialized);
+    }
+
+    #[test]
+    fn test_seekable_patches_skips() {
+        let mut serializer = DossSerializer::seekable(Cursor::new(Vec::new()), options(64, false));
+        write(&mut serializer, &content(100));
+        let serialized = serializer.finish().unwrap().into_inner();
+        let targets = skips(&serialized);
+        assert_eq!(targets.len(), 2);
+        assert!(targets.iter().all(|t| *t > 0));
+        //the block skip jumps over the array including its own skip
+        assert!(targets[0] > targets[1]);
+        assert_eq!(decode_entries(&serialized).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_streaming_keeps_zero_skips() {
+        let mut serializer = DossSerializer::with_options(Vec::new(), options(64, false));
+        write(&mut serializer, &content(100));
+        let streamed = serializer.finish().unwrap();
+        assert_eq!(skips(&streamed), vec![0, 0]);
+
+        let mut serializer = DossSerializer::seekable(Cursor::new(Vec::new()), options(64, false));
+        write(&mut serializer, &content(100));
+        let seeked = serializer.finish().unwrap().into_inner();
+        assert_eq!(decode_entries(&streamed).unwrap(), decode_entries(&seeked).unwrap());
+        assert_eq!(streamed.len(), seeked.len());
+    }
+
+    #[test]
+    fn test_buffered_block_is_patched_in_memory() {
+        let mut serializer = DossSerializer::with_options(Vec::new(), DossSerializerOptions { skip_buffer: 1024, ..options(64, false) });
+        let mut events = vec![DossEvent::ArrayStart];
+        events.extend(content(10));
+        events.extend([DossEvent::Null, DossEvent::ArrayEnd]);
+        write(&mut serializer, &events);
+        let serialized = serializer.finish().unwrap();
+        let targets = skips(&serialized);
+        assert_eq!(targets.len(), 3);
+        assert!(targets.iter().all(|t| *t > 0));
+
+        //the outer array exceeds the buffer and is already written when it ends
+        let mut serializer = DossSerializer::with_options(Vec::new(), DossSerializerOptions { skip_buffer: 200, ..options(64, false) });
+        events.splice(events.len() - 1..events.len() - 1, content(10));
+        write(&mut serializer, &events);
+        assert!(matches!(skips(&serializer.finish().unwrap())[..], [0, a, b, c, d] if a > 0 && b > 0 && c > 0 && d > 0));
+    }
+
+    #[test]
+    fn test_dictionary_changes_are_not_skipped() {
+        let mut serializer = DossSerializer::seekable(Cursor::new(Vec::new()), options(16, true));
+        let mut events = vec![DossEvent::ArrayStart];
+        for _ in 0..3 {
+            events.push(DossEvent::ArrayStart);
+            events.extend((0..10).map(|i| DossEvent::String(format!("repeated value {i}"))));
+            events.push(DossEvent::ArrayEnd);
+        }
+        events.push(DossEvent::ArrayEnd);
+        write(&mut serializer, &events);
+        let serialized = serializer.finish().unwrap().into_inner();
+        //values are stored on their second occurrence, so only the second inner array changes the dictionary
+        assert!(matches!(skips(&serialized)[..], [0, a, 0, b] if a > 0 && b > 0));
+    }
+    #[test]
+    fn test_dictionary_changes_in_stacks_are_skipped() {
+        let mut serializer = DossSerializer::seekable(Cursor::new(Vec::new()), options(16, true));
+        let mut events = vec![DossEvent::ArrayStart, DossEvent::StackStart, DossEvent::ArrayStart];
+        events.extend((0..2).flat_map(|_| (0..10).map(|i| DossEvent::String(format!("repeated value {i}")))));
+        events.extend([DossEvent::ArrayEnd, DossEvent::StackEnd, DossEvent::ArrayEnd]);
+        write(&mut serializer, &events);
+        let serialized = serializer.finish().unwrap().into_inner();
+        //the stack restores the dictionary within the outer array, only the inner array keeps a 0 skip
+        assert!(matches!(skips(&serialized)[..], [a, 0] if a > 0));
+    }
+}
@@ -0,0 +1,152 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use streamablejson::StreamableJSONEntry;
+use streamablejson::parser::StreamableJSONReaderEvent;
+
+use crate::error::DossError;
+use crate::resolver::DossEvent;
+use crate::tree::{BYTES_TYPE, DATETIME_TYPE, DECIMAL_TYPE};
+use crate::types::{Decimal, DossFloat};
+
+/// Turns the events of a [`StreamableJSONReader`](streamablejson::parser::StreamableJSONReader) into [`DossEvent`]s
+/// while text is parsed. Constants become `true`, `false`, `null` and numbers, typed values like `decimal("1.50")`
+/// are held back until their type ends
+#[derive(Debug, Default)]
+pub struct DossTextEvents {
+    pending: Option<(String, Option<String>)>, //typed value name and text read so far
+}
+
+impl DossTextEvents {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the events for `event` to `out`, none while a typed value is held back
+    pub fn push(&mut self, event: StreamableJSONReaderEvent, out: &mut Vec<DossEvent>) -> Result<(), DossError> {
+        if let Some((name, text)) = self.pending.take() {
+            match (event, text) {
+                (StreamableJSONReaderEvent::String(s), None) => {
+                    self.pending = Some((name, Some(s)));
+                    return Ok(());
+                }
+                (StreamableJSONReaderEvent::EndType, Some(text)) => {
+                    match typed_text(&name, &text) {
+                        Some(value) => out.push(value),
+                        None => out.extend([DossEvent::TypeStart(name), DossEvent::String(text), DossEvent::TypeEnd]),
+                    }
+                    return Ok(());
+                }
+                (event, text) => {
+                    //not a typed value after all
+                    out.push(DossEvent::TypeStart(name));
+                    out.extend(text.map(DossEvent::String));
+                    return self.push(event, out);
+                }
+            }
+        }
+        out.push(match event {
+            StreamableJSONReaderEvent::Initialized | StreamableJSONReaderEvent::Finished => return Ok(()),
+            StreamableJSONReaderEvent::StartType(name) if [DECIMAL_TYPE, DATETIME_TYPE, BYTES_TYPE].contains(&name.as_str()) => {
+                self.pending = Some((name, None));
+                return Ok(());
+            }
+            StreamableJSONReaderEvent::StartObject => DossEvent::BlockStart,
+            StreamableJSONReaderEvent::EndObject => DossEvent::BlockEnd,
+            StreamableJSONReaderEvent::StartArray => DossEvent::ArrayStart,
+            StreamableJSONReaderEvent::EndArray => DossEvent::ArrayEnd,
+            StreamableJSONReaderEvent::String(s) => DossEvent::String(s),
+            StreamableJSONReaderEvent::Constant(c) => constant_event(&c)?,
+            StreamableJSONReaderEvent::StartType(name) => DossEvent::TypeStart(name),
+            StreamableJSONReaderEvent::EndType => DossEvent::TypeEnd,
+        });
+        Ok(())
+    }
+
+    /// A typed value started but did not end yet
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+}
+
+/// Integers beyond 64 bits become decimals, so they stay exact. Other numbers are finite floats
+pub(crate) fn constant_event(constant: &str) -> Result<DossEvent, DossError> {
+    match constant {
+        "true" => Ok(DossEvent::True),
+        "false" => Ok(DossEvent::False),
+        "null" => Ok(DossEvent::Null),
+        _ => {
+            if let Ok(v) = constant.parse::<u64>() {
+                Ok(DossEvent::UInt(v))
+            } else if let Ok(v) = constant.parse::<i64>() {
+                Ok(DossEvent::Int(v))
+            } else if !constant.contains(['.', 'e', 'E']) && let Ok(v) = constant.parse::<Decimal>() {
+                Ok(DossEvent::Decimal(v))
+            } else if let Ok(v) = constant.parse::<f64>() && v.is_finite() {
+                Ok(DossEvent::Float(DossFloat::shortest(v)))
+            } else {
+                Err(DossError::UnsupportedValue(constant.to_string()))
+            }
+        }
+    }
+}
+
+/// `decimal("<number>")`, `datetime("<RFC 3339>")` and `bytes("<hex>")` as written by [`DossTreeBuilder`](crate::DossTreeBuilder)
+pub(crate) fn typed_value(name: &str, content: &[StreamableJSONEntry]) -> Option<DossEvent> {
+    let [StreamableJSONEntry::String(text)] = content else {
+        return None;
+    };
+    typed_text(name, text)
+}
+
+fn typed_text(name: &str, text: &str) -> Option<DossEvent> {
+    match name {
+        DECIMAL_TYPE => text.parse().ok().map(DossEvent::Decimal),
+        DATETIME_TYPE => text.parse().ok().map(DossEvent::DateTime),
+        BYTES_TYPE if text.len().is_multiple_of(2) => (0..text.len()).step_by(2)
+            .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+            .collect::<Option<Vec<u8>>>()
+            .map(DossEvent::Binary),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_events() {
+        let mut text = DossTextEvents::new();
+        let mut out = Vec::new();
+        let events = [
+            StreamableJSONReaderEvent::StartArray,
+            StreamableJSONReaderEvent::StartType(String::from("bytes")),
+            StreamableJSONReaderEvent::String(String::from("00ff")),
+            StreamableJSONReaderEvent::EndType,
+            StreamableJSONReaderEvent::StartType(String::from("datetime")),
+            StreamableJSONReaderEvent::String(String::from("soon")),
+            StreamableJSONReaderEvent::EndType,
+            StreamableJSONReaderEvent::StartType(String::from("decimal")),
+            StreamableJSONReaderEvent::Constant(String::from("-3")),
+            StreamableJSONReaderEvent::EndType,
+            StreamableJSONReaderEvent::EndArray,
+        ];
+        for event in events {
+            text.push(event, &mut out).unwrap();
+        }
+        assert_eq!(out, vec![
+            DossEvent::ArrayStart,
+            DossEvent::Binary(vec![0, 255]),
+            DossEvent::TypeStart(String::from("datetime")),
+            DossEvent::String(String::from("soon")),
+            DossEvent::TypeEnd,
+            DossEvent::TypeStart(String::from("decimal")),
+            DossEvent::Int(-3),
+            DossEvent::TypeEnd,
+            DossEvent::ArrayEnd,
+        ]);
+        assert!(!text.is_pending());
+        text.push(StreamableJSONReaderEvent::StartType(String::from("decimal")), &mut out).unwrap();
+        assert!(text.is_pending());
+    }
+}
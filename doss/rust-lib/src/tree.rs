@@ -0,0 +1,119 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::sync::Arc;
+
+use dataflowgrid_commons::orderedbag::OrderedBag;
+use streamablejson::StreamableJSONEntry;
+
+use crate::error::DossError;
+use crate::parser::{ParserCore, Step};
+use crate::registry::DossDictionaryRegistry;
+use crate::resolver::{DossEvent, DossResolver};
+
+/// type name of binaries in trees
+pub const BYTES_TYPE: &str = "bytes";
+/// type name of decimals in trees
+pub const DECIMAL_TYPE: &str = "decimal";
+/// type name of date times in trees
+pub const DATETIME_TYPE: &str = "datetime";
+
+/// Builds streamablejson trees from resolved DOSS events.
+/// Numbers, booleans and null become constants, binaries become `bytes("<hex>")` types
+/// decimals `decimal("<number>")` and date times `datetime("<RFC 3339>")` types.
+/// Settings, hints and file starts are not part of the trees and are ignored.
+#[derive(Debug, Default)]
+pub struct DossTreeBuilder {
+    stack: Vec<StreamableJSONEntry>,
+    results: Vec<StreamableJSONEntry>,
+}
+
+impl DossTreeBuilder {
+    pub fn new() -> Self {
+        DossTreeBuilder {
+            stack: Vec::new(),
+            results: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, event: DossEvent) -> Result<(), DossError> {
+        match event {
+            DossEvent::BlockStart => self.stack.push(StreamableJSONEntry::Object(OrderedBag::new())),
+            DossEvent::ArrayStart => self.stack.push(StreamableJSONEntry::Array(Vec::new())),
+            DossEvent::TypeStart(name) => self.stack.push(StreamableJSONEntry::Type(name, Vec::new())),
+            DossEvent::BlockEnd | DossEvent::ArrayEnd | DossEvent::TypeEnd => {
+                let entry = self.stack.pop().ok_or(DossError::UnbalancedStructure)?;
+                self.add(entry);
+            }
+            DossEvent::True => self.add(StreamableJSONEntry::Constant(String::from("true"))),
+            DossEvent::False => self.add(StreamableJSONEntry::Constant(String::from("false"))),
+            DossEvent::Null => self.add(StreamableJSONEntry::Constant(String::from("null"))),
+            DossEvent::Int(v) => self.add(StreamableJSONEntry::Constant(v.to_string())),
+            DossEvent::UInt(v) => self.add(StreamableJSONEntry::Constant(v.to_string())),
+            DossEvent::Decimal(d) => self.add(StreamableJSONEntry::Type(String::from(DECIMAL_TYPE), vec![StreamableJSONEntry::String(d.to_string())])),
+            DossEvent::Float(v) => self.add(StreamableJSONEntry::Constant(v.to_string())),
+            DossEvent::DateTime(d) => self.add(StreamableJSONEntry::Type(String::from(DATETIME_TYPE), vec![StreamableJSONEntry::String(d.to_string())])),
+            DossEvent::String(s) => self.add(StreamableJSONEntry::String(s)),
+            DossEvent::Binary(b) => {
+                let hex = b.iter().map(|b| format!("{b:02x}")).collect();
+                self.add(StreamableJSONEntry::Type(String::from(BYTES_TYPE), vec![StreamableJSONEntry::String(hex)]));
+            }
+            DossEvent::Config { .. }
+            | DossEvent::Hint { .. }
+            | DossEvent::ImportDict(_)
+            | DossEvent::FileStart(_)
+            | DossEvent::StackStart
+            | DossEvent::StackEnd => {}
+        }
+        Ok(())
+    }
+
+    fn add(&mut self, entry: StreamableJSONEntry) {
+        match self.stack.last_mut() {
+            None => self.results.push(entry),
+            Some(StreamableJSONEntry::Object(obj)) => {
+                if obj.keys_and_values_in_sync() {
+                    obj.insert_key_only(entry);
+                } else {
+                    obj.insert_value_only(entry);
+                }
+            }
+            Some(StreamableJSONEntry::Array(arr)) => arr.push(entry),
+            Some(StreamableJSONEntry::Type(_, content)) => content.push(entry),
+            Some(_) => unreachable!("only containers are pushed onto the stack"),
+        }
+    }
+
+    /// Returns the completed top level items
+    pub fn take_results(&mut self) -> Vec<StreamableJSONEntry> {
+        std::mem::take(&mut self.results)
+    }
+}
+
+/// Decodes all top level items of a complete DOSS stream
+pub fn decode_entries(serialized: &[u8]) -> Result<Vec<StreamableJSONEntry>, DossError> {
+    decode(serialized, DossResolver::new())
+}
+
+/// Decodes all top level items, imports are loaded from `registry`
+pub fn decode_entries_with_registry(serialized: &[u8], registry: Arc<DossDictionaryRegistry>) -> Result<Vec<StreamableJSONEntry>, DossError> {
+    let mut resolver = DossResolver::new();
+    resolver.set_registry(registry);
+    decode(serialized, resolver)
+}
+
+fn decode(serialized: &[u8], resolver: DossResolver) -> Result<Vec<StreamableJSONEntry>, DossError> {
+    let mut builder = DossTreeBuilder::new();
+    //the parser core decompresses frames
+    let mut core = ParserCore::with_resolver(resolver);
+    core.buffer_mut().extend_from_slice(serialized);
+    loop {
+        match core.next()? {
+            Step::Event(event) => builder.push(event)?,
+            Step::NeedData => break,
+            //nothing is skipped
+            Step::Skip(_) => return Err(DossError::UnexpectedEof),
+        }
+    }
+    core.finish()?;
+    Ok(builder.take_results())
+}
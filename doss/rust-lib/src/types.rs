@@ -1,10 +1,68 @@
 /* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
 
-#[derive(Debug)]
-pub struct TypesError {
-    message: String
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::{Add, Mul, Neg, Sub};
+use std::str::FromStr;
+
+use derive_more::{Display, Error};
+
+/// Float value (opcode 5), the width is kept so values written as f32 stay f32
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DossFloat {
+    F32(f32),
+    F64(f64),
+}
+
+impl DossFloat {
+    /// f32 if that keeps the value exactly, otherwise f64
+    pub fn shortest(value: f64) -> DossFloat {
+        match value as f32 as f64 == value || value.is_nan() {
+            true => DossFloat::F32(value as f32),
+            false => DossFloat::F64(value),
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            DossFloat::F32(v) => *v as f64,
+            DossFloat::F64(v) => *v,
+        }
+    }
+}
+
+impl fmt::Display for DossFloat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        //the shortest representation that reads back to the same value
+        match self {
+            DossFloat::F32(v) => write!(f, "{v:?}"),
+            DossFloat::F64(v) => write!(f, "{v:?}"),
+        }
+    }
+}
+
+
+#[derive(Debug, Display, Error, PartialEq)]
+pub enum TypesError {
+    #[display("not a decimal number")]
+    InvalidFormat,
+    #[display("decimal exponent does not fit into 16 bits")]
+    ExponentOutOfRange,
+    #[display("value does not fit into {_0}")]
+    OutOfRange(#[error(not(source))] &'static str),
+    #[display("infinite and NaN floats have no decimal value")]
+    NotFinite,
 }
 
+//the largest power of ten that fits into a limb on every platform
+const CHUNK: usize = 1_000_000_000;
+const CHUNK_DIGITS: u32 = 9;
+
+/// Decimal value (opcode 4) of arbitrary precision: `values` × 10^`extension`.
+/// The magnitude `values` holds little endian limbs, the least significant first.
+/// Comparisons are by value, so `1.50` equals `1.5`. `signed` only records whether the value came from a signed type.
+#[derive(Debug, Clone)]
 pub struct Decimal {
     signed: bool,
     negative: bool,
@@ -14,80 +72,658 @@ pub struct Decimal {
 
 impl Decimal {
     pub fn new(signed: bool, negative: bool, extension: i16, values: Box<[usize]>) -> Decimal {
+        Decimal::from_magnitude(signed, negative, extension, values.into_vec())
+    }
+
+    //trims the magnitude, zero is never negative
+    fn from_magnitude(signed: bool, negative: bool, extension: i16, values: Vec<usize>) -> Decimal {
+        let values = trim(values);
         Decimal {
             signed,
-            negative,
+            negative: negative && !values.is_empty(),
             extension,
-            values
+            values: values.into_boxed_slice()
         }
     }
 
     /// creates a new Decimal with a usize value, not signed, not negative, no extension
     pub fn from_usize(value: usize) -> Decimal {
-        Decimal {
-            signed: false,
-            negative: false,
-            extension: 0,
-            values: Box::new([value])
-        }
+        Decimal::from_magnitude(false, false, 0, vec![value])
     }
 
     /// creates a new Decimal with a isize value, no extension
     pub fn from_isize(value: isize) -> Decimal {
-        if value < 0 {
-            Decimal {
-                signed: true,
-                negative: true,
-                extension: 0,
-                values: Box::new([value.abs() as usize])
-            }
-        } else {
-            Decimal {
-                signed: true,
-                negative: false,
-                extension: 0,
-                values: Box::new([value as usize])
+        Decimal::from_magnitude(true, value < 0, 0, vec![value.unsigned_abs()])
+    }
+
+    /// Magnitude as little endian bytes without trailing zeros, see [`Decimal::from_magnitude_le_bytes`]
+    pub fn magnitude_le_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = self.values.iter().flat_map(|limb| limb.to_le_bytes()).collect();
+        while bytes.last() == Some(&0) {
+            bytes.pop();
+        }
+        bytes
+    }
+
+    pub fn from_magnitude_le_bytes(negative: bool, extension: i16, bytes: &[u8]) -> Decimal {
+        let values = bytes.chunks(size_of::<usize>()).map(|chunk| {
+            let mut limb = [0; size_of::<usize>()];
+            limb[..chunk.len()].copy_from_slice(chunk);
+            usize::from_le_bytes(limb)
+        }).collect();
+        Decimal::from_magnitude(true, negative, extension, values)
+    }
+
+    pub fn is_signed(&self) -> bool {
+        self.signed
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn extension(&self) -> i16 {
+        self.extension
+    }
+
+    pub fn values(&self) -> &[usize] {
+        &self.values
+    }
+
+    /// The same value with the largest possible extension, e.g. `1.50` becomes `15e-1` and `1200` becomes `12e2`
+    pub fn normalized(&self) -> Decimal {
+        let mut values = self.values.to_vec();
+        if values.is_empty() {
+            return Decimal::from_magnitude(self.signed, false, 0, values);
+        }
+        let mut extension = self.extension;
+        while extension < i16::MAX {
+            let mut divided = values.clone();
+            if divrem_small(&mut divided, 10) != 0 {
+                break;
             }
+            values = divided;
+            extension += 1;
         }
+        Decimal::from_magnitude(self.signed, self.negative, extension, values)
     }
 
-    pub fn get_usize(&self) -> Result<usize, TypesError> {
-        if self.negative {
-            return Err(TypesError {
-                message: format!("negative value cannot be converted to usize")
-            });
+    //the magnitude in units of 10^extension, extension must not be larger than self.extension
+    fn magnitude_at(&self, extension: i16) -> Vec<usize> {
+        let mut values = self.values.to_vec();
+        scale_up(&mut values, (self.extension as i32 - extension as i32) as u32);
+        values
+    }
+
+    fn add_signed(&self, other: &Decimal, negate_other: bool) -> Decimal {
+        let extension = self.extension.min(other.extension);
+        let (a, b) = (self.magnitude_at(extension), other.magnitude_at(extension));
+        let other_negative = other.negative != negate_other;
+        let signed = self.signed || other.signed || negate_other;
+        if self.negative == other_negative {
+            return Decimal::from_magnitude(signed, self.negative, extension, add_magnitude(&a, &b));
         }
-        match self.values.len() {
-            0 => Ok(0),
-            1 => Ok(self.values[0]),
-            _ => Err(TypesError {
-                message: format!("Expected value smaller than usize but had {} fields", self.values.len())})
+        match cmp_magnitude(&a, &b) {
+            Ordering::Less => Decimal::from_magnitude(signed, other_negative, extension, sub_magnitude(&b, &a)),
+            _ => Decimal::from_magnitude(signed, self.negative, extension, sub_magnitude(&a, &b)),
+        }
+    }
+
+    /// None if the extension of the product does not fit into 16 bits
+    pub fn checked_mul(&self, other: &Decimal) -> Option<Decimal> {
+        let extension = self.extension.checked_add(other.extension)?;
+        let values = mul_magnitude(&self.values, &other.values);
+        Some(Decimal::from_magnitude(self.signed || other.signed, self.negative != other.negative, extension, values))
+    }
+
+    //the integral magnitude, None if there is a fractional part or it does not fit
+    fn integer_magnitude(&self) -> Option<u128> {
+        let normalized = self.normalized();
+        match normalized.extension {
+            _ if normalized.is_zero() => Some(0),
+            //10^39 > u128::MAX
+            0..39 => limbs_to_u128(&normalized.magnitude_at(0)),
+            _ => None,
         }
     }
 
+    pub fn get_usize(&self) -> Result<usize, TypesError> {
+        u128::try_from(self).ok()
+            .and_then(|v| usize::try_from(v).ok())
+            .ok_or(TypesError::OutOfRange("usize"))
+    }
+
     pub fn get_isize(&self) -> Result<isize, TypesError> {
-        match self.values.len() {
-            0 => Ok(0),
-            1 => {
-                if self.negative {
-                    Ok(-(self.values[0] as isize))
-                } else {
-                    Ok(self.values[0] as isize)
+        i128::try_from(self).ok()
+            .and_then(|v| isize::try_from(v).ok())
+            .ok_or(TypesError::OutOfRange("isize"))
+    }
+
+    /// The nearest f64, infinite if the value is too large
+    pub fn to_f64(&self) -> f64 {
+        //parsing a decimal string rounds correctly
+        let sign = if self.negative { "-" } else { "" };
+        format!("{sign}{}e{}", digits(&self.values), self.extension).parse().unwrap_or(f64::NAN)
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Decimal) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Decimal) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Decimal) -> Ordering {
+        let sign = |d: &Decimal| if d.is_zero() { 0 } else if d.negative { -1 } else { 1 };
+        match sign(self).cmp(&sign(other)) {
+            Ordering::Equal if sign(self) != 0 => {
+                let extension = self.extension.min(other.extension);
+                let ordering = cmp_magnitude(&self.magnitude_at(extension), &other.magnitude_at(extension));
+                if self.negative { ordering.reverse() } else { ordering }
+            }
+            ordering => ordering,
+        }
+    }
+}
+
+impl Hash for Decimal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let normalized = self.normalized();
+        (normalized.negative, normalized.extension, normalized.values).hash(state);
+    }
+}
+
+impl Add for &Decimal {
+    type Output = Decimal;
+
+    fn add(self, other: &Decimal) -> Decimal {
+        self.add_signed(other, false)
+    }
+}
+
+impl Sub for &Decimal {
+    type Output = Decimal;
+
+    fn sub(self, other: &Decimal) -> Decimal {
+        self.add_signed(other, true)
+    }
+}
+
+/// Fails if the extension of the product does not fit into 16 bits, see [`Decimal::checked_mul`]
+impl Mul for &Decimal {
+    type Output = Result<Decimal, TypesError>;
+
+    fn mul(self, other: &Decimal) -> Result<Decimal, TypesError> {
+        self.checked_mul(other).ok_or(TypesError::ExponentOutOfRange)
+    }
+}
+
+impl Mul for Decimal {
+    type Output = Result<Decimal, TypesError>;
+
+    fn mul(self, other: Decimal) -> Result<Decimal, TypesError> {
+        &self * &other
+    }
+}
+
+impl Neg for &Decimal {
+    type Output = Decimal;
+
+    fn neg(self) -> Decimal {
+        Decimal::from_magnitude(true, !self.negative, self.extension, self.values.to_vec())
+    }
+}
+
+macro_rules! owned_ops {
+    ($($op:ident $method:ident),*) => {$(
+        impl $op for Decimal {
+            type Output = Decimal;
+
+            fn $method(self, other: Decimal) -> Decimal {
+                (&self).$method(&other)
+            }
+        }
+    )*};
+}
+
+owned_ops!(Add add, Sub sub);
+
+impl Neg for Decimal {
+    type Output = Decimal;
+
+    fn neg(self) -> Decimal {
+        -&self
+    }
+}
+
+impl From<u128> for Decimal {
+    fn from(value: u128) -> Decimal {
+        Decimal::from_magnitude(false, false, 0, limbs_from_u128(value))
+    }
+}
+
+impl From<i128> for Decimal {
+    fn from(value: i128) -> Decimal {
+        Decimal::from_magnitude(true, value < 0, 0, limbs_from_u128(value.unsigned_abs()))
+    }
+}
+
+impl From<u64> for Decimal {
+    fn from(value: u64) -> Decimal {
+        Decimal::from(value as u128)
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(value: i64) -> Decimal {
+        Decimal::from(value as i128)
+    }
+}
+
+impl TryFrom<&Decimal> for u128 {
+    type Error = TypesError;
+
+    fn try_from(value: &Decimal) -> Result<u128, TypesError> {
+        match value.negative {
+            true => Err(TypesError::OutOfRange("u128")),
+            false => value.integer_magnitude().ok_or(TypesError::OutOfRange("u128")),
+        }
+    }
+}
+
+impl TryFrom<&Decimal> for i128 {
+    type Error = TypesError;
+
+    fn try_from(value: &Decimal) -> Result<i128, TypesError> {
+        let magnitude = value.integer_magnitude().ok_or(TypesError::OutOfRange("i128"))?;
+        match value.negative {
+            true if magnitude <= i128::MIN.unsigned_abs() => Ok(0u128.wrapping_sub(magnitude) as i128),
+            false if magnitude <= i128::MAX as u128 => Ok(magnitude as i128),
+            _ => Err(TypesError::OutOfRange("i128")),
+        }
+    }
+}
+
+/// The shortest decimal that converts back to the same f64, so `0.1` becomes `1e-1`
+impl TryFrom<f64> for Decimal {
+    type Error = TypesError;
+
+    fn try_from(value: f64) -> Result<Decimal, TypesError> {
+        match value.is_finite() {
+            true => format!("{value:e}").parse(),
+            false => Err(TypesError::NotFinite),
+        }
+    }
+}
+
+/// Parses decimal strings like `-12.50` or `1.5e-7`, the extension keeps trailing zeros
+impl FromStr for Decimal {
+    type Err = TypesError;
+
+    fn from_str(s: &str) -> Result<Decimal, TypesError> {
+        let (negative, unsigned) = match s.as_bytes().first() {
+            Some(b'-') => (true, &s[1..]),
+            Some(b'+') => (false, &s[1..]),
+            _ => (false, s),
+        };
+        let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+            None => (unsigned, None),
+        };
+        let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let is_digits = |s: &str| s.bytes().all(|c| c.is_ascii_digit());
+        if (integer.is_empty() && fraction.is_empty()) || !is_digits(integer) || !is_digits(fraction) {
+            return Err(TypesError::InvalidFormat);
+        }
+        let exponent = match exponent {
+            None => 0,
+            Some(e) => {
+                let digits = e.strip_prefix(['+', '-']).unwrap_or(e);
+                if digits.is_empty() || !is_digits(digits) {
+                    return Err(TypesError::InvalidFormat);
                 }
-            },
-            _ => Err(TypesError {
-                message: format!("Expected value smaller than isize but had {} fields", self.values.len())})
+                e.parse::<i64>().map_err(|_| TypesError::ExponentOutOfRange)?
+            }
+        };
+        let extension = exponent.checked_sub(fraction.len() as i64)
+            .and_then(|e| i16::try_from(e).ok())
+            .ok_or(TypesError::ExponentOutOfRange)?;
+
+        let mut values = Vec::new();
+        let digits = [integer.as_bytes(), fraction.as_bytes()].concat();
+        for chunk in digits.chunks(CHUNK_DIGITS as usize) {
+            let value = chunk.iter().fold(0, |v, c| v * 10 + (c - b'0') as usize);
+            mul_small(&mut values, 10usize.pow(chunk.len() as u32), value);
         }
+        Ok(Decimal::from_magnitude(true, negative, extension, values))
     }
 }
 
+/// Plain notation for values with up to 6 leading zeros after the point and no positive extension,
+/// scientific notation otherwise. The digits always keep the extension, so `1.50` stays `1.50`.
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = digits(&self.values);
+        let sign = if self.negative { "-" } else { "" };
+        let extension = self.extension as i64;
+        let adjusted = extension + digits.len() as i64 - 1;
+        if extension <= 0 && adjusted >= -6 {
+            //position of the point within the digits
+            let point = digits.len() as i64 + extension;
+            match point {
+                _ if extension == 0 => write!(f, "{sign}{digits}"),
+                1.. => write!(f, "{sign}{}.{}", &digits[..point as usize], &digits[point as usize..]),
+                _ => write!(f, "{sign}0.{}{digits}", "0".repeat(point.unsigned_abs() as usize)),
+            }
+        } else {
+            let (first, rest) = digits.split_at(1);
+            let point = if rest.is_empty() { "" } else { "." };
+            write!(f, "{sign}{first}{point}{rest}e{adjusted}")
+        }
+    }
+}
+
+#[cfg(feature = "rust_decimal")]
+impl From<rust_decimal::Decimal> for Decimal {
+    fn from(value: rust_decimal::Decimal) -> Decimal {
+        let decimal = Decimal::from(value.mantissa());
+        //the scale is at most 28
+        Decimal { extension: -(value.scale() as i16), ..decimal }
+    }
+}
+
+/// Fails if the value needs more than 96 bits or 28 fractional digits
+#[cfg(feature = "rust_decimal")]
+impl TryFrom<&Decimal> for rust_decimal::Decimal {
+    type Error = TypesError;
+
+    fn try_from(value: &Decimal) -> Result<rust_decimal::Decimal, TypesError> {
+        const MAX_SCALE: i16 = 28;
+        let out_of_range = TypesError::OutOfRange("rust_decimal");
+        let value = if value.extension < -MAX_SCALE { value.normalized() } else { value.clone() };
+        if !(-MAX_SCALE..=MAX_SCALE).contains(&value.extension) {
+            return Err(out_of_range);
+        }
+        let extension = value.extension.min(0);
+        let magnitude = limbs_to_u128(&value.magnitude_at(extension)).ok_or(TypesError::OutOfRange("rust_decimal"))?;
+        let mantissa = if value.negative { -(magnitude as i128) } else { magnitude as i128 };
+        rust_decimal::Decimal::try_from_i128_with_scale(mantissa, extension.unsigned_abs() as u32).map_err(|_| out_of_range)
+    }
+}
+
+#[cfg(feature = "bigdecimal")]
+impl From<&Decimal> for bigdecimal::BigDecimal {
+    fn from(value: &Decimal) -> bigdecimal::BigDecimal {
+        use bigdecimal::num_bigint::{BigInt, Sign};
+        let sign = if value.negative { Sign::Minus } else { Sign::Plus };
+        bigdecimal::BigDecimal::new(BigInt::from_bytes_le(sign, &value.magnitude_le_bytes()), -(value.extension as i64))
+    }
+}
+
+/// Fails if the exponent does not fit into 16 bits
+#[cfg(feature = "bigdecimal")]
+impl TryFrom<&bigdecimal::BigDecimal> for Decimal {
+    type Error = TypesError;
+
+    fn try_from(value: &bigdecimal::BigDecimal) -> Result<Decimal, TypesError> {
+        let (integer, scale) = value.as_bigint_and_exponent();
+        let extension = scale.checked_neg()
+            .and_then(|e| i16::try_from(e).ok())
+            .ok_or(TypesError::ExponentOutOfRange)?;
+        let (sign, bytes) = integer.to_bytes_le();
+        Ok(Decimal::from_magnitude_le_bytes(sign == bigdecimal::num_bigint::Sign::Minus, extension, &bytes))
+    }
+}
+
+fn trim(mut values: Vec<usize>) -> Vec<usize> {
+    while values.last() == Some(&0) {
+        values.pop();
+    }
+    values
+}
+
+fn limbs_from_u128(mut value: u128) -> Vec<usize> {
+    let mut values = Vec::new();
+    while value > 0 {
+        values.push(value as usize);
+        value >>= usize::BITS;
+    }
+    values
+}
+
+fn limbs_to_u128(values: &[usize]) -> Option<u128> {
+    values.iter().enumerate().try_fold(0u128, |result, (i, limb)| {
+        let shift = i as u32 * usize::BITS;
+        match *limb {
+            0 => Some(result),
+            _ if shift >= u128::BITS => None,
+            limb => Some(result | (limb as u128) << shift),
+        }
+    })
+}
+
+//compares trimmed magnitudes
+fn cmp_magnitude(a: &[usize], b: &[usize]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitude(a: &[usize], b: &[usize]) -> Vec<usize> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut result = Vec::with_capacity(long.len() + 1);
+    let mut carry = false;
+    for (i, limb) in long.iter().enumerate() {
+        let (sum, c1) = limb.overflowing_add(short.get(i).copied().unwrap_or(0));
+        let (sum, c2) = sum.overflowing_add(carry as usize);
+        result.push(sum);
+        carry = c1 || c2;
+    }
+    if carry {
+        result.push(1);
+    }
+    result
+}
+
+//a must not be smaller than b
+fn sub_magnitude(a: &[usize], b: &[usize]) -> Vec<usize> {
+    let mut result = Vec::with_capacity(a.len());
+    let mut borrow = false;
+    for (i, limb) in a.iter().enumerate() {
+        let (diff, b1) = limb.overflowing_sub(b.get(i).copied().unwrap_or(0));
+        let (diff, b2) = diff.overflowing_sub(borrow as usize);
+        result.push(diff);
+        borrow = b1 || b2;
+    }
+    trim(result)
+}
+
+fn mul_magnitude(a: &[usize], b: &[usize]) -> Vec<usize> {
+    let mut result = vec![0; a.len() + b.len()];
+    for (i, x) in a.iter().enumerate() {
+        let mut carry = 0u128;
+        for (j, y) in b.iter().enumerate() {
+            let t = *x as u128 * *y as u128 + result[i + j] as u128 + carry;
+            result[i + j] = t as usize;
+            carry = t >> usize::BITS;
+        }
+        result[i + b.len()] = carry as usize;
+    }
+    trim(result)
+}
+
+//values * factor + add
+fn mul_small(values: &mut Vec<usize>, factor: usize, add: usize) {
+    let mut carry = add as u128;
+    for limb in values.iter_mut() {
+        let t = *limb as u128 * factor as u128 + carry;
+        *limb = t as usize;
+        carry = t >> usize::BITS;
+    }
+    if carry > 0 {
+        values.push(carry as usize);
+    }
+}
+
+//divides in place and returns the remainder
+fn divrem_small(values: &mut Vec<usize>, divisor: usize) -> usize {
+    let mut remainder = 0u128;
+    for limb in values.iter_mut().rev() {
+        let t = (remainder << usize::BITS) | *limb as u128;
+        *limb = (t / divisor as u128) as usize;
+        remainder = t % divisor as u128;
+    }
+    while values.last() == Some(&0) {
+        values.pop();
+    }
+    remainder as usize
+}
+
+//multiplies by 10^digits
+fn scale_up(values: &mut Vec<usize>, digits: u32) {
+    if values.is_empty() {
+        return;
+    }
+    for _ in 0..digits / CHUNK_DIGITS {
+        mul_small(values, CHUNK, 0);
+    }
+    mul_small(values, 10usize.pow(digits % CHUNK_DIGITS), 0);
+}
+
+//decimal digits of a magnitude
+fn digits(values: &[usize]) -> String {
+    let mut values = values.to_vec();
+    let mut chunks = Vec::new();
+    while !values.is_empty() {
+        chunks.push(divrem_small(&mut values, CHUNK));
+    }
+    let Some((first, rest)) = chunks.split_last() else {
+        return String::from("0");
+    };
+    let mut digits = first.to_string();
+    for chunk in rest.iter().rev() {
+        digits.push_str(&format!("{chunk:09}"));
+    }
+    digits
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn d(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
     #[test]
     fn test_decimal_usize() {
         let v = Decimal::from_usize(1);
         assert_eq!(v.get_usize().unwrap(), 1);
+        assert_eq!(Decimal::from_usize(usize::MAX).get_isize(), Err(TypesError::OutOfRange("isize")));
+        assert_eq!(Decimal::from_isize(isize::MIN).get_isize(), Ok(isize::MIN));
+        assert_eq!(d("12e2").get_usize(), Ok(1200));
+        assert_eq!(d("1.5").get_usize(), Err(TypesError::OutOfRange("usize")));
+    }
+
+    #[test]
+    fn test_parse_and_format() {
+        for s in ["0", "-1", "1.50", "0.000001", "1e-7", "1.23e4", "0e5", "0.000", "-123456789012345678901234567890.5"] {
+            assert_eq!(d(s).to_string(), s);
+        }
+        assert_eq!(d("+12.5E-3").to_string(), "0.0125");
+        assert_eq!(d("-0").to_string(), "0");
+        assert_eq!(d(".5"), d("0.5"));
+        assert_eq!(d("1200").normalized().to_string(), "1.2e3");
+        assert_eq!(d("1.500").normalized().extension(), -1);
+        for s in ["", "-", ".", "1.2.3", "1e", "e5", "1e+", "0x10", "1_000"] {
+            assert_eq!(s.parse::<Decimal>(), Err(TypesError::InvalidFormat), "{s}");
+        }
+        assert_eq!("1e40000".parse::<Decimal>(), Err(TypesError::ExponentOutOfRange));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_arithmetic_and_compare() {
+        assert_eq!(d("0.1") + d("0.2"), d("0.3"));
+        assert_eq!((d("1.10") + d("2.205")).to_string(), "3.305");
+        assert_eq!((d("1") - d("1.5")).to_string(), "-0.5");
+        assert_eq!((d("-2.5") * d("4")).unwrap().to_string(), "-10.0");
+        assert_eq!(-d("3"), d("-3"));
+        let big = d("18446744073709551616");
+        let square = (&big * &big).unwrap();
+        assert_eq!(square.to_string(), "340282366920938463463374607431768211456");
+        assert_eq!(&square - &square, d("0"));
+        assert!(d("1e32767").checked_mul(&d("1e1")).is_none());
+        assert!(matches!(d("1e32767") * d("1e1"), Err(TypesError::ExponentOutOfRange)));
+
+        assert!(d("-1") < d("0") && d("0") < d("1e-10") && d("1e-10") < d("1"));
+        assert!(d("-2") < d("-1.5"));
+        assert_eq!(d("1.50"), d("1.5"));
+        assert_eq!(d("1.5").cmp(&d("15e-1")), Ordering::Equal);
+        let hash = |v: &Decimal| {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            v.hash(&mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(hash(&d("1.50")), hash(&d("1.5")));
+    }
+
+    #[test]
+    fn test_conversions() {
+        for v in [0, 1, -1, i128::MAX, i128::MIN] {
+            assert_eq!(i128::try_from(&Decimal::from(v)), Ok(v));
+        }
+        assert_eq!(u128::try_from(&Decimal::from(u128::MAX)), Ok(u128::MAX));
+        assert_eq!(u128::try_from(&(&Decimal::from(u128::MAX) + &d("1"))), Err(TypesError::OutOfRange("u128")));
+        assert_eq!(i128::try_from(&d("-170141183460469231731687303715884105729")), Err(TypesError::OutOfRange("i128")));
+        assert_eq!(u128::try_from(&d("-1")), Err(TypesError::OutOfRange("u128")));
+        assert_eq!(i128::try_from(&d("1.2e3")), Ok(1200));
+
+        assert_eq!(Decimal::try_from(0.1).unwrap().to_string(), "0.1");
+        assert_eq!(Decimal::try_from(-1.5e300).unwrap().to_string(), "-1.5e300");
+        assert_eq!(Decimal::try_from(f64::NAN), Err(TypesError::NotFinite));
+        for v in [0.1, -2.5, 1e-300, f64::MAX, f64::MIN_POSITIVE] {
+            assert_eq!(Decimal::try_from(v).unwrap().to_f64(), v);
+        }
+        assert_eq!(d("1e400").to_f64(), f64::INFINITY);
+
+        let bytes = d("-123456789012345678901234567890").magnitude_le_bytes();
+        assert_eq!(Decimal::from_magnitude_le_bytes(true, -2, &bytes), d("-1234567890123456789012345678.90"));
+        assert_eq!(Decimal::from_magnitude_le_bytes(true, 0, &[]).to_string(), "0");
+    }
+
+    #[cfg(feature = "rust_decimal")]
+    #[test]
+    fn test_rust_decimal() {
+        let value = rust_decimal::Decimal::from_str_exact("-79228162514264337593543950335").unwrap();
+        assert_eq!(Decimal::from(value).to_string(), value.to_string());
+        for s in ["1.50", "-0.0000000000000000000000000001", "12e3", "1.000000000000000000000000000000"] {
+            let converted = rust_decimal::Decimal::try_from(&d(s)).unwrap();
+            assert_eq!(Decimal::from(converted), d(s), "{s}");
+        }
+        assert!(rust_decimal::Decimal::try_from(&d("1e29")).is_err());
+        assert!(rust_decimal::Decimal::try_from(&d("1e-29")).is_err());
+    }
+
+    #[cfg(feature = "bigdecimal")]
+    #[test]
+    fn test_bigdecimal() {
+        for s in ["0", "-1.50", "123456789012345678901234567890e-40", "7e300"] {
+            let converted = bigdecimal::BigDecimal::from(&d(s));
+            assert_eq!(converted, s.parse::<bigdecimal::BigDecimal>().unwrap());
+            assert_eq!(Decimal::try_from(&converted).unwrap(), d(s));
+        }
+        let huge = "1e40000".parse::<bigdecimal::BigDecimal>().unwrap();
+        assert_eq!(Decimal::try_from(&huge), Err(TypesError::ExponentOutOfRange));
+    }
+}
@@ -0,0 +1,119 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+//! LEB128 varints, see docs/varint_encoding.md
+
+use crate::error::DossError;
+
+/// a 64 bit value never needs more than 10 bytes
+pub const MAX_VARINT_LEN: usize = 10;
+
+/// Decodes an unsigned varint from the start of `input`.
+/// Returns the value and the number of bytes used or None if `input` ends before the varint.
+pub fn decode_unsigned(input: &[u8]) -> Result<Option<(u64, usize)>, DossError> {
+    let mut result: u64 = 0;
+    for (i, b) in input.iter().take(MAX_VARINT_LEN).enumerate() {
+        //the 10th byte only has room for the highest bit
+        if i == MAX_VARINT_LEN - 1 && *b > 1 {
+            return Err(DossError::VarintOverflow);
+        }
+        result |= ((b & 0x7f) as u64) << (7 * i);
+        if b & 0x80 == 0 {
+            return Ok(Some((result, i + 1)));
+        }
+    }
+    if input.len() >= MAX_VARINT_LEN {
+        return Err(DossError::VarintOverflow);
+    }
+    Ok(None)
+}
+
+/// Decodes a signed varint from the start of `input`, see [`decode_unsigned`]
+pub fn decode_signed(input: &[u8]) -> Result<Option<(i64, usize)>, DossError> {
+    let mut result: u64 = 0;
+    for (i, b) in input.iter().take(MAX_VARINT_LEN).enumerate() {
+        let shift = 7 * i;
+        //the 10th byte only holds the sign bit and its extension
+        if i == MAX_VARINT_LEN - 1 && *b != 0 && *b != 0x7f {
+            return Err(DossError::VarintOverflow);
+        }
+        result |= ((b & 0x7f) as u64) << shift;
+        if b & 0x80 == 0 {
+            if shift + 7 < 64 && b & 0x40 != 0 {
+                result |= !0 << (shift + 7);
+            }
+            return Ok(Some((result as i64, i + 1)));
+        }
+    }
+    if input.len() >= MAX_VARINT_LEN {
+        return Err(DossError::VarintOverflow);
+    }
+    Ok(None)
+}
+
+pub fn encode_unsigned(mut value: u64, out: &mut Vec<u8>) {
+    loop {
+        let b = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(b);
+            return;
+        }
+        out.push(b | 0x80);
+    }
+}
+
+/// Number of bytes `encode_unsigned` writes for `value`
+pub fn unsigned_len(value: u64) -> usize {
+    (64 - value.leading_zeros() as usize).div_ceil(7).max(1)
+}
+
+pub fn encode_signed(mut value: i64, out: &mut Vec<u8>) {
+    loop {
+        let b = (value & 0x7f) as u8;
+        value >>= 7;
+        //done once the remaining bits are just the sign extension of the last byte
+        if (value == 0 && b & 0x40 == 0) || (value == -1 && b & 0x40 != 0) {
+            out.push(b);
+            return;
+        }
+        out.push(b | 0x80);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unsigned() {
+        for v in [0, 1, 127, 128, 300, 16383, 16384, u32::MAX as u64, u64::MAX - 1, u64::MAX] {
+            let mut out = Vec::new();
+            encode_unsigned(v, &mut out);
+            assert_eq!(decode_unsigned(&out).unwrap(), Some((v, out.len())));
+            assert_eq!(decode_unsigned(&out[..out.len() - 1]).unwrap(), None);
+        }
+        let mut out = Vec::new();
+        encode_unsigned(624485, &mut out);
+        assert_eq!(out, [0xe5, 0x8e, 0x26]);
+    }
+
+    #[test]
+    fn test_signed() {
+        for v in [0, 1, -1, 63, 64, -64, -65, 8191, -8192, i32::MIN as i64, i64::MAX, i64::MIN] {
+            let mut out = Vec::new();
+            encode_signed(v, &mut out);
+            assert_eq!(decode_signed(&out).unwrap(), Some((v, out.len())));
+            assert_eq!(decode_signed(&out[..out.len() - 1]).unwrap(), None);
+        }
+        let mut out = Vec::new();
+        encode_signed(-123456, &mut out);
+        assert_eq!(out, [0xc0, 0xbb, 0x78]);
+    }
+
+    #[test]
+    fn test_overflow() {
+        assert!(matches!(decode_unsigned(&[0xff; 11]), Err(DossError::VarintOverflow)));
+        assert!(matches!(decode_unsigned(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02]), Err(DossError::VarintOverflow)));
+        assert!(matches!(decode_signed(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01]), Err(DossError::VarintOverflow)));
+    }
+}
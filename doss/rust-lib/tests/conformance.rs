@@ -0,0 +1,77 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+//! Decodes the examples of docs/examples.md. Every example is a binary fixture in tests/conformance
+//! named after its heading, together with the expected JSON, one top level value per line.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use doss::{DossDictionaryRegistry, DossError, DossEvent, DossPredefinedDictionary, DossPullParser, decode_entries_with_registry};
+use streamablejson::StreamableJSONEntry;
+use streamablejson::deserializer::deserialize_orderedbag_from_string;
+
+fn dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/conformance")
+}
+
+//the predefined dictionary of the "Importing predefined dictionaries" example
+fn registry() -> Arc<DossDictionaryRegistry> {
+    let entries = ["hello", "world"].map(|s| vec![DossEvent::String(s.to_string())]).to_vec();
+    let mut registry = DossDictionaryRegistry::new();
+    registry.register(DossPredefinedDictionary::new("hello_world", 1, entries).unwrap()).unwrap();
+    Arc::new(registry)
+}
+
+fn fixture_names() -> Vec<String> {
+    let examples = std::fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("../docs/examples.md")).unwrap();
+    examples.lines()
+        .filter_map(|line| line.strip_prefix("## "))
+        .map(|heading| heading.trim().to_lowercase().replace(' ', "_"))
+        .collect()
+}
+
+fn fixture(name: &str) -> (Vec<u8>, Vec<StreamableJSONEntry>) {
+    let serialized = std::fs::read(dir().join(format!("{name}.doss"))).unwrap_or_else(|_| panic!("missing fixture {name}.doss"));
+    let json = std::fs::read_to_string(dir().join(format!("{name}.json"))).unwrap();
+    let expected = json.lines().map(|line| deserialize_orderedbag_from_string(line.to_string()).unwrap()).collect();
+    (serialized, expected)
+}
+
+#[test]
+fn test_examples() {
+    let names = fixture_names();
+    assert_eq!(names.len(), 10);
+    for name in names {
+        let (serialized, expected) = fixture(&name);
+        assert_eq!(decode_entries_with_registry(&serialized, registry()).unwrap(), expected, "{name}");
+
+        let mut parser = DossPullParser::new(serialized.as_slice());
+        parser.set_registry(registry());
+        assert!(parser.all(|event| event.is_ok()), "{name}");
+
+        //cut streams fail or miss values, they never decode to the same result
+        for end in 0..serialized.len() {
+            match decode_entries_with_registry(&serialized[..end], registry()) {
+                Ok(entries) => assert!(entries.len() < expected.len(), "{name} cut at {end}"),
+                Err(e) => assert!(matches!(e, DossError::UnexpectedEof), "{name} cut at {end}: {e}"),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_skipping_example() {
+    let (serialized, _) = fixture("skipping_data");
+    let mut parser = DossPullParser::new(serialized.as_slice());
+    assert!(matches!(parser.next_doss_event(), Ok(Some(DossEvent::BlockStart))));
+    parser.skip_level();
+    assert!(matches!(parser.next_doss_event(), Ok(Some(DossEvent::BlockEnd))));
+    assert!(matches!(parser.next_doss_event(), Ok(None)));
+}
+
+#[test]
+fn test_import_needs_registry() {
+    let (serialized, _) = fixture("importing_predefined_dictionaries");
+    let mut parser = DossPullParser::new(serialized.as_slice());
+    assert!(matches!(parser.by_ref().last(), Some(Err(DossError::InvalidReference(0)))));
+}
@@ -0,0 +1,215 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+//! Property tests with random documents and random bytes. Every case is derived from a seed,
+//! failures report it so the case can be replayed.
+
+use std::io::Cursor;
+
+use dataflowgrid_commons::orderedbag::OrderedBag;
+use dataflowgrid_commons::typedstream::{DateTime, TypeStream2OrderedMultiDictProcessor, TypedStreamElement, TypedStreamEvent};
+use doss::{Compression, DossEvent, DossLimits, DossLowLevelStreamEvent, DossPullParser, DossSerializer, DossSerializerOptions, decode_entries};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const CASES: u64 = 300;
+
+//a small pool makes strings repeat, so the dictionary is used
+const WORDS: [&str; 8] = ["id", "name", "value", "grüße", "", "a somewhat longer string value", "x", "🦀"];
+
+fn string(rng: &mut StdRng) -> String {
+    match rng.gen_range(0..4) {
+        0 => (0..rng.gen_range(0..20)).map(|_| rng.r#gen::<char>()).collect(),
+        _ => WORDS[rng.gen_range(0..WORDS.len())].to_string(),
+    }
+}
+
+fn element(rng: &mut StdRng, depth: usize) -> TypedStreamElement {
+    let container = depth < 5 && rng.gen_bool(0.3);
+    match (container, rng.gen_range(0..8)) {
+        (true, 0..=2) => {
+            let mut object = OrderedBag::new();
+            for _ in 0..rng.gen_range(0..6) {
+                object.push(TypedStreamElement::String(string(rng)), element(rng, depth + 1));
+            }
+            TypedStreamElement::Object(object)
+        }
+        (true, 3..=5) => TypedStreamElement::Array((0..rng.gen_range(0..8)).map(|_| element(rng, depth + 1)).collect()),
+        (true, _) => {
+            //names of typed values like decimal("1.5") have a meaning of their own
+            let name = format!("type_{}", rng.gen_range(0..3));
+            TypedStreamElement::Type(name, (0..rng.gen_range(1..3)).map(|_| element(rng, depth + 1)).collect())
+        }
+        (false, 0) => TypedStreamElement::Null,
+        (false, 1) => TypedStreamElement::Boolean(rng.r#gen()),
+        (false, 2) => TypedStreamElement::Float(match rng.gen_bool(0.5) {
+            true => rng.r#gen::<f32>() as f64 * 1e6,
+            false => f64::from_bits(rng.r#gen::<u64>() & !(0x7ff << 52)), //never infinite or NaN
+        }),
+        (false, 3) => {
+            let offset = rng.gen_range(-1439..=1439);
+            TypedStreamElement::DateTime(DateTime::new(rng.gen_range(-1 << 50..1 << 50), rng.gen_range(0..=9), offset).unwrap())
+        }
+        (false, 4) => TypedStreamElement::ByteArray((0..rng.gen_range(0..30)).map(|_| rng.r#gen()).collect()),
+        (false, _) => TypedStreamElement::String(string(rng)),
+    }
+}
+
+fn events(element: &TypedStreamElement, out: &mut Vec<TypedStreamEvent>) {
+    match element {
+        TypedStreamElement::Object(object) => {
+            out.push(TypedStreamEvent::STARTOBJECT);
+            for (key, value) in object.iter() {
+                events(key, out);
+                events(value, out);
+            }
+            out.push(TypedStreamEvent::ENDOBJECT);
+        }
+        TypedStreamElement::Array(array) => {
+            out.push(TypedStreamEvent::STARTARRAY);
+            array.iter().for_each(|e| events(e, out));
+            out.push(TypedStreamEvent::ENDARRAY);
+        }
+        TypedStreamElement::Type(name, content) => {
+            out.push(TypedStreamEvent::STARTTYPE(name.clone()));
+            content.iter().for_each(|e| events(e, out));
+            out.push(TypedStreamEvent::ENDTYPE);
+        }
+        TypedStreamElement::String(s) => out.push(TypedStreamEvent::STRING(s.clone())),
+        TypedStreamElement::Float(f) => out.push(TypedStreamEvent::FLOAT(*f)),
+        TypedStreamElement::DateTime(d) => out.push(TypedStreamEvent::DATETIME(*d)),
+        TypedStreamElement::Null => out.push(TypedStreamEvent::NULL),
+        TypedStreamElement::Boolean(true) => out.push(TypedStreamEvent::TRUE),
+        TypedStreamElement::Boolean(false) => out.push(TypedStreamEvent::FALSE),
+        TypedStreamElement::ByteArray(b) => out.push(TypedStreamEvent::BYTEARRAY(b.clone())),
+        _ => unreachable!("not generated"),
+    }
+}
+
+fn options(rng: &mut StdRng) -> DossSerializerOptions {
+    DossSerializerOptions {
+        max_dict_entries: [0, 3, 1000][rng.gen_range(0..3)],
+        min_occurrences: rng.gen_range(1..4),
+        emit_dict_hint: rng.r#gen(),
+        skip_threshold: [None, Some(8), Some(64)][rng.gen_range(0..3)],
+        skip_buffer: [16, 1 << 20][rng.gen_range(0..2)],
+        //uncompressed without the features
+        compression: Compression::from_name(["none", "zstd", "lz4"][rng.gen_range(0..3)]).unwrap_or_default(),
+        frame_size: [7, 1 << 16][rng.gen_range(0..2)],
+    }
+}
+
+fn serialize(elements: &[TypedStreamElement], options: DossSerializerOptions, seekable: bool) -> Vec<u8> {
+    let mut typed = Vec::new();
+    elements.iter().for_each(|e| events(e, &mut typed));
+    match seekable {
+        true => {
+            let mut serializer = DossSerializer::seekable(Cursor::new(Vec::new()), options);
+            typed.iter().for_each(|e| serializer.write_event(e).unwrap());
+            serializer.finish().unwrap().into_inner()
+        }
+        false => {
+            let mut serializer = DossSerializer::with_options(Vec::new(), options);
+            typed.iter().for_each(|e| serializer.write_event(e).unwrap());
+            serializer.finish().unwrap()
+        }
+    }
+}
+
+#[test]
+fn test_random_trees_roundtrip() {
+    for seed in 0..CASES {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let elements: Vec<_> = (0..rng.gen_range(1..4)).map(|_| element(&mut rng, 0)).collect();
+        let options = options(&mut rng);
+        let serialized = serialize(&elements, options, rng.r#gen());
+
+        let mut processor = TypeStream2OrderedMultiDictProcessor::new();
+        DossPullParser::new(serialized.as_slice()).run(&mut processor).unwrap_or_else(|e| panic!("seed {seed}: {e}"));
+        let decoded: Vec<_> = std::iter::from_fn(|| processor.get_result()).collect();
+        assert!(decoded == elements, "seed {seed}");
+    }
+}
+
+//the events delivered while skipping at random points, the same for streams with and without skip opcodes
+fn read_skipping(serialized: &[u8], seed: u64) -> Vec<DossEvent> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut parser = DossPullParser::new(serialized);
+    let mut events = Vec::new();
+    while let Some(event) = parser.next_doss_event().unwrap() {
+        if !matches!(event, DossEvent::Hint { .. }) && rng.gen_bool(0.1) {
+            parser.skip_level();
+        }
+        events.push(event);
+    }
+    events
+}
+
+#[test]
+fn test_skipping_matches_reading() {
+    for seed in 0..CASES {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let elements: Vec<_> = (0..rng.gen_range(1..4)).map(|_| element(&mut rng, 0)).collect();
+        let options = options(&mut rng);
+        let with_skips = serialize(&elements, DossSerializerOptions { skip_threshold: Some(8), ..options.clone() }, true);
+        let without = serialize(&elements, DossSerializerOptions { skip_threshold: None, ..options }, true);
+        assert_eq!(read_skipping(&with_skips, seed), read_skipping(&without, seed), "seed {seed}");
+    }
+}
+
+//decoding untrusted bytes returns errors, it never panics and stays within the limits
+fn decode_untrusted(bytes: &[u8]) {
+    let mut parser = DossPullParser::new(bytes);
+    parser.set_limits(DossLimits { max_depth: 16, max_string_len: 1 << 10, max_memory: 1 << 16 });
+    let mut rng = StdRng::seed_from_u64(bytes.len() as u64);
+    while let Some(Ok(_)) = parser.next() {
+        if rng.gen_bool(0.05) {
+            parser.skip_level();
+        }
+    }
+    let _ = decode_entries(bytes);
+}
+
+#[test]
+fn test_random_bytes() {
+    for seed in 0..CASES * 10 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let bytes: Vec<u8> = (0..rng.gen_range(0..200)).map(|_| match rng.gen_bool(0.5) {
+            //opcodes are small numbers, random bytes alone rarely get past the first one
+            true => rng.gen_range(0..52),
+            false => rng.r#gen(),
+        }).collect();
+        decode_untrusted(&bytes);
+
+        //every decoded low level event encodes to bytes that decode to the same event, compared encoded for NaN floats
+        if let Ok(Some((event, _))) = DossLowLevelStreamEvent::decode(&bytes) {
+            let mut encoded = Vec::new();
+            event.encode(&mut encoded);
+            let (decoded, used) = DossLowLevelStreamEvent::decode(&encoded).unwrap().unwrap();
+            let mut reencoded = Vec::new();
+            decoded.encode(&mut reencoded);
+            assert_eq!((reencoded, used), (encoded.clone(), encoded.len()), "seed {seed}");
+        }
+    }
+}
+
+#[test]
+fn test_mutated_streams() {
+    for seed in 0..CASES {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let elements: Vec<_> = (0..rng.gen_range(1..3)).map(|_| element(&mut rng, 0)).collect();
+        let options = options(&mut rng);
+        let mut serialized = serialize(&elements, options, true);
+        for _ in 0..rng.gen_range(1..4) {
+            if serialized.is_empty() {
+                break;
+            }
+            let at = rng.gen_range(0..serialized.len());
+            match rng.gen_range(0..3) {
+                0 => serialized[at] = rng.r#gen(),
+                1 => { serialized.remove(at); }
+                _ => serialized.insert(at, rng.gen_range(0..52)),
+            }
+        }
+        decode_untrusted(&serialized);
+    }
+}
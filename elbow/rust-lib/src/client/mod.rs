@@ -1,6 +1,6 @@
 #![allow(dead_code)]
 
-use std::{fmt::{Debug, Formatter}, sync::{Arc, RwLock}};
+use std::{fmt::{Debug, Formatter}, sync::Arc};
 
 use http_body_util::Empty;
 use hyper::Request;
@@ -51,6 +51,7 @@ impl ElbowConnection {
         }
     }
 
+    #[allow(clippy::never_loop)] //reconnecting is not implemented yet
     async fn run(inner: Arc<ElbowConnectionInner>) {
         loop {
             let host = &inner.host;
@@ -69,7 +70,7 @@ impl ElbowConnection {
           
     
             let (ws, _) = handshake::client(&SpawnExecutor, req, stream).await.unwrap();
-            let mut ws = FragmentCollector::new(ws);
+            let _ws = FragmentCollector::new(ws);
             break;
         }
     }
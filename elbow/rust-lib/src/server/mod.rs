@@ -61,9 +61,8 @@ impl ElbowServerConnection {
         let mut ws = fastwebsockets::FragmentCollector::new(ws);
         let reader = self.buffer_out.reader(0);
 
-        match self.callback {
-            Callback::Callback(ref cb) => cb(&self.connection_id),
-            _ => {}
+        if let Callback::Callback(ref cb) = self.callback {
+            cb(&self.connection_id)
         }
         loop {
             tokio::select! {
@@ -170,7 +169,7 @@ use tokio::select;
     async fn runserver(server: Arc<RwLock<ElbowServer>>) -> Result<(), WebSocketError> {
         let listener = TcpListener::bind("127.0.0.1:8080").await;
         let listener = listener.unwrap();
-        println!("Server started, listening on {}", "127.0.0.1:8080");
+        println!("Server started, listening on 127.0.0.1:8080");
         let shutdown_notify = server.read().unwrap().shutdown_notify.clone();
 
         server.read().unwrap().started_notify.notify_waiters();
@@ -197,7 +196,7 @@ use tokio::select;
                 }
             }
         }
-        return Ok(());
+        Ok(())
     }
 
     // Tie hyper's executor to tokio runtime
@@ -0,0 +1,133 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::cell::RefCell;
+use std::sync::Arc;
+
+use doss::{DossDictionaryRegistry, DossError, DossPredefinedDictionary};
+use pgrx::prelude::*;
+
+use crate::PgDossError;
+
+extension_sql!(
+    r#"
+CREATE TABLE doss_dictionaries (
+    name text NOT NULL,
+    version bigint NOT NULL,
+    content bytea NOT NULL,
+    PRIMARY KEY (name, version)
+);
+SELECT pg_catalog.pg_extension_config_dump('doss_dictionaries', '');
+"#,
+    name = "doss_dictionaries"
+);
+
+//the table of the extension, found by its membership so a table of the same name on the search path is not used
+const TABLE_QUERY: &str = "SELECT format('%I.%I', n.nspname, c.relname) FROM pg_catalog.pg_class c \
+    JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace \
+    JOIN pg_catalog.pg_depend d ON d.classid = 'pg_catalog.pg_class'::pg_catalog.regclass AND d.objid = c.oid AND d.deptype = 'e' \
+    WHERE c.relname = 'doss_dictionaries'";
+
+#[derive(Default)]
+struct Cache {
+    table: Option<String>,
+    registry: Arc<DossDictionaryRegistry>,
+    keys: String, //name and version of every dictionary loaded
+    checked: Option<(pg_sys::TimestampTz, pg_sys::CommandId)>, //statement and command that last compared the keys with the table
+}
+
+thread_local! {
+    //the dictionaries of the table as this backend loaded them last
+    static CACHE: RefCell<Cache> = RefCell::new(Cache::default());
+}
+
+/// The schema qualified name of `doss_dictionaries`
+fn table() -> Result<String, PgDossError> {
+    if let Some(table) = CACHE.with(|c| c.borrow().table.clone()) {
+        return Ok(table);
+    }
+    let table = Spi::get_one::<String>(TABLE_QUERY)?.ok_or_else(|| PgDossError::Input(String::from("table doss_dictionaries is missing")))?;
+    CACHE.with(|c| c.borrow_mut().table = Some(table.clone()));
+    Ok(table)
+}
+
+//name and version of every row, the content of a version never changes
+fn keys(table: &str) -> Result<String, PgDossError> {
+    let query = format!("SELECT coalesce(string_agg(name || ' ' || version, ',' ORDER BY name, version), '') FROM {table}");
+    Ok(Spi::get_one::<String>(&query)?.unwrap_or_default())
+}
+
+//the current statement and command, commands advance after every change within a statement
+fn command() -> (pg_sys::TimestampTz, pg_sys::CommandId) {
+    unsafe { (pg_sys::GetCurrentStatementStartTimestamp(), pg_sys::GetCurrentCommandId(false)) }
+}
+
+fn load() -> Result<Arc<DossDictionaryRegistry>, PgDossError> {
+    let table = table()?;
+    let keys = keys(&table)?;
+    let registry = Spi::connect(|client| {
+        let mut registry = DossDictionaryRegistry::new();
+        for row in client.select(&format!("SELECT content FROM {table}"), None, None)? {
+            if let Some(content) = row.get::<Vec<u8>>(1)? {
+                registry.register(DossPredefinedDictionary::from_bytes(&content)?)?;
+            }
+        }
+        Ok::<_, PgDossError>(registry)
+    })?;
+    let registry = Arc::new(registry);
+    CACHE.with(|c| {
+        let mut cache = c.borrow_mut();
+        cache.registry = registry.clone();
+        cache.keys = keys;
+        cache.checked = Some(command());
+    });
+    Ok(registry)
+}
+
+/// The loaded dictionaries. Once per statement and command they are compared with the table, so
+/// dictionaries registered by other backends are seen by the next statement
+fn current() -> Result<Arc<DossDictionaryRegistry>, PgDossError> {
+    let command = command();
+    let (registry, loaded, checked) = CACHE.with(|c| {
+        let cache = c.borrow();
+        (cache.registry.clone(), cache.keys.clone(), cache.checked)
+    });
+    if checked == Some(command) {
+        return Ok(registry);
+    }
+    if keys(&table()?)? != loaded {
+        return load();
+    }
+    CACHE.with(|c| c.borrow_mut().checked = Some(command));
+    Ok(registry)
+}
+
+/// Runs `f` with the registered dictionaries. The table is read again when a stream imports
+/// a dictionary that wasn't registered or has changed since it was loaded
+pub fn with_registry<T>(f: impl Fn(Arc<DossDictionaryRegistry>) -> Result<T, PgDossError>) -> Result<T, PgDossError> {
+    match f(current()?) {
+        Err(PgDossError::Doss(DossError::UnknownDictionary(_) | DossError::DictionaryMismatch(_))) => f(load()?),
+        result => result,
+    }
+}
+
+/// The highest version of a registered dictionary
+pub fn latest(name: &str) -> Result<Arc<DossPredefinedDictionary>, PgDossError> {
+    with_registry(|registry| registry.latest(name).ok_or_else(|| DossError::UnknownDictionary(name.to_string()).into()))
+}
+
+/// Adds a dictionary file (see `DossPredefinedDictionary::to_bytes`) to `doss_dictionaries`, returns its name
+#[pg_extern(volatile)]
+fn doss_dictionary_register(content: &[u8]) -> Result<String, PgDossError> {
+    let dict = DossPredefinedDictionary::from_bytes(content)?;
+    let version = i64::try_from(dict.version()).map_err(|_| DossError::InvalidDictionary(format!("version {}", dict.version())))?;
+    Spi::run_with_args(
+        &format!("INSERT INTO {} (name, version, content) VALUES ($1, $2, $3)", table()?),
+        Some(vec![
+            (PgBuiltInOids::TEXTOID.oid(), dict.name().into_datum()),
+            (PgBuiltInOids::INT8OID.oid(), version.into_datum()),
+            (PgBuiltInOids::BYTEAOID.oid(), content.into_datum()),
+        ]),
+    )?;
+    load()?;
+    Ok(dict.name().to_string())
+}
@@ -0,0 +1,379 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::collections::HashMap;
+use std::ffi::CStr;
+
+use dataflowgrid_commons::readers::reader::IteratorReadable;
+use doss::{Decimal, DossError, DossEvent, DossPullParser, DossSerializer, DossSerializerOptions, decode_entries_with_registry};
+use pgrx::pgrx_sql_entity_graph::metadata::{ArgumentError, Returns, ReturnsError, SqlMapping, SqlTranslatable};
+use pgrx::prelude::*;
+use pgrx::{JsonB, StringInfo};
+use serde_json::{Number, Value};
+use streamablejson::StreamableJSONEntry;
+use streamablejson::parser::{StreamableJSONReader, StreamableJSONReaderCallback, StreamableJSONReaderCallbackReturn, StreamableJSONReaderError, StreamableJSONReaderEvent};
+use streamablejson::serializer::StreamableJSONSerializer;
+
+use crate::dictionaries::with_registry;
+use crate::json::{self, JsonBuilder, write_json};
+use crate::{PgDossError, locate, next_value, parser, read_value, skip_value};
+
+/// A value of the type `doss`, stored in its encoded form like `bytea`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Doss(pub Vec<u8>);
+
+impl FromDatum for Doss {
+    unsafe fn from_polymorphic_datum(datum: pg_sys::Datum, is_null: bool, typoid: pg_sys::Oid) -> Option<Self> {
+        //the same varlena as bytea
+        <Vec<u8>>::from_polymorphic_datum(datum, is_null, typoid).map(Doss)
+    }
+}
+
+impl IntoDatum for Doss {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        self.0.into_datum()
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        pgrx::wrappers::regtypein("doss")
+    }
+}
+
+unsafe impl SqlTranslatable for Doss {
+    fn argument_sql() -> Result<SqlMapping, ArgumentError> {
+        Ok(SqlMapping::As(String::from("doss")))
+    }
+
+    fn return_sql() -> Result<Returns, ReturnsError> {
+        Ok(Returns::One(SqlMapping::As(String::from("doss"))))
+    }
+}
+
+extension_sql!("CREATE TYPE doss;", name = "doss_shell", creates = [Type(Doss)]);
+
+struct EncodeCallback {
+    serializer: DossSerializer<Vec<u8>>,
+}
+
+impl StreamableJSONReaderCallback for EncodeCallback {
+    fn on_streamablejson_event(&mut self, event: StreamableJSONReaderEvent) -> StreamableJSONReaderCallbackReturn {
+        match self.serializer.write_reader_event(event) {
+            Ok(()) => StreamableJSONReaderCallbackReturn::Continue,
+            Err(e) => StreamableJSONReaderCallbackReturn::StopErr(Box::new(e)),
+        }
+    }
+}
+
+fn reader_error(e: StreamableJSONReaderError) -> PgDossError {
+    match e {
+        StreamableJSONReaderError::CallbackError(e) => match e.downcast::<DossError>() {
+            Ok(e) => PgDossError::Doss(*e),
+            Err(e) => PgDossError::Input(e.to_string()),
+        },
+        e => PgDossError::Input(format!("{e:?}")),
+    }
+}
+
+fn options() -> DossSerializerOptions {
+    DossSerializerOptions { emit_dict_hint: false, ..DossSerializerOptions::default() }
+}
+
+/// Encodes streamablejson text
+fn encode(text: &str) -> Result<Doss, PgDossError> {
+    if text.trim().is_empty() {
+        return Err(PgDossError::Input(String::from("empty")));
+    }
+    let mut callback = EncodeCallback { serializer: DossSerializer::with_options(Vec::new(), options()) };
+    let mut reader = StreamableJSONReader::new(&mut callback);
+    let chars: Vec<char> = text.chars().collect();
+    reader.pushdata(&mut IteratorReadable::new(Box::new(chars.into_iter()))).map_err(reader_error)?;
+    reader.finish().map_err(reader_error)?;
+    drop(reader);
+    Ok(Doss(callback.serializer.finish()?))
+}
+
+/// streamablejson text of all values, one per line
+fn text(value: &Doss) -> Result<String, PgDossError> {
+    with_registry(|registry| {
+        let entries = decode_entries_with_registry(&value.0, registry)?;
+        let texts = entries.iter().map(StreamableJSONSerializer::serialize_to_string).collect::<Result<Vec<_>, _>>();
+        Ok(texts.map_err(|e| PgDossError::Json(format!("{e:?}")))?.join("\n"))
+    })
+}
+
+#[pg_extern(immutable, parallel_safe, requires = ["doss_shell"])]
+fn doss_in(input: &CStr) -> Result<Doss, PgDossError> {
+    encode(input.to_str().map_err(|_| PgDossError::Input(String::from("not utf8")))?)
+}
+
+#[pg_extern(stable, parallel_safe, requires = ["doss_shell"])]
+fn doss_out(value: Doss) -> Result<&'static CStr, PgDossError> {
+    let mut out = StringInfo::new();
+    out.push_str(&text(&value)?);
+    Ok(out.leak_cstr())
+}
+
+/// bytea checked to be a valid DOSS stream
+#[pg_extern(stable, parallel_safe, requires = ["doss_shell"])]
+fn doss_from_bytea(value: &[u8]) -> Result<Doss, PgDossError> {
+    with_registry(|registry| {
+        let mut parser = parser(value, registry);
+        while parser.next_doss_event()?.is_some() {}
+        Ok(())
+    })?;
+    Ok(Doss(value.to_vec()))
+}
+
+#[pg_extern(stable, parallel_safe, requires = ["doss_shell"])]
+fn doss_jsonb(value: Doss) -> Result<JsonB, PgDossError> {
+    with_registry(|registry| {
+        let mut parser = parser(&value.0, registry);
+        let first = next_value(&mut parser)?.ok_or(DossError::ItemExpected)?;
+        Ok(JsonB(read_value(&mut parser, first)?))
+    })
+}
+
+#[pg_extern(immutable, parallel_safe, requires = ["doss_shell"])]
+fn jsonb_doss(value: JsonB) -> Result<Doss, PgDossError> {
+    let mut serializer = DossSerializer::with_options(Vec::new(), options());
+    write_json(&mut serializer, &value.0)?;
+    Ok(Doss(serializer.finish()?))
+}
+
+extension_sql!(
+    r#"
+CREATE TYPE doss (
+    INPUT = doss_in,
+    OUTPUT = doss_out,
+    INTERNALLENGTH = VARIABLE,
+    STORAGE = extended
+);
+CREATE CAST (doss AS bytea) WITHOUT FUNCTION;
+CREATE CAST (bytea AS doss) WITH FUNCTION doss_from_bytea(bytea);
+CREATE CAST (doss AS jsonb) WITH FUNCTION doss_jsonb(doss);
+CREATE CAST (jsonb AS doss) WITH FUNCTION jsonb_doss(jsonb);
+"#,
+    name = "doss_type",
+    requires = ["doss_shell", doss_in, doss_out, doss_from_bytea, doss_jsonb, jsonb_doss]
+);
+
+/// Re-encodes the value starting with `first` on its own, references to the dictionary are resolved
+fn copy_value(parser: &mut DossPullParser<&[u8]>, first: DossEvent) -> Result<Doss, PgDossError> {
+    let mut serializer = DossSerializer::with_options(Vec::new(), options());
+    let mut depth = 0;
+    let mut event = first;
+    loop {
+        depth += event.depth_change();
+        serializer.write_doss_event(&event)?;
+        if depth == 0 {
+            return Ok(Doss(serializer.finish()?));
+        }
+        event = next_value(parser)?.ok_or(DossError::UnexpectedEof)?;
+    }
+}
+
+/// The value at a key or index, only the bytes up to its end are read
+fn get(value: &Doss, segment: &str) -> Result<Option<Doss>, PgDossError> {
+    with_registry(|registry| {
+        let mut parser = parser(&value.0, registry);
+        match locate(&mut parser, &[segment])? {
+            Some(event) => Ok(Some(copy_value(&mut parser, event)?)),
+            None => Ok(None),
+        }
+    })
+}
+
+/// Strings without quotes, anything else as streamablejson text like `->>` of jsonb
+fn get_text(value: &Doss, segment: &str) -> Result<Option<String>, PgDossError> {
+    let Some(value) = get(value, segment)? else {
+        return Ok(None);
+    };
+    with_registry(|registry| match decode_entries_with_registry(&value.0, registry)?.as_slice() {
+        [StreamableJSONEntry::String(s)] => Ok(Some(s.clone())),
+        [StreamableJSONEntry::Constant(c)] if c == "null" => Ok(None),
+        _ => Ok(Some(text(&value)?)),
+    })
+}
+
+#[pg_operator(stable, parallel_safe)]
+#[opname(->)]
+fn doss_field(value: Doss, key: &str) -> Result<Option<Doss>, PgDossError> {
+    get(&value, key)
+}
+
+#[pg_operator(stable, parallel_safe)]
+#[opname(->)]
+fn doss_element(value: Doss, index: i32) -> Result<Option<Doss>, PgDossError> {
+    get(&value, &index.to_string())
+}
+
+#[pg_operator(stable, parallel_safe)]
+#[opname(->>)]
+fn doss_field_text(value: Doss, key: &str) -> Result<Option<String>, PgDossError> {
+    get_text(&value, key)
+}
+
+#[pg_operator(stable, parallel_safe)]
+#[opname(->>)]
+fn doss_element_text(value: Doss, index: i32) -> Result<Option<String>, PgDossError> {
+    get_text(&value, &index.to_string())
+}
+
+/// The first value of a stream as it is converted to jsonb
+pub fn json_value(value: &Doss) -> Result<Value, PgDossError> {
+    with_registry(|registry| {
+        let mut parser = parser(&value.0, registry);
+        let mut builder = JsonBuilder::default();
+        while let Some(event) = next_value(&mut parser)? {
+            if let Some(value) = builder.push(event)? {
+                return Ok(value);
+            }
+        }
+        Err(DossError::ItemExpected.into())
+    })
+}
+
+/// Containment of jsonb: objects contain the keys with contained values, arrays contain every element
+/// of the other array somewhere, and a top level array contains a scalar it has as element
+pub fn contains(value: &Value, other: &Value, top: bool) -> bool {
+    match (value, other) {
+        (Value::Object(value), Value::Object(other)) => other.iter().all(|(k, o)| value.get(k).is_some_and(|v| contains(v, o, false))),
+        (Value::Array(value), Value::Array(other)) => other.iter().all(|o| value.iter().any(|v| contains(v, o, false))),
+        (Value::Array(value), other) if top && !other.is_object() => value.iter().any(|v| same_scalar(v, other)),
+        (value, other) => same_scalar(value, other),
+    }
+}
+
+fn same_scalar(value: &Value, other: &Value) -> bool {
+    match (value, other) {
+        //numbers are equal by value, 1.0 and 1 like numeric
+        (Value::Number(a), Value::Number(b)) => a == b || number(a).is_some_and(|a| Some(a) == number(b)),
+        (Value::Object(_) | Value::Array(_), _) => false,
+        (value, other) => value == other,
+    }
+}
+
+/// The exact value of a number, numbers keep their text with serde_json's arbitrary_precision
+pub fn number(n: &Number) -> Option<Decimal> {
+    n.to_string().parse().ok()
+}
+
+/// Like [`contains`] for the value starting with `first`, the parser continues after it. Only the values at
+/// the keys of `other` are read, the rest of an object is jumped over. Array elements are read one by one
+/// unless they can't contain what is left to find
+fn contains_value(parser: &mut DossPullParser<&[u8]>, first: DossEvent, other: &Value, top: bool) -> Result<bool, PgDossError> {
+    match (first, other) {
+        (DossEvent::BlockStart, Value::Object(other)) => {
+            //the last of repeated keys wins like in jsonb
+            let mut found = HashMap::new();
+            loop {
+                let key = match next_value(parser)?.ok_or(DossError::UnexpectedEof)? {
+                    DossEvent::BlockEnd => break,
+                    key => read_value(parser, key)?,
+                };
+                let value = next_value(parser)?.ok_or(DossError::UnexpectedEof)?;
+                match other.get_key_value(&json::key_text(&key)?) {
+                    Some((key, other)) => {
+                        let contained = contains_value(parser, value, other, false)?;
+                        found.insert(key, contained);
+                    }
+                    None => skip_value(parser, &value)?,
+                }
+            }
+            Ok(other.keys().all(|key| found.get(key) == Some(&true)))
+        }
+        (DossEvent::ArrayStart, Value::Array(other)) => {
+            let mut found = vec![false; other.len()];
+            while let Some(element) = next_element(parser)? {
+                let open = || other.iter().zip(&found).filter(|(_, found)| !**found).map(|(other, _)| other);
+                //scalars are never contained in objects and arrays
+                if open().next().is_none() || (matches!(element, DossEvent::BlockStart | DossEvent::ArrayStart) && open().all(|o| !o.is_object() && !o.is_array())) {
+                    skip_value(parser, &element)?;
+                    continue;
+                }
+                let element = read_value(parser, element)?;
+                for (found, other) in found.iter_mut().zip(other) {
+                    *found = *found || contains(&element, other, false);
+                }
+            }
+            Ok(found.into_iter().all(|found| found))
+        }
+        (DossEvent::ArrayStart, other) if top && !other.is_object() => {
+            let mut found = false;
+            while let Some(element) = next_element(parser)? {
+                match element {
+                    DossEvent::BlockStart | DossEvent::ArrayStart => skip_value(parser, &element)?,
+                    element if !found => found = same_scalar(&read_value(parser, element)?, other),
+                    element => skip_value(parser, &element)?,
+                }
+            }
+            Ok(found)
+        }
+        (first @ (DossEvent::BlockStart | DossEvent::ArrayStart), _) => {
+            skip_value(parser, &first)?;
+            Ok(false)
+        }
+        //scalars and types, types are objects in jsonb
+        (first, other) => Ok(contains(&read_value(parser, first)?, other, false)),
+    }
+}
+
+//the next element of an array, None at its end
+fn next_element(parser: &mut DossPullParser<&[u8]>) -> Result<Option<DossEvent>, PgDossError> {
+    match next_value(parser)?.ok_or(DossError::UnexpectedEof)? {
+        DossEvent::ArrayEnd => Ok(None),
+        element => Ok(Some(element)),
+    }
+}
+
+/// Whether the first value of `value` contains `other`. `other` is decoded, `value` is read only as far as needed
+fn contains_doss(value: &Doss, other: &Doss) -> Result<bool, PgDossError> {
+    let other = json_value(other)?;
+    with_registry(|registry| {
+        let mut parser = parser(&value.0, registry);
+        let first = next_value(&mut parser)?.ok_or(DossError::ItemExpected)?;
+        contains_value(&mut parser, first, &other, true)
+    })
+}
+
+#[pg_operator(stable, parallel_safe)]
+#[opname(@>)]
+#[restrict(contsel)]
+#[join(contjoinsel)]
+fn doss_contains(value: Doss, other: Doss) -> Result<bool, PgDossError> {
+    contains_doss(&value, &other)
+}
+
+#[pg_operator(stable, parallel_safe)]
+#[opname(<@)]
+#[restrict(contsel)]
+#[join(contjoinsel)]
+fn doss_contained(value: Doss, other: Doss) -> Result<bool, PgDossError> {
+    contains_doss(&other, &value)
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pg_schema]
+mod tests {
+    use pgrx::prelude::*;
+
+    #[pg_test]
+    fn test_type_and_operators() {
+        Spi::run(r#"CREATE TABLE orders (value doss)"#).unwrap();
+        Spi::run(r#"INSERT INTO orders VALUES ('{"id": 1, "customer": {"name": "a"}, "items": [{"product": "cable"}, {"product": "dock"}]}'), ('{"id": 2, "tags": ["x"], "note": null}')"#).unwrap();
+        let text = Spi::get_one::<String>("SELECT value::text FROM orders WHERE value->>'id' = '2'").unwrap();
+        assert_eq!(text.as_deref(), Some(r#"{"id":2,"tags":["x"],"note":null}"#));
+        assert_eq!(Spi::get_one::<String>("SELECT value->'customer'->>'name' FROM orders WHERE value @> '{\"id\": 1}'").unwrap().as_deref(), Some("a"));
+        assert_eq!(Spi::get_one::<String>("SELECT value->'items'->1->>'product' FROM orders WHERE value @> '{\"id\": 1}'").unwrap().as_deref(), Some("dock"));
+        assert_eq!(Spi::get_one::<String>("SELECT (value->'customer')::text FROM orders WHERE value @> '{\"id\": 1}'").unwrap().as_deref(), Some(r#"{"name":"a"}"#));
+        assert_eq!(Spi::get_one::<i64>("SELECT count(*) FROM orders WHERE value->>'note' IS NULL").unwrap(), Some(2));
+        assert_eq!(Spi::get_one::<i64>(r#"SELECT count(*) FROM orders WHERE value @> '{"items": [{"product": "dock"}]}'"#).unwrap(), Some(1));
+        assert_eq!(Spi::get_one::<bool>(r#"SELECT '{"tags": ["x"]}'::doss <@ value FROM orders WHERE value->>'id' = '2'"#).unwrap(), Some(true));
+        assert_eq!(Spi::get_one::<bool>(r#"SELECT '[1, 2]'::doss @> '1'::doss"#).unwrap(), Some(true));
+        assert_eq!(Spi::get_one::<bool>(r#"SELECT '{"id": 9007199254740993}'::doss @> '{"id": 9007199254740992}'::doss"#).unwrap(), Some(false));
+        assert_eq!(Spi::get_one::<bool>(r#"SELECT '{"a": -1.5, "b": [1.50, {"c": 2}]}'::doss @> '{"a": -1.5, "b": [{"c": 2.0}, 1.5]}'::doss"#).unwrap(), Some(true));
+        assert_eq!(Spi::get_one::<String>(r#"SELECT '{"a": -1.5}'::doss::text"#).unwrap().as_deref(), Some(r#"{"a":-1.5}"#));
+        assert_eq!(Spi::get_one::<bool>(r#"SELECT ('{"a": 1}'::jsonb::doss)::jsonb = '{"a": 1}'::jsonb"#).unwrap(), Some(true));
+        assert!(Spi::get_one::<bool>("SELECT '\\x0905'::bytea::doss IS NULL").is_err());
+        assert!(Spi::get_one::<bool>("SELECT '{'::doss IS NULL").is_err());
+    }
+}
@@ -0,0 +1,162 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+//! GIN operator class `doss_ops` for `@>`. Keys are hashes of the object keys leading to a scalar
+//! and the scalar, array positions are left out like in `jsonb_path_ops`. Matches are always rechecked.
+//! The support functions are stable, values importing predefined dictionaries decode with the table `doss_dictionaries`.
+
+use pgrx::prelude::*;
+use pgrx::Internal;
+use serde_json::Value;
+
+use crate::PgDossError;
+use crate::doss_type::{Doss, json_value, number};
+
+const GIN_SEARCH_MODE_ALL: i32 = 3;
+
+const FNV_OFFSET: u32 = 0x811c_9dc5;
+const FNV_PRIME: u32 = 0x0100_0193;
+
+fn fnv(mut hash: u32, bytes: &[u8]) -> u32 {
+    for b in bytes {
+        hash ^= u32::from(*b);
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+fn collect(value: &Value, path: u32, keys: &mut Vec<i32>) {
+    let scalar = |tag: u8, text: &[u8]| fnv(fnv(path, &[0xff, tag]), text) as i32;
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                collect(value, fnv(fnv(path, &[0xfe]), key.as_bytes()), keys);
+            }
+        }
+        Value::Array(values) => values.iter().for_each(|v| collect(v, path, keys)),
+        Value::Null => keys.push(scalar(b'n', b"")),
+        Value::Bool(b) => keys.push(scalar(b'b', &[u8::from(*b)])),
+        //1 and 1.0 are the same number, the normalized decimal is exact for every size
+        Value::Number(n) => keys.push(match number(n) {
+            Some(d) => scalar(b'd', d.normalized().to_string().as_bytes()),
+            None => scalar(b'd', n.to_string().as_bytes()),
+        }),
+        Value::String(s) => keys.push(scalar(b's', s.as_bytes())),
+    }
+}
+
+/// The keys of a value, sorted and without duplicates
+pub fn keys(value: &Value) -> Vec<i32> {
+    let mut keys = Vec::new();
+    collect(value, FNV_OFFSET, &mut keys);
+    keys.sort_unstable();
+    keys.dedup();
+    keys
+}
+
+//an array of datums in the memory context of the index operation
+unsafe fn datums(keys: &[i32]) -> pg_sys::Datum {
+    let datums = pg_sys::palloc(std::mem::size_of::<pg_sys::Datum>() * keys.len().max(1)) as *mut pg_sys::Datum;
+    for (i, key) in keys.iter().enumerate() {
+        *datums.add(i) = pg_sys::Datum::from(*key);
+    }
+    pg_sys::Datum::from(datums)
+}
+
+#[pg_extern(stable, parallel_safe)]
+fn doss_gin_extract_value(value: Doss, mut nkeys: Internal, _nulls: Internal) -> Result<Internal, PgDossError> {
+    let keys = keys(&json_value(&value)?);
+    unsafe {
+        *nkeys.get_mut::<i32>().expect("nkeys") = keys.len() as i32;
+        Ok(Internal::from(Some(datums(&keys))))
+    }
+}
+
+#[pg_extern(stable, parallel_safe)]
+fn doss_gin_extract_query(
+    query: Doss,
+    mut nkeys: Internal,
+    _strategy: i16,
+    _partial_match: Internal,
+    _extra_data: Internal,
+    _nulls: Internal,
+    mut search_mode: Internal,
+) -> Result<Internal, PgDossError> {
+    let keys = keys(&json_value(&query)?);
+    unsafe {
+        *nkeys.get_mut::<i32>().expect("nkeys") = keys.len() as i32;
+        //empty objects and arrays are contained in every value of their kind
+        if keys.is_empty() {
+            *search_mode.get_mut::<i32>().expect("search mode") = GIN_SEARCH_MODE_ALL;
+        }
+        Ok(Internal::from(Some(datums(&keys))))
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+#[pg_extern(stable, parallel_safe)]
+fn doss_gin_consistent(
+    check: Internal,
+    _strategy: i16,
+    _query: Doss,
+    nkeys: i32,
+    _extra_data: Internal,
+    mut recheck: Internal,
+    _query_keys: Internal,
+    _nulls: Internal,
+) -> bool {
+    unsafe {
+        //hashes can collide and array positions are lost
+        *recheck.get_mut::<bool>().expect("recheck") = true;
+        let check = check.unwrap().expect("check").cast_mut_ptr::<bool>();
+        (0..nkeys as usize).all(|i| *check.add(i))
+    }
+}
+
+extension_sql!(
+    r#"
+CREATE OPERATOR CLASS doss_ops DEFAULT FOR TYPE doss USING gin AS
+    OPERATOR 7 @> (doss, doss),
+    FUNCTION 1 btint4cmp(int4, int4),
+    FUNCTION 2 doss_gin_extract_value(doss, internal, internal),
+    FUNCTION 3 doss_gin_extract_query(doss, internal, int2, internal, internal, internal, internal),
+    FUNCTION 4 doss_gin_consistent(internal, int2, doss, int4, internal, internal, internal, internal),
+    STORAGE int4;
+"#,
+    name = "doss_ops",
+    requires = ["doss_type", doss_gin_extract_value, doss_gin_extract_query, doss_gin_consistent, doss_contains]
+);
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pg_schema]
+mod tests {
+    use pgrx::prelude::*;
+    use serde_json::json;
+
+    use super::keys;
+
+    #[pg_test]
+    fn test_keys() {
+        let value = keys(&json!({"a": [1, {"b": "x"}], "c": null}));
+        assert_eq!(value.len(), 3);
+        for query in [json!({"a": [1]}), json!({"a": [{"b": "x"}]}), json!({"a": 1.0})] {
+            assert!(keys(&query).iter().all(|k| value.contains(k)));
+        }
+        assert!(!value.contains(&keys(&json!({"b": "x"}))[0]));
+        //integers beyond 2^53 have keys of their own
+        let big = |text: &str| keys(&serde_json::from_str(text).unwrap());
+        assert_ne!(big(r#"{"id": 9007199254740993}"#), big(r#"{"id": 9007199254740992}"#));
+        assert_eq!(big(r#"{"id": 1.50}"#), big(r#"{"id": 1.5}"#));
+    }
+
+    #[pg_test]
+    fn test_index() {
+        Spi::run(r#"CREATE TABLE events AS SELECT ('{"id": ' || i || ', "kind": "' || (CASE WHEN i % 100 = 0 THEN 'rare' ELSE 'common' END) || '", "tags": ["t' || (i % 7) || '"]}')::doss AS value FROM generate_series(1, 2000) i"#).unwrap();
+        Spi::run("CREATE INDEX ON events USING gin (value)").unwrap();
+        Spi::run("SET enable_seqscan = off").unwrap();
+        let plan = Spi::get_one::<String>(r#"EXPLAIN SELECT * FROM events WHERE value @> '{"kind": "rare"}'"#).unwrap().unwrap();
+        assert!(plan.contains("Bitmap Heap Scan"), "{plan}");
+        assert_eq!(Spi::get_one::<i64>(r#"SELECT count(*) FROM events WHERE value @> '{"kind": "rare"}'"#).unwrap(), Some(20));
+        assert_eq!(Spi::get_one::<i64>(r#"SELECT count(*) FROM events WHERE value @> '{"kind": "rare", "tags": ["t3"]}'"#).unwrap(), Some(3));
+        assert_eq!(Spi::get_one::<i64>(r#"SELECT count(*) FROM events WHERE value @> '{}'"#).unwrap(), Some(2000));
+    }
+}
@@ -0,0 +1,164 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+use std::io::Write;
+
+use dataflowgrid_commons::typedstream::DateTime;
+use doss::{BYTES_TYPE, DATETIME_TYPE, DossError, DossEvent, DossSerializer};
+use serde_json::{Map, Number, Value};
+
+use crate::PgDossError;
+
+/// key of the type name of a DOSS type in jsonb
+pub const TYPE_KEY: &str = "$type";
+/// key of the content of a DOSS type in jsonb
+pub const ARGS_KEY: &str = "$args";
+
+#[derive(Debug)]
+enum Level {
+    Object(Map<String, Value>, Option<String>), //the key waiting for its value
+    Array(Vec<Value>),
+    Type(String, Vec<Value>),
+}
+
+/// Builds a jsonb value from the events of one DOSS value
+#[derive(Debug, Default)]
+pub struct JsonBuilder {
+    stack: Vec<Level>,
+}
+
+impl JsonBuilder {
+    /// Adds an event, returns the value once it is complete
+    pub fn push(&mut self, event: DossEvent) -> Result<Option<Value>, PgDossError> {
+        let value = match event {
+            DossEvent::BlockStart => return self.open(Level::Object(Map::new(), None)),
+            DossEvent::ArrayStart => return self.open(Level::Array(Vec::new())),
+            DossEvent::TypeStart(name) => return self.open(Level::Type(name, Vec::new())),
+            DossEvent::BlockEnd => match self.stack.pop() {
+                Some(Level::Object(map, None)) => Value::Object(map),
+                _ => return Err(DossError::UnbalancedStructure.into()),
+            },
+            DossEvent::ArrayEnd => match self.stack.pop() {
+                Some(Level::Array(values)) => Value::Array(values),
+                _ => return Err(DossError::UnbalancedStructure.into()),
+            },
+            DossEvent::TypeEnd => match self.stack.pop() {
+                Some(Level::Type(name, args)) => typed(name, args),
+                _ => return Err(DossError::UnbalancedStructure.into()),
+            },
+            event => scalar(event)?,
+        };
+        self.add(value)
+    }
+
+    fn open(&mut self, level: Level) -> Result<Option<Value>, PgDossError> {
+        if let Some(Level::Object(_, None)) = self.stack.last() {
+            return Err(PgDossError::Json(String::from("a key that is not a scalar")));
+        }
+        self.stack.push(level);
+        Ok(None)
+    }
+
+    fn add(&mut self, value: Value) -> Result<Option<Value>, PgDossError> {
+        match self.stack.last_mut() {
+            None => return Ok(Some(value)),
+            //the last of repeated keys wins like in jsonb
+            Some(Level::Object(map, key)) => match key.take() {
+                Some(key) => {
+                    map.insert(key, value);
+                }
+                None => *key = Some(key_text(&value)?),
+            },
+            Some(Level::Array(values) | Level::Type(_, values)) => values.push(value),
+        }
+        Ok(None)
+    }
+}
+
+fn typed(name: String, args: Vec<Value>) -> Value {
+    Value::Object(Map::from_iter([(String::from(TYPE_KEY), Value::String(name)), (String::from(ARGS_KEY), Value::Array(args))]))
+}
+
+fn scalar(event: DossEvent) -> Result<Value, PgDossError> {
+    Ok(match event {
+        DossEvent::True => Value::Bool(true),
+        DossEvent::False => Value::Bool(false),
+        DossEvent::Null => Value::Null,
+        DossEvent::Int(v) => Value::Number(v.into()),
+        DossEvent::UInt(v) => Value::Number(v.into()),
+        //exact with serde_json's arbitrary_precision, jsonb numbers are numeric
+        DossEvent::Decimal(d) => Value::Number(d.to_string().parse::<Number>().map_err(|_| PgDossError::Json(d.to_string()))?),
+        DossEvent::Float(f) => Value::Number(Number::from_f64(f.to_f64()).ok_or_else(|| PgDossError::Json(f.to_string()))?),
+        DossEvent::DateTime(d) => typed(String::from(DATETIME_TYPE), vec![Value::String(d.to_string())]),
+        DossEvent::String(s) => Value::String(s),
+        DossEvent::Binary(b) => typed(String::from(BYTES_TYPE), vec![Value::String(b.iter().map(|b| format!("{b:02x}")).collect())]),
+        event => return Err(PgDossError::Json(format!("{event:?}"))),
+    })
+}
+
+/// Text of a scalar used as object key, jsonb keys are strings
+pub fn key_text(key: &Value) -> Result<String, PgDossError> {
+    match key {
+        Value::String(s) => Ok(s.clone()),
+        Value::Number(_) | Value::Bool(_) | Value::Null => Ok(key.to_string()),
+        key => Err(PgDossError::Json(format!("key {key}"))),
+    }
+}
+
+/// Writes a jsonb value, objects with `$type` and `$args` only become DOSS types
+pub fn write_json<W: Write>(serializer: &mut DossSerializer<W>, value: &Value) -> Result<(), DossError> {
+    match value {
+        Value::Null => serializer.write_doss_event(&DossEvent::Null),
+        Value::Bool(true) => serializer.write_doss_event(&DossEvent::True),
+        Value::Bool(false) => serializer.write_doss_event(&DossEvent::False),
+        Value::Number(n) => serializer.write_doss_event(&match (n.as_u64(), n.as_i64()) {
+            (Some(v), _) => DossEvent::UInt(v),
+            (_, Some(v)) => DossEvent::Int(v),
+            _ => DossEvent::Decimal(n.to_string().parse().map_err(|_| DossError::UnsupportedValue(n.to_string()))?),
+        }),
+        Value::String(s) => serializer.write_doss_event(&DossEvent::String(s.clone())),
+        Value::Array(values) => {
+            serializer.write_doss_event(&DossEvent::ArrayStart)?;
+            for value in values {
+                write_json(serializer, value)?;
+            }
+            serializer.write_doss_event(&DossEvent::ArrayEnd)
+        }
+        Value::Object(map) => match (map.len(), map.get(TYPE_KEY), map.get(ARGS_KEY)) {
+            (2, Some(Value::String(name)), Some(Value::Array(args))) => write_typed(serializer, name, args),
+            _ => {
+                serializer.write_doss_event(&DossEvent::BlockStart)?;
+                for (key, value) in map {
+                    serializer.write_doss_event(&DossEvent::String(key.clone()))?;
+                    write_json(serializer, value)?;
+                }
+                serializer.write_doss_event(&DossEvent::BlockEnd)
+            }
+        },
+    }
+}
+
+fn write_typed<W: Write>(serializer: &mut DossSerializer<W>, name: &str, args: &[Value]) -> Result<(), DossError> {
+    //date times and binaries are values of their own
+    if let [Value::String(text)] = args {
+        let value = match name {
+            DATETIME_TYPE => text.parse::<DateTime>().ok().map(DossEvent::DateTime),
+            BYTES_TYPE => hex(text).map(DossEvent::Binary),
+            _ => None,
+        };
+        if let Some(value) = value {
+            return serializer.write_doss_event(&value);
+        }
+    }
+    serializer.write_doss_event(&DossEvent::TypeStart(name.to_string()))?;
+    for arg in args {
+        write_json(serializer, arg)?;
+    }
+    serializer.write_doss_event(&DossEvent::TypeEnd)
+}
+
+fn hex(text: &str) -> Option<Vec<u8>> {
+    if text.len() % 2 != 0 {
+        return None;
+    }
+    (0..text.len()).step_by(2).map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok()).collect()
+}
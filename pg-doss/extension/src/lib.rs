@@ -1,22 +1,242 @@
+/* This file is part of dataFlowGrid. See file LICENSE for full license details. (c) 2025 Alexander Zich */
+
+//! PostgreSQL functions for DOSS values stored as `bytea`.
+//!
+//! Values convert from and to `jsonb`. Decimals become exact JSON numbers, other DOSS types become objects
+//! `{"$type": name, "$args": [...]}`, date times and binaries as `datetime("<RFC 3339>")` and `bytes("<hex>")`.
+//! Predefined dictionaries are registered in the table `doss_dictionaries`. The type `doss` stores values in
+//! their encoded form with operators like `jsonb` and a GIN operator class for containment.
+
+mod dictionaries;
+mod doss_type;
+mod gin;
+mod json;
+
+use std::sync::Arc;
+
+use derive_more::{Display, Error, From};
+use doss::{DossDictionaryRegistry, DossError, DossEvent, DossPullParser, DossSerializer, DossSerializerOptions};
 use pgrx::prelude::*;
+use pgrx::JsonB;
+
+use crate::dictionaries::with_registry;
+use crate::json::{JsonBuilder, write_json};
 
 ::pgrx::pg_module_magic!();
 
-#[pg_extern]
-fn hello_extension() -> &'static str {
-    "Hello, extension"
+#[derive(Debug, Display, Error, From)]
+pub enum PgDossError {
+    #[display("{_0}")]
+    Doss(DossError),
+    #[display("{_0}")]
+    Spi(pgrx::spi::Error),
+    #[display("{_0} can not be converted to jsonb")]
+    #[from(ignore)]
+    Json(#[error(not(source))] String),
+    #[display("invalid streamablejson: {_0}")]
+    #[from(ignore)]
+    Input(#[error(not(source))] String),
+}
+
+fn parser(value: &[u8], registry: Arc<DossDictionaryRegistry>) -> DossPullParser<&[u8]> {
+    let mut parser = DossPullParser::new(value);
+    parser.set_registry(registry);
+    parser
+}
+
+/// Next event with a value, settings, hints, imports and stacks are applied by the parser
+fn next_value(parser: &mut DossPullParser<&[u8]>) -> Result<Option<DossEvent>, DossError> {
+    while let Some(event) = parser.next_doss_event()? {
+        match event {
+            DossEvent::Config { .. } | DossEvent::Hint { .. } | DossEvent::ImportDict(_) | DossEvent::StackStart | DossEvent::StackEnd => {}
+            DossEvent::FileStart(_) => return Err(DossError::UnsupportedValue(String::from("files"))),
+            event => return Ok(Some(event)),
+        }
+    }
+    Ok(None)
+}
+
+/// The value starting with `first`, reading the rest of it if it is a block, array or type
+fn read_value(parser: &mut DossPullParser<&[u8]>, first: DossEvent) -> Result<serde_json::Value, PgDossError> {
+    let mut builder = JsonBuilder::default();
+    let mut event = first;
+    loop {
+        if let Some(value) = builder.push(event)? {
+            return Ok(value);
+        }
+        event = next_value(parser)?.ok_or(DossError::UnexpectedEof)?;
+    }
+}
+
+/// The first value of a DOSS stream as jsonb
+#[pg_extern(stable, parallel_safe)]
+fn doss_to_jsonb(value: &[u8]) -> Result<JsonB, PgDossError> {
+    with_registry(|registry| {
+        let mut parser = parser(value, registry);
+        let first = next_value(&mut parser)?.ok_or(DossError::ItemExpected)?;
+        Ok(JsonB(read_value(&mut parser, first)?))
+    })
+}
+
+/// Encodes jsonb as DOSS, importing the predefined dictionary `dictionary` if given
+#[pg_extern(stable, parallel_safe)]
+fn jsonb_to_doss(value: JsonB, dictionary: default!(Option<&str>, "NULL")) -> Result<Vec<u8>, PgDossError> {
+    let mut serializer = DossSerializer::with_options(Vec::new(), DossSerializerOptions { emit_dict_hint: false, ..DossSerializerOptions::default() });
+    if let Some(name) = dictionary {
+        let dict = dictionaries::latest(name)?;
+        serializer.import_dictionary(&dict)?;
+    }
+    write_json(&mut serializer, &value.0)?;
+    Ok(serializer.finish()?)
+}
+
+/// The value at `path` of the first value of a stream, NULL if it doesn't exist. Array elements are selected
+/// by their index. Values before the one looked for are jumped over with their skip opcodes, the rest is not read
+#[pg_extern(stable, parallel_safe)]
+fn doss_get(value: &[u8], path: Array<&str>) -> Result<Option<JsonB>, PgDossError> {
+    let path: Option<Vec<&str>> = path.iter().collect();
+    let Some(path) = path else {
+        return Ok(None);
+    };
+    with_registry(|registry| {
+        let mut parser = parser(value, registry);
+        match locate(&mut parser, &path)? {
+            Some(event) => Ok(Some(JsonB(read_value(&mut parser, event)?))),
+            None => Ok(None),
+        }
+    })
+}
+
+/// The first event of the value at `path` of the first value of a stream, the parser continues with the rest of it
+fn locate(parser: &mut DossPullParser<&[u8]>, path: &[&str]) -> Result<Option<DossEvent>, PgDossError> {
+    let Some(mut event) = next_value(parser)? else {
+        return Ok(None);
+    };
+    for segment in path {
+        event = match find(parser, event, segment)? {
+            Some(event) => event,
+            None => return Ok(None),
+        };
+    }
+    Ok(Some(event))
+}
+
+/// Moves to the value of the key or index `segment` within the value started by `event`
+fn find(parser: &mut DossPullParser<&[u8]>, event: DossEvent, segment: &str) -> Result<Option<DossEvent>, PgDossError> {
+    match event {
+        DossEvent::BlockStart => loop {
+            let key = match next_value(parser)?.ok_or(DossError::UnexpectedEof)? {
+                DossEvent::BlockEnd => return Ok(None),
+                key => read_value(parser, key)?,
+            };
+            let value = next_value(parser)?.ok_or(DossError::UnexpectedEof)?;
+            if json::key_text(&key)? == segment {
+                return Ok(Some(value));
+            }
+            skip_value(parser, &value)?;
+        },
+        DossEvent::ArrayStart => {
+            let Ok(index) = segment.parse::<usize>() else {
+                return Ok(None);
+            };
+            for i in 0.. {
+                match next_value(parser)?.ok_or(DossError::UnexpectedEof)? {
+                    DossEvent::ArrayEnd => break,
+                    element if i == index => return Ok(Some(element)),
+                    element => skip_value(parser, &element)?,
+                }
+            }
+            Ok(None)
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Jumps over the rest of the value started by `first` with its skip opcode
+fn skip_value(parser: &mut DossPullParser<&[u8]>, first: &DossEvent) -> Result<(), DossError> {
+    if first.depth_change() > 0 {
+        parser.skip_level();
+        next_value(parser)?;
+    }
+    Ok(())
+}
+
+/// True if the value decodes completely, imports of unknown dictionaries are invalid
+#[pg_extern(stable, parallel_safe)]
+fn doss_is_valid(value: &[u8]) -> bool {
+    let result: Result<(), PgDossError> = with_registry(|registry| {
+        let mut parser = parser(value, registry);
+        while parser.next_doss_event()?.is_some() {}
+        Ok(())
+    });
+    result.is_ok()
 }
 
 #[cfg(any(test, feature = "pg_test"))]
 #[pg_schema]
 mod tests {
     use pgrx::prelude::*;
+    use pgrx::JsonB;
+    use serde_json::json;
+
+    fn roundtrip(value: serde_json::Value) -> serde_json::Value {
+        let doss = Spi::get_one_with_args::<Vec<u8>>("SELECT jsonb_to_doss($1)", vec![(PgBuiltInOids::JSONBOID.oid(), JsonB(value).into_datum())]).unwrap().unwrap();
+        Spi::get_one_with_args::<JsonB>("SELECT doss_to_jsonb($1)", vec![(PgBuiltInOids::BYTEAOID.oid(), doss.into_datum())]).unwrap().unwrap().0
+    }
+
+    #[pg_test]
+    fn test_roundtrip() {
+        for text in [
+            r#"{"hello": "world", "n": [1, -2, 3.25, 12345678901234567890123], "nested": {"a": null, "b": true}}"#,
+            r#"[{"$type": "ObjectId", "$args": ["5f1"]}, {"$type": "datetime", "$args": ["2025-01-01T00:00:00Z"]}, {"$type": "bytes", "$args": ["00ff"]}]"#,
+            r#""text""#,
+        ] {
+            let value: serde_json::Value = serde_json::from_str(text).unwrap();
+            assert_eq!(roundtrip(value.clone()), value);
+        }
+        let exact = Spi::get_one::<JsonB>("SELECT doss_to_jsonb(jsonb_to_doss('{\"d\": 1.10}'::jsonb))").unwrap().unwrap();
+        assert_eq!(exact.0.to_string(), r#"{"d":1.10}"#);
+    }
+
+    #[pg_test]
+    fn test_get() {
+        Spi::run(r#"CREATE TABLE docs AS SELECT jsonb_to_doss('{"skip": {"large": [1, 2, 3]}, "items": [{"a": 1}, {"a": {"b": "found"}}]}'::jsonb) AS d"#).unwrap();
+        let get = |path: &str| Spi::get_one::<JsonB>(&format!("SELECT doss_get(d, '{path}') FROM docs")).unwrap().map(|v| v.0);
+        assert_eq!(get("{items,1,a,b}"), Some(json!("found")));
+        assert_eq!(get("{skip}"), Some(json!({"large": [1, 2, 3]})));
+        assert_eq!(get("{items,2}"), None);
+        assert_eq!(get("{items,x}"), None);
+        assert_eq!(get("{missing}"), None);
+        assert_eq!(get("{}"), Some(json!({"skip": {"large": [1, 2, 3]}, "items": [{"a": 1}, {"a": {"b": "found"}}]})));
+    }
 
     #[pg_test]
-    fn test_hello_extension() {
-        assert_eq!("Hello, extension", crate::hello_extension());
+    fn test_is_valid() {
+        assert_eq!(Spi::get_one::<bool>("SELECT doss_is_valid(jsonb_to_doss('[1, 2]'))").unwrap(), Some(true));
+        assert_eq!(Spi::get_one::<bool>("SELECT doss_is_valid('\\x0905'::bytea)").unwrap(), Some(false));
+        assert_eq!(Spi::get_one::<bool>("SELECT doss_is_valid(substring(jsonb_to_doss('[\"abc\"]') FROM 1 FOR 3))").unwrap(), Some(false));
     }
 
+    #[pg_test]
+    fn test_dictionaries() {
+        let mut dict = doss::DossDictionaryTrainer::new();
+        for _ in 0..3 {
+            dict.add_events(&[doss::DossEvent::String(String::from("customer_name")), doss::DossEvent::String(String::from("delivery_address"))]);
+        }
+        let bytes = dict.build("orders", 1, 16, 1).unwrap().to_bytes().unwrap();
+        let registered = Spi::get_one_with_args::<String>("SELECT doss_dictionary_register($1)", vec![(PgBuiltInOids::BYTEAOID.oid(), bytes.into_datum())]).unwrap();
+        assert_eq!(registered.as_deref(), Some("orders"));
+        let value = Spi::get_one::<JsonB>(r#"SELECT doss_to_jsonb(jsonb_to_doss('{"customer_name": "a"}', 'orders'))"#).unwrap().unwrap();
+        assert_eq!(value.0, json!({"customer_name": "a"}));
+        let valid = Spi::get_one::<bool>(r#"SELECT doss_is_valid(jsonb_to_doss('{"delivery_address": "b"}', 'orders'))"#).unwrap();
+        assert_eq!(valid, Some(true));
+
+        //a version written by another backend is seen without registering it here
+        let bytes = dict.build("orders", 2, 16, 1).unwrap().to_bytes().unwrap();
+        Spi::run_with_args("INSERT INTO doss_dictionaries (name, version, content) VALUES ('orders', 2, $1)", Some(vec![(PgBuiltInOids::BYTEAOID.oid(), bytes.into_datum())])).unwrap();
+        assert_eq!(crate::dictionaries::latest("orders").unwrap().version(), 2);
+        assert!(Spi::get_one::<Vec<u8>>("SELECT jsonb_to_doss('1', 'unknown')").is_err());
+    }
 }
 
 /// This module is required by `cargo pgrx test` invocations.
@@ -14,6 +14,12 @@ pub struct OrderedBagDeserializer {
     results: Vec<StreamableJSONEntry>,
 }
 
+impl Default for OrderedBagDeserializer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl OrderedBagDeserializer {
     pub fn new() -> Self {
         OrderedBagDeserializer {
@@ -40,7 +46,12 @@ impl OrderedBagDeserializer {
                     last.push(entry);
                 }
                 StreamableJSONEntry::Object(opt) => {
-                    opt.push(StreamableJSONEntry::String("1".into()), entry);
+                    //keys and values arrive one after the other
+                    if opt.keys_and_values_in_sync() {
+                        opt.insert_key_only(entry);
+                    } else {
+                        opt.insert_value_only(entry);
+                    }
                 }
                 StreamableJSONEntry::Type(_, attr) => {
                     attr.push(entry);
@@ -69,13 +80,13 @@ impl StreamableJSONReaderCallback for OrderedBagDeserializer {
             StreamableJSONReaderEvent::Constant(s) => self.add_to_last_stack_element(StreamableJSONEntry::Constant(s)),
             StreamableJSONReaderEvent::StartType(s) => self.stack.push(StreamableJSONEntry::Type(s, Vec::new())),
             StreamableJSONReaderEvent::Finished => {
-                assert!(self.stack.len() == 0);
+                assert!(self.stack.is_empty());
             },
             StreamableJSONReaderEvent::Initialized => {
                 assert!(self.stack.is_empty());
             },
         }
-        return StreamableJSONReaderCallbackReturn::Continue;
+        StreamableJSONReaderCallbackReturn::Continue
     }
 }
 
@@ -86,7 +97,7 @@ pub fn deserialize_orderedbag_from_string(text: String) -> Result<StreamableJSON
     reader.pushdata(&mut it).unwrap();
     reader.finish().unwrap();
 
-    return Ok(deserializer.result().unwrap());
+    Ok(deserializer.result().unwrap())
 }
 
 pub struct StringCharIterator {
@@ -101,7 +112,7 @@ impl Iterator for StringCharIterator {
             return None;
         }
         let c = self.text.remove(0);
-        return Some(c);
+        Some(c)
     }
 }
 
@@ -111,8 +122,8 @@ impl StringCharIterator {
             text,
         }
     }
-    pub fn chars(&self) -> Chars {
-        return self.text.chars()
+    pub fn chars(&self) -> Chars<'_> {
+        self.text.chars()
     }
 }
 
@@ -178,4 +189,13 @@ mod tests {
             ));
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_object() {
+        let result = deserialize_orderedbag_from_string(String::from("{\"a\": 1, \"b\": [true]}")).unwrap();
+        let mut expected = OrderedBag::new();
+        expected.push(StreamableJSONEntry::String("a".into()), StreamableJSONEntry::Constant("1".into()));
+        expected.push(StreamableJSONEntry::String("b".into()), StreamableJSONEntry::Array(vec![StreamableJSONEntry::Constant("true".into())]));
+        assert_eq!(result, StreamableJSONEntry::Object(expected));
+    }
+
+}
@@ -2,6 +2,7 @@
 
 #![allow(dead_code)]
 #[derive(Debug, Clone, Copy)]
+#[allow(clippy::upper_case_acronyms)]
 enum StreamableJSONReaderStateEnum {
     INIT{is_key: bool},
     OBJECT {is_key: bool},
@@ -30,6 +31,15 @@ pub enum StreamableJSONReaderError {
     InvalidJSON,
     InvalidState,
     CallbackError(Box<dyn Error>),
+    ReaderError(ReaderError), //the readable failed, before this was reported as CallbackError
+}
+
+/// Why [`StreamableJSONReader::pushdata_with_status`] returned
+#[derive(Debug, PartialEq)]
+pub enum StreamableJSONReaderPushResult {
+    EndOfData, //the readable ended, call finish next
+    NeedMoreData, //the readable has no data right now, call pushdata again once more data arrived
+    Stopped, //a callback returned StopOk
 }
 
 #[derive(Debug)]
@@ -47,9 +57,8 @@ enum Callback<'a> {
 }
 use std::error::Error;
 use std::fmt::{Debug, Formatter, Result as FmtResult};
-use std::io::ErrorKind;
 
-use dataflowgrid_commons::readers::reader::Readable;
+use dataflowgrid_commons::readers::reader::{Readable, ReaderError};
 
 impl<'a> Debug for Callback<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
@@ -85,8 +94,7 @@ pub struct StreamableJSONReader<'a> {
 
 impl<'a> StreamableJSONReader<'a> {
     pub fn new(callback: &'a mut dyn StreamableJSONReaderCallback) -> StreamableJSONReader<'a> {   
-        let mut stack = Vec::new();
-        stack.push(StreamableJSONReaderStateEnum::INIT{is_key: false});
+        let stack = vec![StreamableJSONReaderStateEnum::INIT{is_key: false}];
 
         let r = callback.on_streamablejson_event(StreamableJSONReaderEvent::Initialized);
         StreamableJSONReader {
@@ -108,7 +116,14 @@ impl<'a> StreamableJSONReader<'a> {
         }
     }
 
+    /// Parses everything `data` has right now. Use [`StreamableJSONReader::pushdata_with_status`] to tell
+    /// the end of the data from a readable that waits for more
     pub fn pushdata(&mut self, data: &mut dyn Readable<char>) -> Result<(), StreamableJSONReaderError> {
+        self.pushdata_with_status(data).map(|_| ())
+    }
+
+    /// Like [`StreamableJSONReader::pushdata`] but tells why it returned
+    pub fn pushdata_with_status(&mut self, data: &mut dyn Readable<char>) -> Result<StreamableJSONReaderPushResult, StreamableJSONReaderError> {
         let mut reprocess_char = false;
         let mut c = ' ';
         loop {
@@ -117,18 +132,21 @@ impl<'a> StreamableJSONReader<'a> {
                 return Err(StreamableJSONReaderError::CallbackError(e));
             }
             if let StreamableJSONReaderCallbackReturn::StopOk = e {
-                return Ok(());
+                return Ok(StreamableJSONReaderPushResult::Stopped);
             }
             if !reprocess_char {
                 match data.read_next() {
                     Ok(ch) => {
                         c = ch;
                     }
-                    Err(dataflowgrid_commons::readers::reader::ReaderError::EOF) => {
-                        return Ok(());
+                    Err(ReaderError::EOF) => {
+                        return Ok(StreamableJSONReaderPushResult::EndOfData);
+                    }
+                    Err(ReaderError::NeedMoreData) => {
+                        return Ok(StreamableJSONReaderPushResult::NeedMoreData);
                     }
                     Err(e) => {
-                        return Err(StreamableJSONReaderError::CallbackError(Box::new(std::io::Error::new(ErrorKind::Other, "test"))));
+                        return Err(StreamableJSONReaderError::ReaderError(e));
                     }
                 }
             }
@@ -136,7 +154,7 @@ impl<'a> StreamableJSONReader<'a> {
             if self.stack.is_empty() {
                 return Err(StreamableJSONReaderError::InvalidState);
             }
-            match self.stack.last().unwrap().clone() {
+            match *self.stack.last().unwrap() {
                 StreamableJSONReaderStateEnum::INIT{is_key} => {
                     if c.is_whitespace() {
                         continue;
@@ -162,7 +180,7 @@ impl<'a> StreamableJSONReader<'a> {
                             self.chars.clear();
                         }
                         _ => {
-                            if c.is_alphanumeric() {
+                            if is_constant_char(c) {
                                 self.stack.push(StreamableJSONReaderStateEnum::CONSTANT{is_key});
                                 self.chars.push(c);
                             } else {
@@ -230,7 +248,7 @@ impl<'a> StreamableJSONReader<'a> {
                     if self.stringescape {
                         if self.stringescapeunicode {
                             if self.stringescapeunicodecount < 4 {
-                                if c.is_digit(16) {
+                                if c.is_ascii_hexdigit() {
                                     self.stringescapeunicodevalue = self.stringescapeunicodevalue << 4 | c.to_digit(16).unwrap();
                                     self.stringescapeunicodecount += 1;
                                     continue;
@@ -299,7 +317,7 @@ impl<'a> StreamableJSONReader<'a> {
                     }
                 }
                 StreamableJSONReaderStateEnum::CONSTANT{is_key} => {
-                    if c.is_alphanumeric() {
+                    if is_constant_char(c) {
                         self.chars.push(c);
                     } else if c == '(' {
                         self.stack.pop().unwrap();
@@ -364,6 +382,11 @@ impl<'a> StreamableJSONReader<'a> {
     }
 }
 
+/// Constants are names and numbers, with their signs, decimal points and exponents
+fn is_constant_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '-' | '+' | '.')
+}
+
 #[cfg(test)]
 mod tests;
 
@@ -473,7 +496,7 @@ mod tests2 {
             result: RefCell::new(
                 vec![
                     StreamableJSONReaderCallbackReturn::Continue,
-                    StreamableJSONReaderCallbackReturn::StopErr(Box::new(std::io::Error::new(std::io::ErrorKind::Other, "test"))),
+                    StreamableJSONReaderCallbackReturn::StopErr(Box::new(std::io::Error::other("test"))),
                     StreamableJSONReaderCallbackReturn::Continue,
                 ]
             ),
@@ -2,7 +2,8 @@
 
 use std::cell::RefCell;
 
-use dataflowgrid_commons::readers::reader::IteratorReadable;
+use dataflowgrid_commons::cursedbuffer::CursedBuffer;
+use dataflowgrid_commons::readers::reader::{CursedBufferReadable, IteratorReadable};
 
 use super::*;
 
@@ -178,6 +179,23 @@ fn test_array_with_numbers() {
     assert!(events.pop().is_none());
 }
 
+#[test]
+fn test_array_with_signed_and_fractional_numbers() {
+    let mut c = TestCallback { events: RefCell::new(Vec::new()) };
+    let mut reader = StreamableJSONReader::new(&mut c);
+    let b = "[-3, 9.5,+1,1e-7, -0.1E+2]";
+    reader.pushdata(&mut IteratorReadable::new(Box::new(b.chars()))).unwrap();
+    reader.finish().unwrap();
+
+    let events = c.events.borrow();
+    let constants: Vec<_> = events.iter().filter_map(|e| match e {
+        StreamableJSONReaderEvent::Constant(c) => Some(c.as_str()),
+        _ => None,
+    }).collect();
+    assert_eq!(constants, vec!["-3", "9.5", "+1", "1e-7", "-0.1E+2"]);
+    assert_eq!(events.last().unwrap(), &StreamableJSONReaderEvent::Finished);
+}
+
 #[test]
 fn test_array_with_strings() {
     let mut c = TestCallback { events: RefCell::new(Vec::new()) };
@@ -366,3 +384,24 @@ fn test_nested_type() {
     assert!(events.pop().is_none());
 }
 
+#[test]
+fn test_incremental_pushdata() {
+    let mut c = TestCallback { events: RefCell::new(Vec::new()) };
+    let mut reader = StreamableJSONReader::new(&mut c);
+    let buffer = CursedBuffer::<char>::new();
+    let mut readable = CursedBufferReadable::new(buffer.reader(0));
+
+    buffer.write("[\"ab".chars().collect()).unwrap();
+    assert_eq!(reader.pushdata_with_status(&mut readable).unwrap(), StreamableJSONReaderPushResult::NeedMoreData);
+    buffer.write("c\"]".chars().collect()).unwrap();
+    assert_eq!(reader.pushdata_with_status(&mut readable).unwrap(), StreamableJSONReaderPushResult::NeedMoreData);
+    buffer.close();
+    assert_eq!(reader.pushdata_with_status(&mut readable).unwrap(), StreamableJSONReaderPushResult::EndOfData);
+    reader.finish().unwrap();
+
+    let mut events = c.events.borrow_mut();
+    assert_eq!(events.pop().unwrap(), StreamableJSONReaderEvent::Finished);
+    assert_eq!(events.pop().unwrap(), StreamableJSONReaderEvent::EndArray);
+    assert_eq!(events.pop().unwrap(), StreamableJSONReaderEvent::String(String::from("abc")));
+    assert_eq!(events.pop().unwrap(), StreamableJSONReaderEvent::StartArray);
+}
@@ -22,6 +22,12 @@ pub struct StreamableJSONSerializer {
 
 }
 
+impl Default for StreamableJSONSerializer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl StreamableJSONSerializer {
     pub fn new() -> StreamableJSONSerializer {
         StreamableJSONSerializer {
@@ -25,7 +25,7 @@ fn token(entry: &StreamableJSONEntry) -> TokenStream {
             }
         }
         StreamableJSONEntry::Array(arr) => {
-                let arr: Vec<TokenStream> = arr.iter().map(|x| token(x)).collect();
+                let arr: Vec<TokenStream> = arr.iter().map(token).collect();
                 quote! {
                     StreamableJSONEntry::Array(
                         vec![#(#arr),*]
@@ -42,7 +42,14 @@ fn token(entry: &StreamableJSONEntry) -> TokenStream {
                     StreamableJSONEntry::Constant(#s.into())
                 }
             }
-        StreamableJSONEntry::Type(_, items) => todo!(),
+        StreamableJSONEntry::Type(t, items) => {
+                let items: Vec<TokenStream> = items.iter().map(token).collect();
+                quote! {
+                    StreamableJSONEntry::Type(#t.into(),
+                        vec![#(#items),*]
+                    )
+                }
+            }
     }
 }
 